@@ -0,0 +1,386 @@
+use serde::{Deserialize, Serialize};
+
+const DEFAULT_XINPUT_STICK_DEADZONE: i32 = 16384;
+const DEFAULT_XINPUT_TRIGGER_THRESHOLD: i32 = 140;
+pub(crate) const DEFAULT_HID_STICK_DEADZONE: i32 = 58;
+const DEFAULT_HID_TRIGGER_THRESHOLD: i32 = 141;
+const DEFAULT_HID_STICK_CENTER: i32 = 127;
+/// Half the travel of a byte axis, the unit the `hid_*` values are given in.
+const HID_HALF_RANGE: i64 = 127;
+const HID_TRIGGER_RANGE: i64 = 255;
+/// Furthest a byte axis may wander during calibration and still count as
+/// resting, and furthest its rest may sit from the nominal center.
+const CALIBRATION_MAX_SPREAD: u8 = 24;
+const CALIBRATION_MAX_OFFSET: f64 = 48.0;
+
+/// Which controller input drives the numpad `direction`. `Merged` reads the
+/// dpad and the left stick, settled by `direction_priority`; the other
+/// sources ignore everything else, though dpad bits still reach `down_mask`.
+#[derive(Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DirectionSource {
+    Dpad,
+    LeftStick,
+    RightStick,
+    #[default]
+    Merged,
+}
+
+/// Which of a held dpad and a held left stick [`DirectionSource::Merged`]
+/// reports. `Combined` ORs the two axis by axis, right and up winning over
+/// their opposites; `LastMoved` follows whichever left neutral or changed
+/// direction most recently. `Auto` is `Combined` for XInput, whose dpad and
+/// stick are separate controls, and `Dpad` for the backends that read a hat,
+/// where ORing would turn a hat press and a drifting or half-held stick into
+/// a diagonal nobody input.
+#[derive(Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DirectionPriority {
+    #[default]
+    Auto,
+    Combined,
+    Dpad,
+    Stick,
+    LastMoved,
+}
+
+/// Shape of the stick deadzone. `Square` tests each axis on its own, so a
+/// stick just off an axis still reads as a diagonal once both clear it;
+/// `Radial` tests the distance from center and then picks the nearest of the
+/// eight directions by angle.
+#[derive(Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DeadzoneShape {
+    #[default]
+    Square,
+    Radial,
+}
+
+/// Analog thresholds as exchanged with `input_configure`. Omitted fields keep
+/// their current value; the command answers with every field filled in.
+///
+/// The `hid_*` values are in report bytes (center 127) and also drive
+/// DirectInput sticks and evdev, scaled to each device's axis range. Centers
+/// apply to whichever stick `direction_source` reads.
+#[derive(Clone, Default, Serialize, Deserialize)]
+pub struct AnalogConfig {
+    direction_source: Option<DirectionSource>,
+    direction_priority: Option<DirectionPriority>,
+    deadzone_shape: Option<DeadzoneShape>,
+    xinput_stick_deadzone: Option<i32>,
+    xinput_trigger_threshold: Option<i32>,
+    xinput_center_x: Option<i32>,
+    xinput_center_y: Option<i32>,
+    hid_stick_deadzone: Option<i32>,
+    hid_trigger_threshold: Option<i32>,
+    hid_center_x: Option<i32>,
+    hid_center_y: Option<i32>,
+}
+
+/// Deadzones, trigger thresholds and stick centers read by the backends on
+/// every poll, so changes apply without restarting the worker.
+#[derive(Clone, Copy, PartialEq)]
+pub(crate) struct AnalogSettings {
+    direction_source: DirectionSource,
+    direction_priority: DirectionPriority,
+    deadzone_shape: DeadzoneShape,
+    xinput_stick_deadzone: i32,
+    xinput_trigger_threshold: i32,
+    xinput_center_x: i32,
+    xinput_center_y: i32,
+    hid_stick_deadzone: i32,
+    hid_trigger_threshold: i32,
+    hid_center_x: i32,
+    hid_center_y: i32,
+}
+
+impl Default for AnalogSettings {
+    fn default() -> Self {
+        Self::hid_preset(DEFAULT_HID_STICK_DEADZONE)
+    }
+}
+
+fn checked(
+    value: Option<i32>,
+    current: i32,
+    name: &str,
+    range: std::ops::RangeInclusive<i32>,
+) -> Result<i32, String> {
+    match value {
+        Some(value) if !range.contains(&value) => Err(format!(
+            "{name} must be between {} and {}, got {value}.",
+            range.start(),
+            range.end()
+        )),
+        Some(value) => Ok(value),
+        None => Ok(current),
+    }
+}
+
+impl AnalogSettings {
+    /// The defaults with a controller preset's byte-axis stick deadzone.
+    pub(crate) const fn hid_preset(hid_stick_deadzone: i32) -> Self {
+        Self {
+            direction_source: DirectionSource::Merged,
+            direction_priority: DirectionPriority::Auto,
+            deadzone_shape: DeadzoneShape::Square,
+            xinput_stick_deadzone: DEFAULT_XINPUT_STICK_DEADZONE,
+            xinput_trigger_threshold: DEFAULT_XINPUT_TRIGGER_THRESHOLD,
+            xinput_center_x: 0,
+            xinput_center_y: 0,
+            hid_stick_deadzone,
+            hid_trigger_threshold: DEFAULT_HID_TRIGGER_THRESHOLD,
+            hid_center_x: DEFAULT_HID_STICK_CENTER,
+            hid_center_y: DEFAULT_HID_STICK_CENTER,
+        }
+    }
+
+    pub(crate) fn with_config(self, config: &AnalogConfig) -> Result<Self, String> {
+        let xinput_axis = i32::from(i16::MIN)..=i32::from(i16::MAX);
+        Ok(Self {
+            direction_source: config.direction_source.unwrap_or(self.direction_source),
+            direction_priority: config.direction_priority.unwrap_or(self.direction_priority),
+            deadzone_shape: config.deadzone_shape.unwrap_or(self.deadzone_shape),
+            xinput_stick_deadzone: checked(
+                config.xinput_stick_deadzone,
+                self.xinput_stick_deadzone,
+                "XInput stick deadzone",
+                0..=32767,
+            )?,
+            xinput_trigger_threshold: checked(
+                config.xinput_trigger_threshold,
+                self.xinput_trigger_threshold,
+                "XInput trigger threshold",
+                1..=255,
+            )?,
+            xinput_center_x: checked(
+                config.xinput_center_x,
+                self.xinput_center_x,
+                "XInput stick center X",
+                xinput_axis.clone(),
+            )?,
+            xinput_center_y: checked(
+                config.xinput_center_y,
+                self.xinput_center_y,
+                "XInput stick center Y",
+                xinput_axis,
+            )?,
+            hid_stick_deadzone: checked(
+                config.hid_stick_deadzone,
+                self.hid_stick_deadzone,
+                "HID stick deadzone",
+                0..=127,
+            )?,
+            hid_trigger_threshold: checked(
+                config.hid_trigger_threshold,
+                self.hid_trigger_threshold,
+                "HID trigger threshold",
+                1..=255,
+            )?,
+            hid_center_x: checked(
+                config.hid_center_x,
+                self.hid_center_x,
+                "HID stick center X",
+                0..=255,
+            )?,
+            hid_center_y: checked(
+                config.hid_center_y,
+                self.hid_center_y,
+                "HID stick center Y",
+                0..=255,
+            )?,
+        })
+    }
+
+    pub(crate) fn to_config(self) -> AnalogConfig {
+        AnalogConfig {
+            direction_source: Some(self.direction_source),
+            direction_priority: Some(self.direction_priority),
+            deadzone_shape: Some(self.deadzone_shape),
+            xinput_stick_deadzone: Some(self.xinput_stick_deadzone),
+            xinput_trigger_threshold: Some(self.xinput_trigger_threshold),
+            xinput_center_x: Some(self.xinput_center_x),
+            xinput_center_y: Some(self.xinput_center_y),
+            hid_stick_deadzone: Some(self.hid_stick_deadzone),
+            hid_trigger_threshold: Some(self.hid_trigger_threshold),
+            hid_center_x: Some(self.hid_center_x),
+            hid_center_y: Some(self.hid_center_y),
+        }
+    }
+
+    pub(crate) fn direction_source(self) -> DirectionSource {
+        self.direction_source
+    }
+
+    /// The configured priority, with `Auto` replaced by `auto`, what it
+    /// stands for on the backend asking.
+    pub(crate) fn direction_priority(self, auto: DirectionPriority) -> DirectionPriority {
+        match self.direction_priority {
+            DirectionPriority::Auto => auto,
+            priority => priority,
+        }
+    }
+
+    /// Moves the stick centers to where the stick rested over `rest`, `(x, y)`
+    /// bytes laid out as in DS4 reports. `xinput` sets the XInput centers,
+    /// converting back to its signed axes with Y up, instead of the byte ones.
+    pub(crate) fn calibrated(self, rest: &[(u8, u8)], xinput: bool) -> Result<Self, String> {
+        if rest.is_empty() {
+            return Err("The controller reported no stick position to calibrate.".to_string());
+        }
+        let axis = |values: Vec<u8>| -> Result<f64, String> {
+            let spread = values.iter().max().copied().unwrap_or_default()
+                - values.iter().min().copied().unwrap_or_default();
+            if spread > CALIBRATION_MAX_SPREAD {
+                return Err("The stick moved during calibration; leave it at rest.".to_string());
+            }
+            let mean =
+                values.iter().map(|&value| f64::from(value)).sum::<f64>() / values.len() as f64;
+            if (mean - 127.5).abs() > CALIBRATION_MAX_OFFSET {
+                return Err(
+                    "The stick rests too far from center to calibrate; release it.".to_string(),
+                );
+            }
+            Ok(mean)
+        };
+        let x = axis(rest.iter().map(|&(x, _)| x).collect())?;
+        let y = axis(rest.iter().map(|&(_, y)| y).collect())?;
+
+        let mut settings = self;
+        if xinput {
+            // Inverse of the byte form, which keeps the top 8 bits.
+            let signed = |byte: f64| (byte * 256.0 - 32_640.0).round() as i32;
+            settings.xinput_center_x = signed(x);
+            settings.xinput_center_y = signed(255.0 - y);
+        } else {
+            settings.hid_center_x = x.round() as i32;
+            settings.hid_center_y = y.round() as i32;
+        }
+        Ok(settings)
+    }
+
+    /// XInput stick as `(horizontal, vertical)` in -1..=1, up positive.
+    #[cfg_attr(not(windows), allow(dead_code))]
+    pub(crate) fn xinput_stick(self, x: i16, y: i16) -> (i32, i32) {
+        let x = i32::from(x) - self.xinput_center_x;
+        let y = i32::from(y) - self.xinput_center_y;
+        if self.deadzone_shape == DeadzoneShape::Radial {
+            let unit = |offset: i32| f64::from(offset) / f64::from(i16::MAX);
+            return radial_direction((unit(x), unit(y)), unit(self.xinput_stick_deadzone));
+        }
+        let axis = |offset: i32| {
+            if offset > self.xinput_stick_deadzone {
+                1
+            } else if offset < -self.xinput_stick_deadzone {
+                -1
+            } else {
+                0
+            }
+        };
+        (axis(x), axis(y))
+    }
+
+    #[cfg_attr(not(windows), allow(dead_code))]
+    pub(crate) fn xinput_trigger_pressed(self, value: u8) -> bool {
+        i32::from(value) >= self.xinput_trigger_threshold
+    }
+
+    /// Stick as `(horizontal, vertical)` in -1..=1 for axes given as
+    /// `(value, minimum, maximum)`, each growing the way its axis does. The
+    /// byte settings are scaled onto each range (`0..=255` uses them as-is);
+    /// an axis with an empty range reads centered. A zero deadzone still
+    /// leaves the exact center neutral.
+    pub(crate) fn stick_direction(self, x: (i32, i32, i32), y: (i32, i32, i32)) -> (i32, i32) {
+        let (x_offset, x_deadzone, x_half_range) = self.axis_offset(x, false);
+        let (y_offset, y_deadzone, y_half_range) = self.axis_offset(y, true);
+        match self.deadzone_shape {
+            DeadzoneShape::Square => {
+                let axis = |offset: i64, deadzone: i64| {
+                    if offset >= deadzone.max(1) {
+                        1
+                    } else if offset <= -deadzone.max(1) {
+                        -1
+                    } else {
+                        0
+                    }
+                };
+                (axis(x_offset, x_deadzone), axis(y_offset, y_deadzone))
+            }
+            DeadzoneShape::Radial => radial_direction(
+                (
+                    x_offset as f64 / x_half_range as f64,
+                    y_offset as f64 / y_half_range as f64,
+                ),
+                self.hid_stick_deadzone as f64 / HID_HALF_RANGE as f64,
+            ),
+        }
+    }
+
+    /// Offset from the configured center, deadzone and half range of an axis,
+    /// all in its own units. `vertical` selects the Y center.
+    fn axis_offset(
+        self,
+        (value, minimum, maximum): (i32, i32, i32),
+        vertical: bool,
+    ) -> (i64, i64, i64) {
+        if maximum <= minimum {
+            return (0, 0, 1);
+        }
+        let half_range = ((i64::from(maximum) - i64::from(minimum)) / 2).max(1);
+        let center_byte = if vertical {
+            self.hid_center_y
+        } else {
+            self.hid_center_x
+        };
+        let center = (i64::from(minimum) + i64::from(maximum)) / 2
+            + (i64::from(center_byte) - HID_HALF_RANGE) * half_range / HID_HALF_RANGE;
+        let deadzone = half_range * i64::from(self.hid_stick_deadzone) / HID_HALF_RANGE;
+        (i64::from(value) - center, deadzone, half_range)
+    }
+
+    /// Whether a trigger spanning `minimum..=maximum` has travelled past the
+    /// byte threshold scaled to that range.
+    pub(crate) fn trigger_pressed(self, value: i32, minimum: i32, maximum: i32) -> bool {
+        let travel = i64::from(value) - i64::from(minimum);
+        let range = i64::from(maximum) - i64::from(minimum);
+        travel * HID_TRIGGER_RANGE >= range * i64::from(self.hid_trigger_threshold)
+    }
+}
+
+#[cfg(test)]
+impl AnalogSettings {
+    /// The defaults with another direction source and priority.
+    pub(crate) fn with_direction(
+        self,
+        direction_source: DirectionSource,
+        direction_priority: DirectionPriority,
+    ) -> Self {
+        Self {
+            direction_source,
+            direction_priority,
+            ..self
+        }
+    }
+}
+
+/// Neutral inside the circle of radius `deadzone`, otherwise the nearest of
+/// the eight directions, each owning a 45° sector. Offsets are fractions of
+/// the axis half range.
+fn radial_direction((x, y): (f64, f64), deadzone: f64) -> (i32, i32) {
+    let distance = x.hypot(y);
+    if distance == 0.0 || distance < deadzone {
+        return (0, 0);
+    }
+    // Half a sector off an axis, the other axis starts to count.
+    let threshold = distance * std::f64::consts::FRAC_PI_8.sin();
+    let axis = |offset: f64| {
+        if offset >= threshold {
+            1
+        } else if offset <= -threshold {
+            -1
+        } else {
+            0
+        }
+    };
+    (axis(x), axis(y))
+}
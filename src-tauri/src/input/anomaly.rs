@@ -0,0 +1,275 @@
+use std::collections::VecDeque;
+
+use serde::Serialize;
+
+use super::{emitter::PolledFrame, mask_to_sf6_buttons, SF6_BUTTON_ORDER};
+
+const DEFAULT_TOGGLE_FRAMES: u32 = 6;
+const DEFAULT_CHORD_REPEATS: u32 = 5;
+/// Frames kept for evidence; a longer pattern reports only its last frames.
+const MAX_EVIDENCE_FRAMES: usize = 180;
+/// Oldest anomalies are dropped beyond this so a turbo left running cannot
+/// grow the list without bound.
+const MAX_STORED_ANOMALIES: usize = 256;
+
+#[derive(Clone, Copy)]
+pub(crate) struct AnomalySettings {
+    /// A button changing state on more consecutive frames than this is
+    /// flagged as turbo.
+    pub toggle_frames: u32,
+    /// A chord pressed more times than this at an unchanging interval is
+    /// flagged as a macro.
+    pub chord_repeats: u32,
+}
+
+impl Default for AnomalySettings {
+    fn default() -> Self {
+        Self {
+            toggle_frames: DEFAULT_TOGGLE_FRAMES,
+            chord_repeats: DEFAULT_CHORD_REPEATS,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AnomalyPattern {
+    /// One button pressed and released on alternating frames.
+    Turbo,
+    /// The same multi-button chord pressed at a constant frame interval.
+    RepeatedChord,
+}
+
+#[derive(Clone, Serialize)]
+struct AnomalyEvidenceFrame {
+    frame: u64,
+    direction: u8,
+    buttons: Vec<String>,
+}
+
+/// Payload of `input/anomaly` and entry of `input_get_anomalies`.
+#[derive(Clone, Serialize)]
+pub struct InputAnomaly {
+    pattern: AnomalyPattern,
+    /// The toggling button, or every button of the chord.
+    buttons: Vec<String>,
+    start_frame: u64,
+    end_frame: u64,
+    /// Consecutive toggling frames for turbo, chord presses otherwise.
+    count: u32,
+    /// Frames between chord presses.
+    interval_frames: Option<u64>,
+    /// The frames of `start_frame..=end_frame`, so a flag can be checked
+    /// against what was actually polled.
+    evidence: Vec<AnomalyEvidenceFrame>,
+}
+
+/// Consecutive frames on which one button changed state.
+#[derive(Clone, Copy, Default)]
+struct ToggleRun {
+    start_frame: u64,
+    last_frame: u64,
+    frames: u32,
+}
+
+/// Presses of one chord at a constant interval.
+struct ChordRun {
+    mask: u16,
+    start_frame: u64,
+    last_press: u64,
+    interval: Option<u64>,
+    presses: u32,
+}
+
+/// Watches the SF6 buttons for cadences no player produces by hand. Patterns
+/// are reported once they end, so each report covers the whole run.
+#[derive(Default)]
+pub(crate) struct AnomalyDetector {
+    settings: AnomalySettings,
+    previous_mask: u16,
+    previous_frame: Option<u64>,
+    toggles: [ToggleRun; SF6_BUTTON_ORDER.len()],
+    chord: Option<ChordRun>,
+    recent: VecDeque<(u64, u8, u16)>,
+    anomalies: Vec<InputAnomaly>,
+}
+
+impl AnomalyDetector {
+    /// Returns the anomalies that ended with this frame.
+    pub(crate) fn push(
+        &mut self,
+        frame: &PolledFrame,
+        settings: AnomalySettings,
+    ) -> Vec<InputAnomaly> {
+        self.settings = settings;
+        if self.recent.len() == MAX_EVIDENCE_FRAMES {
+            self.recent.pop_front();
+        }
+        self.recent
+            .push_back((frame.frame, frame.sample.direction, frame.sf6_mask));
+
+        let mut found = Vec::new();
+        let index = frame.frame;
+        let mask = frame.sf6_mask;
+        let changed = mask ^ self.previous_mask;
+        let pressed = mask & !self.previous_mask;
+        // A skipped frame breaks any every-frame pattern.
+        let contiguous = self.previous_frame == index.checked_sub(1);
+        self.previous_mask = mask;
+        self.previous_frame = Some(index);
+
+        for button in 0..self.toggles.len() {
+            let run = self.toggles[button];
+            let bit = 1u16 << button;
+            if changed & bit != 0 && contiguous && run.frames > 0 && run.last_frame + 1 == index {
+                self.toggles[button].frames += 1;
+                self.toggles[button].last_frame = index;
+                continue;
+            }
+
+            self.close_toggle(button, &mut found);
+            if changed & bit != 0 {
+                self.toggles[button] = ToggleRun {
+                    start_frame: index,
+                    last_frame: index,
+                    frames: 1,
+                };
+            }
+        }
+
+        if self
+            .chord
+            .as_ref()
+            .and_then(|run| run.interval.map(|interval| run.last_press + interval))
+            .is_some_and(|expected| index > expected)
+        {
+            self.close_chord(&mut found);
+        }
+        if pressed.count_ones() >= 2 {
+            self.push_chord(pressed, index, &mut found);
+        }
+
+        self.store(&found);
+        found
+    }
+
+    /// Reports the patterns still running when the worker stops.
+    pub(crate) fn finish(&mut self) -> Vec<InputAnomaly> {
+        let mut found = Vec::new();
+        for button in 0..self.toggles.len() {
+            self.close_toggle(button, &mut found);
+        }
+        self.close_chord(&mut found);
+        self.previous_frame = None;
+        self.store(&found);
+        found
+    }
+
+    pub(crate) fn anomalies(&self) -> Vec<InputAnomaly> {
+        self.anomalies.clone()
+    }
+
+    /// Forgets every run and stored anomaly.
+    pub(crate) fn reset(&mut self) {
+        *self = Self::default();
+    }
+
+    fn push_chord(&mut self, mask: u16, frame: u64, found: &mut Vec<InputAnomaly>) {
+        if let Some(run) = self.chord.as_mut().filter(|run| run.mask == mask) {
+            let interval = frame - run.last_press;
+            match run.interval {
+                Some(expected) if expected == interval => run.presses += 1,
+                Some(_) => {
+                    // The cadence changed; the run restarts at the previous press.
+                    let start_frame = run.last_press;
+                    self.close_chord(found);
+                    self.chord = Some(ChordRun {
+                        mask,
+                        start_frame,
+                        last_press: frame,
+                        interval: Some(interval),
+                        presses: 2,
+                    });
+                    return;
+                }
+                None => {
+                    run.interval = Some(interval);
+                    run.presses += 1;
+                }
+            }
+            run.last_press = frame;
+            return;
+        }
+
+        self.close_chord(found);
+        self.chord = Some(ChordRun {
+            mask,
+            start_frame: frame,
+            last_press: frame,
+            interval: None,
+            presses: 1,
+        });
+    }
+
+    fn close_toggle(&mut self, button: usize, found: &mut Vec<InputAnomaly>) {
+        let run = std::mem::take(&mut self.toggles[button]);
+        if run.frames > self.settings.toggle_frames {
+            found.push(self.anomaly(
+                AnomalyPattern::Turbo,
+                1 << button,
+                (run.start_frame, run.last_frame),
+                run.frames,
+                None,
+            ));
+        }
+    }
+
+    fn close_chord(&mut self, found: &mut Vec<InputAnomaly>) {
+        let Some(run) = self.chord.take() else {
+            return;
+        };
+        if run.presses > self.settings.chord_repeats {
+            found.push(self.anomaly(
+                AnomalyPattern::RepeatedChord,
+                run.mask,
+                (run.start_frame, run.last_press),
+                run.presses,
+                run.interval,
+            ));
+        }
+    }
+
+    fn anomaly(
+        &self,
+        pattern: AnomalyPattern,
+        mask: u16,
+        (start_frame, end_frame): (u64, u64),
+        count: u32,
+        interval_frames: Option<u64>,
+    ) -> InputAnomaly {
+        InputAnomaly {
+            pattern,
+            buttons: mask_to_sf6_buttons(mask),
+            start_frame,
+            end_frame,
+            count,
+            interval_frames,
+            evidence: self
+                .recent
+                .iter()
+                .filter(|(frame, ..)| (start_frame..=end_frame).contains(frame))
+                .map(|&(frame, direction, sf6_mask)| AnomalyEvidenceFrame {
+                    frame,
+                    direction,
+                    buttons: mask_to_sf6_buttons(sf6_mask),
+                })
+                .collect(),
+        }
+    }
+
+    fn store(&mut self, found: &[InputAnomaly]) {
+        self.anomalies.extend_from_slice(found);
+        let excess = self.anomalies.len().saturating_sub(MAX_STORED_ANOMALIES);
+        self.anomalies.drain(..excess);
+    }
+}
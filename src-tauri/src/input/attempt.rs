@@ -0,0 +1,517 @@
+//! Splits the input stream into attempts, so an hour spent grinding one
+//! combo comes back as separate tries rather than one long run.
+
+use std::collections::VecDeque;
+
+use serde::{Deserialize, Serialize};
+
+use super::{
+    combo::ComboResultPayload,
+    emitter::PolledFrame,
+    mapping::name_to_mask,
+    motion::MotionSettings,
+    notation::{notation, InputNotation, DEFAULT_NEUTRAL_GAP_FRAMES},
+    SF6_BUTTON_ORDER,
+};
+
+const DEFAULT_TRIGGER_NEUTRAL_FRAMES: u64 = 60;
+const DEFAULT_NEUTRAL_TIMEOUT_FRAMES: u64 = 60;
+/// Ten seconds, longer than any real combo.
+const DEFAULT_MAX_FRAMES: u64 = 600;
+const MAX_ATTEMPT_FRAMES: u64 = 3_600;
+/// Finished attempts `input_get_history` can still look up.
+const MAX_RANGES: usize = 256;
+
+/// `{"kind": "press", "buttons": ["MK"]}` or
+/// `{"kind": "after_neutral", "frames": 60}`.
+#[derive(Clone, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum AttemptTriggerConfig {
+    /// A press of any of the SF6 `buttons`, e.g. the combo's first button.
+    Press { buttons: Vec<String> },
+    /// Any input after at least `frames` of neutral, 60 by default.
+    AfterNeutral {
+        #[serde(default)]
+        frames: Option<u64>,
+    },
+}
+
+#[derive(Clone, Copy)]
+enum AttemptTrigger {
+    Press(u16),
+    AfterNeutral(u64),
+}
+
+#[derive(Clone, Copy)]
+pub(crate) struct AttemptSettings {
+    trigger: AttemptTrigger,
+    /// Neutral frames after which an attempt ends.
+    neutral_timeout_frames: u64,
+    /// Length at which an attempt is cut off even while input goes on.
+    max_frames: u64,
+}
+
+impl AttemptSettings {
+    pub(crate) fn from_config(
+        trigger: &AttemptTriggerConfig,
+        neutral_timeout_frames: Option<u64>,
+        max_frames: Option<u64>,
+    ) -> Result<Self, String> {
+        let trigger = match trigger {
+            AttemptTriggerConfig::Press { buttons } => {
+                let mut mask = 0u16;
+                for button in buttons {
+                    mask |= name_to_mask(&SF6_BUTTON_ORDER, button).ok_or_else(|| {
+                        format!("Unknown SF6 button '{button}' in attempt trigger.")
+                    })?;
+                }
+                if mask == 0 {
+                    return Err("Attempt trigger needs at least one button.".to_string());
+                }
+                AttemptTrigger::Press(mask)
+            }
+            AttemptTriggerConfig::AfterNeutral { frames } => {
+                let frames = frames.unwrap_or(DEFAULT_TRIGGER_NEUTRAL_FRAMES);
+                if frames == 0 {
+                    return Err("Attempt trigger needs at least 1 neutral frame.".to_string());
+                }
+                AttemptTrigger::AfterNeutral(frames)
+            }
+        };
+
+        let neutral_timeout_frames =
+            neutral_timeout_frames.unwrap_or(DEFAULT_NEUTRAL_TIMEOUT_FRAMES);
+        if neutral_timeout_frames == 0 {
+            return Err("Attempt neutral timeout must be at least 1 frame.".to_string());
+        }
+        let max_frames = max_frames.unwrap_or(DEFAULT_MAX_FRAMES);
+        if !(1..=MAX_ATTEMPT_FRAMES).contains(&max_frames) {
+            return Err(format!(
+                "Attempt length must be between 1 and {MAX_ATTEMPT_FRAMES} frames."
+            ));
+        }
+
+        Ok(Self {
+            trigger,
+            neutral_timeout_frames,
+            max_frames,
+        })
+    }
+}
+
+/// Payload of `input/attempt_start`.
+#[derive(Clone, Serialize)]
+pub struct AttemptStartPayload {
+    attempt_id: u64,
+    frame: u64,
+}
+
+#[derive(Clone, Serialize)]
+pub struct AttemptSummary {
+    start_frame: u64,
+    end_frame: u64,
+    /// Cut off at the maximum length rather than ended by neutral.
+    truncated: bool,
+    notation: InputNotation,
+    /// Last result of the armed combo trial during the attempt; `None` when
+    /// no trial is armed or it judged nothing.
+    combo: Option<ComboResultPayload>,
+}
+
+/// Payload of `input/attempt_end`.
+#[derive(Clone, Serialize)]
+pub struct AttemptEndPayload {
+    attempt_id: u64,
+    /// From the first frame to the last one with input, trailing neutral
+    /// excluded.
+    frames: u64,
+    summary: AttemptSummary,
+}
+
+impl AttemptEndPayload {
+    pub(crate) fn combo_success(&self) -> Option<bool> {
+        self.summary.combo.as_ref().map(ComboResultPayload::success)
+    }
+}
+
+pub(crate) enum AttemptEvent {
+    Start(AttemptStartPayload),
+    End(AttemptEndPayload),
+}
+
+struct ActiveAttempt {
+    id: u64,
+    start_frame: u64,
+    frames: Vec<PolledFrame>,
+    combo: Option<ComboResultPayload>,
+}
+
+/// Frames an attempt covered, for `input_get_history`.
+#[derive(Clone, Copy)]
+struct AttemptRange {
+    id: u64,
+    start_frame: u64,
+    end_frame: u64,
+}
+
+/// Worker-fed segmenter. Ids keep counting across runs, so an id never
+/// names two attempts; ranges are dropped with the history they point into.
+#[derive(Default)]
+pub(crate) struct AttemptTracker {
+    next_id: u64,
+    active: Option<ActiveAttempt>,
+    /// Last frame off neutral; `None` when the run has had no input yet.
+    last_input_frame: Option<u64>,
+    ranges: VecDeque<AttemptRange>,
+}
+
+impl AttemptTracker {
+    /// Records the frame. `combo` is the combo result judged on it, if any.
+    pub(crate) fn push(
+        &mut self,
+        frame: &PolledFrame,
+        settings: Option<AttemptSettings>,
+        motion: MotionSettings,
+        combo: Option<&ComboResultPayload>,
+    ) -> Vec<AttemptEvent> {
+        let mut events = Vec::new();
+        let input = frame.sample.direction != 5 || frame.sf6_mask != 0;
+        let neutral_before = self
+            .last_input_frame
+            .map_or(u64::MAX, |last| frame.frame.saturating_sub(last + 1));
+        if input {
+            self.last_input_frame = Some(frame.frame);
+        }
+
+        let Some(settings) = settings else {
+            self.finish(motion, &mut events);
+            return events;
+        };
+
+        if let Some(attempt) = self.active.as_mut() {
+            attempt.frames.push(*frame);
+            if let Some(result) = combo {
+                attempt.combo = Some(result.clone());
+            }
+            let start_frame = attempt.start_frame;
+            if !input && neutral_before.saturating_add(1) >= settings.neutral_timeout_frames {
+                self.finish(motion, &mut events);
+            } else if frame.frame - start_frame + 1 >= settings.max_frames {
+                self.end(frame.frame, true, motion, &mut events);
+            }
+            return events;
+        }
+
+        let triggered = match settings.trigger {
+            AttemptTrigger::Press(mask) => frame.sf6_mask & !frame.previous_sf6_mask & mask != 0,
+            AttemptTrigger::AfterNeutral(frames) => input && neutral_before >= frames,
+        };
+        if triggered {
+            let id = self.next_id;
+            self.next_id += 1;
+            self.active = Some(ActiveAttempt {
+                id,
+                start_frame: frame.frame,
+                frames: vec![*frame],
+                combo: None,
+            });
+            events.push(AttemptEvent::Start(AttemptStartPayload {
+                attempt_id: id,
+                frame: frame.frame,
+            }));
+        }
+        events
+    }
+
+    /// Ends the open attempt, if any, at its last frame with input.
+    pub(crate) fn finish(&mut self, motion: MotionSettings, events: &mut Vec<AttemptEvent>) {
+        let Some(start_frame) = self.active.as_ref().map(|attempt| attempt.start_frame) else {
+            return;
+        };
+        let end_frame = self
+            .last_input_frame
+            .map_or(start_frame, |last| last.max(start_frame));
+        self.end(end_frame, false, motion, events);
+    }
+
+    fn end(
+        &mut self,
+        end_frame: u64,
+        truncated: bool,
+        motion: MotionSettings,
+        events: &mut Vec<AttemptEvent>,
+    ) {
+        let Some(attempt) = self.active.take() else {
+            return;
+        };
+        if self.ranges.len() == MAX_RANGES {
+            self.ranges.pop_front();
+        }
+        self.ranges.push_back(AttemptRange {
+            id: attempt.id,
+            start_frame: attempt.start_frame,
+            end_frame,
+        });
+
+        let frames = attempt
+            .frames
+            .iter()
+            .filter(|frame| frame.frame <= end_frame);
+        events.push(AttemptEvent::End(AttemptEndPayload {
+            attempt_id: attempt.id,
+            frames: end_frame - attempt.start_frame + 1,
+            summary: AttemptSummary {
+                start_frame: attempt.start_frame,
+                end_frame,
+                truncated,
+                notation: notation(frames, motion, DEFAULT_NEUTRAL_GAP_FRAMES),
+                combo: attempt.combo,
+            },
+        }));
+    }
+
+    /// First and last frame of attempt `id`; an open attempt runs to the
+    /// newest frame.
+    pub(crate) fn range(&self, id: u64) -> Option<(u64, u64)> {
+        if let Some(attempt) = self.active.as_ref().filter(|attempt| attempt.id == id) {
+            return Some((attempt.start_frame, u64::MAX));
+        }
+        self.ranges
+            .iter()
+            .find(|range| range.id == id)
+            .map(|range| (range.start_frame, range.end_frame))
+    }
+
+    /// Forgets the previous run. Frame numbers restart with every run, so
+    /// its ranges would point at the wrong frames.
+    pub(crate) fn reset(&mut self) {
+        *self = Self {
+            next_id: self.next_id,
+            ..Self::default()
+        };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::input::{clock::FrameTimestamp, emitter::ButtonHoldTracker, InputSample};
+
+    const LP: u16 = 1 << 0;
+    const MK: u16 = 1 << 4;
+
+    #[derive(Debug, PartialEq)]
+    enum Seen {
+        Start {
+            id: u64,
+            frame: u64,
+        },
+        End {
+            id: u64,
+            frames: u64,
+            truncated: bool,
+        },
+    }
+
+    /// Feeds runs of `(direction, frames, sf6_mask)` one frame at a time,
+    /// numbering frames from 0 across calls.
+    #[derive(Default)]
+    struct Feed {
+        tracker: AttemptTracker,
+        holds: ButtonHoldTracker,
+        frame: u64,
+        notations: Vec<String>,
+    }
+
+    impl Feed {
+        fn run(&mut self, settings: Option<AttemptSettings>, runs: &[(u8, u64, u16)]) -> Vec<Seen> {
+            let mut seen = Vec::new();
+            for &(direction, frames, mask) in runs {
+                for _ in 0..frames {
+                    let sample = InputSample::new(FrameTimestamp::default(), direction, 0);
+                    let mut frame = PolledFrame::new(self.frame, sample, mask, true);
+                    self.holds.track(&mut frame);
+                    let events =
+                        self.tracker
+                            .push(&frame, settings, MotionSettings::default(), None);
+                    seen.extend(events.into_iter().map(|event| self.seen(event)));
+                    self.frame += 1;
+                }
+            }
+            seen
+        }
+
+        fn seen(&mut self, event: AttemptEvent) -> Seen {
+            match event {
+                AttemptEvent::Start(start) => Seen::Start {
+                    id: start.attempt_id,
+                    frame: start.frame,
+                },
+                AttemptEvent::End(end) => {
+                    self.notations.push(end.summary.notation.text().to_string());
+                    Seen::End {
+                        id: end.attempt_id,
+                        frames: end.frames,
+                        truncated: end.summary.truncated,
+                    }
+                }
+            }
+        }
+    }
+
+    fn press(buttons: &[&str], neutral_timeout: u64, max_frames: u64) -> AttemptSettings {
+        let trigger = AttemptTriggerConfig::Press {
+            buttons: buttons.iter().map(|button| button.to_string()).collect(),
+        };
+        AttemptSettings::from_config(&trigger, Some(neutral_timeout), Some(max_frames)).unwrap()
+    }
+
+    fn after_neutral(frames: u64, neutral_timeout: u64) -> AttemptSettings {
+        let trigger = AttemptTriggerConfig::AfterNeutral {
+            frames: Some(frames),
+        };
+        AttemptSettings::from_config(&trigger, Some(neutral_timeout), None).unwrap()
+    }
+
+    #[test]
+    fn press_trigger_starts_on_the_button_and_ends_after_neutral() {
+        let settings = Some(press(&["MK"], 5, 600));
+        let mut feed = Feed::default();
+        // LP does not trigger; MK on frame 13 does, and 5 neutral frames end
+        // the attempt at the last frame with input.
+        let seen = feed.run(
+            settings,
+            &[
+                (5, 10, 0),
+                (5, 2, LP),
+                (5, 1, 0),
+                (5, 3, MK),
+                (2, 4, 0),
+                (5, 5, 0),
+            ],
+        );
+        assert_eq!(
+            seen,
+            [
+                Seen::Start { id: 0, frame: 13 },
+                Seen::End {
+                    id: 0,
+                    frames: 7,
+                    truncated: false
+                },
+            ]
+        );
+        assert_eq!(feed.tracker.range(0), Some((13, 19)));
+    }
+
+    #[test]
+    fn neutral_shorter_than_the_timeout_keeps_the_attempt_open() {
+        let settings = Some(press(&["MK"], 5, 600));
+        let mut feed = Feed::default();
+        let seen = feed.run(settings, &[(5, 1, MK), (5, 4, 0), (5, 1, LP), (5, 4, 0)]);
+        assert_eq!(seen, [Seen::Start { id: 0, frame: 0 }]);
+        assert_eq!(feed.tracker.range(0), Some((0, u64::MAX)));
+
+        let seen = feed.run(settings, &[(5, 1, 0)]);
+        assert_eq!(
+            seen,
+            [Seen::End {
+                id: 0,
+                frames: 6,
+                truncated: false
+            }]
+        );
+    }
+
+    #[test]
+    fn after_neutral_trigger_needs_the_full_gap() {
+        let settings = Some(after_neutral(10, 3));
+        let mut feed = Feed::default();
+        // The first input of a run counts as after neutral.
+        let mut seen = feed.run(settings, &[(6, 2, 0), (5, 3, 0)]);
+        // 9 neutral frames since frame 1 are one short; 10 are enough.
+        seen.extend(feed.run(settings, &[(5, 6, 0), (6, 1, 0), (5, 10, 0), (6, 1, 0)]));
+        assert_eq!(
+            seen,
+            [
+                Seen::Start { id: 0, frame: 0 },
+                Seen::End {
+                    id: 0,
+                    frames: 2,
+                    truncated: false
+                },
+                Seen::Start { id: 1, frame: 22 },
+            ]
+        );
+    }
+
+    #[test]
+    fn held_input_is_cut_at_the_maximum_length() {
+        let settings = Some(press(&["HP"], 5, 10));
+        let mut feed = Feed::default();
+        // HP stays held, so no new press starts a second attempt.
+        let seen = feed.run(settings, &[(5, 25, 1 << 2)]);
+        assert_eq!(
+            seen,
+            [
+                Seen::Start { id: 0, frame: 0 },
+                Seen::End {
+                    id: 0,
+                    frames: 10,
+                    truncated: true
+                },
+            ]
+        );
+        assert_eq!(feed.tracker.range(0), Some((0, 9)));
+    }
+
+    #[test]
+    fn clearing_the_trigger_ends_the_open_attempt() {
+        let settings = Some(press(&["MK"], 60, 600));
+        let mut feed = Feed::default();
+        feed.run(settings, &[(5, 2, MK), (5, 3, 0)]);
+        let seen = feed.run(None, &[(5, 1, 0)]);
+        assert_eq!(
+            seen,
+            [Seen::End {
+                id: 0,
+                frames: 2,
+                truncated: false
+            }]
+        );
+        assert_eq!(feed.notations, ["MK"]);
+    }
+
+    #[test]
+    fn ids_keep_counting_across_runs() {
+        let settings = Some(press(&["MK"], 2, 600));
+        let mut feed = Feed::default();
+        feed.run(settings, &[(5, 1, MK), (5, 2, 0)]);
+        feed.tracker.reset();
+        feed.frame = 0;
+        let seen = feed.run(settings, &[(5, 1, MK)]);
+        assert_eq!(seen, [Seen::Start { id: 1, frame: 0 }]);
+        assert_eq!(feed.tracker.range(0), None);
+    }
+
+    #[test]
+    fn rejects_invalid_settings() {
+        let unknown = AttemptTriggerConfig::Press {
+            buttons: vec!["XP".to_string()],
+        };
+        assert!(AttemptSettings::from_config(&unknown, None, None).is_err());
+        let empty = AttemptTriggerConfig::Press {
+            buttons: Vec::new(),
+        };
+        assert!(AttemptSettings::from_config(&empty, None, None).is_err());
+        let no_gap = AttemptTriggerConfig::AfterNeutral { frames: Some(0) };
+        assert!(AttemptSettings::from_config(&no_gap, None, None).is_err());
+
+        let trigger = AttemptTriggerConfig::AfterNeutral { frames: None };
+        assert!(AttemptSettings::from_config(&trigger, Some(0), None).is_err());
+        assert!(AttemptSettings::from_config(&trigger, None, Some(0)).is_err());
+        let too_long = Some(MAX_ATTEMPT_FRAMES + 1);
+        assert!(AttemptSettings::from_config(&trigger, None, too_long).is_err());
+        assert!(AttemptSettings::from_config(&trigger, None, None).is_ok());
+    }
+}
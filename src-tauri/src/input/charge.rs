@@ -0,0 +1,134 @@
+use serde::Serialize;
+
+use super::{emitter::PolledFrame, mask_to_sf6_buttons, motion::ATTACK_BUTTON_MASK};
+
+const DEFAULT_THRESHOLD_FRAMES: u64 = 45;
+const DEFAULT_RETENTION_FRAMES: u64 = 2;
+pub(crate) const MAX_RETENTION_FRAMES: u64 = 10;
+
+#[derive(Clone, Copy)]
+pub(crate) struct ChargeSettings {
+    /// Charge a release needs to be reported on `input/charge_release`.
+    pub threshold_frames: u64,
+    /// Frames after letting go of the charge direction that the charge is
+    /// still kept, SF6's small leniency for going from charge to release.
+    pub retention_frames: u64,
+}
+
+impl Default for ChargeSettings {
+    fn default() -> Self {
+        Self {
+            threshold_frames: DEFAULT_THRESHOLD_FRAMES,
+            retention_frames: DEFAULT_RETENTION_FRAMES,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ChargeAxis {
+    /// Held with 1, 4 or 7, released with 3, 6 or 9.
+    Back,
+    /// Held with 1, 2 or 3, released with 7, 8 or 9.
+    Down,
+}
+
+/// Charge held on each axis, in frames; part of `InputFramePayload`. A
+/// charge let go is still counted during the retention window.
+#[derive(Clone, Copy, Default, Serialize)]
+pub struct ChargeFrames {
+    back: u64,
+    down: u64,
+}
+
+/// Payload of `input/charge_release`: a charge of at least the threshold
+/// followed by the opposite direction and an attack button.
+#[derive(Clone, Serialize)]
+pub struct InputChargeReleasePayload {
+    axis: ChargeAxis,
+    charge_frames: u64,
+    direction: u8,
+    buttons: Vec<String>,
+    frame: u64,
+}
+
+#[derive(Clone, Copy, Default)]
+struct AxisCharge {
+    frames: u64,
+    /// Frame the charge direction was let go on, while still retained.
+    released_at: Option<u64>,
+}
+
+impl AxisCharge {
+    fn push(&mut self, frame: u64, charging: bool, retention_frames: u64) {
+        if charging {
+            self.frames += 1;
+            self.released_at = None;
+        } else if self.frames > 0 {
+            let released_at = *self.released_at.get_or_insert(frame);
+            if frame - released_at > retention_frames {
+                *self = Self::default();
+            }
+        }
+    }
+}
+
+/// Counts charge from the facing-relative direction, so after mirroring back
+/// is always away from the opponent.
+#[derive(Default)]
+pub(crate) struct ChargeTracker {
+    back: AxisCharge,
+    down: AxisCharge,
+}
+
+impl ChargeTracker {
+    /// Sets `frame.charge` and returns the releases on this frame.
+    pub(crate) fn push(
+        &mut self,
+        frame: &mut PolledFrame,
+        settings: ChargeSettings,
+    ) -> Vec<InputChargeReleasePayload> {
+        let direction = frame.sample.direction;
+        let index = frame.frame;
+        self.back.push(
+            index,
+            matches!(direction, 1 | 4 | 7),
+            settings.retention_frames,
+        );
+        self.down
+            .push(index, matches!(direction, 1..=3), settings.retention_frames);
+        frame.charge = ChargeFrames {
+            back: self.back.frames,
+            down: self.down.frames,
+        };
+
+        let pressed = frame.sf6_mask & !frame.previous_sf6_mask & ATTACK_BUTTON_MASK;
+        if pressed == 0 {
+            return Vec::new();
+        }
+
+        let mut releases = Vec::new();
+        for (axis, charge, released) in [
+            (
+                ChargeAxis::Back,
+                &mut self.back,
+                matches!(direction, 3 | 6 | 9),
+            ),
+            (ChargeAxis::Down, &mut self.down, matches!(direction, 7..=9)),
+        ] {
+            if !released || charge.frames < settings.threshold_frames {
+                continue;
+            }
+            releases.push(InputChargeReleasePayload {
+                axis,
+                charge_frames: charge.frames,
+                direction,
+                buttons: mask_to_sf6_buttons(pressed),
+                frame: index,
+            });
+            // The special move spends the charge.
+            *charge = AxisCharge::default();
+        }
+        releases
+    }
+}
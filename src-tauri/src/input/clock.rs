@@ -0,0 +1,136 @@
+use std::time::{Instant, SystemTime, UNIX_EPOCH};
+
+/// Monotonic time base for one worker run.
+///
+/// The wall clock is read exactly once when the clock starts; every later
+/// timestamp is that anchor plus the `Instant` elapsed since, so timestamps
+/// never go backwards and stay integer-exact however long the session runs.
+#[derive(Clone, Copy)]
+pub(crate) struct FrameClock {
+    started_at: Instant,
+    epoch_us: u64,
+}
+
+/// A single read of the [`FrameClock`], taken once at the start of a tick and
+/// shared by every sample produced during that tick.
+#[derive(Clone, Copy, Default, PartialEq, Eq)]
+pub(crate) struct FrameTimestamp {
+    epoch_us: u64,
+    elapsed_us: u64,
+}
+
+impl FrameClock {
+    pub(crate) fn start() -> Self {
+        let epoch_us = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|duration| duration.as_micros() as u64)
+            .unwrap_or(0);
+
+        Self {
+            started_at: Instant::now(),
+            epoch_us,
+        }
+    }
+
+    /// Wall-clock anchor of this run in microseconds since the Unix epoch.
+    pub(crate) fn epoch_us(&self) -> u64 {
+        self.epoch_us
+    }
+
+    pub(crate) fn now(&self) -> FrameTimestamp {
+        self.timestamp_at(Instant::now())
+    }
+
+    /// The reading for `instant`, taken elsewhere; instants before the start
+    /// read as the start.
+    pub(crate) fn timestamp_at(&self, instant: Instant) -> FrameTimestamp {
+        FrameTimestamp {
+            epoch_us: self.epoch_us,
+            elapsed_us: instant
+                .saturating_duration_since(self.started_at)
+                .as_micros() as u64,
+        }
+    }
+}
+
+impl FrameTimestamp {
+    pub(crate) fn timestamp_us(self) -> u64 {
+        self.epoch_us.saturating_add(self.elapsed_us)
+    }
+
+    pub(crate) fn timestamp_ms(self) -> u64 {
+        self.timestamp_us() / 1_000
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+    use crate::input::{tick_sample, InputSample, DISCONNECT_POLL_ERRORS};
+
+    #[test]
+    fn a_failed_poll_reads_neutral_at_the_tick_timestamp() {
+        let clock = FrameClock::start();
+        let now = clock.now();
+        let mut poll_errors = 0;
+
+        let polled = tick_sample(Ok(InputSample::new(now, 6, 0)), now, &mut poll_errors);
+        std::thread::sleep(Duration::from_millis(2));
+        let fallback = tick_sample(Err("read failed".into()), now, &mut poll_errors).unwrap();
+
+        assert_eq!(polled.unwrap().timestamp_us, fallback.timestamp_us);
+        assert!(clock.now().timestamp_us() > fallback.timestamp_us);
+        assert_eq!((fallback.direction, fallback.down_mask), (5, 0));
+        assert_eq!(poll_errors, 1);
+    }
+
+    #[test]
+    fn failed_polls_in_a_row_drop_the_sample() {
+        let now = FrameClock::start().now();
+        let mut poll_errors = 0;
+
+        for _ in 1..DISCONNECT_POLL_ERRORS {
+            assert!(tick_sample(Err("read failed".into()), now, &mut poll_errors).is_some());
+        }
+        assert!(tick_sample(Err("read failed".into()), now, &mut poll_errors).is_none());
+        assert!(tick_sample(Ok(InputSample::neutral(now)), now, &mut poll_errors).is_some());
+        assert_eq!(poll_errors, 0);
+    }
+
+    #[test]
+    fn timestamps_follow_the_monotonic_clock_from_one_anchor() {
+        let clock = FrameClock::start();
+        let earlier = clock.now();
+        let later = clock.timestamp_at(clock.started_at + Duration::from_millis(5));
+
+        assert_eq!(earlier.epoch_us, later.epoch_us);
+        assert!(later.timestamp_us() >= earlier.timestamp_us());
+        assert_eq!(later.timestamp_us(), clock.epoch_us() + 5_000);
+    }
+
+    #[test]
+    fn anchor_keeps_microseconds_over_long_sessions() {
+        let clock = FrameClock {
+            started_at: Instant::now(),
+            epoch_us: 1_700_000_000_123_456,
+        };
+        let month = Duration::from_secs(30 * 24 * 60 * 60);
+        let reading = clock.timestamp_at(clock.started_at + month + Duration::from_micros(1));
+
+        assert_eq!(
+            reading.timestamp_us(),
+            1_700_000_000_123_456 + month.as_micros() as u64 + 1
+        );
+        assert_eq!(reading.timestamp_ms(), reading.timestamp_us() / 1_000);
+    }
+
+    #[test]
+    fn instants_before_the_start_read_as_the_anchor() {
+        let before = Instant::now();
+        let clock = FrameClock::start();
+
+        assert_eq!(clock.timestamp_at(before).timestamp_us(), clock.epoch_us());
+    }
+}
@@ -0,0 +1,383 @@
+use serde::{Deserialize, Serialize};
+
+use super::{mapping::name_to_mask, motion::motion_name, SF6_BUTTON_ORDER};
+
+const DEFAULT_STEP_MAX_FRAMES: u64 = 30;
+
+/// Serialized combo script, e.g.
+/// `{"name": "cr.MK xx DR", "steps": [{"direction": 2, "buttons": ["MK"]},
+/// {"buttons": ["MP", "MK"], "max_frames": 12}]}`.
+#[derive(Clone, Deserialize)]
+pub struct ComboScriptConfig {
+    #[serde(default)]
+    name: String,
+    steps: Vec<ComboStepConfig>,
+}
+
+#[derive(Clone, Deserialize)]
+struct ComboStepConfig {
+    /// Numpad direction that must be held when the step lands.
+    #[serde(default)]
+    direction: Option<u8>,
+    /// Motion (e.g. `"236"`) that must complete on the step's button press.
+    #[serde(default)]
+    motion: Option<String>,
+    /// SF6 buttons that must be down, at least one of them newly pressed.
+    /// A step without buttons lands when the stick moves to `direction`.
+    #[serde(default)]
+    buttons: Vec<String>,
+    /// Frames after the previous step within which this step must land;
+    /// `max_frames` defaults to 30. Both are ignored on the first step.
+    #[serde(default)]
+    min_frames: u64,
+    #[serde(default)]
+    max_frames: Option<u64>,
+    /// Index of a later step. This step's buttons must stay held until that
+    /// step lands, otherwise the attempt drops.
+    #[serde(default)]
+    hold_until: Option<usize>,
+}
+
+#[derive(Clone)]
+struct ComboStep {
+    direction: Option<u8>,
+    motion: Option<&'static str>,
+    buttons: u16,
+    min_frames: u64,
+    max_frames: u64,
+    hold_until: Option<usize>,
+}
+
+#[derive(Clone)]
+pub(crate) struct ComboScript {
+    name: String,
+    steps: Vec<ComboStep>,
+}
+
+impl ComboScript {
+    pub(crate) fn from_config(config: &ComboScriptConfig) -> Result<Self, String> {
+        if config.steps.is_empty() {
+            return Err("Combo script must contain at least one step.".to_string());
+        }
+
+        let mut steps = Vec::with_capacity(config.steps.len());
+        for (index, step) in config.steps.iter().enumerate() {
+            if let Some(direction) = step.direction {
+                if !(1..=9).contains(&direction) {
+                    return Err(format!(
+                        "Combo step {index} has direction {direction}; expected a numpad direction 1-9."
+                    ));
+                }
+            }
+
+            let mut buttons = 0u16;
+            for button in &step.buttons {
+                buttons |= name_to_mask(&SF6_BUTTON_ORDER, button).ok_or_else(|| {
+                    format!("Unknown SF6 button '{button}' in combo step {index}.")
+                })?;
+            }
+            if buttons == 0 && step.direction.is_none() {
+                return Err(format!(
+                    "Combo step {index} needs a direction or at least one button."
+                ));
+            }
+
+            let motion = match step.motion.as_deref() {
+                Some(motion) => {
+                    let motion = motion_name(motion).ok_or_else(|| {
+                        format!("Unknown motion '{motion}' in combo step {index}.")
+                    })?;
+                    if buttons == 0 {
+                        return Err(format!(
+                            "Combo step {index} requires motion {motion} but no button."
+                        ));
+                    }
+                    Some(motion)
+                }
+                None => None,
+            };
+
+            let max_frames = step.max_frames.unwrap_or(DEFAULT_STEP_MAX_FRAMES);
+            if step.min_frames > max_frames {
+                return Err(format!(
+                    "Combo step {index} has min_frames greater than max_frames."
+                ));
+            }
+
+            if let Some(hold_until) = step.hold_until {
+                if hold_until <= index || hold_until >= config.steps.len() {
+                    return Err(format!(
+                        "Combo step {index} holds until step {hold_until}, which is not a later step."
+                    ));
+                }
+                if buttons == 0 {
+                    return Err(format!(
+                        "Combo step {index} holds until step {hold_until} but has no buttons to hold."
+                    ));
+                }
+            }
+
+            steps.push(ComboStep {
+                direction: step.direction,
+                motion,
+                buttons,
+                min_frames: step.min_frames,
+                max_frames,
+                hold_until: step.hold_until,
+            });
+        }
+
+        Ok(Self {
+            name: config.name.clone(),
+            steps,
+        })
+    }
+}
+
+#[derive(Clone, Serialize)]
+pub struct ComboStepPayload {
+    step: usize,
+    hit: bool,
+    frame_delta: u64,
+}
+
+#[derive(Clone, Copy, Serialize)]
+pub struct ComboStepTiming {
+    step: usize,
+    hit: bool,
+    frame: u64,
+    /// Frames since the previous step landed; 0 for the first step.
+    frame_delta: u64,
+}
+
+#[derive(Clone, Serialize)]
+pub struct ComboResultPayload {
+    name: String,
+    success: bool,
+    /// Every step that was judged, ending with the missed one on failure.
+    steps: Vec<ComboStepTiming>,
+}
+
+impl ComboResultPayload {
+    pub(crate) fn success(&self) -> bool {
+        self.success
+    }
+}
+
+pub(crate) enum ComboEvent {
+    Step(ComboStepPayload),
+    Result(ComboResultPayload),
+}
+
+/// One frame as seen by the matcher.
+struct StepInput<'a> {
+    direction: u8,
+    direction_changed: bool,
+    mask: u16,
+    pressed: u16,
+    motions: &'a [&'static str],
+}
+
+impl ComboStep {
+    fn matches(&self, input: &StepInput) -> bool {
+        if self
+            .direction
+            .is_some_and(|direction| direction != input.direction)
+        {
+            return false;
+        }
+        if self.buttons == 0 {
+            return input.direction_changed;
+        }
+        if input.mask & self.buttons != self.buttons || input.pressed & self.buttons == 0 {
+            return false;
+        }
+        self.motion
+            .is_none_or(|motion| input.motions.contains(&motion))
+    }
+}
+
+/// A button that must stay down until step `until` lands.
+struct ComboHold {
+    mask: u16,
+    until: usize,
+}
+
+struct ComboAttempt {
+    next: usize,
+    last_hit_frame: u64,
+    holds: Vec<ComboHold>,
+    steps: Vec<ComboStepTiming>,
+}
+
+/// Per-frame state machine judging attempts at one script. An attempt starts
+/// whenever the first step lands while idle and ends on success or the first
+/// miss; seeing the first step again mid-attempt drops the old attempt and
+/// starts a new one on the same frame.
+pub(crate) struct ComboMatcher {
+    script: ComboScript,
+    previous_direction: u8,
+    previous_mask: u16,
+    attempt: Option<ComboAttempt>,
+}
+
+impl ComboMatcher {
+    fn new(script: ComboScript) -> Self {
+        Self {
+            script,
+            previous_direction: 5,
+            previous_mask: 0,
+            attempt: None,
+        }
+    }
+
+    fn push(
+        &mut self,
+        frame: u64,
+        direction: u8,
+        sf6_mask: u16,
+        motions: &[&'static str],
+    ) -> Vec<ComboEvent> {
+        let input = StepInput {
+            direction,
+            direction_changed: direction != self.previous_direction,
+            mask: sf6_mask,
+            pressed: sf6_mask & !self.previous_mask,
+            motions,
+        };
+        self.previous_direction = direction;
+        self.previous_mask = sf6_mask;
+
+        let mut events = Vec::new();
+        if let Some(attempt) = self.attempt.as_ref() {
+            let step = &self.script.steps[attempt.next];
+            let frame_delta = frame.saturating_sub(attempt.last_hit_frame);
+            let held = attempt
+                .holds
+                .iter()
+                .all(|hold| sf6_mask & hold.mask == hold.mask);
+
+            if held && frame_delta >= step.min_frames && step.matches(&input) {
+                self.hit(frame, frame_delta, &mut events);
+                return events;
+            }
+            if held && frame_delta <= step.max_frames && !self.script.steps[0].matches(&input) {
+                return events;
+            }
+            self.miss(frame, frame_delta, &mut events);
+        }
+
+        if self.script.steps[0].matches(&input) {
+            self.attempt = Some(ComboAttempt {
+                next: 0,
+                last_hit_frame: frame,
+                holds: Vec::new(),
+                steps: Vec::new(),
+            });
+            self.hit(frame, 0, &mut events);
+        }
+        events
+    }
+
+    fn hit(&mut self, frame: u64, frame_delta: u64, events: &mut Vec<ComboEvent>) {
+        let Some(attempt) = self.attempt.as_mut() else {
+            return;
+        };
+        let index = attempt.next;
+        let step = &self.script.steps[index];
+
+        attempt.holds.retain(|hold| hold.until != index);
+        if let Some(until) = step.hold_until {
+            attempt.holds.push(ComboHold {
+                mask: step.buttons,
+                until,
+            });
+        }
+        attempt.steps.push(ComboStepTiming {
+            step: index,
+            hit: true,
+            frame,
+            frame_delta,
+        });
+        attempt.next += 1;
+        attempt.last_hit_frame = frame;
+        events.push(ComboEvent::Step(ComboStepPayload {
+            step: index,
+            hit: true,
+            frame_delta,
+        }));
+
+        if attempt.next == self.script.steps.len() {
+            self.finish(true, events);
+        }
+    }
+
+    fn miss(&mut self, frame: u64, frame_delta: u64, events: &mut Vec<ComboEvent>) {
+        let Some(attempt) = self.attempt.as_mut() else {
+            return;
+        };
+        attempt.steps.push(ComboStepTiming {
+            step: attempt.next,
+            hit: false,
+            frame,
+            frame_delta,
+        });
+        events.push(ComboEvent::Step(ComboStepPayload {
+            step: attempt.next,
+            hit: false,
+            frame_delta,
+        }));
+        self.finish(false, events);
+    }
+
+    fn finish(&mut self, success: bool, events: &mut Vec<ComboEvent>) {
+        if let Some(attempt) = self.attempt.take() {
+            events.push(ComboEvent::Result(ComboResultPayload {
+                name: self.script.name.clone(),
+                success,
+                steps: attempt.steps,
+            }));
+        }
+    }
+}
+
+/// The loaded script and, while armed, its matcher.
+#[derive(Default)]
+pub(crate) struct ComboTrial {
+    script: Option<ComboScript>,
+    matcher: Option<ComboMatcher>,
+}
+
+impl ComboTrial {
+    /// Replaces the script. Any armed attempt of the previous one is dropped.
+    pub(crate) fn load(&mut self, script: ComboScript) {
+        self.script = Some(script);
+        self.matcher = None;
+    }
+
+    pub(crate) fn arm(&mut self) -> Result<(), String> {
+        let script = self
+            .script
+            .clone()
+            .ok_or_else(|| "No combo script is loaded.".to_string())?;
+        self.matcher = Some(ComboMatcher::new(script));
+        Ok(())
+    }
+
+    pub(crate) fn disarm(&mut self) {
+        self.matcher = None;
+    }
+
+    pub(crate) fn push(
+        &mut self,
+        frame: u64,
+        direction: u8,
+        sf6_mask: u16,
+        motions: &[&'static str],
+    ) -> Vec<ComboEvent> {
+        self.matcher
+            .as_mut()
+            .map(|matcher| matcher.push(frame, direction, sf6_mask, motions))
+            .unwrap_or_default()
+    }
+}
@@ -0,0 +1,193 @@
+//! Built-in controllers recognized by VID/PID: the report format each sends
+//! and the analog settings live input starts from until the user configures
+//! their own.
+
+use super::{
+    analog::{AnalogSettings, DEFAULT_HID_STICK_DEADZONE},
+    NativeInputDevice,
+};
+use PresetDecoder::{Ds4, DualSense, HoriSwitch, SwitchPro};
+
+/// Report format of a preset controller, which picks the HID decoder.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum PresetDecoder {
+    Ds4,
+    DualSense,
+    SwitchPro,
+    HoriSwitch,
+}
+
+#[cfg_attr(not(any(windows, target_os = "macos")), allow(dead_code))]
+pub(crate) struct ControllerPreset {
+    pub vendor_id: u16,
+    pub product_id: u16,
+    /// Stands in for a missing product string.
+    pub name: &'static str,
+    pub decoder: PresetDecoder,
+    pub analog: AnalogSettings,
+}
+
+const FIRST_PARTY: AnalogSettings = AnalogSettings::hid_preset(DEFAULT_HID_STICK_DEADZONE);
+/// Pro Controller sticks are prone to drift.
+const SWITCH_PRO: AnalogSettings = AnalogSettings::hid_preset(72);
+/// Third-party pad sticks tend to rest further off center.
+const THIRD_PARTY_PAD: AnalogSettings = AnalogSettings::hid_preset(68);
+/// Fight sticks in LS mode put the lever on full-throw stick axes, so a wide
+/// deadzone costs nothing and hides noise on a centered axis.
+const FIGHT_STICK: AnalogSettings = AnalogSettings::hid_preset(96);
+
+const fn preset(
+    vendor_id: u16,
+    product_id: u16,
+    name: &'static str,
+    decoder: PresetDecoder,
+    analog: AnalogSettings,
+) -> ControllerPreset {
+    ControllerPreset {
+        vendor_id,
+        product_id,
+        name,
+        decoder,
+        analog,
+    }
+}
+
+/// Third-party entries send DS4 reports in PS4 mode under their own VID/PID,
+/// often without "PS4" in the product string. 8BitDo pads need no entry: in
+/// Switch mode they identify as a Pro Controller.
+pub(crate) const CONTROLLER_PRESETS: [ControllerPreset; 25] = [
+    preset(0x054C, 0x05C4, "DUALSHOCK 4", Ds4, FIRST_PARTY),
+    preset(0x054C, 0x09CC, "DUALSHOCK 4", Ds4, FIRST_PARTY),
+    preset(0x054C, 0x0CE6, "DualSense", DualSense, FIRST_PARTY),
+    preset(0x054C, 0x0DF2, "DualSense Edge", DualSense, FIRST_PARTY),
+    preset(0x057E, 0x2009, "Pro Controller", SwitchPro, SWITCH_PRO),
+    // GP2040-CE emulates it in Switch mode.
+    preset(
+        0x0F0D,
+        0x0092,
+        "HORIPAD for Nintendo Switch",
+        HoriSwitch,
+        THIRD_PARTY_PAD,
+    ),
+    preset(0x0F0D, 0x0055, "HORIPAD 4 FPS", Ds4, THIRD_PARTY_PAD),
+    preset(
+        0x0F0D,
+        0x005E,
+        "HORI Fighting Commander 4",
+        Ds4,
+        THIRD_PARTY_PAD,
+    ),
+    preset(0x0F0D, 0x0066, "HORIPAD 4 FPS Plus", Ds4, THIRD_PARTY_PAD),
+    preset(
+        0x0F0D,
+        0x0084,
+        "HORI Fighting Commander",
+        Ds4,
+        THIRD_PARTY_PAD,
+    ),
+    preset(
+        0x0F0D,
+        0x0087,
+        "HORI Fighting Stick mini 4",
+        Ds4,
+        FIGHT_STICK,
+    ),
+    preset(0x0F0D, 0x008A, "HORI Real Arcade Pro 4", Ds4, FIGHT_STICK),
+    preset(
+        0x0F0D,
+        0x00EE,
+        "HORI Mini Wired Gamepad",
+        Ds4,
+        THIRD_PARTY_PAD,
+    ),
+    preset(0x0E6F, 0x0207, "Victrix Pro Fight Stick", Ds4, FIGHT_STICK),
+    preset(0x2C22, 0x2000, "Qanba Drone", Ds4, FIGHT_STICK),
+    preset(0x2C22, 0x2300, "Qanba Obsidian", Ds4, FIGHT_STICK),
+    preset(0x2C22, 0x2500, "Qanba Dragon", Ds4, FIGHT_STICK),
+    preset(
+        0x0738,
+        0x8250,
+        "Mad Catz FightPad Pro",
+        Ds4,
+        THIRD_PARTY_PAD,
+    ),
+    preset(
+        0x0738,
+        0x8384,
+        "Mad Catz FightStick TE S+",
+        Ds4,
+        FIGHT_STICK,
+    ),
+    preset(0x0738, 0x8480, "Mad Catz FightStick TE2", Ds4, FIGHT_STICK),
+    preset(0x0738, 0x8481, "Mad Catz FightStick TE2+", Ds4, FIGHT_STICK),
+    preset(0x1532, 0x1000, "Razer Raiju", Ds4, THIRD_PARTY_PAD),
+    preset(0x1532, 0x1008, "Razer Panthera Evo", Ds4, FIGHT_STICK),
+    preset(
+        0x146B,
+        0x0D01,
+        "Nacon Revolution Pro Controller",
+        Ds4,
+        THIRD_PARTY_PAD,
+    ),
+    preset(0x0C12, 0x0EF6, "Hit Box Arcade", Ds4, FIGHT_STICK),
+];
+
+/// The preset for `vendor_id`/`product_id`, if the controller has one.
+pub(crate) fn controller_preset(
+    vendor_id: u16,
+    product_id: u16,
+) -> Option<&'static ControllerPreset> {
+    CONTROLLER_PRESETS
+        .iter()
+        .find(|preset| (preset.vendor_id, preset.product_id) == (vendor_id, product_id))
+}
+
+/// Analog settings `device` starts from: its preset's, else the defaults.
+pub(crate) fn preset_analog(device: Option<&NativeInputDevice>) -> AnalogSettings {
+    device
+        .and_then(|device| device.vendor_id.zip(device.product_id))
+        .and_then(|(vendor_id, product_id)| controller_preset(vendor_id, product_id))
+        .map_or_else(AnalogSettings::default, |preset| preset.analog)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::input::NativeInputMode;
+
+    fn device(vendor_id: u16, product_id: u16) -> NativeInputDevice {
+        let mut device = NativeInputDevice::new(NativeInputMode::Hid, "path");
+        device.vendor_id = Some(vendor_id);
+        device.product_id = Some(product_id);
+        device
+    }
+
+    #[test]
+    fn presets_have_unique_ids() {
+        for (index, preset) in CONTROLLER_PRESETS.iter().enumerate() {
+            let ids = (preset.vendor_id, preset.product_id);
+            assert!(
+                CONTROLLER_PRESETS[index + 1..]
+                    .iter()
+                    .all(|other| (other.vendor_id, other.product_id) != ids),
+                "{} is listed twice",
+                preset.name
+            );
+        }
+    }
+
+    #[test]
+    fn analog_starts_from_the_preset() {
+        assert!(preset_analog(Some(&device(0x057E, 0x2009))) == SWITCH_PRO);
+        assert!(preset_analog(Some(&device(0x2C22, 0x2300))) == FIGHT_STICK);
+        assert!(preset_analog(Some(&device(0x054C, 0x0CE6))) == AnalogSettings::default());
+    }
+
+    #[test]
+    fn analog_falls_back_to_the_defaults() {
+        assert!(preset_analog(None) == AnalogSettings::default());
+        assert!(preset_analog(Some(&device(0x1234, 0x5678))) == AnalogSettings::default());
+        let unidentified = NativeInputDevice::new(NativeInputMode::XInput, "0");
+        assert!(preset_analog(Some(&unidentified)) == AnalogSettings::default());
+    }
+}
@@ -0,0 +1,1163 @@
+//! Pure decoding of controller reports into a direction and generic button
+//! mask. Device I/O stays in the platform backends; nothing here touches a
+//! device, so it builds on every platform whichever backend uses it.
+
+// The HID decoders run in the Windows and macOS backends, XInput on Windows
+// only.
+#![cfg_attr(not(windows), allow(dead_code))]
+
+use serde::Serialize;
+
+use super::{
+    analog::{AnalogSettings, DirectionPriority, DirectionSource},
+    direction_axes,
+    hid_profile::HidProfile,
+    to_direction, BUTTON_DPAD_DOWN_MASK, BUTTON_DPAD_LEFT_MASK, BUTTON_DPAD_RIGHT_MASK,
+    BUTTON_DPAD_UP_MASK, BUTTON_EAST_MASK, BUTTON_L1_MASK, BUTTON_L2_MASK, BUTTON_L3_MASK,
+    BUTTON_NORTH_MASK, BUTTON_R1_MASK, BUTTON_R2_MASK, BUTTON_R3_MASK, BUTTON_SELECT_MASK,
+    BUTTON_SOUTH_MASK, BUTTON_START_MASK, BUTTON_TOUCHPAD_MASK, BUTTON_WEST_MASK,
+};
+
+const XINPUT_GAMEPAD_DPAD_UP: u16 = 0x0001;
+const XINPUT_GAMEPAD_DPAD_DOWN: u16 = 0x0002;
+const XINPUT_GAMEPAD_DPAD_LEFT: u16 = 0x0004;
+const XINPUT_GAMEPAD_DPAD_RIGHT: u16 = 0x0008;
+const XINPUT_GAMEPAD_START: u16 = 0x0010;
+const XINPUT_GAMEPAD_BACK: u16 = 0x0020;
+const XINPUT_GAMEPAD_LEFT_THUMB: u16 = 0x0040;
+const XINPUT_GAMEPAD_RIGHT_THUMB: u16 = 0x0080;
+const XINPUT_GAMEPAD_LEFT_SHOULDER: u16 = 0x0100;
+const XINPUT_GAMEPAD_RIGHT_SHOULDER: u16 = 0x0200;
+const XINPUT_GAMEPAD_A: u16 = 0x1000;
+const XINPUT_GAMEPAD_B: u16 = 0x2000;
+const XINPUT_GAMEPAD_X: u16 = 0x4000;
+const XINPUT_GAMEPAD_Y: u16 = 0x8000;
+
+/// A DualSense paired over Bluetooth sends 78-byte reports ending in a
+/// little-endian CRC32 over a 0xA1 header byte and the rest of the report.
+const DUALSENSE_BT_REPORT_LEN: usize = 78;
+const DUALSENSE_BT_CRC_HEADER: u8 = 0xA1;
+
+/// Held state decoded from one report. Backends keep it until the next
+/// report and stamp it into an `InputSample` when polled.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub(crate) struct DecodedInput {
+    pub direction: u8,
+    pub down_mask: u32,
+    /// Left stick as `(x, y)` bytes laid out as in DS4 reports, before the
+    /// deadzone; `None` for devices without one.
+    pub left_stick: Option<(u8, u8)>,
+    /// Left stick direction through the deadzone, 5 without a left stick.
+    pub stick_direction: u8,
+    /// `None` for devices without a right stick.
+    pub right_stick: Option<RightStick>,
+    /// `None` for devices whose triggers are digital.
+    pub triggers: Option<AnalogTriggers>,
+}
+
+/// Right stick as last read. Modern-control players drive assisted specials
+/// with it, so it is reported whichever source `direction` comes from.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+pub struct RightStick {
+    /// Physical numpad direction through the configured deadzone, with 6
+    /// always right.
+    pub(crate) direction: u8,
+    /// Axes scaled to bytes as in DS4 reports: 128 centered, Y growing
+    /// downwards.
+    pub(crate) x: u8,
+    pub(crate) y: u8,
+}
+
+/// Raw L2/R2 travel, 0 released to 255 fully down, for overlays that show
+/// partial presses. The L2/R2 buttons apply the configured threshold to it.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+pub struct AnalogTriggers {
+    pub(crate) left: u8,
+    pub(crate) right: u8,
+}
+
+impl RightStick {
+    pub(crate) fn from_bytes((x, y): (u8, u8), analog: AnalogSettings) -> Self {
+        Self {
+            direction: direction_from_analog_stick((x, y), analog),
+            x,
+            y,
+        }
+    }
+}
+
+/// One report format. `None` means the report is not one this decoder
+/// understands, such as an unrelated report ID or a truncated read, and the
+/// previous state should be kept. Decoders take `&mut self` so formats that
+/// need state across reports fit the same trait.
+pub(crate) trait InputDecoder {
+    type Report: ?Sized;
+
+    fn decode(&mut self, report: &Self::Report, analog: AnalogSettings) -> Option<DecodedInput>;
+}
+
+/// DualShock 4-style input report, as sent by a DS4 and by GP2040-CE in PS4
+/// mode.
+pub(crate) struct Ds4Decoder<'a>(pub &'a mut DirectionArbiter);
+
+impl InputDecoder for Ds4Decoder<'_> {
+    type Report = [u8];
+
+    fn decode(&mut self, report: &[u8], analog: AnalogSettings) -> Option<DecodedInput> {
+        // Report ID 0x01 over USB. A DS4 paired over Bluetooth sends the same
+        // payload as report 0x11 behind two extra header bytes.
+        let payload = match report.first() {
+            Some(0x01) => report,
+            Some(0x11) if report.len() > 2 => &report[2..],
+            _ => return None,
+        };
+        if payload.len() < 10 {
+            return None;
+        }
+
+        Some(decode_sony_buttons(
+            [payload[5], payload[6], payload[7]],
+            [payload[8], payload[9]],
+            (payload[1], payload[2]),
+            (payload[3], payload[4]),
+            analog,
+            self.0,
+        ))
+    }
+}
+
+/// DualSense input report, over USB or Bluetooth. The flag is whether the
+/// pad is paired over Bluetooth.
+pub(crate) struct DualSenseDecoder<'a>(pub &'a mut DirectionArbiter, pub bool);
+
+impl InputDecoder for DualSenseDecoder<'_> {
+    type Report = [u8];
+
+    fn decode(&mut self, report: &[u8], analog: AnalogSettings) -> Option<DecodedInput> {
+        // Report ID 0x01 over USB: triggers sit before the buttons, which
+        // move to bytes 8-10 behind a sequence counter. Over Bluetooth the
+        // same payload arrives as report 0x31 behind an extra tag byte; one
+        // that fails its CRC is dropped rather than decoded into garbage. A
+        // Bluetooth pad whose calibration read failed keeps sending a reduced
+        // report 0x01, laid out as a DS4's.
+        let payload = match report.first() {
+            Some(0x01) if self.1 => return Ds4Decoder(self.0).decode(report, analog),
+            Some(0x01) => report,
+            Some(0x31) if dualsense_bluetooth_crc_matches(report) => &report[1..],
+            _ => return None,
+        };
+        if payload.len() < 11 {
+            return None;
+        }
+
+        Some(decode_sony_buttons(
+            [payload[8], payload[9], payload[10]],
+            [payload[5], payload[6]],
+            (payload[1], payload[2]),
+            (payload[3], payload[4]),
+            analog,
+            self.0,
+        ))
+    }
+}
+
+/// Switch Pro Controller standard full input report.
+pub(crate) struct SwitchProDecoder<'a>(pub &'a mut DirectionArbiter);
+
+impl InputDecoder for SwitchProDecoder<'_> {
+    type Report = [u8];
+
+    fn decode(&mut self, report: &[u8], analog: AnalogSettings) -> Option<DecodedInput> {
+        // Report ID 0x30: right, shared and left button bytes at 3-5 followed
+        // by the 12-bit left (6-8) and right (9-11) sticks.
+        if report.len() < 12 || report[0] != 0x30 {
+            return None;
+        }
+
+        let right = report[3];
+        let shared = report[4];
+        let left = report[5];
+        let mut down_mask = 0u32;
+
+        if right & 0x04 != 0 {
+            down_mask |= BUTTON_SOUTH_MASK;
+        }
+        if right & 0x08 != 0 {
+            down_mask |= BUTTON_EAST_MASK;
+        }
+        if right & 0x01 != 0 {
+            down_mask |= BUTTON_WEST_MASK;
+        }
+        if right & 0x02 != 0 {
+            down_mask |= BUTTON_NORTH_MASK;
+        }
+        if left & 0x40 != 0 {
+            down_mask |= BUTTON_L1_MASK;
+        }
+        if right & 0x40 != 0 {
+            down_mask |= BUTTON_R1_MASK;
+        }
+        if left & 0x80 != 0 {
+            down_mask |= BUTTON_L2_MASK;
+        }
+        if right & 0x80 != 0 {
+            down_mask |= BUTTON_R2_MASK;
+        }
+        if shared & 0x01 != 0 {
+            down_mask |= BUTTON_SELECT_MASK;
+        }
+        if shared & 0x02 != 0 {
+            down_mask |= BUTTON_START_MASK;
+        }
+        if shared & 0x08 != 0 {
+            down_mask |= BUTTON_L3_MASK;
+        }
+        if shared & 0x04 != 0 {
+            down_mask |= BUTTON_R3_MASK;
+        }
+
+        let dpad_up = left & 0x02 != 0;
+        let dpad_down = left & 0x01 != 0;
+        let dpad_left = left & 0x08 != 0;
+        let dpad_right = left & 0x04 != 0;
+
+        if dpad_up {
+            down_mask |= BUTTON_DPAD_UP_MASK;
+        }
+        if dpad_down {
+            down_mask |= BUTTON_DPAD_DOWN_MASK;
+        }
+        if dpad_left {
+            down_mask |= BUTTON_DPAD_LEFT_MASK;
+        }
+        if dpad_right {
+            down_mask |= BUTTON_DPAD_RIGHT_MASK;
+        }
+
+        let dpad_direction = to_direction(
+            i32::from(dpad_right) - i32::from(dpad_left),
+            i32::from(dpad_up) - i32::from(dpad_down),
+        );
+
+        // Scale the 12-bit axes down to bytes; the Pro Controller's Y axis
+        // grows upwards, the opposite of the DS4 convention.
+        let stick = |offset: usize| {
+            let x = u16::from(report[offset]) | (u16::from(report[offset + 1] & 0x0F) << 8);
+            let y = u16::from(report[offset + 1] >> 4) | (u16::from(report[offset + 2]) << 4);
+            ((x >> 4) as u8, 255 - (y >> 4) as u8)
+        };
+
+        let direction = select_direction(dpad_direction, stick(6), stick(9), analog, self.0);
+        Some(DecodedInput {
+            direction,
+            down_mask,
+            left_stick: Some(stick(6)),
+            stick_direction: direction_from_analog_stick(stick(6), analog),
+            right_stick: Some(RightStick::from_bytes(stick(9), analog)),
+            triggers: None,
+        })
+    }
+}
+
+/// HORIPAD-style Switch report, as sent by GP2040-CE in Switch mode. Only
+/// the PID tells it apart, since the 8-byte report has no report ID.
+pub(crate) struct HoriSwitchDecoder<'a>(pub &'a mut DirectionArbiter);
+
+/// Button bits of the 16-bit little-endian field, in Nintendo naming; Home
+/// and Capture have no generic button.
+const HORI_SWITCH_BUTTONS: [(u16, u32); 12] = [
+    (0x0002, BUTTON_SOUTH_MASK), // B
+    (0x0004, BUTTON_EAST_MASK),  // A
+    (0x0001, BUTTON_WEST_MASK),  // Y
+    (0x0008, BUTTON_NORTH_MASK), // X
+    (0x0010, BUTTON_L1_MASK),
+    (0x0020, BUTTON_R1_MASK),
+    (0x0040, BUTTON_L2_MASK), // ZL
+    (0x0080, BUTTON_R2_MASK), // ZR
+    (0x0100, BUTTON_SELECT_MASK),
+    (0x0200, BUTTON_START_MASK),
+    (0x0400, BUTTON_L3_MASK),
+    (0x0800, BUTTON_R3_MASK),
+];
+
+impl InputDecoder for HoriSwitchDecoder<'_> {
+    type Report = [u8];
+
+    fn decode(&mut self, report: &[u8], analog: AnalogSettings) -> Option<DecodedInput> {
+        // Buttons at 0-1, the hat at 2 (8 is centered), then byte sticks
+        // with Y growing downwards as on a DS4.
+        if report.len() < 7 {
+            return None;
+        }
+
+        let buttons = u16::from_le_bytes([report[0], report[1]]);
+        let mut down_mask = HORI_SWITCH_BUTTONS
+            .iter()
+            .filter(|(bit, _)| buttons & bit != 0)
+            .fold(0u32, |mask, (_, generic)| mask | generic);
+        let hat = report[2] & 0x0F;
+        down_mask |= dpad_mask_from_hat(hat);
+
+        let direction = select_direction(
+            direction_from_ds4_hat(hat),
+            (report[3], report[4]),
+            (report[5], report[6]),
+            analog,
+            self.0,
+        );
+        Some(DecodedInput {
+            direction,
+            down_mask,
+            left_stick: Some((report[3], report[4])),
+            stick_direction: direction_from_analog_stick((report[3], report[4]), analog),
+            right_stick: Some(RightStick::from_bytes((report[5], report[6]), analog)),
+            triggers: None,
+        })
+    }
+}
+
+/// Reports laid out by a user [`HidProfile`].
+pub(crate) struct ProfileDecoder<'a>(pub &'a HidProfile, pub &'a mut DirectionArbiter);
+
+impl InputDecoder for ProfileDecoder<'_> {
+    type Report = [u8];
+
+    fn decode(&mut self, report: &[u8], analog: AnalogSettings) -> Option<DecodedInput> {
+        let decoded = self.0.decode(report)?;
+        let mut down_mask = decoded.down_mask;
+        if let Some(hat) = decoded.hat {
+            down_mask |= dpad_mask_from_hat(hat);
+        }
+
+        let held = |mask: u32| i32::from(down_mask & mask != 0);
+        let dpad_direction = to_direction(
+            held(BUTTON_DPAD_RIGHT_MASK) - held(BUTTON_DPAD_LEFT_MASK),
+            held(BUTTON_DPAD_UP_MASK) - held(BUTTON_DPAD_DOWN_MASK),
+        );
+        let direction = select_direction(
+            dpad_direction,
+            decoded.left_stick,
+            decoded.right_stick,
+            analog,
+            self.1,
+        );
+        Some(DecodedInput {
+            direction,
+            down_mask,
+            left_stick: self.0.has_left_stick().then_some(decoded.left_stick),
+            stick_direction: if self.0.has_left_stick() {
+                direction_from_analog_stick(decoded.left_stick, analog)
+            } else {
+                5
+            },
+            right_stick: self
+                .0
+                .has_right_stick()
+                .then(|| RightStick::from_bytes(decoded.right_stick, analog)),
+            triggers: None,
+        })
+    }
+}
+
+/// Platform-independent copy of `XINPUT_GAMEPAD`, filled by the Windows
+/// backend from `XInputGetState`.
+#[derive(Clone, Copy, Default)]
+pub(crate) struct XInputGamepad {
+    pub buttons: u16,
+    pub left_trigger: u8,
+    pub right_trigger: u8,
+    pub thumb_lx: i16,
+    pub thumb_ly: i16,
+    pub thumb_rx: i16,
+    pub thumb_ry: i16,
+}
+
+pub(crate) struct XInputDecoder<'a>(pub &'a mut DirectionArbiter);
+
+impl InputDecoder for XInputDecoder<'_> {
+    type Report = XInputGamepad;
+
+    fn decode(&mut self, gamepad: &XInputGamepad, analog: AnalogSettings) -> Option<DecodedInput> {
+        let buttons = gamepad.buttons;
+        let held = |expected: u16| buttons & expected == expected;
+        let mut down_mask = 0u32;
+
+        if held(XINPUT_GAMEPAD_A) {
+            down_mask |= BUTTON_SOUTH_MASK;
+        }
+        if held(XINPUT_GAMEPAD_B) {
+            down_mask |= BUTTON_EAST_MASK;
+        }
+        if held(XINPUT_GAMEPAD_X) {
+            down_mask |= BUTTON_WEST_MASK;
+        }
+        if held(XINPUT_GAMEPAD_Y) {
+            down_mask |= BUTTON_NORTH_MASK;
+        }
+        if held(XINPUT_GAMEPAD_LEFT_SHOULDER) {
+            down_mask |= BUTTON_L1_MASK;
+        }
+        if held(XINPUT_GAMEPAD_RIGHT_SHOULDER) {
+            down_mask |= BUTTON_R1_MASK;
+        }
+        if analog.xinput_trigger_pressed(gamepad.left_trigger) {
+            down_mask |= BUTTON_L2_MASK;
+        }
+        if analog.xinput_trigger_pressed(gamepad.right_trigger) {
+            down_mask |= BUTTON_R2_MASK;
+        }
+        if held(XINPUT_GAMEPAD_BACK) {
+            down_mask |= BUTTON_SELECT_MASK;
+        }
+        if held(XINPUT_GAMEPAD_START) {
+            down_mask |= BUTTON_START_MASK;
+        }
+        if held(XINPUT_GAMEPAD_LEFT_THUMB) {
+            down_mask |= BUTTON_L3_MASK;
+        }
+        if held(XINPUT_GAMEPAD_RIGHT_THUMB) {
+            down_mask |= BUTTON_R3_MASK;
+        }
+
+        let dpad_up = held(XINPUT_GAMEPAD_DPAD_UP);
+        let dpad_down = held(XINPUT_GAMEPAD_DPAD_DOWN);
+        let dpad_left = held(XINPUT_GAMEPAD_DPAD_LEFT);
+        let dpad_right = held(XINPUT_GAMEPAD_DPAD_RIGHT);
+
+        if dpad_up {
+            down_mask |= BUTTON_DPAD_UP_MASK;
+        }
+        if dpad_down {
+            down_mask |= BUTTON_DPAD_DOWN_MASK;
+        }
+        if dpad_left {
+            down_mask |= BUTTON_DPAD_LEFT_MASK;
+        }
+        if dpad_right {
+            down_mask |= BUTTON_DPAD_RIGHT_MASK;
+        }
+
+        let dpad = to_direction(
+            i32::from(dpad_right) - i32::from(dpad_left),
+            i32::from(dpad_up) - i32::from(dpad_down),
+        );
+        let stick = |(horizontal, vertical)| to_direction(horizontal, vertical);
+        let left_stick = stick(analog.xinput_stick(gamepad.thumb_lx, gamepad.thumb_ly));
+        let right_stick = analog.xinput_stick(gamepad.thumb_rx, gamepad.thumb_ry);
+        let direction = match analog.direction_source() {
+            DirectionSource::Dpad => dpad,
+            DirectionSource::LeftStick => left_stick,
+            DirectionSource::RightStick => stick(right_stick),
+            DirectionSource::Merged => self.0.merge(
+                dpad,
+                left_stick,
+                analog.direction_priority(DirectionPriority::Combined),
+            ),
+        };
+
+        // XInput axes are signed with Y up; the byte form flips Y.
+        let byte = |value: i16| ((i32::from(value) + 32_768) >> 8) as u8;
+        Some(DecodedInput {
+            direction,
+            down_mask,
+            left_stick: Some((byte(gamepad.thumb_lx), 255 - byte(gamepad.thumb_ly))),
+            stick_direction: left_stick,
+            right_stick: Some(RightStick {
+                direction: to_direction(right_stick.0, right_stick.1),
+                x: byte(gamepad.thumb_rx),
+                y: 255 - byte(gamepad.thumb_ry),
+            }),
+            triggers: Some(AnalogTriggers {
+                left: gamepad.left_trigger,
+                right: gamepad.right_trigger,
+            }),
+        })
+    }
+}
+
+fn dualsense_bluetooth_crc_matches(report: &[u8]) -> bool {
+    let Some(report) = report.get(..DUALSENSE_BT_REPORT_LEN) else {
+        return false;
+    };
+    let (data, crc) = report.split_at(DUALSENSE_BT_REPORT_LEN - 4);
+    let expected = u32::from_le_bytes([crc[0], crc[1], crc[2], crc[3]]);
+    crc32([DUALSENSE_BT_CRC_HEADER].iter().chain(data)) == expected
+}
+
+/// CRC-32 in its common IEEE form, as Sony pads use over Bluetooth.
+fn crc32<'a>(bytes: impl IntoIterator<Item = &'a u8>) -> u32 {
+    let mut crc = !0u32;
+    for &byte in bytes {
+        crc ^= u32::from(byte);
+        for _ in 0..8 {
+            crc = if crc & 1 != 0 {
+                (crc >> 1) ^ 0xEDB8_8320
+            } else {
+                crc >> 1
+            };
+        }
+    }
+    !crc
+}
+
+/// Decodes the button bytes shared by DS4 and DualSense reports: the first
+/// button byte carries the hat in its low nibble and the face buttons above
+/// it, the second the shoulders, menu buttons and stick clicks, the third
+/// the touchpad click in bit 1. Sticks are `(x, y)` byte pairs.
+fn decode_sony_buttons(
+    [buttons0, buttons1, buttons2]: [u8; 3],
+    [left_trigger_analog, right_trigger_analog]: [u8; 2],
+    left_stick: (u8, u8),
+    right_stick: (u8, u8),
+    analog: AnalogSettings,
+    arbiter: &mut DirectionArbiter,
+) -> DecodedInput {
+    let mut down_mask = 0u32;
+
+    if buttons0 & 0x20 != 0 {
+        down_mask |= BUTTON_SOUTH_MASK;
+    }
+    if buttons0 & 0x40 != 0 {
+        down_mask |= BUTTON_EAST_MASK;
+    }
+    if buttons0 & 0x10 != 0 {
+        down_mask |= BUTTON_WEST_MASK;
+    }
+    if buttons0 & 0x80 != 0 {
+        down_mask |= BUTTON_NORTH_MASK;
+    }
+    if buttons1 & 0x01 != 0 {
+        down_mask |= BUTTON_L1_MASK;
+    }
+    if buttons1 & 0x02 != 0 {
+        down_mask |= BUTTON_R1_MASK;
+    }
+    if buttons1 & 0x04 != 0 || analog.trigger_pressed(left_trigger_analog.into(), 0, 255) {
+        down_mask |= BUTTON_L2_MASK;
+    }
+    if buttons1 & 0x08 != 0 || analog.trigger_pressed(right_trigger_analog.into(), 0, 255) {
+        down_mask |= BUTTON_R2_MASK;
+    }
+    if buttons1 & 0x10 != 0 {
+        down_mask |= BUTTON_SELECT_MASK;
+    }
+    if buttons1 & 0x20 != 0 {
+        down_mask |= BUTTON_START_MASK;
+    }
+    if buttons1 & 0x40 != 0 {
+        down_mask |= BUTTON_L3_MASK;
+    }
+    if buttons1 & 0x80 != 0 {
+        down_mask |= BUTTON_R3_MASK;
+    }
+    if buttons2 & 0x02 != 0 {
+        down_mask |= BUTTON_TOUCHPAD_MASK;
+    }
+
+    let hat = buttons0 & 0x0F;
+    down_mask |= dpad_mask_from_hat(hat);
+
+    let direction = select_direction(
+        direction_from_ds4_hat(hat),
+        left_stick,
+        right_stick,
+        analog,
+        arbiter,
+    );
+    DecodedInput {
+        direction,
+        down_mask,
+        left_stick: Some(left_stick),
+        stick_direction: direction_from_analog_stick(left_stick, analog),
+        right_stick: Some(RightStick::from_bytes(right_stick, analog)),
+        triggers: Some(AnalogTriggers {
+            left: left_trigger_analog,
+            right: right_trigger_analog,
+        }),
+    }
+}
+
+/// Converts an 8-way hat index (0 = up, clockwise, anything else = centered)
+/// into the generic dpad mask bits.
+pub(crate) fn dpad_mask_from_hat(hat: u8) -> u32 {
+    let mut down_mask = 0u32;
+    if matches!(hat, 0 | 1 | 7) {
+        down_mask |= BUTTON_DPAD_UP_MASK;
+    }
+    if matches!(hat, 3..=5) {
+        down_mask |= BUTTON_DPAD_DOWN_MASK;
+    }
+    if matches!(hat, 5..=7) {
+        down_mask |= BUTTON_DPAD_LEFT_MASK;
+    }
+    if matches!(hat, 1..=3) {
+        down_mask |= BUTTON_DPAD_RIGHT_MASK;
+    }
+    down_mask
+}
+
+pub(crate) fn direction_from_ds4_hat(hat: u8) -> u8 {
+    match hat {
+        0 => 8,
+        1 => 9,
+        2 => 6,
+        3 => 3,
+        4 => 2,
+        5 => 1,
+        6 => 4,
+        7 => 7,
+        _ => 5,
+    }
+}
+
+/// Picks the `direction` source configured in `analog`. `dpad_direction` is 5
+/// while no dpad direction is held; sticks are `(x, y)` byte pairs.
+pub(crate) fn select_direction(
+    dpad_direction: u8,
+    left_stick: (u8, u8),
+    right_stick: (u8, u8),
+    analog: AnalogSettings,
+    arbiter: &mut DirectionArbiter,
+) -> u8 {
+    let stick = |stick: (u8, u8)| direction_from_analog_stick(stick, analog);
+    match analog.direction_source() {
+        DirectionSource::Dpad => dpad_direction,
+        DirectionSource::LeftStick => stick(left_stick),
+        DirectionSource::RightStick => stick(right_stick),
+        DirectionSource::Merged => arbiter.merge(
+            dpad_direction,
+            stick(left_stick),
+            analog.direction_priority(DirectionPriority::Dpad),
+        ),
+    }
+}
+
+/// Settles a held dpad against a held left stick for
+/// [`DirectionSource::Merged`]. Keeps what each read last for
+/// [`DirectionPriority::LastMoved`]; each source owns one, as does the SOCD
+/// stage, which merges again once it has cleaned the dpad.
+#[derive(Clone, Copy, Debug, Default)]
+pub(crate) struct DirectionArbiter {
+    dpad: u8,
+    stick: u8,
+    stick_moved_last: bool,
+}
+
+impl DirectionArbiter {
+    /// Numpad directions in, 5 for neutral. Other than under
+    /// [`DirectionPriority::Combined`], the preferred input wins while it is
+    /// off neutral, otherwise the other one shows. `priority` comes resolved
+    /// for the backend; a stray `Auto` prefers the dpad.
+    pub(crate) fn merge(&mut self, dpad: u8, stick: u8, priority: DirectionPriority) -> u8 {
+        // A dpad move on the same poll as a stick move counts as the later.
+        if stick != self.stick && stick != 5 {
+            self.stick_moved_last = true;
+        }
+        if dpad != self.dpad && dpad != 5 {
+            self.stick_moved_last = false;
+        }
+        self.dpad = dpad;
+        self.stick = stick;
+
+        if priority == DirectionPriority::Combined {
+            let (dpad_x, dpad_y) = direction_axes(dpad);
+            let (stick_x, stick_y) = direction_axes(stick);
+            let axis = |dpad: i32, stick: i32| {
+                if dpad > 0 || stick > 0 {
+                    1
+                } else if dpad < 0 || stick < 0 {
+                    -1
+                } else {
+                    0
+                }
+            };
+            return to_direction(axis(dpad_x, stick_x), axis(dpad_y, stick_y));
+        }
+
+        let stick_first = match priority {
+            DirectionPriority::Auto | DirectionPriority::Combined | DirectionPriority::Dpad => {
+                false
+            }
+            DirectionPriority::Stick => true,
+            DirectionPriority::LastMoved => self.stick_moved_last,
+        };
+        let (first, second) = if stick_first {
+            (stick, dpad)
+        } else {
+            (dpad, stick)
+        };
+        if first != 5 {
+            first
+        } else {
+            second
+        }
+    }
+}
+
+/// Byte stick axes as in DS4 reports, where Y grows downwards.
+pub(crate) fn direction_from_analog_stick((x, y): (u8, u8), analog: AnalogSettings) -> u8 {
+    let (horizontal, vertical) = analog.stick_direction((x.into(), 0, 255), (y.into(), 0, 255));
+    to_direction(horizontal, -vertical)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Sticks at rest and the hat released; buttons and triggers zeroed.
+    fn ds4_usb_report() -> [u8; 64] {
+        let mut report = [0u8; 64];
+        report[0] = 0x01;
+        report[1..5].fill(128);
+        report[5] = 0x08;
+        report
+    }
+
+    fn dualsense_usb_report() -> [u8; 64] {
+        let mut report = [0u8; 64];
+        report[0] = 0x01;
+        report[1..5].fill(128);
+        report[8] = 0x08;
+        report
+    }
+
+    /// A report 0x31 with the USB payload behind the tag byte, sealed with
+    /// its CRC.
+    fn dualsense_bluetooth_report(payload: &[u8; 64]) -> [u8; DUALSENSE_BT_REPORT_LEN] {
+        let mut report = [0u8; DUALSENSE_BT_REPORT_LEN];
+        report[0] = 0x31;
+        report[1] = 0x10;
+        report[2..12].copy_from_slice(&payload[1..11]);
+        let crc = crc32([DUALSENSE_BT_CRC_HEADER].iter().chain(&report[..74]));
+        report[74..].copy_from_slice(&crc.to_le_bytes());
+        report
+    }
+
+    /// Both sticks centered (0x800) and no buttons.
+    fn switch_pro_report() -> [u8; 49] {
+        let mut report = [0u8; 49];
+        report[0] = 0x30;
+        report[6..9].copy_from_slice(&[0x00, 0x08, 0x80]);
+        report[9..12].copy_from_slice(&[0x00, 0x08, 0x80]);
+        report
+    }
+
+    fn hori_report() -> [u8; 8] {
+        [0x00, 0x00, 0x08, 128, 128, 128, 128, 0x00]
+    }
+
+    fn analog(source: DirectionSource) -> AnalogSettings {
+        AnalogSettings::default().with_direction(source, DirectionPriority::Combined)
+    }
+
+    #[test]
+    fn ds4_usb_report_decodes_buttons_and_hat() {
+        let mut report = ds4_usb_report();
+        report[5] = 0x20 | 0x01;
+        report[6] = 0x02 | 0x20;
+        report[7] = 0x02;
+
+        let mut arbiter = DirectionArbiter::default();
+        let decoded = Ds4Decoder(&mut arbiter)
+            .decode(&report, AnalogSettings::default())
+            .unwrap();
+
+        assert_eq!(decoded.direction, 9);
+        assert_eq!(
+            decoded.down_mask,
+            BUTTON_SOUTH_MASK
+                | BUTTON_R1_MASK
+                | BUTTON_START_MASK
+                | BUTTON_TOUCHPAD_MASK
+                | BUTTON_DPAD_UP_MASK
+                | BUTTON_DPAD_RIGHT_MASK
+        );
+        assert_eq!(decoded.left_stick, Some((128, 128)));
+        assert_eq!(decoded.stick_direction, 5);
+        assert_eq!(decoded.triggers, Some(AnalogTriggers { left: 0, right: 0 }));
+    }
+
+    #[test]
+    fn ds4_bluetooth_report_skips_its_header() {
+        let mut report = [0u8; 78];
+        report[0] = 0x11;
+        report[1] = 0xC0;
+        report[3..7].fill(128);
+        report[7] = 0x80 | 0x08;
+        report[8] = 0x04;
+        report[10] = 200;
+
+        let mut arbiter = DirectionArbiter::default();
+        let decoded = Ds4Decoder(&mut arbiter)
+            .decode(&report, AnalogSettings::default())
+            .unwrap();
+
+        assert_eq!(decoded.direction, 5);
+        assert_eq!(decoded.down_mask, BUTTON_NORTH_MASK | BUTTON_L2_MASK);
+        assert_eq!(
+            decoded.triggers,
+            Some(AnalogTriggers {
+                left: 200,
+                right: 0
+            })
+        );
+    }
+
+    #[test]
+    fn ds4_analog_trigger_presses_past_the_threshold() {
+        let mut report = ds4_usb_report();
+        report[8] = 140;
+        report[9] = 141;
+
+        let mut arbiter = DirectionArbiter::default();
+        let decoded = Ds4Decoder(&mut arbiter)
+            .decode(&report, AnalogSettings::default())
+            .unwrap();
+
+        assert_eq!(decoded.down_mask, BUTTON_R2_MASK);
+    }
+
+    #[test]
+    fn ds4_rejects_other_and_truncated_reports() {
+        let mut arbiter = DirectionArbiter::default();
+        let mut decoder = Ds4Decoder(&mut arbiter);
+        let mut other = ds4_usb_report();
+        other[0] = 0x02;
+
+        assert_eq!(decoder.decode(&other, AnalogSettings::default()), None);
+        assert_eq!(
+            decoder.decode(&ds4_usb_report()[..9], AnalogSettings::default()),
+            None
+        );
+        assert_eq!(
+            decoder.decode(&[0x11, 0xC0], AnalogSettings::default()),
+            None
+        );
+    }
+
+    #[test]
+    fn ds4_direction_follows_the_configured_source() {
+        let mut report = ds4_usb_report();
+        report[1] = 255;
+        report[3] = 0;
+        let decode = |source| {
+            let mut arbiter = DirectionArbiter::default();
+            Ds4Decoder(&mut arbiter)
+                .decode(&report, analog(source))
+                .unwrap()
+                .direction
+        };
+
+        assert_eq!(decode(DirectionSource::Merged), 6);
+        assert_eq!(decode(DirectionSource::LeftStick), 6);
+        assert_eq!(decode(DirectionSource::RightStick), 4);
+        assert_eq!(decode(DirectionSource::Dpad), 5);
+    }
+
+    #[test]
+    fn hid_default_prefers_the_hat_over_a_held_stick() {
+        let mut report = ds4_usb_report();
+        report[5] = 0x02;
+        report[2] = 255;
+
+        let mut arbiter = DirectionArbiter::default();
+        let sample = Ds4Decoder(&mut arbiter)
+            .decode(&report, AnalogSettings::default())
+            .unwrap();
+
+        assert_eq!(sample.direction, 6);
+        assert_eq!(sample.stick_direction, 2);
+    }
+
+    #[test]
+    fn dualsense_usb_report_reads_buttons_behind_the_triggers() {
+        let mut report = dualsense_usb_report();
+        report[6] = 255;
+        report[8] = 0x40 | 0x06;
+        report[9] = 0x10;
+        report[10] = 0x02;
+
+        let mut arbiter = DirectionArbiter::default();
+        let decoded = DualSenseDecoder(&mut arbiter, false)
+            .decode(&report, AnalogSettings::default())
+            .unwrap();
+
+        assert_eq!(decoded.direction, 4);
+        assert_eq!(
+            decoded.down_mask,
+            BUTTON_EAST_MASK
+                | BUTTON_R2_MASK
+                | BUTTON_SELECT_MASK
+                | BUTTON_TOUCHPAD_MASK
+                | BUTTON_DPAD_LEFT_MASK
+        );
+        assert_eq!(
+            decoded.triggers,
+            Some(AnalogTriggers {
+                left: 0,
+                right: 255
+            })
+        );
+    }
+
+    #[test]
+    fn dualsense_bluetooth_report_decodes_when_its_crc_matches() {
+        let mut payload = dualsense_usb_report();
+        payload[8] = 0x10 | 0x04;
+        let report = dualsense_bluetooth_report(&payload);
+
+        let mut arbiter = DirectionArbiter::default();
+        let decoded = DualSenseDecoder(&mut arbiter, true)
+            .decode(&report, AnalogSettings::default())
+            .unwrap();
+
+        assert_eq!(decoded.direction, 2);
+        assert_eq!(decoded.down_mask, BUTTON_WEST_MASK | BUTTON_DPAD_DOWN_MASK);
+    }
+
+    #[test]
+    fn dualsense_bluetooth_report_is_dropped_on_crc_mismatch() {
+        let mut payload = dualsense_usb_report();
+        payload[8] = 0x10 | 0x04;
+        let mut report = dualsense_bluetooth_report(&payload);
+        report[9] ^= 0x20;
+
+        let mut arbiter = DirectionArbiter::default();
+        let mut decoder = DualSenseDecoder(&mut arbiter, true);
+
+        assert_eq!(decoder.decode(&report, AnalogSettings::default()), None);
+        assert_eq!(
+            decoder.decode(&report[..40], AnalogSettings::default()),
+            None
+        );
+    }
+
+    #[test]
+    fn dualsense_reduced_bluetooth_report_uses_the_ds4_layout() {
+        let report = [0x01, 128, 128, 128, 128, 0x20 | 0x04, 0x01, 0x00, 0, 255];
+
+        let mut arbiter = DirectionArbiter::default();
+        let decoded = DualSenseDecoder(&mut arbiter, true)
+            .decode(&report, AnalogSettings::default())
+            .unwrap();
+
+        assert_eq!(decoded.direction, 2);
+        assert_eq!(
+            decoded.down_mask,
+            BUTTON_SOUTH_MASK | BUTTON_L1_MASK | BUTTON_R2_MASK | BUTTON_DPAD_DOWN_MASK
+        );
+        assert_eq!(
+            decoded.triggers,
+            Some(AnalogTriggers {
+                left: 0,
+                right: 255
+            })
+        );
+    }
+
+    #[test]
+    fn dualsense_usb_report_keeps_the_usb_layout() {
+        // Same leading bytes as the reduced report, read from the USB
+        // offsets: nothing is held there.
+        let mut report = dualsense_usb_report();
+        report[5] = 0x20 | 0x04;
+        report[6] = 0x01;
+
+        let mut arbiter = DirectionArbiter::default();
+        let decoded = DualSenseDecoder(&mut arbiter, false)
+            .decode(&report, AnalogSettings::default())
+            .unwrap();
+
+        assert_eq!(decoded.direction, 5);
+        assert_eq!(decoded.down_mask, 0);
+        assert_eq!(
+            decoded.triggers,
+            Some(AnalogTriggers {
+                left: 0x24,
+                right: 0x01
+            })
+        );
+    }
+
+    #[test]
+    fn crc32_matches_the_ieee_check_value() {
+        assert_eq!(crc32(b"123456789"), 0xCBF4_3926);
+    }
+
+    #[test]
+    fn switch_pro_report_decodes_buttons_and_dpad() {
+        let mut report = switch_pro_report();
+        report[3] = 0x04 | 0x40;
+        report[4] = 0x02;
+        report[5] = 0x02 | 0x04;
+
+        let mut arbiter = DirectionArbiter::default();
+        let decoded = SwitchProDecoder(&mut arbiter)
+            .decode(&report, AnalogSettings::default())
+            .unwrap();
+
+        assert_eq!(decoded.direction, 9);
+        assert_eq!(
+            decoded.down_mask,
+            BUTTON_SOUTH_MASK
+                | BUTTON_R1_MASK
+                | BUTTON_START_MASK
+                | BUTTON_DPAD_UP_MASK
+                | BUTTON_DPAD_RIGHT_MASK
+        );
+        assert_eq!(decoded.left_stick, Some((128, 127)));
+        assert_eq!(decoded.triggers, None);
+    }
+
+    #[test]
+    fn switch_pro_sticks_scale_to_bytes_with_y_down() {
+        let mut report = switch_pro_report();
+        report[6..9].copy_from_slice(&[0xFF, 0x0F, 0x80]);
+        report[9..12].copy_from_slice(&[0x00, 0xF8, 0xFF]);
+
+        let mut arbiter = DirectionArbiter::default();
+        let decoded = SwitchProDecoder(&mut arbiter)
+            .decode(&report, AnalogSettings::default())
+            .unwrap();
+
+        assert_eq!(decoded.direction, 6);
+        assert_eq!(decoded.left_stick, Some((255, 127)));
+        assert_eq!(
+            decoded.right_stick,
+            Some(RightStick {
+                direction: 8,
+                x: 128,
+                y: 0
+            })
+        );
+    }
+
+    #[test]
+    fn switch_pro_rejects_other_reports() {
+        let mut arbiter = DirectionArbiter::default();
+        let mut decoder = SwitchProDecoder(&mut arbiter);
+        let mut simple = switch_pro_report();
+        simple[0] = 0x3F;
+
+        assert_eq!(decoder.decode(&simple, AnalogSettings::default()), None);
+        assert_eq!(
+            decoder.decode(&switch_pro_report()[..11], AnalogSettings::default()),
+            None
+        );
+    }
+
+    #[test]
+    fn hori_report_decodes_buttons_and_hat() {
+        let mut report = hori_report();
+        report[0] = 0x02 | 0x20;
+        report[1] = 0x02;
+        report[2] = 0x03;
+
+        let mut arbiter = DirectionArbiter::default();
+        let decoded = HoriSwitchDecoder(&mut arbiter)
+            .decode(&report, AnalogSettings::default())
+            .unwrap();
+
+        assert_eq!(decoded.direction, 3);
+        assert_eq!(
+            decoded.down_mask,
+            BUTTON_SOUTH_MASK
+                | BUTTON_R1_MASK
+                | BUTTON_START_MASK
+                | BUTTON_DPAD_DOWN_MASK
+                | BUTTON_DPAD_RIGHT_MASK
+        );
+    }
+
+    #[test]
+    fn hori_report_falls_back_to_the_left_stick() {
+        let mut report = hori_report();
+        report[3] = 0;
+
+        let mut arbiter = DirectionArbiter::default();
+        let decoded = HoriSwitchDecoder(&mut arbiter)
+            .decode(&report, AnalogSettings::default())
+            .unwrap();
+
+        assert_eq!(decoded.direction, 4);
+        assert_eq!(decoded.stick_direction, 4);
+        assert_eq!(decoded.down_mask, 0);
+        assert_eq!(
+            HoriSwitchDecoder(&mut arbiter).decode(&report[..6], AnalogSettings::default()),
+            None
+        );
+    }
+
+    #[test]
+    fn xinput_gamepad_decodes_buttons_triggers_and_stick() {
+        let gamepad = XInputGamepad {
+            buttons: XINPUT_GAMEPAD_A | XINPUT_GAMEPAD_RIGHT_SHOULDER | XINPUT_GAMEPAD_DPAD_DOWN,
+            left_trigger: 139,
+            right_trigger: 140,
+            thumb_lx: i16::MIN,
+            ..XInputGamepad::default()
+        };
+
+        let mut arbiter = DirectionArbiter::default();
+        let decoded = XInputDecoder(&mut arbiter)
+            .decode(&gamepad, AnalogSettings::default())
+            .unwrap();
+
+        assert_eq!(decoded.direction, 1);
+        assert_eq!(
+            decoded.down_mask,
+            BUTTON_SOUTH_MASK | BUTTON_R1_MASK | BUTTON_R2_MASK | BUTTON_DPAD_DOWN_MASK
+        );
+        let dpad = XInputDecoder(&mut arbiter)
+            .decode(&gamepad, analog(DirectionSource::Dpad))
+            .unwrap();
+        assert_eq!(dpad.direction, 2);
+    }
+
+    #[test]
+    fn combined_priority_ors_each_axis() {
+        let mut arbiter = DirectionArbiter::default();
+        let priority = DirectionPriority::Combined;
+
+        assert_eq!(arbiter.merge(6, 2, priority), 3);
+        assert_eq!(arbiter.merge(4, 6, priority), 6);
+        assert_eq!(arbiter.merge(2, 8, priority), 8);
+        assert_eq!(arbiter.merge(5, 1, priority), 1);
+        assert_eq!(arbiter.merge(5, 5, priority), 5);
+    }
+
+    #[test]
+    fn dpad_priority_prefers_a_held_dpad() {
+        let mut arbiter = DirectionArbiter::default();
+        let priority = DirectionPriority::Dpad;
+
+        assert_eq!(arbiter.merge(6, 2, priority), 6);
+        assert_eq!(arbiter.merge(5, 2, priority), 2);
+        assert_eq!(arbiter.merge(4, 5, priority), 4);
+    }
+
+    #[test]
+    fn stick_priority_prefers_a_held_stick() {
+        let mut arbiter = DirectionArbiter::default();
+        let priority = DirectionPriority::Stick;
+
+        assert_eq!(arbiter.merge(6, 2, priority), 2);
+        assert_eq!(arbiter.merge(6, 5, priority), 6);
+        assert_eq!(arbiter.merge(5, 3, priority), 3);
+    }
+
+    #[test]
+    fn last_moved_priority_follows_the_latest_input() {
+        let mut arbiter = DirectionArbiter::default();
+        let priority = DirectionPriority::LastMoved;
+
+        assert_eq!(arbiter.merge(5, 2, priority), 2);
+        assert_eq!(arbiter.merge(6, 2, priority), 6);
+        assert_eq!(arbiter.merge(6, 2, priority), 6);
+        assert_eq!(arbiter.merge(6, 3, priority), 3);
+        assert_eq!(arbiter.merge(9, 3, priority), 9);
+        // Both moving on one poll counts the dpad as the later.
+        assert_eq!(arbiter.merge(8, 1, priority), 8);
+        assert_eq!(arbiter.merge(5, 1, priority), 1);
+    }
+}
@@ -0,0 +1,205 @@
+//! Settings saved per controller, applied when `input_start` opens it again,
+//! and the HID allow-list stored next to them.
+
+use std::{collections::BTreeMap, fs, path::PathBuf};
+
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Manager};
+
+use super::{
+    analog::{AnalogConfig, AnalogSettings},
+    mapping::{ButtonMapping, ButtonMappingConfig},
+    socd::SocdPolicy,
+    InputSettings, NativeInputDevice, NativeInputMode,
+};
+
+const PROFILE_STORE_VERSION: u32 = 1;
+const PROFILE_STORE_FILE: &str = "input_profiles.json";
+
+/// One saved profile. `analog` includes the direction source.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct DeviceProfileConfig {
+    mapping: ButtonMappingConfig,
+    analog: AnalogConfig,
+    socd: SocdPolicy,
+}
+
+/// Entry of `input_profile_list` and answer to `input_profile_save`.
+#[derive(Clone, Serialize)]
+pub struct DeviceProfileEntry {
+    key: String,
+    profile: DeviceProfileConfig,
+}
+
+/// VID/PID of a board the HID backend reads as a DS4 although it neither
+/// names itself PS4 nor uses a known ID, as some Brook and Hori boards do.
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub struct HidDeviceId {
+    pub(crate) vendor_id: u16,
+    pub(crate) product_id: u16,
+}
+
+#[derive(Default, Serialize, Deserialize)]
+struct ProfileStoreFile {
+    version: u32,
+    profiles: BTreeMap<String, DeviceProfileConfig>,
+    #[serde(default)]
+    hid_allowlist: Vec<HidDeviceId>,
+}
+
+/// Identity a device's profile is stored under. XInput pads are
+/// indistinguishable and share `"xinput"`; other devices are keyed by
+/// backend, VID/PID and product string, e.g.
+/// `"hid:054c:09cc:Wireless Controller"`, so the same model on another port
+/// still matches.
+pub(crate) fn device_profile_key(device: &NativeInputDevice) -> String {
+    match (device.backend, device.vendor_id, device.product_id) {
+        (NativeInputMode::XInput | NativeInputMode::Keyboard, ..) => {
+            device.backend.name().to_string()
+        }
+        (backend, Some(vendor_id), Some(product_id)) => format!(
+            "{}:{vendor_id:04x}:{product_id:04x}:{}",
+            backend.name(),
+            device.product.as_deref().unwrap_or_default()
+        ),
+        _ => device.id.clone(),
+    }
+}
+
+impl DeviceProfileConfig {
+    /// Captures the parts of `settings` a profile holds.
+    pub(crate) fn from_settings(settings: &InputSettings) -> Self {
+        Self {
+            mapping: settings.mapping.to_config(),
+            analog: settings.analog.to_config(),
+            socd: settings.socd,
+        }
+    }
+
+    /// Applies the profile over the defaults, so a value left out of an
+    /// older profile does not keep whatever the previous device used.
+    pub(crate) fn apply(&self, settings: &mut InputSettings) -> Result<(), String> {
+        let mapping = ButtonMapping::from_config(&self.mapping)?;
+        let analog = AnalogSettings::default().with_config(&self.analog)?;
+        settings.mapping = mapping;
+        settings.analog = analog;
+        settings.socd = self.socd;
+        Ok(())
+    }
+}
+
+/// The profile file under the app data dir. It is read on every lookup;
+/// profiles change rarely and the file is small.
+pub(crate) struct DeviceProfileStore {
+    path: PathBuf,
+}
+
+impl DeviceProfileStore {
+    pub(crate) fn new(app: &AppHandle) -> Result<Self, String> {
+        let dir = app
+            .path()
+            .app_data_dir()
+            .map_err(|error| format!("Failed to locate the app data directory: {error}"))?;
+        Ok(Self {
+            path: dir.join(PROFILE_STORE_FILE),
+        })
+    }
+
+    /// Every stored profile. A missing file is an empty store; an unreadable
+    /// one is reported as the `Err` so the caller can warn and carry on.
+    pub(crate) fn load(&self) -> Result<BTreeMap<String, DeviceProfileConfig>, String> {
+        Ok(self.load_file()?.profiles)
+    }
+
+    pub(crate) fn hid_allowlist(&self) -> Result<Vec<HidDeviceId>, String> {
+        Ok(self.load_file()?.hid_allowlist)
+    }
+
+    pub(crate) fn set_hid_allowlist(&self, mut devices: Vec<HidDeviceId>) -> Result<(), String> {
+        devices.sort_unstable();
+        devices.dedup();
+        let mut file = self.load_file()?;
+        file.hid_allowlist = devices;
+        self.write(file)
+    }
+
+    fn load_file(&self) -> Result<ProfileStoreFile, String> {
+        let text = match fs::read_to_string(&self.path) {
+            Ok(text) => text,
+            Err(error) if error.kind() == std::io::ErrorKind::NotFound => {
+                return Ok(ProfileStoreFile::default())
+            }
+            Err(error) => {
+                return Err(format!(
+                    "Failed to read input profiles '{}': {error}",
+                    self.path.display()
+                ))
+            }
+        };
+
+        let file: ProfileStoreFile = serde_json::from_str(&text).map_err(|error| {
+            format!(
+                "Input profiles '{}' are not valid: {error}",
+                self.path.display()
+            )
+        })?;
+        if file.version != PROFILE_STORE_VERSION {
+            return Err(format!(
+                "Input profiles '{}' have version {}; expected {PROFILE_STORE_VERSION}.",
+                self.path.display(),
+                file.version
+            ));
+        }
+        Ok(file)
+    }
+
+    pub(crate) fn list(&self) -> Result<Vec<DeviceProfileEntry>, String> {
+        Ok(self
+            .load()?
+            .into_iter()
+            .map(|(key, profile)| DeviceProfileEntry { key, profile })
+            .collect())
+    }
+
+    /// Stores `profile` under `key`, replacing any earlier one. An unreadable
+    /// store is refused rather than overwritten.
+    pub(crate) fn save(
+        &self,
+        key: String,
+        profile: DeviceProfileConfig,
+    ) -> Result<DeviceProfileEntry, String> {
+        let mut file = self.load_file()?;
+        file.profiles.insert(key.clone(), profile.clone());
+        self.write(file)?;
+        Ok(DeviceProfileEntry { key, profile })
+    }
+
+    pub(crate) fn delete(&self, key: &str) -> Result<(), String> {
+        let mut file = self.load_file()?;
+        if file.profiles.remove(key).is_none() {
+            return Err(format!("No input profile is saved for '{key}'."));
+        }
+        self.write(file)
+    }
+
+    /// Writes through a temporary file so a crash cannot leave half a store.
+    fn write(&self, mut file: ProfileStoreFile) -> Result<(), String> {
+        file.version = PROFILE_STORE_VERSION;
+        let text = serde_json::to_string_pretty(&file)
+            .map_err(|error| format!("Failed to serialize input profiles: {error}"))?;
+        let temporary = self.path.with_extension("json.tmp");
+        if let Some(dir) = self.path.parent() {
+            fs::create_dir_all(dir).map_err(|error| {
+                format!("Failed to create directory '{}': {error}", dir.display())
+            })?;
+        }
+        fs::write(&temporary, text)
+            .and_then(|_| fs::rename(&temporary, &self.path))
+            .map_err(|error| {
+                format!(
+                    "Failed to write input profiles '{}': {error}",
+                    self.path.display()
+                )
+            })
+    }
+}
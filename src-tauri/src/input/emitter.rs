@@ -0,0 +1,318 @@
+use serde::{Deserialize, Serialize};
+use tauri::AppHandle;
+
+use super::{
+    charge::ChargeFrames,
+    grouping::GroupedFrame,
+    mask_to_buttons, mask_to_sf6_buttons,
+    stream::{publish, StreamHandle},
+    system::system_actions,
+    InputFramePayload, InputSample, Player, SF6_BUTTON_ORDER,
+};
+
+const DEFAULT_BATCH_SIZE: usize = 4;
+
+/// How the worker publishes polled frames.
+///
+/// `Stream` emits every tick on `input/frame`. `Edge` emits on `input/edge`
+/// only when the direction, buttons or connection state change, plus the first
+/// and last frame of a run so consumers can establish state and an optional
+/// keep-alive; it alone sees frames after press grouping. `Batch` emits
+/// `Vec<InputFramePayload>` on `input/frames` every `batch_size` frames and
+/// immediately on any state change.
+#[derive(Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum InputEmitMode {
+    #[default]
+    Stream,
+    Edge,
+    Batch,
+}
+
+#[derive(Clone, Copy)]
+pub(crate) struct EmitSettings {
+    pub mode: InputEmitMode,
+    pub batch_size: usize,
+    /// Frames after which `Edge` mode re-sends an unchanged frame, so
+    /// consumers can tell a held state from a stalled worker; 0 never does.
+    pub keepalive_frames: u64,
+}
+
+impl Default for EmitSettings {
+    fn default() -> Self {
+        Self {
+            mode: InputEmitMode::Stream,
+            batch_size: DEFAULT_BATCH_SIZE,
+            keepalive_frames: 0,
+        }
+    }
+}
+
+/// One tick's result after mapping, before it is serialized for the frontend.
+#[derive(Clone, Copy)]
+pub(crate) struct PolledFrame {
+    pub player: Player,
+    pub frame: u64,
+    pub sample: InputSample,
+    pub sf6_mask: u16,
+    pub connected: bool,
+    /// SF6 mask of the previous frame; all released on the first frame.
+    pub previous_sf6_mask: u16,
+    /// Frame each SF6 button currently down was pressed on.
+    pub pressed_at: [u64; SF6_BUTTON_ORDER.len()],
+    /// System actions held, one bit per [`super::system::SystemAction`].
+    pub system: u8,
+    pub charge: ChargeFrames,
+}
+
+/// Fills the frame-to-frame fields of [`PolledFrame`] for one run.
+#[derive(Default)]
+pub(crate) struct ButtonHoldTracker {
+    previous_sf6_mask: u16,
+    pressed_at: [u64; SF6_BUTTON_ORDER.len()],
+}
+
+impl ButtonHoldTracker {
+    pub(crate) fn track(&mut self, frame: &mut PolledFrame) {
+        let pressed = frame.sf6_mask & !self.previous_sf6_mask;
+        for (index, pressed_at) in self.pressed_at.iter_mut().enumerate() {
+            if pressed & (1 << index) != 0 {
+                *pressed_at = frame.frame;
+            }
+        }
+
+        frame.previous_sf6_mask = self.previous_sf6_mask;
+        frame.pressed_at = self.pressed_at;
+        self.previous_sf6_mask = frame.sf6_mask;
+    }
+}
+
+#[derive(Clone, Serialize)]
+struct InputEdgePayload {
+    player: Player,
+    frame: u64,
+    timestamp_ms: u64,
+    timestamp_us: u64,
+    direction: u8,
+    direction_raw: u8,
+    physical_down: Vec<String>,
+    down: Vec<String>,
+    pressed: Vec<String>,
+    released: Vec<String>,
+    physical_pressed: Vec<String>,
+    physical_released: Vec<String>,
+    connected: bool,
+    /// Some of `pressed` landed on later frames and were grouped into this
+    /// press. Physical buttons are never grouped.
+    simultaneous: bool,
+    /// Frames events were held back to allow grouping; 0 when it is off.
+    grouping_frames: u64,
+}
+
+pub(crate) struct FrameEmitter {
+    app: AppHandle,
+    stream: StreamHandle,
+    previous: Option<PolledFrame>,
+    /// Last grouped frame, which edges are taken against.
+    previous_edge: Option<PolledFrame>,
+    previous_edge_emitted: bool,
+    /// Frame number of the last `input/edge` event.
+    last_edge_frame: Option<u64>,
+    batch: Vec<InputFramePayload>,
+}
+
+impl PolledFrame {
+    /// A frame with no edge information yet; see [`ButtonHoldTracker`].
+    pub(crate) fn new(frame: u64, sample: InputSample, sf6_mask: u16, connected: bool) -> Self {
+        Self {
+            player: Player::P1,
+            frame,
+            sample,
+            sf6_mask,
+            connected,
+            previous_sf6_mask: 0,
+            pressed_at: [frame; SF6_BUTTON_ORDER.len()],
+            system: 0,
+            charge: ChargeFrames::default(),
+        }
+    }
+
+    pub(crate) fn payload(&self) -> InputFramePayload {
+        let held_frames = SF6_BUTTON_ORDER
+            .iter()
+            .zip(self.pressed_at)
+            .enumerate()
+            .filter(|(index, _)| self.sf6_mask & (1 << index) != 0)
+            .map(|(_, (name, pressed_at))| {
+                (
+                    (*name).to_string(),
+                    self.frame.saturating_sub(pressed_at) + 1,
+                )
+            })
+            .collect();
+
+        InputFramePayload {
+            player: self.player,
+            frame: self.frame,
+            timestamp_ms: self.sample.timestamp_ms,
+            timestamp_us: self.sample.timestamp_us,
+            direction: self.sample.direction,
+            direction_raw: self.sample.direction_raw,
+            physical_down: mask_to_buttons(self.sample.down_mask),
+            down: mask_to_sf6_buttons(self.sf6_mask),
+            right_stick: self.sample.right_stick,
+            triggers: self.sample.triggers,
+            pressed: mask_to_sf6_buttons(self.sf6_mask & !self.previous_sf6_mask),
+            released: mask_to_sf6_buttons(self.previous_sf6_mask & !self.sf6_mask),
+            held_frames,
+            system: system_actions(self.system),
+            charge: self.charge,
+            first_press_us: self.sample.first_press_us,
+            device_timestamp_us: self.sample.device_timestamp_us,
+            connected: self.connected,
+        }
+    }
+
+    fn differs_from(&self, other: &PolledFrame) -> bool {
+        let right_direction =
+            |frame: &PolledFrame| frame.sample.right_stick.map(|stick| stick.direction);
+        self.sample.direction != other.sample.direction
+            || right_direction(self) != right_direction(other)
+            || self.sample.down_mask != other.sample.down_mask
+            || self.sf6_mask != other.sf6_mask
+            || self.connected != other.connected
+    }
+
+    /// Edge payload relative to `previous`; with no previous frame every held
+    /// button counts as pressed.
+    fn edge_payload(
+        &self,
+        previous: Option<&PolledFrame>,
+        simultaneous: bool,
+        grouping_frames: u64,
+    ) -> InputEdgePayload {
+        let (previous_physical, previous_sf6) = previous
+            .map(|previous| (previous.sample.down_mask, previous.sf6_mask))
+            .unwrap_or((0, 0));
+        let physical = self.sample.down_mask;
+
+        InputEdgePayload {
+            player: self.player,
+            frame: self.frame,
+            timestamp_ms: self.sample.timestamp_ms,
+            timestamp_us: self.sample.timestamp_us,
+            direction: self.sample.direction,
+            direction_raw: self.sample.direction_raw,
+            physical_down: mask_to_buttons(physical),
+            down: mask_to_sf6_buttons(self.sf6_mask),
+            pressed: mask_to_sf6_buttons(self.sf6_mask & !previous_sf6),
+            released: mask_to_sf6_buttons(previous_sf6 & !self.sf6_mask),
+            physical_pressed: mask_to_buttons(physical & !previous_physical),
+            physical_released: mask_to_buttons(previous_physical & !physical),
+            connected: self.connected,
+            simultaneous,
+            grouping_frames,
+        }
+    }
+}
+
+impl FrameEmitter {
+    pub(crate) fn new(app: AppHandle, stream: StreamHandle) -> Self {
+        Self {
+            app,
+            stream,
+            previous: None,
+            previous_edge: None,
+            previous_edge_emitted: true,
+            last_edge_frame: None,
+            batch: Vec::with_capacity(DEFAULT_BATCH_SIZE),
+        }
+    }
+
+    /// Publishes a frame as polled; `Edge` mode waits for [`Self::push_edge`].
+    pub(crate) fn push(&mut self, frame: PolledFrame, settings: EmitSettings) {
+        if settings.mode != InputEmitMode::Batch {
+            self.flush_batch();
+        }
+
+        let changed = self
+            .previous
+            .as_ref()
+            .is_none_or(|previous| frame.differs_from(previous));
+
+        match settings.mode {
+            InputEmitMode::Stream => {
+                publish(&self.app, &self.stream, "input/frame", frame.payload());
+            }
+            InputEmitMode::Edge => {}
+            InputEmitMode::Batch => {
+                self.batch.push(frame.payload());
+                if changed || self.batch.len() >= settings.batch_size {
+                    self.flush_batch();
+                }
+            }
+        }
+
+        self.previous = Some(frame);
+    }
+
+    /// Publishes a frame released by the press grouper in `Edge` mode.
+    pub(crate) fn push_edge(
+        &mut self,
+        grouped: GroupedFrame,
+        settings: EmitSettings,
+        grouping_frames: u64,
+    ) {
+        let frame = grouped.frame;
+        let changed = self
+            .previous_edge
+            .as_ref()
+            .is_none_or(|previous| frame.differs_from(previous));
+
+        let keepalive = settings.keepalive_frames > 0
+            && self
+                .last_edge_frame
+                .is_some_and(|last| frame.frame.saturating_sub(last) >= settings.keepalive_frames);
+        let emitted = match settings.mode {
+            InputEmitMode::Edge => {
+                if changed || keepalive {
+                    let payload = frame.edge_payload(
+                        self.previous_edge.as_ref(),
+                        grouped.simultaneous,
+                        grouping_frames,
+                    );
+                    publish(&self.app, &self.stream, "input/edge", payload);
+                    self.last_edge_frame = Some(frame.frame);
+                }
+                changed || keepalive
+            }
+            InputEmitMode::Stream | InputEmitMode::Batch => true,
+        };
+
+        self.previous_edge = Some(frame);
+        self.previous_edge_emitted = emitted;
+    }
+
+    /// Flushes any partial batch and emits the final frame of the run if it
+    /// was skipped as unchanged. Grouped frames must be pushed first.
+    pub(crate) fn finish(&mut self, grouping_frames: u64) {
+        self.flush_batch();
+        if self.previous_edge_emitted {
+            return;
+        }
+
+        if let Some(last) = self.previous_edge.take() {
+            let payload = last.edge_payload(Some(&last), false, grouping_frames);
+            publish(&self.app, &self.stream, "input/edge", payload);
+        }
+    }
+
+    fn flush_batch(&mut self) {
+        if self.batch.is_empty() {
+            return;
+        }
+
+        let batch = std::mem::take(&mut self.batch);
+        publish(&self.app, &self.stream, "input/frames", batch);
+    }
+}
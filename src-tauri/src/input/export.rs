@@ -0,0 +1,223 @@
+//! Text renderings of a recording for sharing and spreadsheets.
+
+use std::{
+    fs::File,
+    io::{BufWriter, Write},
+    path::Path,
+};
+
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Emitter};
+
+use super::{
+    mapping::ButtonMapping,
+    mask_to_sf6_buttons,
+    motion::{MotionDetector, MotionSettings},
+    recording::RecordingReader,
+    SF6_BUTTON_ORDER,
+};
+
+#[derive(Clone, Copy, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum InputExportFormat {
+    /// One line per held direction and button state, e.g.
+    /// `236+HP (f120, held 3f)`.
+    Notation,
+    /// One row per frame with a 0/1 column per SF6 button.
+    Csv,
+}
+
+/// Payload of `input/export_progress`, emitted about every percent.
+#[derive(Clone, Serialize)]
+struct InputExportProgress {
+    out_path: String,
+    frames: u64,
+    /// Share of the recording read, from 0 to 1.
+    progress: f64,
+}
+
+#[derive(Clone, Serialize)]
+pub struct InputExportSummary {
+    out_path: String,
+    format: InputExportFormat,
+    frames: u64,
+    /// Lines written, header included.
+    lines: u64,
+}
+
+/// One line of notation: a direction and button state and how long it held.
+struct NotationLine {
+    label: String,
+    start_frame: u64,
+    connected: bool,
+    direction: u8,
+    sf6_mask: u16,
+}
+
+/// Renders the recording at `recording_path` into `out_path`. Buttons are
+/// named through `mapping`, so the export matches what the worker would
+/// report for the recording today.
+pub(crate) fn export_recording(
+    app: &AppHandle,
+    recording_path: &Path,
+    format: InputExportFormat,
+    out_path: &Path,
+    mapping: ButtonMapping,
+    motion: MotionSettings,
+) -> Result<InputExportSummary, String> {
+    let mut reader = RecordingReader::open(recording_path)?;
+    let file = File::create(out_path)
+        .map_err(|error| format!("Failed to create export '{}': {error}", out_path.display()))?;
+    let mut writer = BufWriter::new(file);
+    let write_error =
+        |error: std::io::Error| format!("Failed to write export '{}': {error}", out_path.display());
+
+    let mut frames = 0u64;
+    let mut lines = 0u64;
+    let mut first_frame = None;
+    let mut last_frame = 0;
+    let mut reported_percent = 0;
+    let mut detector = MotionDetector::default();
+    let mut open_line: Option<NotationLine> = None;
+
+    if let InputExportFormat::Csv = format {
+        writeln!(
+            writer,
+            "frame,timestamp_us,direction,{},connected",
+            SF6_BUTTON_ORDER.join(",")
+        )
+        .map_err(write_error)?;
+        lines += 1;
+    }
+
+    while let Some(recorded) = reader.next_frame()? {
+        let first = *first_frame.get_or_insert(recorded.frame);
+        let frame = recorded.frame.saturating_sub(first);
+        let sf6_mask = mapping.apply(recorded.down_mask);
+        last_frame = frame;
+        frames += 1;
+
+        match format {
+            InputExportFormat::Notation => {
+                let motion_name = detector
+                    .push(frame, recorded.direction, sf6_mask, motion)
+                    .iter()
+                    .find(|payload| !payload.negative_edge())
+                    .map(|payload| payload.motion());
+                let unchanged = open_line.as_ref().is_some_and(|line| {
+                    line.connected == recorded.connected
+                        && line.direction == recorded.direction
+                        && line.sf6_mask == sf6_mask
+                });
+                if !unchanged {
+                    if let Some(line) = open_line.take() {
+                        write_notation_line(&mut writer, &line, frame).map_err(write_error)?;
+                        lines += 1;
+                    }
+                    open_line = Some(NotationLine {
+                        label: notation_label(
+                            recorded.connected,
+                            recorded.direction,
+                            sf6_mask,
+                            motion_name,
+                        ),
+                        start_frame: frame,
+                        connected: recorded.connected,
+                        direction: recorded.direction,
+                        sf6_mask,
+                    });
+                }
+            }
+            InputExportFormat::Csv => {
+                let buttons: Vec<&str> = (0..SF6_BUTTON_ORDER.len())
+                    .map(|index| {
+                        if sf6_mask & (1 << index) != 0 {
+                            "1"
+                        } else {
+                            "0"
+                        }
+                    })
+                    .collect();
+                writeln!(
+                    writer,
+                    "{frame},{},{},{},{}",
+                    recorded.timestamp_us,
+                    recorded.direction,
+                    buttons.join(","),
+                    u8::from(recorded.connected)
+                )
+                .map_err(write_error)?;
+                lines += 1;
+            }
+        }
+
+        let percent = (reader.progress() * 100.0) as u32;
+        if percent > reported_percent {
+            reported_percent = percent;
+            emit_progress(app, out_path, frames, reader.progress());
+        }
+    }
+
+    if let Some(line) = open_line.take() {
+        write_notation_line(&mut writer, &line, last_frame + 1).map_err(write_error)?;
+        lines += 1;
+    }
+    writer.flush().map_err(write_error)?;
+    emit_progress(app, out_path, frames, 1.0);
+
+    Ok(InputExportSummary {
+        out_path: out_path.display().to_string(),
+        format,
+        frames,
+        lines,
+    })
+}
+
+/// `236+HP` when the press completed a motion, `2+MK` for a held direction,
+/// `LP` when neutral and `5` with nothing held.
+fn notation_label(
+    connected: bool,
+    direction: u8,
+    sf6_mask: u16,
+    motion: Option<&'static str>,
+) -> String {
+    if !connected {
+        return "disconnected".to_string();
+    }
+
+    let buttons = mask_to_sf6_buttons(sf6_mask).join("+");
+    let direction = motion
+        .map(str::to_string)
+        .unwrap_or_else(|| direction.to_string());
+    match (direction.as_str(), buttons.is_empty()) {
+        (_, true) => direction,
+        ("5", false) => buttons,
+        (_, false) => format!("{direction}+{buttons}"),
+    }
+}
+
+/// Writes `line`, which held until `end_frame` started.
+fn write_notation_line(
+    writer: &mut impl Write,
+    line: &NotationLine,
+    end_frame: u64,
+) -> std::io::Result<()> {
+    writeln!(
+        writer,
+        "{} (f{}, held {}f)",
+        line.label,
+        line.start_frame,
+        end_frame.saturating_sub(line.start_frame).max(1)
+    )
+}
+
+fn emit_progress(app: &AppHandle, out_path: &Path, frames: u64, progress: f64) {
+    let _ = app.emit(
+        "input/export_progress",
+        InputExportProgress {
+            out_path: out_path.display().to_string(),
+            frames,
+            progress,
+        },
+    );
+}
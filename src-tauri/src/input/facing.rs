@@ -0,0 +1,73 @@
+use serde::{Deserialize, Serialize};
+
+use super::{InputSample, BUTTON_DPAD_LEFT_MASK, BUTTON_DPAD_RIGHT_MASK};
+
+/// Side the player's character faces. SF6 numpad notation is relative to it,
+/// so when facing left the worker mirrors directions horizontally.
+#[derive(Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Facing {
+    Left,
+    #[default]
+    Right,
+}
+
+impl Facing {
+    pub(crate) fn opposite(self) -> Self {
+        match self {
+            Self::Left => Self::Right,
+            Self::Right => Self::Left,
+        }
+    }
+}
+
+/// Worker-owned mirroring stage, run after SOCD cleaning and before mapping.
+#[derive(Default)]
+pub(crate) struct FacingMirror {
+    active: Facing,
+}
+
+impl FacingMirror {
+    /// Mirrors `sample` for `facing`, keeping the untouched values in
+    /// `direction_raw` and `down_mask_raw`. A change of side waits until
+    /// nothing horizontal is held, so flipping mid-hold cannot turn a held 6
+    /// into a 4 and fake a release and press.
+    pub(crate) fn apply(&mut self, sample: &mut InputSample, facing: Facing) {
+        sample.direction_raw = sample.direction;
+        sample.down_mask_raw = sample.down_mask;
+        if facing != self.active && !holds_horizontal(sample) {
+            self.active = facing;
+        }
+        if self.active == Facing::Right {
+            return;
+        }
+
+        sample.direction = mirror_direction(sample.direction);
+        let horizontal = sample.down_mask & (BUTTON_DPAD_LEFT_MASK | BUTTON_DPAD_RIGHT_MASK);
+        sample.down_mask &= !(BUTTON_DPAD_LEFT_MASK | BUTTON_DPAD_RIGHT_MASK);
+        if horizontal & BUTTON_DPAD_LEFT_MASK != 0 {
+            sample.down_mask |= BUTTON_DPAD_RIGHT_MASK;
+        }
+        if horizontal & BUTTON_DPAD_RIGHT_MASK != 0 {
+            sample.down_mask |= BUTTON_DPAD_LEFT_MASK;
+        }
+    }
+}
+
+fn holds_horizontal(sample: &InputSample) -> bool {
+    !matches!(sample.direction, 2 | 5 | 8)
+        || sample.down_mask & (BUTTON_DPAD_LEFT_MASK | BUTTON_DPAD_RIGHT_MASK) != 0
+}
+
+/// Swaps 4/6, 1/3 and 7/9.
+fn mirror_direction(direction: u8) -> u8 {
+    match direction {
+        1 => 3,
+        3 => 1,
+        4 => 6,
+        6 => 4,
+        7 => 9,
+        9 => 7,
+        direction => direction,
+    }
+}
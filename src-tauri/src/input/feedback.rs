@@ -0,0 +1,178 @@
+//! Rumble and lightbar cues written to the controller the live worker has
+//! open, so training events can be felt on the pad itself.
+
+use std::{
+    sync::mpsc::{self, Receiver, Sender},
+    time::{Duration, Instant},
+};
+
+use serde::{Deserialize, Serialize};
+
+use super::platform::InputSource;
+
+const MAX_RUMBLE_DURATION_MS: u64 = 5_000;
+/// The worker answers within a tick; this only guards against it exiting
+/// between the command finding it and the request arriving.
+const REPLY_TIMEOUT: Duration = Duration::from_millis(500);
+
+/// `{"effect": "rumble", "intensity": 0.6, "duration_ms": 200}` or
+/// `{"effect": "lightbar", "red": 0, "green": 255, "blue": 0}`.
+#[derive(Clone, Copy, Deserialize)]
+#[serde(tag = "effect", rename_all = "snake_case")]
+#[cfg_attr(not(any(windows, target_os = "macos")), allow(dead_code))]
+pub enum FeedbackEffect {
+    /// Both motors at `intensity` (0.0-1.0). The worker stops them after
+    /// `duration_ms`; a later rumble replaces the running one.
+    Rumble {
+        intensity: f64,
+        duration_ms: u64,
+    },
+    Lightbar {
+        red: u8,
+        green: u8,
+        blue: u8,
+    },
+}
+
+impl FeedbackEffect {
+    const STOP_RUMBLE: Self = Self::Rumble {
+        intensity: 0.0,
+        duration_ms: 0,
+    };
+
+    pub(crate) fn validate(self) -> Result<(), String> {
+        if let Self::Rumble {
+            intensity,
+            duration_ms,
+        } = self
+        {
+            if !(0.0..=1.0).contains(&intensity) {
+                return Err(format!(
+                    "Rumble intensity must be between 0 and 1, got {intensity}."
+                ));
+            }
+            if duration_ms > MAX_RUMBLE_DURATION_MS {
+                return Err(format!(
+                    "Rumble duration must be at most {MAX_RUMBLE_DURATION_MS} ms, got {duration_ms}."
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            Self::Rumble { .. } => "rumble",
+            Self::Lightbar { .. } => "lightbar",
+        }
+    }
+}
+
+/// What a backend did with an effect.
+#[cfg_attr(not(any(windows, target_os = "macos")), allow(dead_code))]
+pub(crate) enum FeedbackOutcome {
+    Applied,
+    Unsupported,
+}
+
+#[derive(Clone, Serialize)]
+pub struct InputFeedbackResult {
+    applied: bool,
+    /// Why the effect was skipped when the device cannot show it.
+    warning: Option<String>,
+}
+
+pub(crate) struct FeedbackRequest {
+    effect: FeedbackEffect,
+    reply: Sender<Result<InputFeedbackResult, String>>,
+}
+
+/// Hands `effect` to the worker behind `sender` and waits for its answer.
+pub(crate) fn request(
+    sender: &Sender<FeedbackRequest>,
+    effect: FeedbackEffect,
+) -> Result<InputFeedbackResult, String> {
+    let (reply, answer) = mpsc::channel();
+    let stopped = || "Live input stopped before the feedback was sent.".to_string();
+    sender
+        .send(FeedbackRequest { effect, reply })
+        .map_err(|_| stopped())?;
+    answer.recv_timeout(REPLY_TIMEOUT).map_err(|_| stopped())?
+}
+
+/// Worker-owned end of `input_feedback`: applies queued effects to the open
+/// source and stops rumble once it is due.
+pub(crate) struct FeedbackQueue {
+    requests: Receiver<FeedbackRequest>,
+    rumble_until: Option<Instant>,
+}
+
+impl FeedbackQueue {
+    pub(crate) fn new(requests: Receiver<FeedbackRequest>) -> Self {
+        Self {
+            requests,
+            rumble_until: None,
+        }
+    }
+
+    pub(crate) fn update(&mut self, mut source: Option<&mut InputSource>, now: Instant) {
+        if self.rumble_until.is_some_and(|until| now >= until) {
+            self.stop_rumble(source.as_deref_mut());
+        }
+
+        while let Ok(request) = self.requests.try_recv() {
+            let result = match source.as_deref_mut() {
+                Some(source) => source
+                    .feedback(request.effect)
+                    .map(|outcome| self.applied(request.effect, outcome, now)),
+                None => Err("The controller is disconnected.".to_string()),
+            };
+            let _ = request.reply.send(result);
+        }
+    }
+
+    /// Stops a running rumble as the worker exits.
+    pub(crate) fn finish(&mut self, source: Option<&mut InputSource>) {
+        if self.rumble_until.is_some() {
+            self.stop_rumble(source);
+        }
+    }
+
+    fn stop_rumble(&mut self, source: Option<&mut InputSource>) {
+        self.rumble_until = None;
+        if let Some(source) = source {
+            let _ = source.feedback(FeedbackEffect::STOP_RUMBLE);
+        }
+    }
+
+    fn applied(
+        &mut self,
+        effect: FeedbackEffect,
+        outcome: FeedbackOutcome,
+        now: Instant,
+    ) -> InputFeedbackResult {
+        match outcome {
+            FeedbackOutcome::Applied => {
+                if let FeedbackEffect::Rumble {
+                    intensity,
+                    duration_ms,
+                } = effect
+                {
+                    self.rumble_until =
+                        (intensity > 0.0).then(|| now + Duration::from_millis(duration_ms));
+                }
+                InputFeedbackResult {
+                    applied: true,
+                    warning: None,
+                }
+            }
+            FeedbackOutcome::Unsupported => InputFeedbackResult {
+                applied: false,
+                warning: Some(format!(
+                    "The connected controller does not support {} feedback.",
+                    effect.name()
+                )),
+            },
+        }
+    }
+}
@@ -0,0 +1,91 @@
+use std::collections::VecDeque;
+
+use super::emitter::PolledFrame;
+
+/// Each frame of window holds edge and segment events back one more frame.
+pub(crate) const MAX_GROUPING_FRAMES: u64 = 3;
+
+/// Plink tolerance. The worker's 60 Hz clock is not aligned to the game's,
+/// so two buttons SF6 reads as one press can land on adjacent worker frames.
+#[derive(Clone, Copy, Default)]
+pub(crate) struct GroupingSettings {
+    /// Presses this many frames after another press join it; 0 disables
+    /// grouping and its latency.
+    pub window_frames: u64,
+}
+
+/// A frame bound for the edge and segment streams.
+#[derive(Clone, Copy)]
+pub(crate) struct GroupedFrame {
+    pub frame: PolledFrame,
+    /// Presses from later frames were merged into this frame's presses.
+    pub simultaneous: bool,
+}
+
+/// Delays frames by the grouping window so a press landing within it can be
+/// folded back into the earlier press. Only SF6 buttons are merged; physical
+/// buttons keep the frames they were polled on.
+#[derive(Default)]
+pub(crate) struct PressGrouper {
+    pending: VecDeque<GroupedFrame>,
+}
+
+impl PressGrouper {
+    /// Queues `frame` and returns the frames no later press can join anymore.
+    pub(crate) fn push(
+        &mut self,
+        frame: PolledFrame,
+        settings: GroupingSettings,
+    ) -> Vec<GroupedFrame> {
+        let window = settings.window_frames;
+        let pressed = frame.sf6_mask & !frame.previous_sf6_mask;
+        if pressed != 0 {
+            self.merge(frame.frame, pressed, window);
+        }
+        self.pending.push_back(GroupedFrame {
+            frame,
+            simultaneous: false,
+        });
+
+        let mut ready = Vec::new();
+        while self
+            .pending
+            .front()
+            .is_some_and(|pending| pending.frame.frame + window <= frame.frame)
+        {
+            ready.extend(self.pending.pop_front());
+        }
+        ready
+    }
+
+    /// Releases everything still queued as the run ends.
+    pub(crate) fn finish(&mut self) -> Vec<GroupedFrame> {
+        self.pending.drain(..).collect()
+    }
+
+    /// Adds `pressed` to every queued frame from the earliest press within
+    /// `window`. Buttons already down at some point since then are pressed
+    /// again rather than late, and keep their own edge.
+    fn merge(&mut self, frame: u64, pressed: u16, window: u64) {
+        let Some(anchor) = self.pending.iter().position(|pending| {
+            frame.saturating_sub(pending.frame.frame) <= window
+                && pending.frame.sf6_mask & !pending.frame.previous_sf6_mask != 0
+        }) else {
+            return;
+        };
+
+        let held_since = self.pending.range(anchor..).fold(
+            self.pending[anchor].frame.previous_sf6_mask,
+            |mask, pending| mask | pending.frame.sf6_mask,
+        );
+        let merged = pressed & !held_since;
+        if merged == 0 {
+            return;
+        }
+
+        self.pending[anchor].simultaneous = true;
+        for pending in self.pending.range_mut(anchor..) {
+            pending.frame.sf6_mask |= merged;
+        }
+    }
+}
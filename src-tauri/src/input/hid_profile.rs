@@ -0,0 +1,327 @@
+//! User-described HID report layouts, so a controller without a built-in
+//! decoder can be read by mapping its bytes by hand, typically from what
+//! `input_start_raw` shows.
+
+// Only the hidapi backend on Windows and macOS decodes with a profile.
+#![cfg_attr(not(any(windows, target_os = "macos")), allow(dead_code))]
+
+use std::{collections::BTreeMap, fs};
+
+use serde::Deserialize;
+
+use super::{mapping::name_to_button_mask, BUTTON_ORDER};
+
+/// The HID source reads reports into a buffer this large.
+const MAX_REPORT_LENGTH: usize = 78;
+const CENTERED_AXIS: u8 = 0x80;
+
+/// Serialized form of a [`HidProfile`]. Offsets count bytes from the start of
+/// the report as `input/raw` shows it, report ID included, e.g.
+/// `{"report_id": 1, "report_length": 27, "buttons": {"South": {"offset": 1,
+/// "bit": 1}}, "hat": {"offset": 3}, "left_stick": {"x": {"offset": 4}, "y":
+/// {"offset": 5}}}`.
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+pub(crate) struct HidProfileConfig {
+    /// Device the profile is for. A profile naming neither applies to every
+    /// gamepad, so pin the device when several are connected.
+    vendor_id: Option<u16>,
+    product_id: Option<u16>,
+    /// Reports starting with another byte are ignored.
+    report_id: Option<u8>,
+    /// Shortest report the profile decodes; every offset must fall inside it.
+    report_length: usize,
+    /// Generic button name to the bit that is set while it is held. The
+    /// `DPad*` names suit sticks that report the lever as buttons.
+    #[serde(default)]
+    buttons: BTreeMap<String, HidButtonConfig>,
+    hat: Option<HidHatConfig>,
+    left_stick: Option<HidStickConfig>,
+    right_stick: Option<HidStickConfig>,
+}
+
+#[derive(Clone, Copy, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct HidButtonConfig {
+    offset: usize,
+    bit: u8,
+}
+
+/// An 8-way hat in one nibble: 0 is up, counting clockwise to 7, and any
+/// other value is centered.
+#[derive(Clone, Copy, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct HidHatConfig {
+    offset: usize,
+    #[serde(default)]
+    nibble: HatNibble,
+}
+
+#[derive(Clone, Copy, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum HatNibble {
+    #[default]
+    Low,
+    High,
+}
+
+#[derive(Clone, Copy, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct HidStickConfig {
+    x: HidAxisConfig,
+    y: HidAxisConfig,
+}
+
+/// A byte axis that reads 0 at left or up, as on a DS4, unless `inverted`.
+#[derive(Clone, Copy, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct HidAxisConfig {
+    offset: usize,
+    #[serde(default)]
+    inverted: bool,
+}
+
+#[derive(Clone, Copy)]
+struct HidButton {
+    mask: u32,
+    offset: usize,
+    bit: u8,
+}
+
+#[derive(Clone, Copy)]
+struct HidAxis {
+    offset: usize,
+    inverted: bool,
+}
+
+impl HidAxis {
+    fn read(self, report: &[u8]) -> u8 {
+        let value = report[self.offset];
+        if self.inverted {
+            u8::MAX - value
+        } else {
+            value
+        }
+    }
+}
+
+/// State read from one report. Sticks are `(x, y)` byte pairs, centered when
+/// the profile does not describe them.
+pub(crate) struct HidProfileReport {
+    /// Generic buttons, including any `DPad*` buttons but not the hat.
+    pub down_mask: u32,
+    pub hat: Option<u8>,
+    pub left_stick: (u8, u8),
+    pub right_stick: (u8, u8),
+}
+
+/// A validated profile, read by the HID source for reports no built-in
+/// decoder recognizes.
+pub(crate) struct HidProfile {
+    vendor_id: Option<u16>,
+    product_id: Option<u16>,
+    report_id: Option<u8>,
+    report_length: usize,
+    buttons: Vec<HidButton>,
+    /// Offset and shift of the hat nibble.
+    hat: Option<(usize, u8)>,
+    left_stick: Option<[HidAxis; 2]>,
+    right_stick: Option<[HidAxis; 2]>,
+}
+
+impl HidProfile {
+    /// `source` is either the profile's JSON or the path of a JSON file
+    /// holding it.
+    pub(crate) fn load(source: &str) -> Result<Self, String> {
+        let source = source.trim();
+        let config: HidProfileConfig = if source.starts_with('{') {
+            serde_json::from_str(source).map_err(|error| format!("Invalid HID profile: {error}"))?
+        } else {
+            let text = fs::read_to_string(source)
+                .map_err(|error| format!("Failed to read HID profile '{source}': {error}"))?;
+            serde_json::from_str(&text)
+                .map_err(|error| format!("HID profile '{source}' is invalid: {error}"))?
+        };
+        Self::from_config(&config)
+    }
+
+    /// Checks every field at once and reports each problem by its path in
+    /// the profile, e.g. `buttons.South.offset`.
+    pub(crate) fn from_config(config: &HidProfileConfig) -> Result<Self, String> {
+        let mut bits = BitClaims {
+            report_id: config.report_id,
+            report_length: config.report_length,
+            owners: BTreeMap::new(),
+            errors: Vec::new(),
+        };
+
+        if config.report_length == 0 {
+            bits.errors
+                .push("report_length must be at least 1.".to_string());
+        } else if config.report_length > MAX_REPORT_LENGTH {
+            bits.errors.push(format!(
+                "report_length {} exceeds the {MAX_REPORT_LENGTH}-byte report buffer.",
+                config.report_length
+            ));
+        }
+
+        let mut buttons = Vec::new();
+        for (name, button) in &config.buttons {
+            let field = format!("buttons.{name}");
+            let Some(mask) = name_to_button_mask(name) else {
+                bits.errors.push(format!(
+                    "{field} is not a known button; expected one of {}.",
+                    BUTTON_ORDER.join(", ")
+                ));
+                continue;
+            };
+            if button.bit > 7 {
+                bits.errors
+                    .push(format!("{field}.bit must be 0-7, got {}.", button.bit));
+                continue;
+            }
+            if bits.claim(&field, button.offset, button.bit..=button.bit) {
+                buttons.push(HidButton {
+                    mask,
+                    offset: button.offset,
+                    bit: button.bit,
+                });
+            }
+        }
+
+        let hat = config.hat.and_then(|hat| {
+            let shift = match hat.nibble {
+                HatNibble::Low => 0,
+                HatNibble::High => 4,
+            };
+            bits.claim("hat", hat.offset, shift..=shift + 3)
+                .then_some((hat.offset, shift))
+        });
+
+        let left_stick = config
+            .left_stick
+            .and_then(|stick| bits.claim_stick("left_stick", stick));
+        let right_stick = config
+            .right_stick
+            .and_then(|stick| bits.claim_stick("right_stick", stick));
+
+        if config.buttons.is_empty()
+            && config.hat.is_none()
+            && config.left_stick.is_none()
+            && config.right_stick.is_none()
+        {
+            bits.errors
+                .push("A HID profile needs at least one button, a hat or a stick.".to_string());
+        }
+
+        if !bits.errors.is_empty() {
+            return Err(format!("Invalid HID profile: {}", bits.errors.join(" ")));
+        }
+
+        Ok(Self {
+            vendor_id: config.vendor_id,
+            product_id: config.product_id,
+            report_id: config.report_id,
+            report_length: config.report_length,
+            buttons,
+            hat,
+            left_stick,
+            right_stick,
+        })
+    }
+
+    pub(crate) fn has_left_stick(&self) -> bool {
+        self.left_stick.is_some()
+    }
+
+    pub(crate) fn has_right_stick(&self) -> bool {
+        self.right_stick.is_some()
+    }
+
+    pub(crate) fn matches_device(&self, vendor_id: u16, product_id: u16) -> bool {
+        self.vendor_id.is_none_or(|id| id == vendor_id)
+            && self.product_id.is_none_or(|id| id == product_id)
+    }
+
+    /// `None` for reports that are too short or carry another report ID.
+    pub(crate) fn decode(&self, report: &[u8]) -> Option<HidProfileReport> {
+        if report.len() < self.report_length || self.report_id.is_some_and(|id| report[0] != id) {
+            return None;
+        }
+
+        let down_mask = self
+            .buttons
+            .iter()
+            .filter(|button| report[button.offset] & (1 << button.bit) != 0)
+            .fold(0, |mask, button| mask | button.mask);
+        let stick = |axes: Option<[HidAxis; 2]>| {
+            axes.map_or((CENTERED_AXIS, CENTERED_AXIS), |[x, y]| {
+                (x.read(report), y.read(report))
+            })
+        };
+
+        Some(HidProfileReport {
+            down_mask,
+            hat: self
+                .hat
+                .map(|(offset, shift)| (report[offset] >> shift) & 0x0F),
+            left_stick: stick(self.left_stick),
+            right_stick: stick(self.right_stick),
+        })
+    }
+}
+
+/// Tracks which profile field owns each report bit while validating.
+struct BitClaims {
+    report_id: Option<u8>,
+    report_length: usize,
+    owners: BTreeMap<(usize, u8), String>,
+    errors: Vec<String>,
+}
+
+impl BitClaims {
+    /// Records `field` as the owner of `bits` at `offset`; `false`, with the
+    /// problem noted, if the byte is out of range or a bit is already taken.
+    fn claim(&mut self, field: &str, offset: usize, bits: impl Iterator<Item = u8>) -> bool {
+        if offset >= self.report_length {
+            self.errors.push(format!(
+                "{field}.offset {offset} is beyond report_length {}.",
+                self.report_length
+            ));
+            return false;
+        }
+        if offset == 0 && self.report_id.is_some() {
+            self.errors
+                .push(format!("{field}.offset 0 is the report ID byte."));
+            return false;
+        }
+
+        let bits: Vec<u8> = bits.collect();
+        let taken = bits
+            .iter()
+            .find_map(|bit| self.owners.get(&(offset, *bit)).map(|owner| (owner, bit)));
+        if let Some((owner, bit)) = taken {
+            self.errors.push(format!(
+                "{owner} and {field} both use offset {offset} bit {bit}."
+            ));
+            return false;
+        }
+        for bit in bits {
+            self.owners.insert((offset, bit), field.to_string());
+        }
+        true
+    }
+
+    fn claim_stick(&mut self, field: &str, stick: HidStickConfig) -> Option<[HidAxis; 2]> {
+        let x = self.claim_axis(&format!("{field}.x"), stick.x);
+        let y = self.claim_axis(&format!("{field}.y"), stick.y);
+        Some([x?, y?])
+    }
+
+    fn claim_axis(&mut self, field: &str, axis: HidAxisConfig) -> Option<HidAxis> {
+        self.claim(field, axis.offset, 0..=7).then_some(HidAxis {
+            offset: axis.offset,
+            inverted: axis.inverted,
+        })
+    }
+}
@@ -0,0 +1,74 @@
+use std::collections::VecDeque;
+
+use super::{emitter::PolledFrame, InputFramePayload};
+
+/// At 60 Hz, ten seconds of frames.
+const DEFAULT_HISTORY_FRAMES: usize = 600;
+
+/// Ring buffer of the most recent frames of the current run, written by the
+/// worker and read by `input_get_history` so the UI can rebuild its display
+/// after a reload.
+pub(crate) struct InputHistory {
+    frames: VecDeque<PolledFrame>,
+    capacity: usize,
+}
+
+impl Default for InputHistory {
+    fn default() -> Self {
+        Self::with_capacity(DEFAULT_HISTORY_FRAMES)
+    }
+}
+
+impl InputHistory {
+    fn with_capacity(capacity: usize) -> Self {
+        Self {
+            frames: VecDeque::with_capacity(capacity),
+            capacity,
+        }
+    }
+
+    pub(crate) fn push(&mut self, frame: PolledFrame) {
+        if self.frames.len() == self.capacity {
+            self.frames.pop_front();
+        }
+        self.frames.push_back(frame);
+    }
+
+    pub(crate) fn clear(&mut self) {
+        self.frames.clear();
+    }
+
+    /// Drops the oldest frames if the buffer shrinks below its current length.
+    pub(crate) fn set_capacity(&mut self, capacity: usize) {
+        while self.frames.len() > capacity {
+            self.frames.pop_front();
+        }
+        self.capacity = capacity;
+    }
+
+    /// The frames of the last `count` frame numbers, oldest first.
+    pub(crate) fn recent(&self, count: u64) -> impl Iterator<Item = &PolledFrame> {
+        let last = self.frames.back().map_or(0, |frame| frame.frame);
+        self.frames
+            .iter()
+            .filter(move |frame| frame.frame + count > last)
+    }
+
+    /// Buffered frames in order, limited to those after `since_frame`, those
+    /// stamped after `since_us` and those within the inclusive `range` when
+    /// set.
+    pub(crate) fn payloads(
+        &self,
+        since_frame: Option<u64>,
+        since_us: Option<u64>,
+        range: Option<(u64, u64)>,
+    ) -> Vec<InputFramePayload> {
+        self.frames
+            .iter()
+            .filter(|frame| since_frame.is_none_or(|since| frame.frame > since))
+            .filter(|frame| since_us.is_none_or(|since| frame.sample.timestamp_us > since))
+            .filter(|frame| range.is_none_or(|(start, end)| (start..=end).contains(&frame.frame)))
+            .map(PolledFrame::payload)
+            .collect()
+    }
+}
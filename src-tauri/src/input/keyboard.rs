@@ -0,0 +1,222 @@
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+
+use super::{
+    to_direction, BUTTON_DPAD_DOWN_MASK, BUTTON_DPAD_LEFT_MASK, BUTTON_DPAD_RIGHT_MASK,
+    BUTTON_DPAD_UP_MASK, BUTTON_EAST_MASK, BUTTON_L1_MASK, BUTTON_L2_MASK, BUTTON_NORTH_MASK,
+    BUTTON_ORDER, BUTTON_R1_MASK, BUTTON_R2_MASK, BUTTON_SELECT_MASK, BUTTON_SOUTH_MASK,
+    BUTTON_START_MASK, BUTTON_WEST_MASK,
+};
+
+/// Serialized keyboard layout: key names for each direction plus physical
+/// button name to key names, e.g. `{"left": ["A", "Left"], "buttons": {"West": ["U"]}}`.
+///
+/// Keys press generic physical buttons so the regular [`super::mapping`]
+/// layer still decides which SF6 button they become.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct KeyboardBindingsConfig {
+    up: Vec<String>,
+    down: Vec<String>,
+    left: Vec<String>,
+    right: Vec<String>,
+    buttons: BTreeMap<String, Vec<String>>,
+}
+
+#[derive(Clone)]
+pub(crate) struct KeyboardBindings {
+    up: Vec<u8>,
+    down: Vec<u8>,
+    left: Vec<u8>,
+    right: Vec<u8>,
+    buttons: Vec<(u32, u8)>,
+}
+
+impl Default for KeyboardBindings {
+    fn default() -> Self {
+        Self {
+            up: vec![b'W', 0x26],
+            down: vec![b'S', 0x28],
+            left: vec![b'A', 0x25],
+            right: vec![b'D', 0x27],
+            buttons: vec![
+                (BUTTON_WEST_MASK, b'U'),
+                (BUTTON_NORTH_MASK, b'I'),
+                (BUTTON_R1_MASK, b'O'),
+                (BUTTON_SOUTH_MASK, b'J'),
+                (BUTTON_EAST_MASK, b'K'),
+                (BUTTON_R2_MASK, b'L'),
+                (BUTTON_L1_MASK, b'P'),
+                (BUTTON_L2_MASK, 0xBA),
+                (BUTTON_SELECT_MASK, 0x08),
+                (BUTTON_START_MASK, 0x0D),
+            ],
+        }
+    }
+}
+
+impl KeyboardBindings {
+    pub(crate) fn from_config(config: &KeyboardBindingsConfig) -> Result<Self, String> {
+        let mut buttons = Vec::new();
+        for (physical, key_names) in &config.buttons {
+            let index = BUTTON_ORDER
+                .iter()
+                .position(|candidate| candidate == physical)
+                .ok_or_else(|| {
+                    format!("Unknown physical button '{physical}' in keyboard bindings.")
+                })?;
+            for key in parse_keys(key_names)? {
+                buttons.push((1u32 << index, key));
+            }
+        }
+
+        Ok(Self {
+            up: parse_keys(&config.up)?,
+            down: parse_keys(&config.down)?,
+            left: parse_keys(&config.left)?,
+            right: parse_keys(&config.right)?,
+            buttons,
+        })
+    }
+
+    pub(crate) fn to_config(&self) -> KeyboardBindingsConfig {
+        let mut buttons: BTreeMap<String, Vec<String>> = BTreeMap::new();
+        for (mask, key) in &self.buttons {
+            let physical = BUTTON_ORDER[mask.trailing_zeros() as usize];
+            buttons
+                .entry(physical.to_string())
+                .or_default()
+                .push(key_name(*key));
+        }
+
+        KeyboardBindingsConfig {
+            up: self.up.iter().copied().map(key_name).collect(),
+            down: self.down.iter().copied().map(key_name).collect(),
+            left: self.left.iter().copied().map(key_name).collect(),
+            right: self.right.iter().copied().map(key_name).collect(),
+            buttons,
+        }
+    }
+
+    /// Resolves the bound keys into `(direction, down_mask)` using `is_down`
+    /// to query a virtual-key code.
+    ///
+    /// Direction keys also set the dpad bits so the worker's SOCD stage can
+    /// apply the configured policy; until then opposite keys cancel out.
+    #[cfg_attr(not(windows), allow(dead_code))]
+    pub(crate) fn resolve(&self, is_down: impl Fn(u8) -> bool) -> (u8, u32) {
+        let any_down = |keys: &[u8]| keys.iter().any(|key| is_down(*key));
+        let (up, down) = (any_down(&self.up), any_down(&self.down));
+        let (left, right) = (any_down(&self.left), any_down(&self.right));
+
+        let mut down_mask = self
+            .buttons
+            .iter()
+            .filter(|(_, key)| is_down(*key))
+            .fold(0, |mask, (button, _)| mask | button);
+        for (held, mask) in [
+            (up, BUTTON_DPAD_UP_MASK),
+            (down, BUTTON_DPAD_DOWN_MASK),
+            (left, BUTTON_DPAD_LEFT_MASK),
+            (right, BUTTON_DPAD_RIGHT_MASK),
+        ] {
+            if held {
+                down_mask |= mask;
+            }
+        }
+
+        let horizontal = i32::from(right) - i32::from(left);
+        let vertical = i32::from(up) - i32::from(down);
+        (to_direction(horizontal, vertical), down_mask)
+    }
+}
+
+const NAMED_KEYS: [(&str, u8); 33] = [
+    ("Backspace", 0x08),
+    ("Tab", 0x09),
+    ("Enter", 0x0D),
+    ("Shift", 0x10),
+    ("Ctrl", 0x11),
+    ("Alt", 0x12),
+    ("Space", 0x20),
+    ("Left", 0x25),
+    ("Up", 0x26),
+    ("Right", 0x27),
+    ("Down", 0x28),
+    ("LShift", 0xA0),
+    ("RShift", 0xA1),
+    ("LCtrl", 0xA2),
+    ("RCtrl", 0xA3),
+    ("LAlt", 0xA4),
+    ("RAlt", 0xA5),
+    (";", 0xBA),
+    ("=", 0xBB),
+    (",", 0xBC),
+    ("-", 0xBD),
+    (".", 0xBE),
+    ("/", 0xBF),
+    ("`", 0xC0),
+    ("[", 0xDB),
+    ("\\", 0xDC),
+    ("]", 0xDD),
+    ("'", 0xDE),
+    ("Insert", 0x2D),
+    ("Delete", 0x2E),
+    ("Home", 0x24),
+    ("End", 0x23),
+    ("Escape", 0x1B),
+];
+
+/// Parses a key name into a Windows virtual-key code. Letters and digits map
+/// to themselves, `Numpad0`-`Numpad9` and `F1`-`F12` are recognized, and the
+/// remaining keys come from [`NAMED_KEYS`].
+fn parse_key(name: &str) -> Option<u8> {
+    if let Some((_, code)) = NAMED_KEYS.iter().find(|(candidate, _)| *candidate == name) {
+        return Some(*code);
+    }
+
+    let bytes = name.as_bytes();
+    if bytes.len() == 1 && bytes[0].is_ascii_alphanumeric() {
+        return Some(bytes[0].to_ascii_uppercase());
+    }
+
+    if let Some(digit) = name.strip_prefix("Numpad") {
+        return digit
+            .parse::<u8>()
+            .ok()
+            .filter(|digit| *digit <= 9)
+            .map(|digit| 0x60 + digit);
+    }
+
+    if let Some(number) = name.strip_prefix('F') {
+        return number
+            .parse::<u8>()
+            .ok()
+            .filter(|number| (1..=12).contains(number))
+            .map(|number| 0x6F + number);
+    }
+
+    None
+}
+
+fn key_name(code: u8) -> String {
+    if let Some((name, _)) = NAMED_KEYS.iter().find(|(_, candidate)| *candidate == code) {
+        return (*name).to_string();
+    }
+
+    match code {
+        b'0'..=b'9' | b'A'..=b'Z' => char::from(code).to_string(),
+        0x60..=0x69 => format!("Numpad{}", code - 0x60),
+        0x70..=0x7B => format!("F{}", code - 0x6F),
+        _ => format!("0x{code:02X}"),
+    }
+}
+
+fn parse_keys(names: &[String]) -> Result<Vec<u8>, String> {
+    names
+        .iter()
+        .map(|name| {
+            parse_key(name).ok_or_else(|| format!("Unknown key '{name}' in keyboard bindings."))
+        })
+        .collect()
+}
@@ -0,0 +1,179 @@
+//! Opt-in instrumentation of how old a frame is by the time the frontend
+//! paints it, split into the stages between the tick deadline and the paint.
+
+use std::{
+    collections::VecDeque,
+    time::{Duration, Instant},
+};
+
+use serde::Serialize;
+
+/// Samples kept per stage, one minute of frames; percentiles cover these.
+const MAX_SAMPLES: usize = 3_600;
+/// Emitted frames awaiting `input_ack`; older frames can no longer be acked.
+const MAX_PENDING_FRAMES: usize = 120;
+const REPORT_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Worker-side timings of one live frame.
+pub(crate) struct FrameTiming {
+    pub frame: u64,
+    /// How late the pacer woke for this tick.
+    pub pacing: Duration,
+    pub tick_start: Instant,
+    /// When `poll` returned the device state.
+    pub read_at: Instant,
+    /// When the frame had been published on every event channel.
+    pub emitted_at: Instant,
+}
+
+#[derive(Clone, Copy, Default, Serialize)]
+pub struct LatencyStageStats {
+    samples: usize,
+    p50_us: u64,
+    p95_us: u64,
+    max_us: u64,
+}
+
+/// Answer to `input_latency_report` and payload of `input/latency`.
+#[derive(Clone, Copy, Default, Serialize)]
+pub struct InputLatencyReport {
+    enabled: bool,
+    /// Pacer wake-up past the tick deadline, i.e. sleep overshoot.
+    pacing: LatencyStageStats,
+    /// Tick start to `poll` returning.
+    poll: LatencyStageStats,
+    /// Device read to the frame being emitted, covering the pipeline and the
+    /// Tauri emit path.
+    emit: LatencyStageStats,
+    /// Emit to the frontend's `input_ack`.
+    render: LatencyStageStats,
+    /// Device read to `input_ack`: how stale the painted input is.
+    total: LatencyStageStats,
+}
+
+#[derive(Default)]
+struct StageSamples(VecDeque<u64>);
+
+impl StageSamples {
+    fn push(&mut self, duration: Duration) {
+        if self.0.len() == MAX_SAMPLES {
+            self.0.pop_front();
+        }
+        self.0.push_back(duration.as_micros() as u64);
+    }
+
+    fn stats(&self) -> LatencyStageStats {
+        let mut sorted: Vec<u64> = self.0.iter().copied().collect();
+        sorted.sort_unstable();
+        let percentile = |percent: usize| {
+            sorted
+                .get((sorted.len() * percent / 100).min(sorted.len().saturating_sub(1)))
+                .copied()
+                .unwrap_or_default()
+        };
+        LatencyStageStats {
+            samples: sorted.len(),
+            p50_us: percentile(50),
+            p95_us: percentile(95),
+            max_us: sorted.last().copied().unwrap_or_default(),
+        }
+    }
+}
+
+/// Collects [`FrameTiming`]s from the live worker and `input_ack`s from the
+/// frontend while enabled.
+#[derive(Default)]
+pub(crate) struct LatencyTracker {
+    enabled: bool,
+    emit_reports: bool,
+    last_report: Option<Instant>,
+    /// Frame number, read and emit time of frames not acked yet.
+    pending: VecDeque<(u64, Instant, Instant)>,
+    pacing: StageSamples,
+    poll: StageSamples,
+    emit: StageSamples,
+    render: StageSamples,
+    total: StageSamples,
+}
+
+impl LatencyTracker {
+    /// Starts or stops measuring; samples from an earlier session are kept
+    /// until the next reset. `input/latency` is emitted every second while
+    /// `emit_reports` is set.
+    pub(crate) fn configure(&mut self, enabled: bool, emit_reports: bool) {
+        self.enabled = enabled;
+        self.emit_reports = enabled && emit_reports;
+        self.last_report = None;
+    }
+
+    /// Records one frame and returns a report when one is due on
+    /// `input/latency`.
+    pub(crate) fn record(&mut self, timing: FrameTiming) -> Option<InputLatencyReport> {
+        if !self.enabled {
+            return None;
+        }
+
+        self.pacing.push(timing.pacing);
+        self.poll
+            .push(timing.read_at.saturating_duration_since(timing.tick_start));
+        self.emit
+            .push(timing.emitted_at.saturating_duration_since(timing.read_at));
+        if self.pending.len() == MAX_PENDING_FRAMES {
+            self.pending.pop_front();
+        }
+        self.pending
+            .push_back((timing.frame, timing.read_at, timing.emitted_at));
+
+        let due = self
+            .last_report
+            .is_none_or(|last| timing.emitted_at.duration_since(last) >= REPORT_INTERVAL);
+        if !self.emit_reports || !due {
+            return None;
+        }
+        self.last_report = Some(timing.emitted_at);
+        Some(self.report())
+    }
+
+    /// Marks `frame` as painted. Unknown or already acked frames are ignored,
+    /// so the frontend may ack every frame it renders without checking.
+    pub(crate) fn ack(&mut self, frame: u64, rendered_at: Instant) {
+        if !self.enabled {
+            return;
+        }
+        let Some(index) = self
+            .pending
+            .iter()
+            .position(|(pending, ..)| *pending == frame)
+        else {
+            return;
+        };
+
+        let (_, read_at, emitted_at) = self.pending[index];
+        // Frames before this one were skipped by the frontend.
+        self.pending.drain(..=index);
+        self.render
+            .push(rendered_at.saturating_duration_since(emitted_at));
+        self.total
+            .push(rendered_at.saturating_duration_since(read_at));
+    }
+
+    pub(crate) fn report(&self) -> InputLatencyReport {
+        InputLatencyReport {
+            enabled: self.enabled,
+            pacing: self.pacing.stats(),
+            poll: self.poll.stats(),
+            emit: self.emit.stats(),
+            render: self.render.stats(),
+            total: self.total.stats(),
+        }
+    }
+
+    /// Drops the samples of the previous run, whose frame numbers restart.
+    pub(crate) fn reset(&mut self) {
+        *self = Self {
+            enabled: self.enabled,
+            emit_reports: self.emit_reports,
+            ..Self::default()
+        };
+    }
+}
@@ -0,0 +1,114 @@
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+
+use super::{
+    BUTTON_DI_MASK, BUTTON_EAST_MASK, BUTTON_HK_MASK, BUTTON_HP_MASK, BUTTON_L1_MASK,
+    BUTTON_L2_MASK, BUTTON_LK_MASK, BUTTON_LP_MASK, BUTTON_MK_MASK, BUTTON_MP_MASK,
+    BUTTON_NORTH_MASK, BUTTON_ORDER, BUTTON_PARRY_MASK, BUTTON_R1_MASK, BUTTON_R2_MASK,
+    BUTTON_SOUTH_MASK, BUTTON_WEST_MASK, SF6_BUTTON_ORDER,
+};
+
+/// Serialized form of a [`ButtonMapping`]: physical button name to the SF6
+/// buttons it presses, e.g. `{"bindings": {"West": ["LP"], "L1": ["DI"]}}`.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct ButtonMappingConfig {
+    bindings: BTreeMap<String, Vec<String>>,
+}
+
+/// Translates the generic physical `down_mask` reported by the backends into
+/// an SF6 button mask. Physical buttons without a binding are dropped.
+#[derive(Clone, Copy)]
+pub(crate) struct ButtonMapping {
+    targets: [u16; BUTTON_ORDER.len()],
+}
+
+impl Default for ButtonMapping {
+    /// Mirrors SF6's default pad layout so unconfigured users see no change.
+    fn default() -> Self {
+        let mut mapping = Self {
+            targets: [0; BUTTON_ORDER.len()],
+        };
+        mapping.bind(BUTTON_WEST_MASK, BUTTON_LP_MASK);
+        mapping.bind(BUTTON_NORTH_MASK, BUTTON_MP_MASK);
+        mapping.bind(BUTTON_R1_MASK, BUTTON_HP_MASK);
+        mapping.bind(BUTTON_SOUTH_MASK, BUTTON_LK_MASK);
+        mapping.bind(BUTTON_EAST_MASK, BUTTON_MK_MASK);
+        mapping.bind(BUTTON_R2_MASK, BUTTON_HK_MASK);
+        mapping.bind(BUTTON_L1_MASK, BUTTON_DI_MASK);
+        mapping.bind(BUTTON_L2_MASK, BUTTON_PARRY_MASK);
+        mapping
+    }
+}
+
+impl ButtonMapping {
+    pub(crate) fn from_config(config: &ButtonMappingConfig) -> Result<Self, String> {
+        let mut mapping = Self {
+            targets: [0; BUTTON_ORDER.len()],
+        };
+
+        for (physical, sf6_buttons) in &config.bindings {
+            let physical_mask = name_to_button_mask(physical).ok_or_else(|| {
+                format!("Unknown physical button '{physical}' in button mapping.")
+            })?;
+
+            let mut sf6_mask = 0u16;
+            for sf6_button in sf6_buttons {
+                sf6_mask |= name_to_mask(&SF6_BUTTON_ORDER, sf6_button).ok_or_else(|| {
+                    format!("Unknown SF6 button '{sf6_button}' bound to '{physical}' in button mapping.")
+                })?;
+            }
+
+            mapping.bind(physical_mask, sf6_mask);
+        }
+
+        Ok(mapping)
+    }
+
+    pub(crate) fn to_config(self) -> ButtonMappingConfig {
+        let bindings = BUTTON_ORDER
+            .iter()
+            .zip(self.targets)
+            .filter(|(_, target)| *target != 0)
+            .map(|(physical, target)| {
+                let sf6_buttons = SF6_BUTTON_ORDER
+                    .iter()
+                    .enumerate()
+                    .filter(|(index, _)| target & (1 << index) != 0)
+                    .map(|(_, name)| (*name).to_string())
+                    .collect();
+                ((*physical).to_string(), sf6_buttons)
+            })
+            .collect();
+
+        ButtonMappingConfig { bindings }
+    }
+
+    pub(crate) fn apply(&self, physical_mask: u32) -> u16 {
+        self.targets
+            .iter()
+            .enumerate()
+            .filter(|(index, _)| physical_mask & (1 << index) != 0)
+            .fold(0, |sf6_mask, (_, target)| sf6_mask | target)
+    }
+
+    fn bind(&mut self, physical_mask: u32, sf6_mask: u16) {
+        let index = physical_mask.trailing_zeros() as usize;
+        self.targets[index] = sf6_mask;
+    }
+}
+
+pub(super) fn name_to_mask(order: &[&str], name: &str) -> Option<u16> {
+    order
+        .iter()
+        .position(|candidate| *candidate == name)
+        .map(|index| 1u16 << index)
+}
+
+/// [`name_to_mask`] for the generic physical buttons, which outgrew 16 bits.
+pub(super) fn name_to_button_mask(name: &str) -> Option<u32> {
+    BUTTON_ORDER
+        .iter()
+        .position(|candidate| *candidate == name)
+        .map(|index| 1u32 << index)
+}
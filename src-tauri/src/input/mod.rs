@@ -1,17 +1,92 @@
+mod analog;
+mod anomaly;
+mod attempt;
+mod charge;
+mod clock;
+mod combo;
+mod controller_preset;
+mod decoder;
+mod device_profile;
+mod emitter;
+mod export;
+mod facing;
+mod feedback;
+mod grouping;
+mod hid_profile;
+mod history;
+mod keyboard;
+mod latency;
+mod mapping;
+mod motion;
+mod notation;
+mod pacer;
+mod pipeline;
 mod platform;
+mod playback;
+mod reaction;
+mod recording;
+mod segments;
+mod socd;
+mod stats;
+mod status;
+mod stream;
+mod subframe;
+mod system;
+mod watchdog;
 
 use serde::{Deserialize, Serialize};
 use std::{
+    collections::BTreeMap,
+    path::{Path, PathBuf},
     sync::{
         atomic::{AtomicBool, Ordering},
-        Arc, Mutex,
+        mpsc::{self, Sender},
+        Arc, Mutex, MutexGuard,
     },
     thread::{self, JoinHandle},
     time::{Duration, Instant},
 };
 use tauri::{async_runtime::spawn_blocking, AppHandle, Emitter, State};
 
-const BUTTON_ORDER: [&str; 16] = [
+use analog::{AnalogConfig, AnalogSettings, DirectionPriority, DirectionSource};
+use anomaly::{AnomalyDetector, AnomalySettings, InputAnomaly};
+use attempt::{AttemptSettings, AttemptTracker, AttemptTriggerConfig};
+use charge::{ChargeFrames, ChargeSettings, MAX_RETENTION_FRAMES};
+use clock::{FrameClock, FrameTimestamp};
+use combo::{ComboScript, ComboScriptConfig, ComboTrial};
+use controller_preset::preset_analog;
+use decoder::{AnalogTriggers, RightStick};
+use device_profile::{
+    device_profile_key, DeviceProfileConfig, DeviceProfileEntry, DeviceProfileStore, HidDeviceId,
+};
+use emitter::{EmitSettings, InputEmitMode, PolledFrame};
+use export::{InputExportFormat, InputExportSummary};
+use facing::{Facing, FacingMirror};
+use feedback::{FeedbackEffect, FeedbackQueue, FeedbackRequest, InputFeedbackResult};
+use grouping::{GroupingSettings, MAX_GROUPING_FRAMES};
+use hid_profile::HidProfile;
+use history::InputHistory;
+use keyboard::{KeyboardBindings, KeyboardBindingsConfig};
+use latency::{FrameTiming, InputLatencyReport, LatencyTracker};
+use mapping::{ButtonMapping, ButtonMappingConfig};
+use motion::{motion_preset, motion_preset_names, MotionSettings};
+use notation::{notation, InputNotation, DEFAULT_NEUTRAL_GAP_FRAMES};
+use pacer::{FramePacer, InputTimingStats};
+use pipeline::FramePipeline;
+use platform::MergePolicy;
+use playback::{playback_frame_duration, PlaybackSource, MAX_PLAYBACK_SPEED, MIN_PLAYBACK_SPEED};
+use reaction::{ReactionConfig, ReactionSummary, ReactionTrainer};
+use recording::{InputRecorder, InputRecordingSummary};
+use segments::SegmentSettings;
+use socd::{SocdCleaner, SocdPolicy};
+use stats::{InputStats, InputStatsSummary};
+use status::{InputStatus, StatusReporter};
+use stream::{InputStreamInfo, InputStreamServer, StreamHandle};
+use subframe::{PollingSettings, SubFrameAggregator, FRAME_RATE_HZ, MAX_POLLING_HZ};
+use system::SystemAction;
+use watchdog::{HidDiagnostics, WatchdogSettings, MAX_STALE_MS, MIN_STALE_MS};
+
+const BUTTON_ORDER: [&str; 21] = [
     "South",
     "East",
     "West",
@@ -28,76 +103,638 @@ const BUTTON_ORDER: [&str; 16] = [
     "DPadDown",
     "DPadLeft",
     "DPadRight",
+    "Touchpad",
+    "Button13",
+    "Button14",
+    "Button15",
+    "Button16",
 ];
+const SF6_BUTTON_ORDER: [&str; 8] = ["LP", "MP", "HP", "LK", "MK", "HK", "DI", "Parry"];
 const FRAME_DURATION: Duration = Duration::from_nanos(16_666_667);
+const FRAMES_PER_SECOND: usize = 60;
+const MAX_HISTORY_SECONDS: u32 = 600;
+/// One minute.
+const MAX_KEEPALIVE_FRAMES: u64 = 3_600;
+/// Consecutive failed polls after which the backend is treated as unplugged.
+const DISCONNECT_POLL_ERRORS: u32 = 3;
+const RECONNECT_INTERVAL: Duration = Duration::from_secs(1);
+/// How long `input_calibrate_stick` watches the resting stick.
+const STICK_CALIBRATION_DURATION: Duration = Duration::from_secs(1);
+/// Raw capture blocks on reads this long so it still notices a stop request.
+const RAW_READ_TIMEOUT_MS: i32 = 50;
+/// Longest gap `input_start_raw` accepts between two `input/raw` events.
+const MAX_RAW_INTERVAL_MS: u64 = 1_000;
+/// Device key prefix selecting a HID device by serial number instead of path.
+pub(crate) const SERIAL_KEY_PREFIX: &str = "serial:";
+
+pub(crate) const BUTTON_SOUTH_MASK: u32 = 1 << 0;
+pub(crate) const BUTTON_EAST_MASK: u32 = 1 << 1;
+pub(crate) const BUTTON_WEST_MASK: u32 = 1 << 2;
+pub(crate) const BUTTON_NORTH_MASK: u32 = 1 << 3;
+pub(crate) const BUTTON_L1_MASK: u32 = 1 << 4;
+pub(crate) const BUTTON_R1_MASK: u32 = 1 << 5;
+pub(crate) const BUTTON_L2_MASK: u32 = 1 << 6;
+pub(crate) const BUTTON_R2_MASK: u32 = 1 << 7;
+pub(crate) const BUTTON_SELECT_MASK: u32 = 1 << 8;
+pub(crate) const BUTTON_START_MASK: u32 = 1 << 9;
+pub(crate) const BUTTON_L3_MASK: u32 = 1 << 10;
+pub(crate) const BUTTON_R3_MASK: u32 = 1 << 11;
+pub(crate) const BUTTON_DPAD_UP_MASK: u32 = 1 << 12;
+pub(crate) const BUTTON_DPAD_DOWN_MASK: u32 = 1 << 13;
+pub(crate) const BUTTON_DPAD_LEFT_MASK: u32 = 1 << 14;
+pub(crate) const BUTTON_DPAD_RIGHT_MASK: u32 = 1 << 15;
+pub(crate) const BUTTON_TOUCHPAD_MASK: u32 = 1 << 16;
+/// First of DirectInput buttons 13-16, which have no gamepad name and take
+/// the next three bits.
+#[cfg_attr(not(windows), allow(dead_code))]
+pub(crate) const BUTTON_13_MASK: u32 = 1 << 17;
+pub(crate) const BUTTON_DPAD_MASK: u32 =
+    BUTTON_DPAD_UP_MASK | BUTTON_DPAD_DOWN_MASK | BUTTON_DPAD_LEFT_MASK | BUTTON_DPAD_RIGHT_MASK;
+
+pub(crate) const BUTTON_LP_MASK: u16 = 1 << 0;
+pub(crate) const BUTTON_MP_MASK: u16 = 1 << 1;
+pub(crate) const BUTTON_HP_MASK: u16 = 1 << 2;
+pub(crate) const BUTTON_LK_MASK: u16 = 1 << 3;
+pub(crate) const BUTTON_MK_MASK: u16 = 1 << 4;
+pub(crate) const BUTTON_HK_MASK: u16 = 1 << 5;
+pub(crate) const BUTTON_DI_MASK: u16 = 1 << 6;
+pub(crate) const BUTTON_PARRY_MASK: u16 = 1 << 7;
 
-pub(crate) const BUTTON_SOUTH_MASK: u16 = 1 << 0;
-pub(crate) const BUTTON_EAST_MASK: u16 = 1 << 1;
-pub(crate) const BUTTON_WEST_MASK: u16 = 1 << 2;
-pub(crate) const BUTTON_NORTH_MASK: u16 = 1 << 3;
-pub(crate) const BUTTON_L1_MASK: u16 = 1 << 4;
-pub(crate) const BUTTON_R1_MASK: u16 = 1 << 5;
-pub(crate) const BUTTON_L2_MASK: u16 = 1 << 6;
-pub(crate) const BUTTON_R2_MASK: u16 = 1 << 7;
-pub(crate) const BUTTON_SELECT_MASK: u16 = 1 << 8;
-pub(crate) const BUTTON_START_MASK: u16 = 1 << 9;
-pub(crate) const BUTTON_L3_MASK: u16 = 1 << 10;
-pub(crate) const BUTTON_R3_MASK: u16 = 1 << 11;
-pub(crate) const BUTTON_DPAD_UP_MASK: u16 = 1 << 12;
-pub(crate) const BUTTON_DPAD_DOWN_MASK: u16 = 1 << 13;
-pub(crate) const BUTTON_DPAD_LEFT_MASK: u16 = 1 << 14;
-pub(crate) const BUTTON_DPAD_RIGHT_MASK: u16 = 1 << 15;
-
-#[derive(Clone, Copy, Debug, Deserialize)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
 #[serde(rename_all = "lowercase")]
 pub enum NativeInputMode {
     XInput,
     Hid,
+    Keyboard,
+    /// Legacy arcade sticks and other game controllers through DirectInput
+    /// 8, Windows only.
+    DirectInput,
+    Evdev,
+    /// gilrs fallback for controllers the other backends cannot decode.
+    Generic,
+    /// Xbox-layout gamepads through Windows.Gaming.Input, Windows only.
+    /// Windows delivers its input only while this app has focus, so the pad
+    /// reads neutral while SF6 is in front.
+    Wgi,
+    /// The first mode of the `input_set_auto_order` list with a usable
+    /// device. `input_start` resolves it, so it never reaches a worker.
+    Auto,
 }
 
-#[derive(Clone, Serialize)]
+/// Modes `auto` tries, best first: HID decodes the most and reads reports
+/// as they arrive, XInput and evdev cover most other pads, and the polled
+/// fallbacks come last. The keyboard is left out, since it always counts as
+/// present, and so is `wgi`, which goes quiet whenever SF6 has focus.
+const DEFAULT_AUTO_ORDER: [NativeInputMode; 5] = [
+    NativeInputMode::Hid,
+    NativeInputMode::XInput,
+    NativeInputMode::Evdev,
+    NativeInputMode::DirectInput,
+    NativeInputMode::Generic,
+];
+
+impl NativeInputMode {
+    pub(crate) const fn name(self) -> &'static str {
+        match self {
+            Self::XInput => "xinput",
+            Self::Hid => "hid",
+            Self::Keyboard => "keyboard",
+            Self::DirectInput => "directinput",
+            Self::Evdev => "evdev",
+            Self::Generic => "generic",
+            Self::Wgi => "wgi",
+            Self::Auto => "auto",
+        }
+    }
+
+    /// Strips this mode's `"<mode>:"` prefix from a [`NativeInputDevice`] id,
+    /// leaving the backend-specific key (HID path, XInput slot, ...).
+    pub(crate) fn device_key(self, device_id: &str) -> Result<&str, String> {
+        device_id
+            .strip_prefix(self.name())
+            .and_then(|rest| rest.strip_prefix(':'))
+            .ok_or_else(|| {
+                format!(
+                    "Device '{device_id}' does not belong to native input mode '{}'.",
+                    self.name()
+                )
+            })
+    }
+}
+
+/// `mode` argument of `input_start`: one mode, or a list merged into a single
+/// stream, e.g. `["hid", "keyboard"]`. Earlier modes win `direction` unless
+/// `input_set_merge_policy` picks `last_active`.
+#[derive(Clone, Deserialize)]
+#[serde(untagged)]
+pub enum InputModeSelection {
+    Single(NativeInputMode),
+    Merged(Vec<NativeInputMode>),
+}
+
+impl InputModeSelection {
+    fn into_modes(self) -> Vec<NativeInputMode> {
+        match self {
+            Self::Single(mode) => vec![mode],
+            Self::Merged(modes) => modes,
+        }
+    }
+}
+
+/// Rejects mode lists that cannot run together.
+fn validate_modes(modes: &[NativeInputMode]) -> Result<(), String> {
+    if modes.is_empty() {
+        return Err("At least one native input mode is required.".to_string());
+    }
+    for (index, mode) in modes.iter().enumerate() {
+        if modes[..index].contains(mode) {
+            return Err(format!(
+                "Native input mode '{}' is listed more than once.",
+                mode.name()
+            ));
+        }
+    }
+    if modes.contains(&NativeInputMode::Auto) && modes.len() > 1 {
+        return Err("Native input mode 'auto' cannot be merged with other modes.".to_string());
+    }
+    if modes.contains(&NativeInputMode::XInput) && modes.contains(&NativeInputMode::DirectInput) {
+        return Err("Native input modes 'xinput' and 'directinput' cannot be merged: DirectInput also lists XInput pads, so they would be read twice.".to_string());
+    }
+    if modes.contains(&NativeInputMode::Wgi)
+        && modes
+            .iter()
+            .any(|mode| matches!(mode, NativeInputMode::XInput | NativeInputMode::DirectInput))
+    {
+        return Err("Native input mode 'wgi' cannot be merged with 'xinput' or 'directinput': they list the same pads, so they would be read twice.".to_string());
+    }
+    if modes.contains(&NativeInputMode::Generic)
+        && modes
+            .iter()
+            .any(|mode| !matches!(mode, NativeInputMode::Generic | NativeInputMode::Keyboard))
+    {
+        return Err("Native input mode 'generic' can only be merged with 'keyboard': it reads every gamepad the OS knows, so the other controller backends would be read twice.".to_string());
+    }
+    Ok(())
+}
+
+#[derive(Clone, Default, Serialize)]
 pub struct NativeInputDetectResult {
-    xinput: bool,
-    hid: bool,
+    pub(crate) xinput: bool,
+    pub(crate) hid: bool,
+    pub(crate) keyboard: bool,
+    pub(crate) directinput: bool,
+    pub(crate) evdev: bool,
+    /// Gamepads the generic fallback found that no other backend lists by
+    /// VID/PID.
+    pub(crate) generic: bool,
+    pub(crate) wgi: bool,
+    /// Every supported device found, probed for `connection` and `openable`.
+    /// The flags above are derived from it.
+    pub(crate) devices: Vec<NativeInputDevice>,
+    /// HID gamepads that were found but cannot be used, and why.
+    pub(crate) rejected: Vec<NativeInputRejectedDevice>,
+    /// Every combination of the detected modes `input_start` can merge.
+    pub(crate) merges: Vec<Vec<NativeInputMode>>,
 }
 
 impl NativeInputDetectResult {
-    pub(crate) const fn new(xinput: bool, hid: bool) -> Self {
-        Self { xinput, hid }
+    pub(crate) fn from_devices(
+        devices: Vec<NativeInputDevice>,
+        rejected: Vec<NativeInputRejectedDevice>,
+    ) -> Self {
+        let found = |mode| devices.iter().any(|device| device.backend == mode);
+        let detected: Vec<NativeInputMode> = [
+            NativeInputMode::XInput,
+            NativeInputMode::Hid,
+            NativeInputMode::Keyboard,
+            NativeInputMode::DirectInput,
+            NativeInputMode::Evdev,
+            NativeInputMode::Generic,
+            NativeInputMode::Wgi,
+        ]
+        .into_iter()
+        .filter(|mode| found(*mode))
+        .collect();
+
+        let merges = (1u32..1 << detected.len())
+            .filter(|subset| subset.count_ones() >= 2)
+            .map(|subset| {
+                detected
+                    .iter()
+                    .enumerate()
+                    .filter(|(index, _)| subset & (1 << index) != 0)
+                    .map(|(_, mode)| *mode)
+                    .collect::<Vec<_>>()
+            })
+            .filter(|modes| validate_modes(modes).is_ok())
+            .collect();
+
+        Self {
+            xinput: found(NativeInputMode::XInput),
+            hid: found(NativeInputMode::Hid),
+            keyboard: found(NativeInputMode::Keyboard),
+            directinput: found(NativeInputMode::DirectInput),
+            evdev: found(NativeInputMode::Evdev),
+            generic: found(NativeInputMode::Generic),
+            wgi: found(NativeInputMode::Wgi),
+            devices,
+            rejected,
+            merges,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DeviceConnection {
+    Usb,
+    Bluetooth,
+    Other,
+}
+
+/// Why a HID device was not offered as a controller.
+#[derive(Clone, Copy, PartialEq, Eq, Serialize)]
+#[cfg_attr(not(any(windows, target_os = "macos")), allow(dead_code))]
+#[serde(rename_all = "snake_case")]
+pub enum HidRejection {
+    /// Not a joystick or gamepad collection at all; never reported.
+    NotGamepad,
+    /// A gamepad whose reports cannot be decoded, typically a fight stick
+    /// left in PC mode instead of PS4 or Switch mode.
+    UnsupportedGamepad,
+}
+
+#[derive(Clone, Serialize)]
+pub struct NativeInputRejectedDevice {
+    #[serde(flatten)]
+    pub(crate) device: NativeInputDevice,
+    pub(crate) reason: HidRejection,
+}
+
+/// One entry of `input_list_devices`. `id` is `"<mode>:<key>"` and can be
+/// passed back to `input_start` to open that exact device.
+#[derive(Clone, Serialize)]
+pub struct NativeInputDevice {
+    pub(crate) id: String,
+    pub(crate) backend: NativeInputMode,
+    pub(crate) manufacturer: Option<String>,
+    pub(crate) product: Option<String>,
+    pub(crate) vendor_id: Option<u16>,
+    pub(crate) product_id: Option<u16>,
+    pub(crate) path: Option<String>,
+    /// Set when the device reports a non-empty one. `"hid:serial:<serial>"`
+    /// opens the device whichever port it is plugged into.
+    pub(crate) serial_number: Option<String>,
+    pub(crate) xinput_user_index: Option<u32>,
+    pub(crate) connection: Option<DeviceConnection>,
+    /// Whether a test open succeeded. Only `input_detect` probes HID and
+    /// evdev devices; entries that only exist while answering polls are
+    /// always openable.
+    pub(crate) openable: Option<bool>,
+}
+
+impl NativeInputDevice {
+    pub(crate) fn new(backend: NativeInputMode, key: &str) -> Self {
+        Self {
+            id: format!("{}:{key}", backend.name()),
+            backend,
+            manufacturer: None,
+            product: None,
+            vendor_id: None,
+            product_id: None,
+            path: None,
+            serial_number: None,
+            xinput_user_index: None,
+            connection: None,
+            openable: None,
+        }
+    }
+
+    /// Whether `device_id` names this device, by its `id` or by serial.
+    pub(crate) fn answers_to(&self, device_id: &str) -> bool {
+        self.id == device_id
+            || self.serial_number.as_deref().is_some_and(|serial| {
+                self.backend
+                    .device_key(device_id)
+                    .ok()
+                    .and_then(|key| key.strip_prefix(SERIAL_KEY_PREFIX))
+                    == Some(serial)
+            })
     }
 }
 
+#[derive(Clone, Copy, Serialize)]
+#[cfg_attr(not(windows), allow(dead_code))]
+#[serde(rename_all = "lowercase")]
+pub enum XInputBatteryType {
+    Disconnected,
+    Wired,
+    Alkaline,
+    Nimh,
+    Unknown,
+}
+
+#[derive(Clone, Copy, Serialize)]
+#[cfg_attr(not(windows), allow(dead_code))]
+#[serde(rename_all = "lowercase")]
+pub enum XInputBatteryLevel {
+    Empty,
+    Low,
+    Medium,
+    Full,
+}
+
+#[derive(Clone, Copy, Serialize)]
+pub struct XInputBattery {
+    pub(crate) battery_type: XInputBatteryType,
+    pub(crate) level: XInputBatteryLevel,
+}
+
+/// Answer to `input_controller_info`, also sent on `input/device` when an
+/// XInput slot disconnects or reconnects. A disconnected slot has only
+/// `user_index` set.
+#[derive(Clone, Default, Serialize)]
+pub struct XInputControllerInfo {
+    pub(crate) user_index: u32,
+    pub(crate) connected: bool,
+    /// `None` while disconnected or if the XInput runtime cannot report it.
+    pub(crate) battery: Option<XInputBattery>,
+    /// Device subtype, e.g. `"gamepad"` or `"arcade_stick"`.
+    pub(crate) subtype: Option<&'static str>,
+    pub(crate) wireless: bool,
+    pub(crate) force_feedback: bool,
+    pub(crate) voice: bool,
+    /// Plug-in modules such as a chatpad.
+    pub(crate) plugin_modules: bool,
+    /// Whether the device has the navigation buttons (Start, Back, dpad).
+    pub(crate) navigation: bool,
+}
+
 #[derive(Clone, Copy, Default)]
 pub(crate) struct InputSample {
     pub timestamp_ms: u64,
+    pub timestamp_us: u64,
     pub direction: u8,
-    pub down_mask: u16,
+    /// `direction` before facing mirroring.
+    pub direction_raw: u8,
+    pub down_mask: u32,
+    /// `down_mask` before facing mirroring.
+    pub down_mask_raw: u32,
+    /// Raw left stick bytes, read by `input_calibrate_stick`.
+    pub left_stick: Option<(u8, u8)>,
+    /// Left stick direction through the deadzone, 5 for devices without one.
+    /// The SOCD stage merges it back with the dpad once it has cleaned that.
+    pub stick_direction: u8,
+    /// What [`DirectionPriority::Auto`] stands for on the backend that read
+    /// the sample, so the SOCD stage merges the way the backend did.
+    pub auto_priority: DirectionPriority,
+    pub right_stick: Option<RightStick>,
+    pub triggers: Option<AnalogTriggers>,
+    /// When the first button newly pressed in this frame was polled; set by
+    /// the live worker only.
+    pub first_press_us: Option<u64>,
+    /// Arrival of the report that changed the state, for backends that read
+    /// reports as they come in rather than on the worker's poll.
+    pub reported_at: Option<Instant>,
+    /// `reported_at` on the run's clock; set by the live worker.
+    pub device_timestamp_us: Option<u64>,
 }
 
 impl InputSample {
-    pub(crate) fn neutral(timestamp_ms: u64) -> Self {
+    pub(crate) fn new(now: FrameTimestamp, direction: u8, down_mask: u32) -> Self {
         Self {
-            timestamp_ms,
-            direction: 5,
-            down_mask: 0,
+            timestamp_ms: now.timestamp_ms(),
+            timestamp_us: now.timestamp_us(),
+            direction,
+            direction_raw: direction,
+            down_mask,
+            down_mask_raw: down_mask,
+            left_stick: None,
+            stick_direction: 5,
+            auto_priority: DirectionPriority::Dpad,
+            right_stick: None,
+            triggers: None,
+            first_press_us: None,
+            reported_at: None,
+            device_timestamp_us: None,
+        }
+    }
+
+    pub(crate) fn neutral(now: FrameTimestamp) -> Self {
+        Self::new(now, 5, 0)
+    }
+}
+
+/// The sample one live tick hands on from `polled`. A failed poll reads
+/// neutral, stamped `now` like every sample of the tick, until
+/// [`DISCONNECT_POLL_ERRORS`] fail in a row; then it is `None` and the worker
+/// drops the source.
+fn tick_sample(
+    polled: Result<InputSample, String>,
+    now: FrameTimestamp,
+    poll_errors: &mut u32,
+) -> Option<InputSample> {
+    match polled {
+        Ok(sample) => {
+            *poll_errors = 0;
+            Some(sample)
+        }
+        Err(_) => {
+            *poll_errors += 1;
+            (*poll_errors < DISCONNECT_POLL_ERRORS).then(|| InputSample::neutral(now))
+        }
+    }
+}
+
+/// Inverse of `to_direction`: numpad direction to `(horizontal, vertical)`.
+pub(crate) fn direction_axes(direction: u8) -> (i32, i32) {
+    match direction {
+        1..=9 => {
+            let index = i32::from(direction - 1);
+            (index % 3 - 1, index / 3 - 1)
+        }
+        _ => (0, 0),
+    }
+}
+
+pub(crate) fn to_direction(horizontal: i32, vertical: i32) -> u8 {
+    match (horizontal, vertical) {
+        (0, 0) => 5,
+        (1, 0) => 6,
+        (-1, 0) => 4,
+        (0, 1) => 8,
+        (0, -1) => 2,
+        (1, 1) => 9,
+        (-1, 1) => 7,
+        (1, -1) => 3,
+        _ => 1,
+    }
+}
+
+/// Seat a live worker feeds, serialized as `"P1"` or `"P2"`. P1 is the
+/// player being trained; P2 is only displayed.
+#[derive(Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "UPPERCASE")]
+pub enum Player {
+    #[default]
+    P1,
+    P2,
+}
+
+impl Player {
+    fn other(self) -> Self {
+        match self {
+            Self::P1 => Self::P2,
+            Self::P2 => Self::P1,
         }
     }
 }
 
 #[derive(Clone, Serialize)]
-struct InputFramePayload {
+pub struct InputFramePayload {
+    player: Player,
     frame: u64,
     timestamp_ms: u64,
+    timestamp_us: u64,
+    /// Numpad direction relative to `facing`; `direction_raw` is the physical
+    /// one, with 6 always right.
     direction: u8,
+    direction_raw: u8,
     physical_down: Vec<String>,
+    down: Vec<String>,
+    /// `None` for sources without a right stick, such as the keyboard and
+    /// playback. Never mirrored by `facing`.
+    right_stick: Option<RightStick>,
+    /// `None` for sources whose triggers are digital.
+    triggers: Option<AnalogTriggers>,
+    /// SF6 buttons that went down or up since the previous frame of the run.
+    pressed: Vec<String>,
+    released: Vec<String>,
+    /// Consecutive frames each button in `down` has been held, counting this
+    /// one.
+    held_frames: BTreeMap<String, u64>,
+    /// Drive Impact, parry and throw held this frame, whether by chord or by
+    /// their macro button.
+    system: Vec<SystemAction>,
+    charge: ChargeFrames,
+    /// Poll time of the first press merged into this frame. Above 60 Hz it
+    /// can precede `timestamp_us` by up to a frame.
+    first_press_us: Option<u64>,
+    /// When the HID report that first changed this frame's state arrived,
+    /// taken by the device reader rather than rounded to a worker poll.
+    /// `None` for polled backends such as XInput and when nothing changed.
+    device_timestamp_us: Option<u64>,
+    /// `false` while the device is unplugged; the frame is then an explicit
+    /// neutral rather than real idle input.
+    connected: bool,
+}
+
+/// Sent once per worker run. Every frame timestamp of the run is `epoch_us`
+/// plus monotonic time elapsed since, so `timestamp_us - epoch_us` is a
+/// drift-free offset into the run.
+#[derive(Clone, Serialize)]
+struct InputStartedPayload {
+    player: Player,
+    /// The first of `modes`.
+    mode: NativeInputMode,
+    modes: Vec<NativeInputMode>,
+    epoch_us: u64,
+    /// Counts worker runs. Frame numbers restart at 0 with every run, so a
+    /// new `run` means displays keyed by frame should reset.
+    run: u64,
+}
+
+/// One changed report on `input/raw`. `report_id` is the first byte, which
+/// is the report ID on devices that number their reports. When the capture
+/// is throttled, reports that changed again within the interval are folded
+/// into the newest one.
+#[derive(Clone, Serialize)]
+struct InputRawReportPayload {
+    timestamp_us: u64,
+    report_id: u8,
+    length: usize,
+    hex: String,
+}
+
+/// Marks P2's errors and warnings, which share their events with P1's.
+fn player_message(player: Player, message: String) -> String {
+    match player {
+        Player::P1 => message,
+        Player::P2 => format!("P2: {message}"),
+    }
+}
+
+fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{byte:02x}")).collect()
+}
+
+/// Payload of `input/device-info`, sent whenever the worker opens its
+/// devices: at start and after every reconnect. `devices` follows the order
+/// of the run's modes; the keyboard has no entry.
+#[derive(Clone, Serialize)]
+struct InputDeviceInfoPayload {
+    player: Player,
+    devices: Vec<NativeInputDevice>,
+    /// Capabilities of the XInput slot, including its subtype.
+    xinput: Option<XInputControllerInfo>,
+}
+
+impl InputDeviceInfoPayload {
+    fn new(player: Player, source: &platform::InputSource) -> Self {
+        Self {
+            player,
+            devices: source.devices(),
+            xinput: source
+                .xinput_user_index()
+                .and_then(|user_index| platform::xinput_controller_info(user_index).ok()),
+        }
+    }
+}
+
+/// Payload of `input/active_source`: the mode a `last_active` run switched
+/// to.
+#[derive(Clone, Serialize)]
+struct InputActiveSourcePayload {
+    player: Player,
+    mode: NativeInputMode,
+}
+
+/// Payload of `input/device`, and of `input/device-lost` and
+/// `input/device-reconnected` sent beside it once a running source drops out
+/// or reopens. `xinput` is the slot of an XInput run, the one read last when
+/// the run is not pinned to a slot.
+#[derive(Clone, Serialize)]
+struct InputDevicePayload {
+    player: Player,
+    connected: bool,
+    xinput: Option<XInputControllerInfo>,
+}
+
+impl InputDevicePayload {
+    fn new(player: Player, connected: bool, xinput_user_index: Option<u32>) -> Self {
+        Self {
+            player,
+            connected,
+            xinput: xinput_user_index
+                .and_then(|user_index| platform::xinput_controller_info(user_index).ok()),
+        }
+    }
+}
+
+fn mask_to_buttons(mask: u32) -> Vec<String> {
+    mask_to_names(&BUTTON_ORDER, mask)
+}
+
+fn mask_to_sf6_buttons(mask: u16) -> Vec<String> {
+    mask_to_names(&SF6_BUTTON_ORDER, mask.into())
 }
 
-fn mask_to_buttons(mask: u16) -> Vec<String> {
-    BUTTON_ORDER
+fn mask_to_names(order: &[&str], mask: u32) -> Vec<String> {
+    order
         .iter()
         .enumerate()
         .filter_map(|(index, name)| {
-            let bit = 1u16 << index;
+            let bit = 1u32 << index;
             if mask & bit == bit {
                 Some((*name).to_string())
             } else {
@@ -107,57 +744,459 @@ fn mask_to_buttons(mask: u16) -> Vec<String> {
         .collect()
 }
 
+#[derive(Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+enum WorkerKind {
+    Live,
+    Playback,
+    Raw,
+}
+
 struct InputWorker {
+    kind: WorkerKind,
+    /// Live modes and pinned device, compared by `input_start`; no modes for
+    /// the other kinds.
+    modes: Vec<NativeInputMode>,
+    device_id: Option<String>,
+    /// Id of the connected device `input_start` resolved a live worker to,
+    /// pinned or not, which the other seat may not open.
+    device: Option<String>,
     stop_flag: Arc<AtomicBool>,
     join_handle: Option<JoinHandle<()>>,
+    /// Live workers only; playback has no device to signal.
+    feedback: Option<Sender<FeedbackRequest>>,
+    /// Live workers only. While set, the device keeps being polled but
+    /// frames are not published.
+    pause_flag: Option<Arc<AtomicBool>>,
+}
+
+#[derive(Clone, Default)]
+pub(crate) struct InputSettings {
+    pub mapping: ButtonMapping,
+    pub keyboard: KeyboardBindings,
+    pub emit: EmitSettings,
+    pub socd: SocdPolicy,
+    pub merge: MergePolicy,
+    /// Modes `auto` tries, best first; empty means [`DEFAULT_AUTO_ORDER`].
+    pub auto_order: Arc<[NativeInputMode]>,
+    pub motion: MotionSettings,
+    pub analog: AnalogSettings,
+    /// `analog` came from `input_configure` or stick calibration rather than
+    /// the opened device's preset, so `input_start` leaves it alone.
+    pub analog_configured: bool,
+    pub segments: SegmentSettings,
+    /// Publishes each press on `input/notation`.
+    pub notation_events: bool,
+    pub grouping: GroupingSettings,
+    pub polling: PollingSettings,
+    pub facing: Facing,
+    pub anomaly: AnomalySettings,
+    pub charge: ChargeSettings,
+    pub watchdog: WatchdogSettings,
+    /// Attempt segmentation, while a trigger is set.
+    pub attempts: Option<AttemptSettings>,
+    /// Generic decoder for HID reports the built-in formats do not cover.
+    pub hid_profile: Option<Arc<HidProfile>>,
+    /// Saved allow-list, refreshed by every `input_start`.
+    pub hid_allowlist: Arc<[HidDeviceId]>,
 }
 
 impl InputWorker {
-    fn start(app: AppHandle, mode: NativeInputMode) -> Result<Self, String> {
+    fn start(
+        app: AppHandle,
+        modes: Vec<NativeInputMode>,
+        device_id: Option<String>,
+        device: Option<String>,
+        profile: Option<String>,
+        player: Player,
+        shared: SharedInputState,
+    ) -> Result<Self, String> {
         let stop_flag = Arc::new(AtomicBool::new(false));
         let thread_stop_flag = Arc::clone(&stop_flag);
+        let pause_flag = Arc::new(AtomicBool::new(false));
+        let thread_pause_flag = Arc::clone(&pause_flag);
+        let (feedback_sender, feedback_requests) = mpsc::channel();
+        let worker_device_id = device_id.clone();
+        let status = StatusReporter::start(
+            app.clone(),
+            Arc::clone(&shared.status),
+            WorkerKind::Live,
+            &modes,
+            profile,
+            player,
+        );
+        let worker_modes = modes.clone();
 
         let join_handle = thread::Builder::new()
             .name("native-input-poller".to_string())
             .spawn(move || {
-                let mut source = match platform::InputSource::new(mode) {
-                    Ok(source) => source,
-                    Err(message) => {
-                        let _ = app.emit("input/error", message);
-                        return;
-                    }
-                };
+                let settings = shared
+                    .settings
+                    .lock()
+                    .map(|settings| settings.clone())
+                    .unwrap_or_default();
+                let mut source =
+                    match platform::InputSource::new(&modes, device_id.as_deref(), &settings) {
+                        Ok(source) => Some(source),
+                        Err(message) => {
+                            status.error(message.clone());
+                            let _ = app.emit("input/error", player_message(player, message));
+                            return;
+                        }
+                    };
+                status.running();
+                if let Ok(mut hid) = shared.hid.lock() {
+                    *hid = None;
+                }
+                if let Some(source) = source.as_ref() {
+                    let _ = app.emit(
+                        "input/device-info",
+                        InputDeviceInfoPayload::new(player, source),
+                    );
+                }
 
+                let clock = FrameClock::start();
+                let _ = app.emit(
+                    "input/started",
+                    InputStartedPayload {
+                        player,
+                        mode: modes[0],
+                        modes: modes.clone(),
+                        epoch_us: clock.epoch_us(),
+                        run: status.run(),
+                    },
+                );
+                let mut pacer = FramePacer::start(FRAME_DURATION);
+                let mut timing_stats = InputTimingStats::default();
+                let mut pipeline = FramePipeline::new(app.clone(), shared.clone());
+                let mut socd = SocdCleaner::default();
+                let mut feedback = FeedbackQueue::new(feedback_requests);
+                let mut subframe = SubFrameAggregator::default();
+                let mut facing = FacingMirror::default();
                 let mut frame_index: u64 = 0;
+                let mut poll_errors: u32 = 0;
+                let mut next_reconnect = Instant::now();
+                let mut resync_pending = false;
+                let mut xinput_user_index = None;
 
                 while !thread_stop_flag.load(Ordering::Relaxed) {
                     let tick_start = Instant::now();
-                    let sample = source.poll();
+                    let settings = shared
+                        .settings
+                        .lock()
+                        .map(|settings| settings.clone())
+                        .unwrap_or_default();
+                    let now = clock.now();
 
-                    let payload = InputFramePayload {
-                        frame: frame_index,
-                        timestamp_ms: sample.timestamp_ms,
-                        direction: sample.direction,
-                        physical_down: mask_to_buttons(sample.down_mask),
-                    };
+                    if source.is_none() && tick_start >= next_reconnect {
+                        match platform::InputSource::new(&modes, device_id.as_deref(), &settings) {
+                            Ok(reopened) => {
+                                let slot = reopened.xinput_user_index().or(xinput_user_index);
+                                let _ = app.emit(
+                                    "input/device-info",
+                                    InputDeviceInfoPayload::new(player, &reopened),
+                                );
+                                source = Some(reopened);
+                                poll_errors = 0;
+                                let payload = InputDevicePayload::new(player, true, slot);
+                                let _ = app.emit("input/device", payload.clone());
+                                let _ = app.emit("input/device-reconnected", payload);
+                            }
+                            Err(_) => next_reconnect = tick_start + RECONNECT_INTERVAL,
+                        }
+                    }
+
+                    let polled = source.as_mut().map(|source| source.poll(now, &settings));
+                    let read_at = Instant::now();
+                    // An unpinned XInput source moves to whichever slot
+                    // answers; the status follows it for input_controller_info.
+                    let polled_slot = source
+                        .as_ref()
+                        .and_then(|source| source.xinput_user_index());
+                    if polled_slot.is_some() && polled_slot != xinput_user_index {
+                        xinput_user_index = polled_slot;
+                        status.xinput_user_index(polled_slot);
+                    }
+                    let sample = polled.and_then(|polled| {
+                        let sample = tick_sample(polled, now, &mut poll_errors);
+                        if sample.is_none() {
+                            source = None;
+                            next_reconnect = tick_start + RECONNECT_INTERVAL;
+                            let payload = InputDevicePayload::new(player, false, xinput_user_index);
+                            let _ = app.emit("input/device", payload.clone());
+                            let _ = app.emit("input/device-lost", payload);
+                        }
+                        sample
+                    });
+                    if let Some(reports) = source
+                        .as_mut()
+                        .and_then(|source| source.take_reports_read())
+                    {
+                        timing_stats.record_reports(reports);
+                    }
+                    if let Some(source) = source.as_mut() {
+                        if let Some(mode) = source.take_active_change() {
+                            let _ = app.emit(
+                                "input/active_source",
+                                InputActiveSourcePayload { player, mode },
+                            );
+                        }
+                        for warning in source.take_warnings() {
+                            let _ = app.emit("input/warning", player_message(player, warning));
+                        }
+                        if let Some(diagnostics) = source.hid_diagnostics() {
+                            if let Ok(mut hid) = shared.hid.lock() {
+                                *hid = Some(diagnostics);
+                            }
+                        }
+                    }
+                    feedback.update(source.as_mut(), tick_start);
+                    let connected = sample.is_some();
+                    let mut sample = sample.unwrap_or_else(|| InputSample::neutral(now));
+                    subframe.finish(&mut sample);
+                    sample.device_timestamp_us = sample
+                        .reported_at
+                        .map(|reported_at| clock.timestamp_at(reported_at).timestamp_us());
+                    socd.clean(&mut sample, settings.socd, settings.analog);
+                    facing.apply(&mut sample, settings.facing);
+                    let sf6_mask = settings.mapping.apply(sample.down_mask);
 
-                    let _ = app.emit("input/frame", payload);
-                    frame_index = frame_index.saturating_add(1);
+                    let mut frame = PolledFrame::new(frame_index, sample, sf6_mask, connected);
+                    frame.player = player;
+                    let paused = thread_pause_flag.load(Ordering::Relaxed);
+                    if paused {
+                        resync_pending = true;
+                    } else if std::mem::take(&mut resync_pending) {
+                        pipeline.resync(frame, &settings);
+                    } else {
+                        pipeline.push(frame, &settings);
+                    }
+                    status.frame(frame_index);
+                    let latency = shared.latency.lock().ok().filter(|_| !paused);
+                    if let Some(mut latency) = latency {
+                        let timing = FrameTiming {
+                            frame: frame_index,
+                            pacing: pacer.last_deviation(),
+                            tick_start,
+                            read_at,
+                            emitted_at: Instant::now(),
+                        };
+                        if let Some(report) = latency.record(timing) {
+                            let _ = app.emit("input/latency", report);
+                        }
+                    }
 
-                    let elapsed = tick_start.elapsed();
-                    if elapsed < FRAME_DURATION {
-                        thread::sleep(FRAME_DURATION - elapsed);
+                    let elapsed_frames = match settings.polling.sub_poll_interval() {
+                        Some(interval) => {
+                            pacer.wait_polling(&mut timing_stats, interval, |deadline, timer| {
+                                let polled = source.as_mut().map(|source| {
+                                    source.poll_until(deadline, timer, &clock, &settings)
+                                });
+                                if let Some(Ok(sample)) = polled {
+                                    subframe.push(&sample);
+                                }
+                            })
+                        }
+                        None => pacer.wait(&mut timing_stats),
+                    };
+                    frame_index = frame_index.saturating_add(elapsed_frames);
+                    if let Ok(mut timing) = shared.timing.lock() {
+                        *timing = timing_stats;
                     }
                 }
+
+                feedback.finish(source.as_mut());
+                pipeline.finish();
+                status.stopped();
             })
             .map_err(|error| format!("Failed to start native input polling thread: {error}"))?;
 
         Ok(Self {
+            kind: WorkerKind::Live,
+            modes: worker_modes,
+            device_id: worker_device_id,
+            device,
+            stop_flag,
+            join_handle: Some(join_handle),
+            feedback: Some(feedback_sender),
+            pause_flag: Some(pause_flag),
+        })
+    }
+
+    /// Replays `source` on the live channels at `speed` times the recorded
+    /// cadence, then emits `input/playback_finished` unless stopped early.
+    fn start_playback(
+        app: AppHandle,
+        mut source: PlaybackSource,
+        speed: f64,
+        shared: SharedInputState,
+    ) -> Result<Self, String> {
+        let stop_flag = Arc::new(AtomicBool::new(false));
+        let thread_stop_flag = Arc::clone(&stop_flag);
+        let status = StatusReporter::start(
+            app.clone(),
+            Arc::clone(&shared.status),
+            WorkerKind::Playback,
+            &[],
+            None,
+            Player::P1,
+        );
+
+        let join_handle = thread::Builder::new()
+            .name("input-playback".to_string())
+            .spawn(move || {
+                status.running();
+                let mut pacer = FramePacer::start(playback_frame_duration(speed));
+                let mut timing_stats = InputTimingStats::default();
+                let mut pipeline = FramePipeline::new(app.clone(), shared.clone());
+                let mut frame_index: u64 = 0;
+
+                while !thread_stop_flag.load(Ordering::Relaxed) {
+                    let settings = shared
+                        .settings
+                        .lock()
+                        .map(|settings| settings.clone())
+                        .unwrap_or_default();
+
+                    for (frame, sample, connected) in source.take_due(frame_index) {
+                        let sf6_mask = settings.mapping.apply(sample.down_mask);
+                        let frame = PolledFrame::new(frame, sample, sf6_mask, connected);
+                        pipeline.push(frame, &settings);
+                        status.frame(frame.frame);
+                    }
+
+                    if source.is_finished() {
+                        break;
+                    }
+                    frame_index = frame_index.saturating_add(pacer.wait(&mut timing_stats));
+                }
+
+                pipeline.finish();
+                status.stopped();
+                if source.is_finished() {
+                    let _ = app.emit("input/playback_finished", ());
+                }
+            })
+            .map_err(|error| format!("Failed to start input playback thread: {error}"))?;
+
+        Ok(Self {
+            kind: WorkerKind::Playback,
+            modes: Vec::new(),
+            device_id: None,
+            device: None,
+            stop_flag,
+            join_handle: Some(join_handle),
+            feedback: None,
+            pause_flag: None,
+        })
+    }
+
+    /// Streams the unparsed reports of the HID device at `path` on
+    /// `input/raw`, skipping reports identical to the previous one and
+    /// emitting at most one per `interval`.
+    fn start_raw(
+        app: AppHandle,
+        path: String,
+        interval: Duration,
+        shared: SharedInputState,
+    ) -> Result<Self, String> {
+        let stop_flag = Arc::new(AtomicBool::new(false));
+        let thread_stop_flag = Arc::clone(&stop_flag);
+        let worker_path = path.clone();
+        let status = StatusReporter::start(
+            app.clone(),
+            Arc::clone(&shared.status),
+            WorkerKind::Raw,
+            &[],
+            None,
+            Player::P1,
+        );
+
+        let join_handle = thread::Builder::new()
+            .name("raw-hid-capture".to_string())
+            .spawn(move || {
+                let (mut reader, info) = match platform::RawHidReader::open(&path) {
+                    Ok(opened) => opened,
+                    Err(message) => {
+                        status.error(message.clone());
+                        let _ = app.emit("input/error", message);
+                        return;
+                    }
+                };
+                status.running();
+                let _ = app.emit("input/raw_info", info);
+
+                let clock = FrameClock::start();
+                let mut previous = Vec::new();
+                // Capture time of a changed report not emitted yet.
+                let mut pending: Option<u64> = None;
+                let mut last_emit: Option<Instant> = None;
+                let mut reports: u64 = 0;
+                while !thread_stop_flag.load(Ordering::Relaxed) {
+                    let wait = match (pending, last_emit) {
+                        (Some(_), Some(at)) => interval
+                            .saturating_sub(at.elapsed())
+                            .as_millis()
+                            .min(RAW_READ_TIMEOUT_MS as u128)
+                            as i32,
+                        _ => RAW_READ_TIMEOUT_MS,
+                    };
+                    match reader.read(wait) {
+                        Ok(Some(report)) if report != previous.as_slice() => {
+                            previous.clear();
+                            previous.extend_from_slice(report);
+                            pending = Some(clock.now().timestamp_us());
+                        }
+                        Ok(_) => {}
+                        Err(message) => {
+                            status.error(message.clone());
+                            let _ = app.emit("input/error", message);
+                            return;
+                        }
+                    }
+
+                    let Some(timestamp_us) = pending else {
+                        continue;
+                    };
+                    if last_emit.is_some_and(|at| at.elapsed() < interval) {
+                        continue;
+                    }
+                    pending = None;
+                    last_emit = Some(Instant::now());
+                    reports += 1;
+                    status.frame(reports);
+                    let _ = app.emit(
+                        "input/raw",
+                        InputRawReportPayload {
+                            timestamp_us,
+                            report_id: previous[0],
+                            length: previous.len(),
+                            hex: to_hex(&previous),
+                        },
+                    );
+                }
+                status.stopped();
+            })
+            .map_err(|error| format!("Failed to start raw HID capture thread: {error}"))?;
+
+        Ok(Self {
+            kind: WorkerKind::Raw,
+            modes: Vec::new(),
+            device_id: Some(worker_path),
+            device: None,
             stop_flag,
             join_handle: Some(join_handle),
+            feedback: None,
+            pause_flag: None,
         })
     }
 
+    fn is_finished(&self) -> bool {
+        self.join_handle
+            .as_ref()
+            .is_none_or(|join_handle| join_handle.is_finished())
+    }
+
     fn stop(mut self) {
         self.stop_flag.store(true, Ordering::Relaxed);
         if let Some(join_handle) = self.join_handle.take() {
@@ -166,61 +1205,1520 @@ impl InputWorker {
     }
 }
 
-#[derive(Default)]
+/// Joins a worker whose thread already exited (playback reached the end, or
+/// the device failed to open) so the slot is free for the next start.
+fn clear_finished_worker(worker: &mut Option<InputWorker>) {
+    if worker.as_ref().is_some_and(InputWorker::is_finished) {
+        if let Some(worker) = worker.take() {
+            worker.stop();
+        }
+    }
+}
+
+/// State shared between the commands and the worker thread.
+#[derive(Clone, Default)]
+struct SharedInputState {
+    settings: Arc<Mutex<InputSettings>>,
+    timing: Arc<Mutex<InputTimingStats>>,
+    history: Arc<Mutex<InputHistory>>,
+    recorder: Arc<Mutex<Option<InputRecorder>>>,
+    combo: Arc<Mutex<ComboTrial>>,
+    reaction: Arc<Mutex<ReactionTrainer>>,
+    stats: Arc<Mutex<InputStats>>,
+    attempts: Arc<Mutex<AttemptTracker>>,
+    anomalies: Arc<Mutex<AnomalyDetector>>,
+    latency: Arc<Mutex<LatencyTracker>>,
+    status: Arc<Mutex<InputStatus>>,
+    /// Watchdog counters of the live run's HID source, if it has one.
+    hid: Arc<Mutex<Option<HidDiagnostics>>>,
+    /// Virtual pad mirroring the live worker's output, torn down with it.
+    passthrough: Arc<Mutex<Option<platform::VirtualPad>>>,
+    /// WebSocket server mirroring the event stream, while enabled.
+    stream: StreamHandle,
+}
+
+impl SharedInputState {
+    /// Forgets what the previous live run measured; frame numbers restart.
+    fn reset_live_run(&self) -> Result<(), String> {
+        if let Ok(mut timing) = self.timing.lock() {
+            *timing = InputTimingStats::default();
+        }
+        self.history
+            .lock()
+            .map_err(|_| "Failed to lock input history.".to_string())?
+            .clear();
+        self.stats
+            .lock()
+            .map_err(|_| "Failed to lock input stats.".to_string())?
+            .reset();
+        self.attempts
+            .lock()
+            .map_err(|_| "Failed to lock attempt tracker.".to_string())?
+            .reset();
+        self.anomalies
+            .lock()
+            .map_err(|_| "Failed to lock anomaly detector.".to_string())?
+            .reset();
+        self.latency
+            .lock()
+            .map_err(|_| "Failed to lock latency tracker.".to_string())?
+            .reset();
+        Ok(())
+    }
+}
+
 pub struct InputRuntimeState {
     worker: Mutex<Option<InputWorker>>,
+    shared: SharedInputState,
+    /// Second live worker, whose frames are tagged P2. It shares only the
+    /// stream server with P1; every other command works on P1.
+    p2_worker: Mutex<Option<InputWorker>>,
+    p2_shared: SharedInputState,
 }
 
-#[tauri::command]
-pub async fn input_detect() -> Result<NativeInputDetectResult, String> {
-    spawn_blocking(platform::input_detect)
-        .await
-        .map_err(|error| format!("Failed to detect native input devices: {error}"))
+impl Default for InputRuntimeState {
+    fn default() -> Self {
+        let shared = SharedInputState::default();
+        let p2_shared = SharedInputState {
+            stream: Arc::clone(&shared.stream),
+            ..SharedInputState::default()
+        };
+        Self {
+            worker: Mutex::new(None),
+            shared,
+            p2_worker: Mutex::new(None),
+            p2_shared,
+        }
+    }
 }
 
-#[tauri::command]
-pub async fn input_start(
-    app: AppHandle,
-    state: State<'_, InputRuntimeState>,
-    mode: NativeInputMode,
-) -> Result<(), String> {
-    let detect = spawn_blocking(platform::input_detect)
-        .await
-        .map_err(|error| format!("Failed to detect native input devices: {error}"))?;
-    match mode {
-        NativeInputMode::XInput if !detect.xinput => {
-            return Err("Native input mode 'xinput' did not detect a connected controller.".to_string())
-        }
-        NativeInputMode::Hid if !detect.hid => {
-            return Err("Native input mode 'hid' did not detect a supported PS4 HID controller.".to_string())
+impl InputRuntimeState {
+    fn seat(&self, player: Player) -> (&Mutex<Option<InputWorker>>, &SharedInputState) {
+        match player {
+            Player::P1 => (&self.worker, &self.shared),
+            Player::P2 => (&self.p2_worker, &self.p2_shared),
         }
-        _ => {}
     }
 
-    let mut worker_guard = state
-        .worker
-        .lock()
-        .map_err(|_| "Failed to lock input runtime state.".to_string())?;
+    fn lock_settings(&self) -> Result<MutexGuard<'_, InputSettings>, String> {
+        self.shared
+            .settings
+            .lock()
+            .map_err(|_| "Failed to lock input settings.".to_string())
+    }
 
-    if worker_guard.is_some() {
-        return Ok(());
+    fn lock_history(&self) -> Result<MutexGuard<'_, InputHistory>, String> {
+        self.shared
+            .history
+            .lock()
+            .map_err(|_| "Failed to lock input history.".to_string())
     }
 
-    let worker = InputWorker::start(app, mode)?;
-    *worker_guard = Some(worker);
-    Ok(())
+    fn lock_recorder(&self) -> Result<MutexGuard<'_, Option<InputRecorder>>, String> {
+        self.shared
+            .recorder
+            .lock()
+            .map_err(|_| "Failed to lock input recorder.".to_string())
+    }
+
+    fn lock_combo(&self) -> Result<MutexGuard<'_, ComboTrial>, String> {
+        self.shared
+            .combo
+            .lock()
+            .map_err(|_| "Failed to lock combo trial.".to_string())
+    }
+
+    fn lock_stream(&self) -> Result<MutexGuard<'_, Option<InputStreamServer>>, String> {
+        self.shared
+            .stream
+            .lock()
+            .map_err(|_| "Failed to lock input stream server.".to_string())
+    }
+
+    fn lock_reaction(&self) -> Result<MutexGuard<'_, ReactionTrainer>, String> {
+        self.shared
+            .reaction
+            .lock()
+            .map_err(|_| "Failed to lock reaction trainer.".to_string())
+    }
+
+    fn lock_stats(&self) -> Result<MutexGuard<'_, InputStats>, String> {
+        self.shared
+            .stats
+            .lock()
+            .map_err(|_| "Failed to lock input stats.".to_string())
+    }
+
+    fn lock_attempts(&self) -> Result<MutexGuard<'_, AttemptTracker>, String> {
+        self.shared
+            .attempts
+            .lock()
+            .map_err(|_| "Failed to lock attempt tracker.".to_string())
+    }
+
+    fn lock_anomalies(&self) -> Result<MutexGuard<'_, AnomalyDetector>, String> {
+        self.shared
+            .anomalies
+            .lock()
+            .map_err(|_| "Failed to lock anomaly detector.".to_string())
+    }
+
+    fn lock_latency(&self) -> Result<MutexGuard<'_, LatencyTracker>, String> {
+        self.shared
+            .latency
+            .lock()
+            .map_err(|_| "Failed to lock latency tracker.".to_string())
+    }
+
+    fn lock_passthrough(&self) -> Result<MutexGuard<'_, Option<platform::VirtualPad>>, String> {
+        self.shared
+            .passthrough
+            .lock()
+            .map_err(|_| "Failed to lock controller passthrough.".to_string())
+    }
+
+    fn lock_status(&self) -> Result<MutexGuard<'_, InputStatus>, String> {
+        self.shared
+            .status
+            .lock()
+            .map_err(|_| "Failed to lock input status.".to_string())
+    }
 }
 
 #[tauri::command]
-pub fn input_stop(state: State<'_, InputRuntimeState>) -> Result<(), String> {
-    let mut worker_guard = state
-        .worker
-        .lock()
-        .map_err(|_| "Failed to lock input runtime state.".to_string())?;
+pub async fn input_detect(app: AppHandle) -> Result<NativeInputDetectResult, String> {
+    let hid_allowlist = saved_hid_allowlist(&app);
+    spawn_blocking(move || platform::input_detect(&hid_allowlist))
+        .await
+        .map_err(|error| format!("Failed to detect native input devices: {error}"))
+}
 
-    if let Some(worker) = worker_guard.take() {
-        worker.stop();
-    }
+#[tauri::command]
+pub async fn input_list_devices(app: AppHandle) -> Result<Vec<NativeInputDevice>, String> {
+    let hid_allowlist = saved_hid_allowlist(&app);
+    spawn_blocking(move || platform::input_list_devices(&hid_allowlist))
+        .await
+        .map_err(|error| format!("Failed to list native input devices: {error}"))
+}
 
+/// First mode of `order` with a device that did not fail to open, so a HID
+/// pad that is present but blocked falls through to the next backend.
+fn resolve_auto_mode(
+    order: &[NativeInputMode],
+    detect: &NativeInputDetectResult,
+) -> Result<NativeInputMode, String> {
+    let order = if order.is_empty() {
+        &DEFAULT_AUTO_ORDER[..]
+    } else {
+        order
+    };
+    order
+        .iter()
+        .copied()
+        .find(|mode| {
+            detect
+                .devices
+                .iter()
+                .any(|device| device.backend == *mode && device.openable != Some(false))
+        })
+        .ok_or_else(|| "Native input mode 'auto' did not find a usable controller.".to_string())
+}
+
+/// The saved HID allow-list. Like a bad profile store, one that cannot be
+/// read only raises `input/warning`.
+fn saved_hid_allowlist(app: &AppHandle) -> Arc<[HidDeviceId]> {
+    match DeviceProfileStore::new(app).and_then(|store| store.hid_allowlist()) {
+        Ok(devices) => devices.into(),
+        Err(message) => {
+            let _ = app.emit("input/warning", message);
+            Arc::from([])
+        }
+    }
+}
+
+/// What `input_start` did, serialized as `"started"`, `"already_running"` or
+/// `"restarted"`.
+#[derive(Clone, Copy, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum InputStartResult {
+    Started,
+    AlreadyRunning,
+    Restarted,
+}
+
+/// Starts live input from one mode or several merged ones. A worker already
+/// running the same modes and device is kept; one running anything else is
+/// stopped and replaced, announced by an `input/status` `restarting` event.
+/// With merged modes `device_id` pins the source it belongs to. HID devices
+/// can also be pinned as `"hid:serial:<serial>"`, which still finds them
+/// after moving to another port. Until `input_configure` or stick
+/// calibration sets them, analog settings start from the opened device's
+/// controller preset, and a saved device profile overrides both.
+///
+/// `player` P2 runs a second worker next to P1's, pinned to another device.
+/// It starts from a copy of P1's settings facing the other way unless
+/// `facing` is given, and skips device profiles. P2 only feeds the frame
+/// events and its own history and status; trainers, stats, recording and
+/// every setting command stay with P1.
+#[tauri::command]
+pub async fn input_start(
+    app: AppHandle,
+    state: State<'_, InputRuntimeState>,
+    mode: InputModeSelection,
+    device_id: Option<String>,
+    facing: Option<Facing>,
+    player: Option<Player>,
+) -> Result<InputStartResult, String> {
+    let player = player.unwrap_or_default();
+    let modes = mode.into_modes();
+    validate_modes(&modes)?;
+
+    let hid_allowlist = saved_hid_allowlist(&app);
+    let detect_allowlist = Arc::clone(&hid_allowlist);
+    let detect = spawn_blocking(move || platform::input_detect(&detect_allowlist))
+        .await
+        .map_err(|error| format!("Failed to detect native input devices: {error}"))?;
+    for mode in &modes {
+        match mode {
+            NativeInputMode::XInput if !detect.xinput => {
+                return Err(
+                    "Native input mode 'xinput' did not detect a connected controller.".to_string(),
+                )
+            }
+            NativeInputMode::Hid if !detect.hid => {
+                return Err(
+                    "Native input mode 'hid' did not detect a supported PS4 HID controller."
+                        .to_string(),
+                )
+            }
+            NativeInputMode::Keyboard if !detect.keyboard => {
+                return Err(
+                    "Native input mode 'keyboard' is not available on this platform.".to_string(),
+                )
+            }
+            NativeInputMode::DirectInput if !detect.directinput => {
+                return Err(
+                    "Native input mode 'directinput' did not detect a connected game controller."
+                        .to_string(),
+                )
+            }
+            NativeInputMode::Evdev if !detect.evdev => {
+                return Err(
+                    "Native input mode 'evdev' did not detect a gamepad under /dev/input."
+                        .to_string(),
+                )
+            }
+            NativeInputMode::Generic if !detect.generic => {
+                return Err("Native input mode 'generic' did not detect a controller that the other modes cannot read.".to_string())
+            }
+            NativeInputMode::Wgi if !detect.wgi => {
+                return Err(
+                    "Native input mode 'wgi' did not detect a connected gamepad.".to_string(),
+                )
+            }
+            _ => {}
+        }
+    }
+    let modes = match modes.as_slice() {
+        [NativeInputMode::Auto] => {
+            let order = Arc::clone(&state.lock_settings()?.auto_order);
+            vec![resolve_auto_mode(&order, &detect)?]
+        }
+        _ => modes,
+    };
+
+    let list_allowlist = Arc::clone(&hid_allowlist);
+    let devices = spawn_blocking(move || platform::input_list_devices(&list_allowlist))
+        .await
+        .map_err(|error| format!("Failed to list native input devices: {error}"))?;
+    if let Some(device_id) = &device_id {
+        if let [mode] = modes.as_slice() {
+            mode.device_key(device_id)?;
+        } else if !modes.iter().any(|mode| mode.device_key(device_id).is_ok()) {
+            return Err(format!(
+                "Device '{device_id}' does not belong to any of the requested input modes."
+            ));
+        }
+        if !devices.iter().any(|device| device.answers_to(device_id)) {
+            return Err(format!(
+                "Native input device '{device_id}' is no longer connected."
+            ));
+        }
+    }
+    // The profile and the other seat's claim follow the pinned device,
+    // otherwise the first device of the first mode, which is the one that
+    // backend opens.
+    let profile_device = match &device_id {
+        Some(device_id) => devices.iter().find(|device| device.answers_to(device_id)),
+        None => modes.iter().find_map(|mode| {
+            devices
+                .iter()
+                .find(|device| device.backend == *mode && device.openable != Some(false))
+        }),
+    };
+
+    let other_device = state
+        .seat(player.other())
+        .0
+        .lock()
+        .map_err(|_| "Failed to lock input runtime state.".to_string())?
+        .as_ref()
+        .filter(|worker| worker.kind == WorkerKind::Live && !worker.is_finished())
+        .and_then(|worker| worker.device.clone());
+    if player == Player::P2 && device_id.is_none() {
+        return Err("P2 needs a device_id so it cannot open P1's controller.".to_string());
+    }
+    if let Some((device, other_device)) = profile_device.zip(other_device.as_deref()) {
+        if reads_same_device(&devices, device, other_device) {
+            return Err(format!(
+                "Native input device '{}' is already read by the other player.",
+                device.id
+            ));
+        }
+    }
+
+    let (worker_slot, shared) = state.seat(player);
+    let mut worker_guard = worker_slot
+        .lock()
+        .map_err(|_| "Failed to lock input runtime state.".to_string())?;
+
+    match player {
+        Player::P1 => {
+            let mut settings = state.lock_settings()?;
+            if let Some(facing) = facing {
+                settings.facing = facing;
+            }
+            if !settings.analog_configured {
+                settings.analog = preset_analog(profile_device);
+            }
+            settings.hid_allowlist = hid_allowlist;
+        }
+        Player::P2 => {
+            let mut settings = state.lock_settings()?.clone();
+            settings.facing = facing.unwrap_or(settings.facing.opposite());
+            if !settings.analog_configured {
+                settings.analog = preset_analog(profile_device);
+            }
+            settings.hid_allowlist = hid_allowlist;
+            *shared
+                .settings
+                .lock()
+                .map_err(|_| "Failed to lock input settings.".to_string())? = settings;
+        }
+    }
+
+    clear_finished_worker(&mut worker_guard);
+    let mut result = InputStartResult::Started;
+    if let Some(worker) = worker_guard.as_ref() {
+        match worker.kind {
+            WorkerKind::Playback => {
+                return Err(
+                    "Input playback is running; stop it before starting live input.".to_string(),
+                )
+            }
+            WorkerKind::Raw => {
+                return Err(
+                    "Raw HID capture is running; stop it before starting live input.".to_string(),
+                )
+            }
+            WorkerKind::Live => {}
+        }
+        if worker.modes == modes && worker.device_id == device_id {
+            return Ok(InputStartResult::AlreadyRunning);
+        }
+
+        status::emit_restarting(&app, player);
+        if let Some(worker) = worker_guard.take() {
+            worker.stop();
+        }
+        result = InputStartResult::Restarted;
+    }
+
+    shared.reset_live_run()?;
+
+    let profile = match player {
+        Player::P1 => apply_device_profile(&app, &state, profile_device)?,
+        Player::P2 => None,
+    };
+    let device = profile_device.map(|device| device.id.clone());
+    let worker = InputWorker::start(
+        app,
+        modes,
+        device_id,
+        device,
+        profile,
+        player,
+        shared.clone(),
+    )?;
+    *worker_guard = Some(worker);
+    Ok(result)
+}
+
+/// Whether `device` is the one the other seat opened as `other_device_id`.
+/// Either seat may have named it by path or by serial, so the other id is
+/// resolved against the connected devices first.
+fn reads_same_device(
+    devices: &[NativeInputDevice],
+    device: &NativeInputDevice,
+    other_device_id: &str,
+) -> bool {
+    devices
+        .iter()
+        .find(|other| other.answers_to(other_device_id))
+        .map_or_else(
+            || device.answers_to(other_device_id),
+            |other| other.id == device.id,
+        )
+}
+
+/// Applies the saved profile of `device`, if any, and returns its key. A
+/// profile store that cannot be read only raises `input/warning`, so a bad
+/// file never keeps input from starting.
+fn apply_device_profile(
+    app: &AppHandle,
+    state: &InputRuntimeState,
+    device: Option<&NativeInputDevice>,
+) -> Result<Option<String>, String> {
+    let Some(device) = device else {
+        return Ok(None);
+    };
+    let key = device_profile_key(device);
+    let profile = match DeviceProfileStore::new(app).and_then(|store| store.load()) {
+        Ok(mut profiles) => profiles.remove(&key),
+        Err(message) => {
+            let _ = app.emit("input/warning", message);
+            None
+        }
+    };
+    let Some(profile) = profile else {
+        return Ok(None);
+    };
+
+    if let Err(error) = profile.apply(&mut *state.lock_settings()?) {
+        let _ = app.emit(
+            "input/warning",
+            format!("Input profile '{key}' was not applied: {error}"),
+        );
+        return Ok(None);
+    }
+    Ok(Some(key))
+}
+
+#[tauri::command]
+pub async fn input_play(
+    app: AppHandle,
+    state: State<'_, InputRuntimeState>,
+    path: String,
+    speed: Option<f64>,
+) -> Result<(), String> {
+    let speed = speed.unwrap_or(1.0);
+    if !(MIN_PLAYBACK_SPEED..=MAX_PLAYBACK_SPEED).contains(&speed) {
+        return Err(format!(
+            "Playback speed must be between {MIN_PLAYBACK_SPEED} and {MAX_PLAYBACK_SPEED}."
+        ));
+    }
+
+    let source = spawn_blocking(move || PlaybackSource::open(Path::new(&path)))
+        .await
+        .map_err(|error| format!("Failed to load recording: {error}"))??;
+
+    let mut worker_guard = state
+        .worker
+        .lock()
+        .map_err(|_| "Failed to lock input runtime state.".to_string())?;
+
+    clear_finished_worker(&mut worker_guard);
+    if let Some(worker) = worker_guard.as_ref() {
+        return Err(match worker.kind {
+            WorkerKind::Live => {
+                "Live input is running; stop it before starting playback.".to_string()
+            }
+            WorkerKind::Playback => "Input playback is already running.".to_string(),
+            WorkerKind::Raw => {
+                "Raw HID capture is running; stop it before starting playback.".to_string()
+            }
+        });
+    }
+
+    state.lock_history()?.clear();
+    state.lock_stats()?.reset();
+    state.lock_attempts()?.reset();
+    state.lock_anomalies()?.reset();
+    let worker = InputWorker::start_playback(app, source, speed, state.shared.clone())?;
+    *worker_guard = Some(worker);
+    Ok(())
+}
+
+/// Captures every report of the HID device at `device_path`, supported or
+/// not, so users can send in dumps for new decoders. Paths come from
+/// `input_list_devices` or the `rejected` list of `input_detect`. Stopped by
+/// `input_stop` like any other worker.
+///
+/// `interval_ms` throttles the events for devices that report at 1 kHz;
+/// without it every changed report is sent.
+#[tauri::command]
+pub fn input_start_raw(
+    app: AppHandle,
+    state: State<'_, InputRuntimeState>,
+    device_path: String,
+    interval_ms: Option<u64>,
+) -> Result<(), String> {
+    let interval_ms = interval_ms.unwrap_or(0);
+    if interval_ms > MAX_RAW_INTERVAL_MS {
+        return Err(format!(
+            "Raw capture interval must be at most {MAX_RAW_INTERVAL_MS} ms."
+        ));
+    }
+
+    let mut worker_guard = state
+        .worker
+        .lock()
+        .map_err(|_| "Failed to lock input runtime state.".to_string())?;
+
+    clear_finished_worker(&mut worker_guard);
+    if let Some(worker) = worker_guard.as_ref() {
+        if worker.kind == WorkerKind::Raw && worker.device_id.as_deref() == Some(&device_path) {
+            return Ok(());
+        }
+        return Err("Stop input before starting a raw HID capture.".to_string());
+    }
+
+    let worker = InputWorker::start_raw(
+        app,
+        device_path,
+        Duration::from_millis(interval_ms),
+        state.shared.clone(),
+    )?;
+    *worker_guard = Some(worker);
+    Ok(())
+}
+
+/// Stops `player`'s worker, or without one both workers and the stream
+/// server.
+#[tauri::command]
+pub fn input_stop(
+    state: State<'_, InputRuntimeState>,
+    player: Option<Player>,
+) -> Result<(), String> {
+    let players = match player {
+        Some(player) => vec![player],
+        None => vec![Player::P1, Player::P2],
+    };
+    for player in players {
+        let mut worker_guard = state
+            .seat(player)
+            .0
+            .lock()
+            .map_err(|_| "Failed to lock input runtime state.".to_string())?;
+        if let Some(worker) = worker_guard.take() {
+            worker.stop();
+        }
+    }
+    if player.is_none() {
+        if let Some(server) = state.lock_stream()?.take() {
+            server.stop();
+        }
+    }
+
+    Ok(())
+}
+
+/// Mirrors frames, edges, segments and motions to WebSocket clients on
+/// `port`, localhost only unless `lan` is set. Port 0 picks a free port; the
+/// bound address is returned. `input_stop` shuts the server down as well.
+#[tauri::command]
+pub fn input_stream_start(
+    state: State<'_, InputRuntimeState>,
+    port: u16,
+    lan: Option<bool>,
+) -> Result<InputStreamInfo, String> {
+    let mut stream = state.lock_stream()?;
+    if let Some(server) = stream.as_ref() {
+        return Err(format!(
+            "Input streaming is already running on {}.",
+            server.address()
+        ));
+    }
+
+    let server = InputStreamServer::start(
+        port,
+        lan.unwrap_or(false),
+        Arc::clone(&state.shared.settings),
+    )?;
+    let info = server.info();
+    *stream = Some(server);
+    Ok(info)
+}
+
+#[tauri::command]
+pub fn input_stream_stop(state: State<'_, InputRuntimeState>) -> Result<(), String> {
+    if let Some(server) = state.lock_stream()?.take() {
+        server.stop();
+    }
+    Ok(())
+}
+
+/// Starts mirroring the running live input to a virtual Xbox 360 pad. The pad
+/// is unplugged by `input_passthrough_stop` or when the worker stops. Pin an
+/// XInput source with `device_id`, or after a disconnect the worker may fall
+/// back to reading the virtual pad itself.
+#[tauri::command]
+pub async fn input_passthrough_start(state: State<'_, InputRuntimeState>) -> Result<(), String> {
+    {
+        let mut worker_guard = state
+            .worker
+            .lock()
+            .map_err(|_| "Failed to lock input runtime state.".to_string())?;
+        clear_finished_worker(&mut worker_guard);
+        if worker_guard.as_ref().map(|worker| worker.kind) != Some(WorkerKind::Live) {
+            return Err("Start live input before enabling controller passthrough.".to_string());
+        }
+    }
+    if state.lock_passthrough()?.is_some() {
+        return Ok(());
+    }
+
+    let pad = spawn_blocking(platform::VirtualPad::new)
+        .await
+        .map_err(|error| format!("Failed to start controller passthrough: {error}"))??;
+    *state.lock_passthrough()? = Some(pad);
+    Ok(())
+}
+
+#[tauri::command]
+pub fn input_passthrough_stop(state: State<'_, InputRuntimeState>) -> Result<(), String> {
+    state.lock_passthrough()?.take();
+    Ok(())
+}
+
+/// Sends a rumble or lightbar cue to the controller the live worker has open.
+/// Effects the device cannot show are skipped and answered with a `warning`.
+#[tauri::command]
+pub async fn input_feedback(
+    state: State<'_, InputRuntimeState>,
+    effect: FeedbackEffect,
+) -> Result<InputFeedbackResult, String> {
+    effect.validate()?;
+    let sender = {
+        let mut worker_guard = state
+            .worker
+            .lock()
+            .map_err(|_| "Failed to lock input runtime state.".to_string())?;
+        clear_finished_worker(&mut worker_guard);
+        worker_guard
+            .as_ref()
+            .and_then(|worker| worker.feedback.clone())
+            .ok_or_else(|| "Start live input before sending controller feedback.".to_string())?
+    };
+
+    spawn_blocking(move || feedback::request(&sender, effect))
+        .await
+        .map_err(|error| format!("Failed to send controller feedback: {error}"))?
+}
+
+/// Stops publishing frames without stopping the live worker, which keeps
+/// polling so the device stays open and its frame counter keeps running.
+/// `input_stop` works as usual while paused.
+#[tauri::command]
+pub fn input_pause(state: State<'_, InputRuntimeState>) -> Result<(), String> {
+    set_input_paused(&state, true)
+}
+
+/// Publishes frames again, starting with the full current state on
+/// `input/sync`.
+#[tauri::command]
+pub fn input_resume(state: State<'_, InputRuntimeState>) -> Result<(), String> {
+    set_input_paused(&state, false)
+}
+
+fn set_input_paused(state: &InputRuntimeState, paused: bool) -> Result<(), String> {
+    let mut worker_guard = state
+        .worker
+        .lock()
+        .map_err(|_| "Failed to lock input runtime state.".to_string())?;
+    clear_finished_worker(&mut worker_guard);
+    let pause_flag = worker_guard
+        .as_ref()
+        .and_then(|worker| worker.pause_flag.as_ref())
+        .ok_or_else(|| "Live input is not running.".to_string())?;
+
+    pause_flag.store(paused, Ordering::Relaxed);
+    state.lock_status()?.set_paused(paused);
+    Ok(())
+}
+
+/// Lifecycle of the current or most recent worker, as last reported on
+/// `input/status`.
+#[tauri::command]
+pub fn input_status(
+    state: State<'_, InputRuntimeState>,
+    player: Option<Player>,
+) -> Result<InputStatus, String> {
+    let (worker, shared) = state.seat(player.unwrap_or_default());
+    let running = worker
+        .lock()
+        .map_err(|_| "Failed to lock input runtime state.".to_string())?
+        .as_ref()
+        .is_some_and(|worker| !worker.is_finished());
+    let status = shared
+        .status
+        .lock()
+        .map_err(|_| "Failed to lock input status.".to_string())?
+        .clone();
+    Ok(status.with_running(running))
+}
+
+/// Slot, battery and capabilities of the XInput controller the live run reads
+/// or read last. Pin a slot by starting with its device id, e.g. `"xinput:1"`.
+#[tauri::command]
+pub fn input_controller_info(
+    state: State<'_, InputRuntimeState>,
+) -> Result<XInputControllerInfo, String> {
+    let user_index = state
+        .lock_status()?
+        .xinput_user_index()
+        .ok_or_else(|| "Live input is not reading an XInput controller.".to_string())?;
+    platform::xinput_controller_info(user_index)
+}
+
+#[tauri::command]
+pub fn input_timing_stats(state: State<'_, InputRuntimeState>) -> Result<InputTimingStats, String> {
+    state
+        .shared
+        .timing
+        .lock()
+        .map(|timing| *timing)
+        .map_err(|_| "Failed to lock input timing stats.".to_string())
+}
+
+/// Report and decode counters of the HID source of the current or most
+/// recent live run; `None` when it did not read HID.
+#[tauri::command]
+pub fn input_hid_diagnostics(
+    state: State<'_, InputRuntimeState>,
+) -> Result<Option<HidDiagnostics>, String> {
+    state
+        .shared
+        .hid
+        .lock()
+        .map(|hid| *hid)
+        .map_err(|_| "Failed to lock HID diagnostics.".to_string())
+}
+
+/// Sets how long HID input may go without a decodable report before the
+/// held state is released.
+#[tauri::command]
+pub fn input_set_hid_watchdog(
+    state: State<'_, InputRuntimeState>,
+    stale_ms: u64,
+) -> Result<(), String> {
+    if !(MIN_STALE_MS..=MAX_STALE_MS).contains(&stale_ms) {
+        return Err(format!(
+            "HID stale window must be between {MIN_STALE_MS} and {MAX_STALE_MS} ms."
+        ));
+    }
+
+    state.lock_settings()?.watchdog.stale_after = Duration::from_millis(stale_ms);
+    Ok(())
+}
+
+/// Turns latency instrumentation on or off. While on, the live worker times
+/// every frame and the frontend should call `input_ack` as it paints each one;
+/// `emit_reports` adds an `input/latency` report every second.
+#[tauri::command]
+pub fn input_set_latency_tracking(
+    state: State<'_, InputRuntimeState>,
+    enabled: bool,
+    emit_reports: Option<bool>,
+) -> Result<(), String> {
+    state
+        .lock_latency()?
+        .configure(enabled, emit_reports.unwrap_or(false));
+    Ok(())
+}
+
+/// Called by the frontend once it has rendered `frame`.
+#[tauri::command]
+pub fn input_ack(state: State<'_, InputRuntimeState>, frame: u64) -> Result<(), String> {
+    let rendered_at = Instant::now();
+    state.lock_latency()?.ack(frame, rendered_at);
+    Ok(())
+}
+
+#[tauri::command]
+pub fn input_latency_report(
+    state: State<'_, InputRuntimeState>,
+) -> Result<InputLatencyReport, String> {
+    Ok(state.lock_latency()?.report())
+}
+
+/// Buffered frames of `player`, P1 by default, after `since_frame` or after
+/// the run-clock time `since_us`, limited to attempt `attempt_id` when set.
+/// Frames of an attempt older than the history length are gone.
+#[tauri::command]
+pub fn input_get_history(
+    state: State<'_, InputRuntimeState>,
+    since_frame: Option<u64>,
+    since_us: Option<u64>,
+    attempt_id: Option<u64>,
+    player: Option<Player>,
+) -> Result<Vec<InputFramePayload>, String> {
+    let player = player.unwrap_or_default();
+    let range = match attempt_id {
+        Some(_) if player == Player::P2 => {
+            return Err("Attempts are only tracked for P1.".to_string())
+        }
+        Some(attempt_id) => Some(
+            state
+                .lock_attempts()?
+                .range(attempt_id)
+                .ok_or_else(|| format!("No attempt {attempt_id} in this run."))?,
+        ),
+        None => None,
+    };
+    let history = state
+        .seat(player)
+        .1
+        .history
+        .lock()
+        .map_err(|_| "Failed to lock input history.".to_string())?;
+    Ok(history.payloads(since_frame, since_us, range))
+}
+
+/// Numpad notation of the last `last_n_seconds` of history. Idle neutral
+/// stretches longer than `neutral_gap_frames` (30 by default) become a
+/// separator.
+#[tauri::command]
+pub fn input_get_notation(
+    state: State<'_, InputRuntimeState>,
+    last_n_seconds: u32,
+    neutral_gap_frames: Option<u64>,
+) -> Result<InputNotation, String> {
+    if !(1..=MAX_HISTORY_SECONDS).contains(&last_n_seconds) {
+        return Err(format!(
+            "Notation length must be between 1 and {MAX_HISTORY_SECONDS} seconds."
+        ));
+    }
+    let gap_frames = neutral_gap_frames.unwrap_or(DEFAULT_NEUTRAL_GAP_FRAMES);
+    if gap_frames == 0 {
+        return Err("Neutral gap must be at least 1 frame.".to_string());
+    }
+
+    let motion = state.lock_settings()?.motion;
+    let history = state.lock_history()?;
+    Ok(notation(
+        history.recent(u64::from(last_n_seconds) * FRAMES_PER_SECOND as u64),
+        motion,
+        gap_frames,
+    ))
+}
+
+#[tauri::command]
+pub fn input_set_history_seconds(
+    state: State<'_, InputRuntimeState>,
+    seconds: u32,
+) -> Result<(), String> {
+    if !(1..=MAX_HISTORY_SECONDS).contains(&seconds) {
+        return Err(format!(
+            "Input history length must be between 1 and {MAX_HISTORY_SECONDS} seconds."
+        ));
+    }
+
+    state
+        .lock_history()?
+        .set_capacity(seconds as usize * FRAMES_PER_SECOND);
+    Ok(())
+}
+
+#[tauri::command]
+pub fn input_get_stats(state: State<'_, InputRuntimeState>) -> Result<InputStatsSummary, String> {
+    Ok(state.lock_stats()?.summary())
+}
+
+#[tauri::command]
+pub fn input_reset_stats(state: State<'_, InputRuntimeState>) -> Result<(), String> {
+    state.lock_stats()?.reset();
+    Ok(())
+}
+
+/// Sets the `(first, second)` SF6 button pairs whose press-to-press gaps are
+/// tracked, e.g. `[["MP", "LP"]]` for an MP~LP plink.
+#[tauri::command]
+pub fn input_set_stats_pairs(
+    state: State<'_, InputRuntimeState>,
+    pairs: Vec<(String, String)>,
+) -> Result<(), String> {
+    state.lock_stats()?.set_pairs(&pairs)
+}
+
+/// Turbo and macro patterns flagged since the last start, oldest first.
+#[tauri::command]
+pub fn input_get_anomalies(
+    state: State<'_, InputRuntimeState>,
+) -> Result<Vec<InputAnomaly>, String> {
+    Ok(state.lock_anomalies()?.anomalies())
+}
+
+/// Tunes when `input/anomaly` fires: `toggle_frames` is how many consecutive
+/// frames a button may change state before it counts as turbo, and
+/// `chord_repeats` how often a chord may repeat at one exact interval.
+#[tauri::command]
+pub fn input_set_anomaly_thresholds(
+    state: State<'_, InputRuntimeState>,
+    toggle_frames: Option<u32>,
+    chord_repeats: Option<u32>,
+) -> Result<(), String> {
+    if toggle_frames.is_some_and(|frames| frames < 2) {
+        return Err("Turbo detection needs a threshold of at least 2 frames.".to_string());
+    }
+    if chord_repeats.is_some_and(|repeats| repeats < 2) {
+        return Err("Chord repeat detection needs a threshold of at least 2 presses.".to_string());
+    }
+
+    let mut settings = state.lock_settings()?;
+    if let Some(toggle_frames) = toggle_frames {
+        settings.anomaly.toggle_frames = toggle_frames;
+    }
+    if let Some(chord_repeats) = chord_repeats {
+        settings.anomaly.chord_repeats = chord_repeats;
+    }
+    Ok(())
+}
+
+#[tauri::command]
+pub fn input_record_start(state: State<'_, InputRuntimeState>, path: String) -> Result<(), String> {
+    let mut recorder = state.lock_recorder()?;
+    if let Some(active) = recorder.as_ref() {
+        return Err(format!(
+            "A recording is already active at '{}'.",
+            active.path()
+        ));
+    }
+
+    *recorder = Some(InputRecorder::create(PathBuf::from(path))?);
     Ok(())
 }
+
+#[tauri::command]
+pub fn input_record_stop(
+    state: State<'_, InputRuntimeState>,
+) -> Result<InputRecordingSummary, String> {
+    state
+        .lock_recorder()?
+        .take()
+        .ok_or_else(|| "No recording is active.".to_string())?
+        .finish()
+}
+
+/// Converts a recording to notation text or CSV on a blocking thread, since a
+/// long session can take a while; progress is reported on
+/// `input/export_progress`.
+#[tauri::command]
+pub async fn input_export(
+    app: AppHandle,
+    state: State<'_, InputRuntimeState>,
+    recording_path: String,
+    format: InputExportFormat,
+    out_path: String,
+) -> Result<InputExportSummary, String> {
+    let (mapping, motion) = {
+        let settings = state.lock_settings()?;
+        (settings.mapping, settings.motion)
+    };
+
+    spawn_blocking(move || {
+        export::export_recording(
+            &app,
+            Path::new(&recording_path),
+            format,
+            Path::new(&out_path),
+            mapping,
+            motion,
+        )
+    })
+    .await
+    .map_err(|error| format!("Failed to export recording: {error}"))?
+}
+
+#[tauri::command]
+pub fn input_set_mapping(
+    state: State<'_, InputRuntimeState>,
+    mapping: ButtonMappingConfig,
+) -> Result<(), String> {
+    let mapping = ButtonMapping::from_config(&mapping)?;
+    state.lock_settings()?.mapping = mapping;
+    Ok(())
+}
+
+#[tauri::command]
+pub fn input_get_mapping(
+    state: State<'_, InputRuntimeState>,
+) -> Result<ButtonMappingConfig, String> {
+    Ok(state.lock_settings()?.mapping.to_config())
+}
+
+/// `keepalive_frames` makes `Edge` mode re-send an unchanged frame after
+/// that many silent frames (0 turns it off, the default). Omitted options
+/// keep their current value.
+#[tauri::command]
+pub fn input_set_emit_mode(
+    state: State<'_, InputRuntimeState>,
+    mode: InputEmitMode,
+    batch_size: Option<usize>,
+    keepalive_frames: Option<u64>,
+) -> Result<(), String> {
+    if batch_size == Some(0) {
+        return Err("Input batch size must be at least 1.".to_string());
+    }
+    if keepalive_frames.is_some_and(|frames| frames > MAX_KEEPALIVE_FRAMES) {
+        return Err(format!(
+            "Edge keep-alive must be at most {MAX_KEEPALIVE_FRAMES} frames."
+        ));
+    }
+
+    let mut settings = state.lock_settings()?;
+    settings.emit.mode = mode;
+    if let Some(batch_size) = batch_size {
+        settings.emit.batch_size = batch_size;
+    }
+    if let Some(keepalive_frames) = keepalive_frames {
+        settings.emit.keepalive_frames = keepalive_frames;
+    }
+    Ok(())
+}
+
+/// Turns the `input/segments` stream on or off. `max_hold_frames` caps the
+/// reported hold count (99 by default, like the in-game display).
+#[tauri::command]
+pub fn input_set_segments(
+    state: State<'_, InputRuntimeState>,
+    enabled: bool,
+    max_hold_frames: Option<u64>,
+) -> Result<(), String> {
+    if max_hold_frames == Some(0) {
+        return Err("Segment hold cap must be at least 1 frame.".to_string());
+    }
+
+    let mut settings = state.lock_settings()?;
+    settings.segments.enabled = enabled;
+    if let Some(max_hold_frames) = max_hold_frames {
+        settings.segments.max_hold_frames = max_hold_frames;
+    }
+    Ok(())
+}
+
+/// Turns the `input/notation` stream on or off: P1's presses as notation
+/// tokens such as `236+HP` or `j.MK`, taken after press grouping.
+#[tauri::command]
+pub fn input_set_notation_events(
+    state: State<'_, InputRuntimeState>,
+    enabled: bool,
+) -> Result<(), String> {
+    state.lock_settings()?.notation_events = enabled;
+    Ok(())
+}
+
+/// Sets the plink tolerance: an SF6 button pressed up to `window_frames`
+/// after another is reported as part of the same press on `input/edge` and
+/// `input/segments`, which are then delayed by that many frames. `input/frame`
+/// and every other consumer still see frames as polled. 0 turns it off.
+#[tauri::command]
+pub fn input_set_press_grouping(
+    state: State<'_, InputRuntimeState>,
+    window_frames: u64,
+) -> Result<(), String> {
+    if window_frames > MAX_GROUPING_FRAMES {
+        return Err(format!(
+            "Press grouping window must be at most {MAX_GROUPING_FRAMES} frames."
+        ));
+    }
+
+    state.lock_settings()?.grouping.window_frames = window_frames;
+    Ok(())
+}
+
+#[tauri::command]
+pub fn input_set_socd(
+    state: State<'_, InputRuntimeState>,
+    policy: SocdPolicy,
+) -> Result<(), String> {
+    state.lock_settings()?.socd = policy;
+    Ok(())
+}
+
+/// Sets the modes `auto` tries, best first, e.g. `["xinput", "hid"]` to
+/// prefer XInput. An empty list restores the default order; the answer is
+/// the order now in effect.
+#[tauri::command]
+pub fn input_set_auto_order(
+    state: State<'_, InputRuntimeState>,
+    order: Vec<NativeInputMode>,
+) -> Result<Vec<NativeInputMode>, String> {
+    for (index, mode) in order.iter().enumerate() {
+        if *mode == NativeInputMode::Auto {
+            return Err("The 'auto' order cannot list 'auto' itself.".to_string());
+        }
+        if order[..index].contains(mode) {
+            return Err(format!(
+                "Native input mode '{}' is listed more than once.",
+                mode.name()
+            ));
+        }
+    }
+
+    let mut settings = state.lock_settings()?;
+    settings.auto_order = order.into();
+    Ok(if settings.auto_order.is_empty() {
+        DEFAULT_AUTO_ORDER.to_vec()
+    } else {
+        settings.auto_order.to_vec()
+    })
+}
+
+/// Sets how the sources of a multi-mode run are merged. `last_active`
+/// announces every switch on `input/active_source`.
+#[tauri::command]
+pub fn input_set_merge_policy(
+    state: State<'_, InputRuntimeState>,
+    policy: MergePolicy,
+) -> Result<(), String> {
+    state.lock_settings()?.merge = policy;
+    Ok(())
+}
+
+/// Sets the charge an `input/charge_release` needs and how long a charge
+/// survives letting go of its direction.
+#[tauri::command]
+pub fn input_set_charge(
+    state: State<'_, InputRuntimeState>,
+    threshold_frames: u64,
+    retention_frames: Option<u64>,
+) -> Result<(), String> {
+    if threshold_frames == 0 {
+        return Err("Charge threshold must be at least 1 frame.".to_string());
+    }
+    if retention_frames.is_some_and(|frames| frames > MAX_RETENTION_FRAMES) {
+        return Err(format!(
+            "Charge retention must be at most {MAX_RETENTION_FRAMES} frames."
+        ));
+    }
+
+    let mut settings = state.lock_settings()?;
+    settings.charge.threshold_frames = threshold_frames;
+    if let Some(retention_frames) = retention_frames {
+        settings.charge.retention_frames = retention_frames;
+    }
+    Ok(())
+}
+
+#[tauri::command]
+pub fn input_set_motion_window(
+    state: State<'_, InputRuntimeState>,
+    window_frames: u64,
+    button_buffer_frames: Option<u64>,
+) -> Result<(), String> {
+    if window_frames == 0 {
+        return Err("Motion window must be at least 1 frame.".to_string());
+    }
+
+    let mut settings = state.lock_settings()?;
+    settings.motion.window_frames = window_frames;
+    if let Some(button_buffer_frames) = button_buffer_frames {
+        settings.motion.button_buffer_frames = button_buffer_frames;
+    }
+    Ok(())
+}
+
+/// Starts segmenting input into attempts on `trigger`, or stops when it is
+/// `None`. An attempt ends after `neutral_timeout_frames` of neutral (60 by
+/// default) or at `max_frames` (600 by default), whichever comes first.
+#[tauri::command]
+pub fn input_set_attempt_trigger(
+    state: State<'_, InputRuntimeState>,
+    trigger: Option<AttemptTriggerConfig>,
+    neutral_timeout_frames: Option<u64>,
+    max_frames: Option<u64>,
+) -> Result<(), String> {
+    let attempts = trigger
+        .map(|trigger| AttemptSettings::from_config(&trigger, neutral_timeout_frames, max_frames))
+        .transpose()?;
+    state.lock_settings()?.attempts = attempts;
+    Ok(())
+}
+
+/// Selects the motion leniency preset: `strict`, `sf6_default` or
+/// `lenient`. It replaces any window set with `input_set_motion_window`.
+#[tauri::command]
+pub fn input_set_motion_rules(
+    state: State<'_, InputRuntimeState>,
+    preset: String,
+) -> Result<(), String> {
+    let Some(preset) = motion_preset(&preset) else {
+        return Err(format!(
+            "Unknown motion preset '{preset}'; expected one of {}.",
+            motion_preset_names().join(", ")
+        ));
+    };
+
+    state.lock_settings()?.motion = MotionSettings::from_preset(preset);
+    Ok(())
+}
+
+/// Sets how often the live worker polls the device. Frames are still emitted
+/// at 60 Hz; faster rates merge the presses seen between frames so one-poll
+/// taps are kept.
+#[tauri::command]
+pub fn input_set_polling_rate(
+    state: State<'_, InputRuntimeState>,
+    polling_hz: u32,
+) -> Result<(), String> {
+    if !(FRAME_RATE_HZ..=MAX_POLLING_HZ).contains(&polling_hz) {
+        return Err(format!(
+            "Polling rate must be between {FRAME_RATE_HZ} and {MAX_POLLING_HZ} Hz, got {polling_hz}."
+        ));
+    }
+
+    state.lock_settings()?.polling.polling_hz = polling_hz;
+    Ok(())
+}
+
+/// Switches the side directions are mirrored for. While something horizontal
+/// is held the switch waits for its release.
+#[tauri::command]
+pub fn input_set_facing(state: State<'_, InputRuntimeState>, facing: Facing) -> Result<(), String> {
+    state.lock_settings()?.facing = facing;
+    Ok(())
+}
+
+/// Loads a generic HID profile from its JSON or the path of a JSON file. It
+/// decodes reports the built-in formats reject from then on, and lets
+/// otherwise unsupported gamepads it matches be opened at the next start.
+#[tauri::command]
+pub fn input_load_hid_profile(
+    state: State<'_, InputRuntimeState>,
+    profile: String,
+) -> Result<(), String> {
+    let profile = HidProfile::load(&profile)?;
+    state.lock_settings()?.hid_profile = Some(Arc::new(profile));
+    Ok(())
+}
+
+#[tauri::command]
+pub fn input_clear_hid_profile(state: State<'_, InputRuntimeState>) -> Result<(), String> {
+    state.lock_settings()?.hid_profile = None;
+    Ok(())
+}
+
+/// Saves the current mapping, analog settings and SOCD policy as the
+/// profile of `device_id`, an entry of `input_list_devices`. `input_start`
+/// applies it whenever that model is opened again.
+#[tauri::command]
+pub async fn input_profile_save(
+    app: AppHandle,
+    state: State<'_, InputRuntimeState>,
+    device_id: String,
+) -> Result<DeviceProfileEntry, String> {
+    save_device_profile(&app, &state, &device_id).await
+}
+
+async fn save_device_profile(
+    app: &AppHandle,
+    state: &InputRuntimeState,
+    device_id: &str,
+) -> Result<DeviceProfileEntry, String> {
+    let hid_allowlist = saved_hid_allowlist(app);
+    let devices = spawn_blocking(move || platform::input_list_devices(&hid_allowlist))
+        .await
+        .map_err(|error| format!("Failed to list native input devices: {error}"))?;
+    let device = devices
+        .iter()
+        .find(|device| device.answers_to(device_id))
+        .ok_or_else(|| format!("Native input device '{device_id}' is not connected."))?;
+
+    let profile = DeviceProfileConfig::from_settings(&*state.lock_settings()?);
+    DeviceProfileStore::new(app)?.save(device_profile_key(device), profile)
+}
+
+#[tauri::command]
+pub fn input_profile_list(app: AppHandle) -> Result<Vec<DeviceProfileEntry>, String> {
+    DeviceProfileStore::new(&app)?.list()
+}
+
+#[tauri::command]
+pub fn input_profile_delete(app: AppHandle, key: String) -> Result<(), String> {
+    DeviceProfileStore::new(&app)?.delete(&key)
+}
+
+/// Saves the VID/PIDs the HID backend treats as DS4-compatible boards, e.g.
+/// `[{"vendor_id": 3853, "product_id": 102}]`, replacing the previous list.
+/// Detection picks it up at once; a running worker on its next start.
+#[tauri::command]
+pub fn input_set_hid_allowlist(app: AppHandle, devices: Vec<HidDeviceId>) -> Result<(), String> {
+    DeviceProfileStore::new(&app)?.set_hid_allowlist(devices)
+}
+
+#[tauri::command]
+pub fn input_get_hid_allowlist(app: AppHandle) -> Result<Vec<HidDeviceId>, String> {
+    DeviceProfileStore::new(&app)?.hid_allowlist()
+}
+
+/// Updates the given analog thresholds and returns the full effective set.
+#[tauri::command]
+pub fn input_configure(
+    state: State<'_, InputRuntimeState>,
+    config: AnalogConfig,
+) -> Result<AnalogConfig, String> {
+    let mut settings = state.lock_settings()?;
+    settings.analog = settings.analog.with_config(&config)?;
+    settings.analog_configured = true;
+    Ok(settings.analog.to_config())
+}
+
+/// Samples the resting stick for a second and moves the stick centers to
+/// where it sits, so a drifting pad stops registering directions at rest.
+/// Measures whichever stick `direction_source` reads on P1's running input.
+/// With `device_id` the result is saved to that device's profile as
+/// `input_profile_save` would; answers with the effective analog settings.
+#[tauri::command]
+pub async fn input_calibrate_stick(
+    app: AppHandle,
+    state: State<'_, InputRuntimeState>,
+    device_id: Option<String>,
+) -> Result<AnalogConfig, String> {
+    let xinput = {
+        let worker_guard = state
+            .worker
+            .lock()
+            .map_err(|_| "Failed to lock input runtime state.".to_string())?;
+        let modes = worker_guard
+            .as_ref()
+            .filter(|worker| worker.kind == WorkerKind::Live && !worker.is_finished())
+            .map(|worker| worker.modes.clone())
+            .ok_or_else(|| "Start input before calibrating the stick.".to_string())?;
+        // XInput centers are in its own axis units, the others in bytes.
+        let xinput = modes.contains(&NativeInputMode::XInput);
+        if xinput && modes.len() > 1 {
+            return Err("Calibrate XInput with no other input mode running.".to_string());
+        }
+        xinput
+    };
+
+    spawn_blocking(|| thread::sleep(STICK_CALIBRATION_DURATION))
+        .await
+        .map_err(|error| format!("Stick calibration was interrupted: {error}"))?;
+
+    let right = state.lock_settings()?.analog.direction_source() == DirectionSource::RightStick;
+    let rest: Vec<(u8, u8)> = state
+        .lock_history()?
+        .recent(STICK_CALIBRATION_DURATION.as_millis() as u64 * FRAMES_PER_SECOND as u64 / 1_000)
+        .filter(|frame| frame.connected)
+        .filter_map(|frame| {
+            if right {
+                frame.sample.right_stick.map(|stick| (stick.x, stick.y))
+            } else {
+                frame.sample.left_stick
+            }
+        })
+        .collect();
+    let analog = {
+        let mut settings = state.lock_settings()?;
+        settings.analog = settings.analog.calibrated(&rest, xinput)?;
+        settings.analog_configured = true;
+        settings.analog
+    };
+
+    if let Some(device_id) = device_id {
+        save_device_profile(&app, &state, &device_id).await?;
+    }
+    Ok(analog.to_config())
+}
+
+#[tauri::command]
+pub fn input_set_keyboard_bindings(
+    state: State<'_, InputRuntimeState>,
+    bindings: KeyboardBindingsConfig,
+) -> Result<(), String> {
+    let bindings = KeyboardBindings::from_config(&bindings)?;
+    state.lock_settings()?.keyboard = bindings;
+    Ok(())
+}
+
+#[tauri::command]
+pub fn input_get_keyboard_bindings(
+    state: State<'_, InputRuntimeState>,
+) -> Result<KeyboardBindingsConfig, String> {
+    Ok(state.lock_settings()?.keyboard.to_config())
+}
+
+#[tauri::command]
+pub fn combo_load(
+    state: State<'_, InputRuntimeState>,
+    script: ComboScriptConfig,
+) -> Result<(), String> {
+    let script = ComboScript::from_config(&script)?;
+    state.lock_combo()?.load(script);
+    Ok(())
+}
+
+#[tauri::command]
+pub fn combo_arm(state: State<'_, InputRuntimeState>) -> Result<(), String> {
+    state.lock_combo()?.arm()
+}
+
+#[tauri::command]
+pub fn combo_disarm(state: State<'_, InputRuntimeState>) -> Result<(), String> {
+    state.lock_combo()?.disarm();
+    Ok(())
+}
+
+/// Starts a reaction drill: `input/cue` after a random delay, then
+/// `input/reaction_result` once the target buttons are pressed.
+#[tauri::command]
+pub fn reaction_start(
+    state: State<'_, InputRuntimeState>,
+    config: ReactionConfig,
+) -> Result<(), String> {
+    state.lock_reaction()?.start(&config)
+}
+
+#[tauri::command]
+pub fn reaction_stop(state: State<'_, InputRuntimeState>) -> Result<ReactionSummary, String> {
+    Ok(state.lock_reaction()?.stop())
+}
+
+#[tauri::command]
+pub fn reaction_get_summary(
+    state: State<'_, InputRuntimeState>,
+) -> Result<ReactionSummary, String> {
+    Ok(state.lock_reaction()?.summary())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hid_device(path: &str, serial: Option<&str>) -> NativeInputDevice {
+        let mut device = NativeInputDevice::new(NativeInputMode::Hid, path);
+        device.serial_number = serial.map(str::to_string);
+        device
+    }
+
+    #[test]
+    fn other_seat_device_is_resolved_by_serial() {
+        let devices = [hid_device("a", Some("S1")), hid_device("b", Some("S2"))];
+        // One seat pinned the stick by path, the other asks for it by serial.
+        assert!(reads_same_device(&devices, &devices[0], "hid:a"));
+        assert!(reads_same_device(&devices, &devices[0], "hid:serial:S1"));
+        assert!(!reads_same_device(&devices, &devices[1], "hid:serial:S1"));
+        assert!(!reads_same_device(&devices, &devices[1], "hid:a"));
+    }
+
+    #[test]
+    fn disconnected_other_seat_device_is_compared_by_id() {
+        let devices = [hid_device("a", Some("S1"))];
+        assert!(!reads_same_device(&devices, &devices[0], "hid:gone"));
+        assert!(!reads_same_device(&devices, &devices[0], "hid:serial:S9"));
+        let unlisted = hid_device("b", None);
+        assert!(reads_same_device(&devices, &unlisted, "hid:b"));
+    }
+}
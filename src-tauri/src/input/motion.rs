@@ -0,0 +1,625 @@
+use std::collections::VecDeque;
+
+use serde::Serialize;
+
+use super::{
+    BUTTON_HK_MASK, BUTTON_HP_MASK, BUTTON_LK_MASK, BUTTON_LP_MASK, BUTTON_MK_MASK, BUTTON_MP_MASK,
+    SF6_BUTTON_ORDER,
+};
+
+/// Direction changes kept for matching; the longest motion needs 6.
+const DIRECTION_HISTORY_LEN: usize = 32;
+
+pub(crate) const ATTACK_BUTTON_MASK: u16 = BUTTON_LP_MASK
+    | BUTTON_MP_MASK
+    | BUTTON_HP_MASK
+    | BUTTON_LK_MASK
+    | BUTTON_MK_MASK
+    | BUTTON_HK_MASK;
+
+const UP: &[u8] = &[7, 8, 9];
+const DOWN: &[u8] = &[2];
+const LEFT: &[u8] = &[4];
+const RIGHT: &[u8] = &[6];
+
+/// Variant of the patterns every preset accepts.
+const STANDARD: &str = "standard";
+
+/// A motion is an ordered list of steps, each accepting any of a set of
+/// numpad directions; other directions may appear between steps, which is how
+/// 2369 still counts as 236. Shortcuts are patterns of their own, with a
+/// `variant` a preset has to accept.
+struct MotionPattern {
+    motion: &'static str,
+    variant: &'static str,
+    steps: &'static [&'static [u8]],
+}
+
+/// Most specific first: when several motions complete on the same press, the
+/// first match wins, so 6236 reports 623, 63214 beats 214 and a super beats
+/// the quarter circle and DP it ends in. A standard pattern comes before the
+/// shortcuts for the same motion.
+///
+/// Supers are two quarter circles; `super_skip_diagonal` lets the second one
+/// skip its diagonal (23626). Directions between the two, such as a stick
+/// rolled back through 3 (2363236) or 1 (2361236), are strays like any other
+/// and only count against the step window. 2363214 ends in a half circle
+/// rather than a second quarter circle, so it reads as 63214.
+const MOTIONS: &[MotionPattern] = &[
+    MotionPattern {
+        motion: "360",
+        variant: STANDARD,
+        steps: &[RIGHT, DOWN, LEFT, UP],
+    },
+    MotionPattern {
+        motion: "360",
+        variant: STANDARD,
+        steps: &[DOWN, LEFT, UP, RIGHT],
+    },
+    MotionPattern {
+        motion: "360",
+        variant: STANDARD,
+        steps: &[LEFT, UP, RIGHT, DOWN],
+    },
+    MotionPattern {
+        motion: "360",
+        variant: STANDARD,
+        steps: &[UP, RIGHT, DOWN, LEFT],
+    },
+    MotionPattern {
+        motion: "360",
+        variant: STANDARD,
+        steps: &[LEFT, DOWN, RIGHT, UP],
+    },
+    MotionPattern {
+        motion: "360",
+        variant: STANDARD,
+        steps: &[DOWN, RIGHT, UP, LEFT],
+    },
+    MotionPattern {
+        motion: "360",
+        variant: STANDARD,
+        steps: &[RIGHT, UP, LEFT, DOWN],
+    },
+    MotionPattern {
+        motion: "360",
+        variant: STANDARD,
+        steps: &[UP, LEFT, DOWN, RIGHT],
+    },
+    MotionPattern {
+        motion: "236236",
+        variant: STANDARD,
+        steps: &[&[2], &[3], &[6], &[2], &[3], &[6]],
+    },
+    MotionPattern {
+        motion: "236236",
+        variant: "super_skip_diagonal",
+        steps: &[&[2], &[3], &[6], &[2], &[6]],
+    },
+    MotionPattern {
+        motion: "214214",
+        variant: STANDARD,
+        steps: &[&[2], &[1], &[4], &[2], &[1], &[4]],
+    },
+    MotionPattern {
+        motion: "214214",
+        variant: "super_skip_diagonal",
+        steps: &[&[2], &[1], &[4], &[2], &[4]],
+    },
+    MotionPattern {
+        motion: "63214",
+        variant: STANDARD,
+        steps: &[&[6], &[3], &[2], &[1], &[4]],
+    },
+    MotionPattern {
+        motion: "41236",
+        variant: STANDARD,
+        steps: &[&[4], &[1], &[2], &[3], &[6]],
+    },
+    MotionPattern {
+        motion: "41236",
+        variant: "skip_1",
+        steps: &[&[4], &[2], &[3], &[6]],
+    },
+    MotionPattern {
+        motion: "623",
+        variant: STANDARD,
+        steps: &[&[6], &[2], &[3]],
+    },
+    MotionPattern {
+        motion: "623",
+        variant: "shortcut_323",
+        steps: &[&[3], &[2], &[3]],
+    },
+    MotionPattern {
+        motion: "214",
+        variant: STANDARD,
+        steps: &[&[2], &[1], &[4]],
+    },
+    MotionPattern {
+        motion: "236",
+        variant: STANDARD,
+        steps: &[&[2], &[3], &[6]],
+    },
+];
+
+/// Named leniency rules selected with `input_set_motion_rules`.
+pub(crate) struct MotionPreset {
+    pub name: &'static str,
+    /// Maximum frames between the starts of two consecutive motion steps.
+    window_frames: u64,
+    /// Motions given a window of their own; a 360 is slower to input than
+    /// a quarter circle.
+    motion_windows: &'static [(&'static str, u64)],
+    /// Maximum frames between the last motion direction and the button.
+    button_buffer_frames: u64,
+    /// Shortcut variants accepted besides the standard patterns.
+    variants: &'static [&'static str],
+}
+
+const STRICT: MotionPreset = MotionPreset {
+    name: "strict",
+    window_frames: 6,
+    motion_windows: &[("360", 8)],
+    button_buffer_frames: 4,
+    variants: &[],
+};
+
+/// Close to the game: about 10 frames per step and after the motion, with
+/// the DP and half circle shortcuts it accepts.
+const SF6_DEFAULT: MotionPreset = MotionPreset {
+    name: "sf6_default",
+    window_frames: 10,
+    motion_windows: &[("360", 14)],
+    button_buffer_frames: 10,
+    variants: &["shortcut_323", "skip_1", "super_skip_diagonal"],
+};
+
+const LENIENT: MotionPreset = MotionPreset {
+    name: "lenient",
+    window_frames: 15,
+    motion_windows: &[("360", 20)],
+    button_buffer_frames: 15,
+    variants: &["shortcut_323", "skip_1", "super_skip_diagonal"],
+};
+
+const MOTION_PRESETS: &[&MotionPreset] = &[&STRICT, &SF6_DEFAULT, &LENIENT];
+
+pub(crate) fn motion_preset(name: &str) -> Option<&'static MotionPreset> {
+    MOTION_PRESETS
+        .iter()
+        .copied()
+        .find(|preset| preset.name == name)
+}
+
+pub(crate) fn motion_preset_names() -> Vec<&'static str> {
+    MOTION_PRESETS.iter().map(|preset| preset.name).collect()
+}
+
+#[derive(Clone, Copy)]
+pub(crate) struct MotionSettings {
+    pub preset: &'static MotionPreset,
+    /// Maximum frames between the starts of two consecutive motion steps,
+    /// for motions the preset gives no window of their own.
+    pub window_frames: u64,
+    /// Maximum frames between the last motion direction and the button.
+    pub button_buffer_frames: u64,
+}
+
+impl MotionSettings {
+    pub(crate) fn from_preset(preset: &'static MotionPreset) -> Self {
+        Self {
+            preset,
+            window_frames: preset.window_frames,
+            button_buffer_frames: preset.button_buffer_frames,
+        }
+    }
+
+    fn window_for(&self, motion: &str) -> u64 {
+        self.preset
+            .motion_windows
+            .iter()
+            .find(|(name, _)| *name == motion)
+            .map_or(self.window_frames, |(_, frames)| *frames)
+    }
+
+    fn accepts(&self, variant: &str) -> bool {
+        variant == STANDARD || self.preset.variants.contains(&variant)
+    }
+}
+
+impl Default for MotionSettings {
+    fn default() -> Self {
+        Self::from_preset(&SF6_DEFAULT)
+    }
+}
+
+#[derive(Clone, Serialize)]
+pub struct InputMotionPayload {
+    motion: &'static str,
+    /// Pattern that matched, `"standard"` or a shortcut such as
+    /// `"shortcut_323"`.
+    variant: &'static str,
+    button: String,
+    frame: u64,
+    /// The motion was finished by releasing `button` rather than pressing it.
+    negative_edge: bool,
+    /// Frames from entering the motion's last direction to the button.
+    frames_after_motion: u64,
+    /// Frames from entering the motion's first direction to the button.
+    motion_frames: u64,
+}
+
+impl InputMotionPayload {
+    pub(crate) fn motion(&self) -> &'static str {
+        self.motion
+    }
+
+    pub(crate) fn negative_edge(&self) -> bool {
+        self.negative_edge
+    }
+}
+
+/// Looks up the canonical name of a recognized motion.
+pub(crate) fn motion_name(name: &str) -> Option<&'static str> {
+    MOTIONS
+        .iter()
+        .map(|pattern| pattern.motion)
+        .find(|motion| *motion == name)
+}
+
+/// A direction and the frame it started on. It lasts until the next entry.
+#[derive(Clone, Copy)]
+struct DirectionChange {
+    frame: u64,
+    direction: u8,
+}
+
+/// Worker-owned recognizer fed with every frame's direction and SF6 mask.
+#[derive(Default)]
+pub(crate) struct MotionDetector {
+    history: VecDeque<DirectionChange>,
+    previous_mask: u16,
+}
+
+impl MotionDetector {
+    /// Records the frame and returns one event per attack button pressed on
+    /// it that completes a motion, then one per button released that would
+    /// have, for negative-edge inputs. Presses come first.
+    pub(crate) fn push(
+        &mut self,
+        frame: u64,
+        direction: u8,
+        sf6_mask: u16,
+        settings: MotionSettings,
+    ) -> Vec<InputMotionPayload> {
+        if self.history.back().map(|last| last.direction) != Some(direction) {
+            if self.history.len() == DIRECTION_HISTORY_LEN {
+                self.history.pop_front();
+            }
+            self.history.push_back(DirectionChange { frame, direction });
+        }
+
+        let pressed = sf6_mask & !self.previous_mask & ATTACK_BUTTON_MASK;
+        let released = self.previous_mask & !sf6_mask & ATTACK_BUTTON_MASK;
+        self.previous_mask = sf6_mask;
+        if pressed | released == 0 {
+            return Vec::new();
+        }
+
+        let Some((pattern, (frames_after_motion, motion_frames))) =
+            MOTIONS.iter().find_map(|pattern| {
+                if !settings.accepts(pattern.variant) {
+                    return None;
+                }
+                self.matches(pattern, frame, settings)
+                    .map(|frames| (pattern, frames))
+            })
+        else {
+            return Vec::new();
+        };
+
+        [(pressed, false), (released, true)]
+            .into_iter()
+            .flat_map(|(mask, negative_edge)| {
+                SF6_BUTTON_ORDER
+                    .iter()
+                    .enumerate()
+                    .filter(move |(index, _)| mask & (1 << index) != 0)
+                    .map(move |(_, button)| InputMotionPayload {
+                        motion: pattern.motion,
+                        variant: pattern.variant,
+                        button: (*button).to_string(),
+                        frame,
+                        negative_edge,
+                        frames_after_motion,
+                        motion_frames,
+                    })
+            })
+            .collect()
+    }
+
+    /// Walks the history backwards, matching each step to the most recent
+    /// qualifying direction before the step after it. On a match, answers
+    /// how many frames after entering the last and the first step the
+    /// button came.
+    fn matches(
+        &self,
+        pattern: &MotionPattern,
+        button_frame: u64,
+        settings: MotionSettings,
+    ) -> Option<(u64, u64)> {
+        let mut steps = pattern.steps.iter().rev();
+        let last_step = steps.next()?;
+
+        let mut index = self
+            .history
+            .iter()
+            .rposition(|change| last_step.contains(&change.direction))?;
+        let ended = self
+            .history
+            .get(index + 1)
+            .map_or(button_frame, |next| next.frame);
+        if button_frame.saturating_sub(ended) > settings.button_buffer_frames {
+            return None;
+        }
+        let frames_after_motion = button_frame.saturating_sub(self.history[index].frame);
+
+        let window_frames = settings.window_for(pattern.motion);
+        let mut later_start = self.history[index].frame;
+        for step in steps {
+            let found = (0..index)
+                .rev()
+                .find(|candidate| step.contains(&self.history[*candidate].direction))?;
+            let change = self.history[found];
+            if later_start - change.frame > window_frames {
+                return None;
+            }
+            later_start = change.frame;
+            index = found;
+        }
+
+        Some((
+            frames_after_motion,
+            button_frame.saturating_sub(later_start),
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Held for `frames` frames: a direction and the SF6 buttons down.
+    type Run = (u8, u64, u16);
+
+    /// `(motion, variant, button, negative_edge)` of an expected event.
+    type Event = (&'static str, &'static str, &'static str, bool);
+
+    struct Case {
+        name: &'static str,
+        preset: &'static str,
+        runs: &'static [Run],
+        expected: &'static [Event],
+    }
+
+    const LP: u16 = BUTTON_LP_MASK;
+    const HP: u16 = BUTTON_HP_MASK;
+
+    #[rustfmt::skip]
+    const CASES: &[Case] = &[
+        Case {
+            name: "236",
+            preset: "sf6_default",
+            runs: &[(2, 2, 0), (3, 2, 0), (6, 2, 0), (6, 1, LP)],
+            expected: &[("236", STANDARD, "LP", false)],
+        },
+        Case {
+            name: "2369 still counts as 236",
+            preset: "sf6_default",
+            runs: &[(2, 2, 0), (3, 2, 0), (6, 2, 0), (9, 1, LP)],
+            expected: &[("236", STANDARD, "LP", false)],
+        },
+        Case {
+            name: "6236 reports 623",
+            preset: "sf6_default",
+            runs: &[(6, 2, 0), (2, 2, 0), (3, 2, 0), (6, 1, LP)],
+            expected: &[("623", STANDARD, "LP", false)],
+        },
+        Case {
+            name: "63214 beats 214",
+            preset: "sf6_default",
+            runs: &[(6, 2, 0), (3, 2, 0), (2, 2, 0), (1, 2, 0), (4, 1, LP)],
+            expected: &[("63214", STANDARD, "LP", false)],
+        },
+        Case {
+            name: "two buttons on one frame",
+            preset: "sf6_default",
+            runs: &[(2, 2, 0), (3, 2, 0), (6, 1, LP | HP)],
+            expected: &[
+                ("236", STANDARD, "LP", false),
+                ("236", STANDARD, "HP", false),
+            ],
+        },
+        // The 4 to 8 step takes exactly the preset's 360 window, then one
+        // frame more; the half circle inside it still matches.
+        Case {
+            name: "360 within the strict window",
+            preset: "strict",
+            runs: &[(6, 2, 0), (3, 2, 0), (2, 2, 0), (1, 2, 0), (4, 7, 0), (7, 1, 0), (8, 1, LP)],
+            expected: &[("360", STANDARD, "LP", false)],
+        },
+        Case {
+            name: "360 past the strict window",
+            preset: "strict",
+            runs: &[(6, 2, 0), (3, 2, 0), (2, 2, 0), (1, 2, 0), (4, 8, 0), (7, 1, 0), (8, 1, LP)],
+            expected: &[("63214", STANDARD, "LP", false)],
+        },
+        Case {
+            name: "360 within the sf6_default window",
+            preset: "sf6_default",
+            runs: &[(6, 2, 0), (3, 2, 0), (2, 2, 0), (1, 2, 0), (4, 13, 0), (7, 1, 0), (8, 1, LP)],
+            expected: &[("360", STANDARD, "LP", false)],
+        },
+        Case {
+            name: "360 past the sf6_default window",
+            preset: "sf6_default",
+            runs: &[(6, 2, 0), (3, 2, 0), (2, 2, 0), (1, 2, 0), (4, 14, 0), (7, 1, 0), (8, 1, LP)],
+            expected: &[("63214", STANDARD, "LP", false)],
+        },
+        Case {
+            name: "360 within the lenient window",
+            preset: "lenient",
+            runs: &[(6, 2, 0), (3, 2, 0), (2, 2, 0), (1, 2, 0), (4, 19, 0), (7, 1, 0), (8, 1, LP)],
+            expected: &[("360", STANDARD, "LP", false)],
+        },
+        Case {
+            name: "360 past the lenient window",
+            preset: "lenient",
+            runs: &[(6, 2, 0), (3, 2, 0), (2, 2, 0), (1, 2, 0), (4, 20, 0), (7, 1, 0), (8, 1, LP)],
+            expected: &[("63214", STANDARD, "LP", false)],
+        },
+        Case {
+            name: "button at the end of the buffer",
+            preset: "sf6_default",
+            runs: &[(2, 2, 0), (3, 2, 0), (6, 2, 0), (5, 10, 0), (5, 1, LP)],
+            expected: &[("236", STANDARD, "LP", false)],
+        },
+        Case {
+            name: "button past the buffer",
+            preset: "sf6_default",
+            runs: &[(2, 2, 0), (3, 2, 0), (6, 2, 0), (5, 11, 0), (5, 1, LP)],
+            expected: &[],
+        },
+        Case {
+            name: "negative edge",
+            preset: "sf6_default",
+            runs: &[(2, 2, LP), (3, 2, LP), (6, 2, LP), (6, 1, 0)],
+            expected: &[("236", STANDARD, "LP", true)],
+        },
+        Case {
+            name: "236236",
+            preset: "sf6_default",
+            runs: &[(2, 2, 0), (3, 2, 0), (6, 2, 0), (2, 2, 0), (3, 2, 0), (6, 1, LP)],
+            expected: &[("236236", STANDARD, "LP", false)],
+        },
+        Case {
+            name: "214214",
+            preset: "strict",
+            runs: &[(2, 2, 0), (1, 2, 0), (4, 2, 0), (2, 2, 0), (1, 2, 0), (4, 1, LP)],
+            expected: &[("214214", STANDARD, "LP", false)],
+        },
+        Case {
+            name: "23626 under sf6_default",
+            preset: "sf6_default",
+            runs: &[(2, 2, 0), (3, 2, 0), (6, 2, 0), (2, 2, 0), (6, 1, LP)],
+            expected: &[("236236", "super_skip_diagonal", "LP", false)],
+        },
+        Case {
+            name: "21424 under lenient",
+            preset: "lenient",
+            runs: &[(2, 2, 0), (1, 2, 0), (4, 2, 0), (2, 2, 0), (4, 1, LP)],
+            expected: &[("214214", "super_skip_diagonal", "LP", false)],
+        },
+        Case {
+            name: "23626 is only a 236 under strict",
+            preset: "strict",
+            runs: &[(2, 2, 0), (3, 2, 0), (6, 2, 0), (2, 2, 0), (6, 1, LP)],
+            expected: &[("236", STANDARD, "LP", false)],
+        },
+        Case {
+            name: "2363236 rolls back through 3",
+            preset: "sf6_default",
+            runs: &[(2, 2, 0), (3, 2, 0), (6, 2, 0), (3, 2, 0), (2, 2, 0), (3, 2, 0), (6, 1, LP)],
+            expected: &[("236236", STANDARD, "LP", false)],
+        },
+        Case {
+            name: "2361236 with a stray 1",
+            preset: "strict",
+            runs: &[(2, 2, 0), (3, 2, 0), (6, 2, 0), (1, 2, 0), (2, 2, 0), (3, 2, 0), (6, 1, LP)],
+            expected: &[("236236", STANDARD, "LP", false)],
+        },
+        Case {
+            name: "a stray held past the window splits the super",
+            preset: "sf6_default",
+            runs: &[(2, 2, 0), (3, 2, 0), (6, 2, 0), (1, 9, 0), (2, 2, 0), (3, 2, 0), (6, 1, LP)],
+            expected: &[("236", STANDARD, "LP", false)],
+        },
+        Case {
+            name: "2363214 reads as 63214",
+            preset: "sf6_default",
+            runs: &[(2, 2, 0), (3, 2, 0), (6, 2, 0), (3, 2, 0), (2, 2, 0), (1, 2, 0), (4, 1, LP)],
+            expected: &[("63214", STANDARD, "LP", false)],
+        },
+        Case {
+            name: "323 shortcut under sf6_default",
+            preset: "sf6_default",
+            runs: &[(3, 2, 0), (2, 2, 0), (3, 1, LP)],
+            expected: &[("623", "shortcut_323", "LP", false)],
+        },
+        Case {
+            name: "323 shortcut rejected under strict",
+            preset: "strict",
+            runs: &[(3, 2, 0), (2, 2, 0), (3, 1, LP)],
+            expected: &[],
+        },
+        Case {
+            name: "4236 under sf6_default",
+            preset: "sf6_default",
+            runs: &[(4, 2, 0), (2, 2, 0), (3, 2, 0), (6, 1, LP)],
+            expected: &[("41236", "skip_1", "LP", false)],
+        },
+        Case {
+            name: "4236 is only a 236 under strict",
+            preset: "strict",
+            runs: &[(4, 2, 0), (2, 2, 0), (3, 2, 0), (6, 1, LP)],
+            expected: &[("236", STANDARD, "LP", false)],
+        },
+    ];
+
+    fn run(preset: &'static str, runs: &[Run]) -> Vec<InputMotionPayload> {
+        let settings = MotionSettings::from_preset(motion_preset(preset).unwrap());
+        let mut detector = MotionDetector::default();
+        let mut frame = 0;
+        let mut events = Vec::new();
+        for &(direction, frames, mask) in runs {
+            for _ in 0..frames {
+                events.extend(detector.push(frame, direction, mask, settings));
+                frame += 1;
+            }
+        }
+        events
+    }
+
+    #[test]
+    fn recognizes_motions() {
+        for case in CASES {
+            let events = run(case.preset, case.runs);
+            let events: Vec<_> = events
+                .iter()
+                .map(|event| {
+                    (
+                        event.motion,
+                        event.variant,
+                        event.button.as_str(),
+                        event.negative_edge,
+                    )
+                })
+                .collect();
+            assert_eq!(events, case.expected, "{}", case.name);
+        }
+    }
+
+    #[test]
+    fn reports_motion_timing() {
+        let events = run(
+            "sf6_default",
+            &[(2, 2, 0), (3, 2, 0), (6, 2, 0), (5, 3, 0), (5, 1, LP)],
+        );
+
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].frame, 9);
+        assert_eq!(events[0].frames_after_motion, 5);
+        assert_eq!(events[0].motion_frames, 9);
+    }
+}
@@ -0,0 +1,381 @@
+//! Numpad notation of the recent input history, e.g. `236HP | 2MK 236HP`.
+
+use serde::Serialize;
+
+use super::{
+    emitter::PolledFrame,
+    grouping::GroupedFrame,
+    mask_to_sf6_buttons,
+    motion::{MotionDetector, MotionSettings},
+};
+
+pub(crate) const DEFAULT_NEUTRAL_GAP_FRAMES: u64 = 30;
+/// Stands for an idle stretch in the compact text.
+const NEUTRAL_SEPARATOR: &str = "|";
+/// Frames from pressing up to leaving the ground, and the time in the air
+/// after that, of a typical SF6 jump. Input alone cannot tell whether a
+/// character actually jumped, so presses in that window are only assumed to
+/// be airborne.
+const PREJUMP_FRAMES: u64 = 4;
+const AIRBORNE_FRAMES: u64 = 38;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum NotationTokenKind {
+    /// A held direction, e.g. `2`.
+    Direction,
+    /// Buttons pressed on one frame, e.g. `6+HP`, `236+HP` or `j.MK`.
+    Press,
+    /// Neutral with nothing held for longer than the gap threshold.
+    Gap,
+}
+
+#[derive(Clone, Serialize)]
+pub struct NotationToken {
+    kind: NotationTokenKind,
+    text: String,
+    frame: u64,
+    /// How long a direction or gap lasted; `None` for presses.
+    frames: Option<u64>,
+    /// Motion the motion detector confirmed for a press.
+    motion: Option<&'static str>,
+}
+
+/// Answer to `input_get_notation`.
+#[derive(Clone, Serialize)]
+pub struct InputNotation {
+    /// Presses and gaps only, e.g. `236HP | 2MK 236HP`.
+    text: String,
+    tokens: Vec<NotationToken>,
+}
+
+#[cfg(test)]
+impl InputNotation {
+    pub(crate) fn text(&self) -> &str {
+        &self.text
+    }
+}
+
+/// Direction run being extended frame by frame.
+struct OpenDirection {
+    token: usize,
+    direction: u8,
+    start_frame: u64,
+    /// Nothing was held for the whole run.
+    idle: bool,
+}
+
+/// Coalesces frames into tokens. Frames carry the facing-mirrored direction
+/// and the mapped SF6 mask, so the notation reads as the game would see it.
+struct NotationBuilder {
+    gap_frames: u64,
+    detector: MotionDetector,
+    motion: MotionSettings,
+    tokens: Vec<NotationToken>,
+    compact: Vec<String>,
+    open: Option<OpenDirection>,
+    jump_frame: Option<u64>,
+    last_frame: u64,
+}
+
+impl NotationBuilder {
+    fn push(&mut self, frame: &PolledFrame) {
+        let direction = frame.sample.direction;
+        let motion = self
+            .detector
+            .push(frame.frame, direction, frame.sf6_mask, self.motion)
+            .iter()
+            .find(|payload| !payload.negative_edge())
+            .map(|payload| payload.motion());
+
+        if self.open.as_ref().map(|open| open.direction) != Some(direction) {
+            self.close(frame.frame);
+            if matches!(direction, 7..=9) && !self.airborne(frame.frame) {
+                self.jump_frame = Some(frame.frame);
+            }
+            self.open = Some(OpenDirection {
+                token: self.tokens.len(),
+                direction,
+                start_frame: frame.frame,
+                idle: true,
+            });
+            self.tokens.push(NotationToken {
+                kind: NotationTokenKind::Direction,
+                text: direction.to_string(),
+                frame: frame.frame,
+                frames: None,
+                motion: None,
+            });
+        }
+        if let Some(open) = self.open.as_mut() {
+            open.idle &= frame.sf6_mask == 0;
+        }
+        self.last_frame = frame.frame;
+
+        let pressed = frame.sf6_mask & !frame.previous_sf6_mask;
+        if pressed == 0 {
+            return;
+        }
+        let (token, compact) = press_token(frame, motion, self.airborne(frame.frame));
+        self.tokens.push(token);
+        self.compact.push(compact);
+    }
+
+    fn airborne(&self, frame: u64) -> bool {
+        airborne(self.jump_frame, frame)
+    }
+
+    /// Ends the open direction run, which lasted until `end_frame` started.
+    fn close(&mut self, end_frame: u64) {
+        let Some(open) = self.open.take() else {
+            return;
+        };
+        let frames = end_frame.saturating_sub(open.start_frame).max(1);
+        let token = &mut self.tokens[open.token];
+        token.frames = Some(frames);
+        if open.direction == 5 && open.idle && frames > self.gap_frames {
+            token.kind = NotationTokenKind::Gap;
+            token.text = NEUTRAL_SEPARATOR.to_string();
+            if self
+                .compact
+                .last()
+                .is_some_and(|last| last != NEUTRAL_SEPARATOR)
+            {
+                self.compact.push(NEUTRAL_SEPARATOR.to_string());
+            }
+        }
+    }
+
+    fn finish(mut self) -> InputNotation {
+        self.close(self.last_frame + 1);
+        if self
+            .compact
+            .last()
+            .is_some_and(|last| last == NEUTRAL_SEPARATOR)
+        {
+            self.compact.pop();
+        }
+        InputNotation {
+            text: self.compact.join(" "),
+            tokens: self.tokens,
+        }
+    }
+}
+
+fn airborne(jump_frame: Option<u64>, frame: u64) -> bool {
+    jump_frame.is_some_and(|jump_frame| {
+        (jump_frame + PREJUMP_FRAMES..jump_frame + PREJUMP_FRAMES + AIRBORNE_FRAMES)
+            .contains(&frame)
+    })
+}
+
+/// The press token of `frame` and its compact text, e.g. `236+HP` and
+/// `236HP`.
+fn press_token(
+    frame: &PolledFrame,
+    motion: Option<&'static str>,
+    airborne: bool,
+) -> (NotationToken, String) {
+    let buttons = mask_to_sf6_buttons(frame.sf6_mask & !frame.previous_sf6_mask).join("+");
+    let (text, compact) = if airborne {
+        let text = format!("j.{buttons}");
+        (text.clone(), text)
+    } else {
+        let prefix = motion
+            .map(str::to_string)
+            .unwrap_or_else(|| frame.sample.direction.to_string());
+        if prefix == "5" {
+            (buttons.clone(), buttons)
+        } else {
+            (format!("{prefix}+{buttons}"), format!("{prefix}{buttons}"))
+        }
+    };
+    let token = NotationToken {
+        kind: NotationTokenKind::Press,
+        text,
+        frame: frame.frame,
+        frames: None,
+        motion,
+    };
+    (token, compact)
+}
+
+/// Payload of `input/notation`: one press as it happens.
+#[derive(Clone, Serialize)]
+pub struct InputNotationPayload {
+    #[serde(flatten)]
+    token: NotationToken,
+    /// The press without the `+`, as in the compact notation text.
+    compact: String,
+    /// Buttons pressed on nearby frames were grouped into this press.
+    simultaneous: bool,
+}
+
+/// Turns the P1 stream into press tokens live, with the same motion and
+/// jump reading as [`notation`]. Fed after press grouping, so a plink reads
+/// as one press.
+#[derive(Default)]
+pub(crate) struct NotationStream {
+    detector: MotionDetector,
+    direction: Option<u8>,
+    jump_frame: Option<u64>,
+}
+
+impl NotationStream {
+    pub(crate) fn push(
+        &mut self,
+        grouped: &GroupedFrame,
+        motion: MotionSettings,
+    ) -> Option<InputNotationPayload> {
+        let frame = &grouped.frame;
+        let direction = frame.sample.direction;
+        let motion = self
+            .detector
+            .push(frame.frame, direction, frame.sf6_mask, motion)
+            .iter()
+            .find(|payload| !payload.negative_edge())
+            .map(|payload| payload.motion());
+
+        if self.direction != Some(direction) {
+            if matches!(direction, 7..=9) && !airborne(self.jump_frame, frame.frame) {
+                self.jump_frame = Some(frame.frame);
+            }
+            self.direction = Some(direction);
+        }
+        if frame.sf6_mask & !frame.previous_sf6_mask == 0 {
+            return None;
+        }
+
+        let (token, compact) = press_token(frame, motion, airborne(self.jump_frame, frame.frame));
+        Some(InputNotationPayload {
+            token,
+            compact,
+            simultaneous: grouped.simultaneous,
+        })
+    }
+}
+
+/// Renders `frames`, oldest first. A press is prefixed with the motion the
+/// detector confirmed for it, else with the direction held, so `236HP`
+/// means the motion was read and `6HP` that only 6 was.
+pub(crate) fn notation<'a>(
+    frames: impl IntoIterator<Item = &'a PolledFrame>,
+    motion: MotionSettings,
+    gap_frames: u64,
+) -> InputNotation {
+    let mut builder = NotationBuilder {
+        gap_frames,
+        detector: MotionDetector::default(),
+        motion,
+        tokens: Vec::new(),
+        compact: Vec::new(),
+        open: None,
+        jump_frame: None,
+        last_frame: 0,
+    };
+    for frame in frames {
+        builder.push(frame);
+    }
+    builder.finish()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{NotationTokenKind::*, *};
+    use crate::input::{
+        FrameTimestamp, InputSample, BUTTON_HP_MASK, BUTTON_LP_MASK, BUTTON_MK_MASK,
+    };
+
+    /// Held for `frames` frames: a direction and the SF6 buttons down.
+    type Run = (u8, u64, u16);
+
+    /// `(kind, text, frames)` of an expected token.
+    type Token = (NotationTokenKind, &'static str, Option<u64>);
+
+    struct Case {
+        name: &'static str,
+        gap_frames: u64,
+        runs: &'static [Run],
+        text: &'static str,
+        tokens: &'static [Token],
+    }
+
+    const LP: u16 = BUTTON_LP_MASK;
+    const HP: u16 = BUTTON_HP_MASK;
+    const MK: u16 = BUTTON_MK_MASK;
+
+    #[rustfmt::skip]
+    const CASES: &[Case] = &[
+        Case {
+            name: "holds last until the direction changes, neutral at the gap stays 5",
+            gap_frames: 3,
+            runs: &[(6, 4, 0), (4, 2, 0), (5, 3, 0)],
+            text: "",
+            tokens: &[(Direction, "6", Some(4)), (Direction, "4", Some(2)), (Direction, "5", Some(3))],
+        },
+        Case {
+            name: "neutral longer than the gap collapses into a separator",
+            gap_frames: 3,
+            runs: &[(2, 1, 0), (3, 1, 0), (6, 1, HP), (5, 20, 0), (2, 1, MK)],
+            text: "236HP | 2MK",
+            tokens: &[
+                (Direction, "2", Some(1)), (Direction, "3", Some(1)), (Direction, "6", Some(1)),
+                (Press, "236+HP", None), (Gap, "|", Some(20)), (Direction, "2", Some(1)),
+                (Press, "2+MK", None),
+            ],
+        },
+        Case {
+            name: "neutral with a button held is no gap",
+            gap_frames: 3,
+            runs: &[(5, 6, LP), (2, 1, LP)],
+            text: "LP",
+            tokens: &[(Direction, "5", Some(6)), (Press, "LP", None), (Direction, "2", Some(1))],
+        },
+        Case {
+            name: "separators at either end are left out of the text",
+            gap_frames: 3,
+            runs: &[(5, 5, 0), (2, 1, LP), (5, 5, 0)],
+            text: "2LP",
+            tokens: &[
+                (Gap, "|", Some(5)), (Direction, "2", Some(1)), (Press, "2+LP", None),
+                (Gap, "|", Some(5)),
+            ],
+        },
+        Case {
+            name: "a press after the pre-jump frames reads airborne",
+            gap_frames: 3,
+            runs: &[(8, 5, 0), (5, 1, MK)],
+            text: "j.MK",
+            tokens: &[(Direction, "8", Some(5)), (Direction, "5", Some(1)), (Press, "j.MK", None)],
+        },
+    ];
+
+    fn run(case: &Case) -> InputNotation {
+        let mut frames = Vec::new();
+        let mut previous_sf6_mask = 0;
+        for &(direction, length, sf6_mask) in case.runs {
+            for _ in 0..length {
+                let sample = InputSample::new(FrameTimestamp::default(), direction, 0);
+                let mut frame = PolledFrame::new(frames.len() as u64, sample, sf6_mask, true);
+                frame.previous_sf6_mask = previous_sf6_mask;
+                previous_sf6_mask = sf6_mask;
+                frames.push(frame);
+            }
+        }
+        notation(&frames, MotionSettings::default(), case.gap_frames)
+    }
+
+    #[test]
+    fn coalesces_frames_into_tokens() {
+        for case in CASES {
+            let notation = run(case);
+            let tokens: Vec<_> = notation
+                .tokens
+                .iter()
+                .map(|token| (token.kind, token.text.as_str(), token.frames))
+                .collect();
+            assert_eq!(notation.text, case.text, "{}", case.name);
+            assert_eq!(tokens, case.tokens, "{}", case.name);
+        }
+    }
+}
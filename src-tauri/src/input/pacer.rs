@@ -0,0 +1,303 @@
+use std::{
+    thread,
+    time::{Duration, Instant},
+};
+
+use serde::Serialize;
+
+/// How long before the deadline the pacer stops sleeping and spins, which
+/// covers the 1 ms scheduler granularity plus wake-up latency.
+const SPIN_MARGIN: Duration = Duration::from_micros(1_500);
+
+/// Schedules ticks against absolute deadlines (`next_tick += frame_duration`)
+/// so sleep overshoot never accumulates into drift. A tick that overruns by a
+/// whole frame or more skips the missed deadlines instead of bunching them.
+pub(crate) struct FramePacer {
+    frame_duration: Duration,
+    next_tick: Instant,
+    last_deviation: Duration,
+    sleep_timer: SleepTimer,
+    _timer_resolution: TimerResolution,
+}
+
+/// Deviation of each tick from its deadline, exposed through
+/// `input_timing_stats`.
+#[derive(Clone, Copy, Default, Serialize)]
+pub struct InputTimingStats {
+    ticks: u64,
+    missed_frames: u64,
+    average_deviation_us: f64,
+    max_deviation_us: u64,
+    #[serde(skip)]
+    total_deviation_us: u64,
+    /// Device reports drained per frame, for backends that queue reports
+    /// (HID); all zero otherwise.
+    reports_read: u64,
+    last_frame_reports: u32,
+    max_frame_reports: u32,
+}
+
+impl FramePacer {
+    pub(crate) fn start(frame_duration: Duration) -> Self {
+        Self {
+            frame_duration,
+            next_tick: Instant::now() + frame_duration,
+            last_deviation: Duration::ZERO,
+            sleep_timer: SleepTimer::new(),
+            _timer_resolution: TimerResolution::request(),
+        }
+    }
+
+    /// Blocks until the next deadline and returns how many frames passed since
+    /// the previous tick: 1 normally, more when deadlines had to be skipped.
+    pub(crate) fn wait(&mut self, stats: &mut InputTimingStats) -> u64 {
+        let now = Instant::now();
+        if now + SPIN_MARGIN < self.next_tick {
+            thread::sleep(self.next_tick - now - SPIN_MARGIN);
+        }
+        while Instant::now() < self.next_tick {
+            std::hint::spin_loop();
+        }
+
+        let skipped = self.advance(Instant::now());
+        stats.record(self.last_deviation, skipped);
+        skipped + 1
+    }
+
+    /// Moves the deadline on from a tick that woke at `woke_at`, past any
+    /// whole frames it overran, and returns how many it skipped.
+    fn advance(&mut self, woke_at: Instant) -> u64 {
+        let deviation = woke_at.saturating_duration_since(self.next_tick);
+        let skipped = (deviation.as_nanos() / self.frame_duration.as_nanos()) as u64;
+
+        self.next_tick += self.frame_duration * (skipped as u32 + 1);
+        self.last_deviation = deviation;
+        skipped
+    }
+
+    /// How late the most recent [`wait`](Self::wait) woke past its deadline.
+    pub(crate) fn last_deviation(&self) -> Duration {
+        self.last_deviation
+    }
+
+    /// Like [`wait`](Self::wait), but first calls `poll` with sub-poll
+    /// deadlines `interval` apart until the next tick is within the spin
+    /// margin. `poll` is expected to block until its deadline, on the lent
+    /// timer when it has nothing to wait on itself.
+    pub(crate) fn wait_polling(
+        &mut self,
+        stats: &mut InputTimingStats,
+        interval: Duration,
+        mut poll: impl FnMut(Instant, &SleepTimer),
+    ) -> u64 {
+        let mut deadline = Instant::now() + interval;
+        while deadline + SPIN_MARGIN < self.next_tick {
+            poll(deadline, &self.sleep_timer);
+            deadline = (deadline + interval).max(Instant::now());
+        }
+        self.wait(stats)
+    }
+}
+
+/// Sleeps until a deadline without spinning, for sub-frame polls where a
+/// spin per poll would keep a core busy. Windows uses a high-resolution
+/// waitable timer, as `thread::sleep` there only wakes on 1 ms timer ticks;
+/// the pacer creates it once and lends it to every sub-poll.
+#[cfg(windows)]
+pub(crate) struct SleepTimer(windows_sys::Win32::Foundation::HANDLE);
+
+#[cfg(windows)]
+impl SleepTimer {
+    fn new() -> Self {
+        use windows_sys::Win32::System::Threading::{
+            CreateWaitableTimerExW, CREATE_WAITABLE_TIMER_HIGH_RESOLUTION, TIMER_ALL_ACCESS,
+        };
+
+        // A null handle, where high-resolution timers are unsupported, falls
+        // back to `thread::sleep`.
+        Self(unsafe {
+            CreateWaitableTimerExW(
+                std::ptr::null(),
+                std::ptr::null(),
+                CREATE_WAITABLE_TIMER_HIGH_RESOLUTION,
+                TIMER_ALL_ACCESS,
+            )
+        })
+    }
+
+    pub(crate) fn sleep_until(&self, deadline: Instant) {
+        use windows_sys::Win32::System::Threading::{
+            SetWaitableTimer, WaitForSingleObject, INFINITE,
+        };
+
+        let remaining = deadline.saturating_duration_since(Instant::now());
+        if remaining.is_zero() {
+            return;
+        }
+
+        // Negative due times are relative, in 100 ns units.
+        let due_time = -((remaining.as_nanos() / 100) as i64);
+        unsafe {
+            if !self.0.is_null()
+                && SetWaitableTimer(self.0, &due_time, 0, None, std::ptr::null(), 0) != 0
+            {
+                WaitForSingleObject(self.0, INFINITE);
+            } else {
+                thread::sleep(remaining);
+            }
+        }
+    }
+}
+
+#[cfg(windows)]
+impl Drop for SleepTimer {
+    fn drop(&mut self) {
+        if !self.0.is_null() {
+            unsafe { windows_sys::Win32::Foundation::CloseHandle(self.0) };
+        }
+    }
+}
+
+#[cfg(not(windows))]
+pub(crate) struct SleepTimer;
+
+#[cfg(not(windows))]
+impl SleepTimer {
+    fn new() -> Self {
+        Self
+    }
+
+    pub(crate) fn sleep_until(&self, deadline: Instant) {
+        let remaining = deadline.saturating_duration_since(Instant::now());
+        if !remaining.is_zero() {
+            thread::sleep(remaining);
+        }
+    }
+}
+
+impl InputTimingStats {
+    fn record(&mut self, deviation: Duration, skipped: u64) {
+        let deviation_us = deviation.as_micros() as u64;
+        self.ticks += 1;
+        self.missed_frames += skipped;
+        self.total_deviation_us += deviation_us;
+        self.max_deviation_us = self.max_deviation_us.max(deviation_us);
+        self.average_deviation_us = self.total_deviation_us as f64 / self.ticks as f64;
+    }
+
+    pub(crate) fn record_reports(&mut self, reports: u32) {
+        self.reports_read += u64::from(reports);
+        self.last_frame_reports = reports;
+        self.max_frame_reports = self.max_frame_reports.max(reports);
+    }
+}
+
+/// Raises the Windows timer resolution to 1 ms for the lifetime of the pacer
+/// so `thread::sleep` wakes close to the spin margin instead of ~15.6 ms late.
+#[cfg(windows)]
+struct TimerResolution;
+
+#[cfg(windows)]
+impl TimerResolution {
+    fn request() -> Self {
+        unsafe { windows_sys::Win32::Media::timeBeginPeriod(1) };
+        Self
+    }
+}
+
+#[cfg(windows)]
+impl Drop for TimerResolution {
+    fn drop(&mut self) {
+        unsafe { windows_sys::Win32::Media::timeEndPeriod(1) };
+    }
+}
+
+#[cfg(not(windows))]
+struct TimerResolution;
+
+#[cfg(not(windows))]
+impl TimerResolution {
+    fn request() -> Self {
+        Self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FRAME: Duration = Duration::from_micros(16_667);
+
+    #[test]
+    fn on_time_tick_advances_one_frame() {
+        let mut pacer = FramePacer::start(FRAME);
+        let deadline = pacer.next_tick;
+
+        assert_eq!(pacer.advance(deadline + Duration::from_micros(200)), 0);
+        assert_eq!(pacer.next_tick, deadline + FRAME);
+        assert_eq!(pacer.last_deviation(), Duration::from_micros(200));
+    }
+
+    #[test]
+    fn late_tick_skips_the_missed_deadlines() {
+        let mut pacer = FramePacer::start(FRAME);
+        let deadline = pacer.next_tick;
+        let woke_at = deadline + FRAME * 2 + FRAME / 2;
+
+        assert_eq!(pacer.advance(woke_at), 2);
+        // The next deadline stays on the original grid, after the late tick.
+        assert_eq!(pacer.next_tick, deadline + FRAME * 3);
+        assert!(pacer.next_tick > woke_at);
+        assert_eq!(pacer.advance(pacer.next_tick), 0);
+        assert_eq!(pacer.next_tick, deadline + FRAME * 4);
+    }
+
+    #[test]
+    fn wait_blocks_until_the_deadline_and_counts_the_tick() {
+        let mut pacer = FramePacer::start(Duration::from_millis(2));
+        let deadline = pacer.next_tick;
+        let mut stats = InputTimingStats::default();
+
+        let frames = pacer.wait(&mut stats);
+
+        assert!(Instant::now() >= deadline);
+        assert!(frames >= 1);
+        assert_eq!(stats.ticks, 1);
+        assert_eq!(stats.missed_frames, frames - 1);
+    }
+
+    #[test]
+    fn wait_polling_lends_one_timer_to_every_sub_poll() {
+        let mut pacer = FramePacer::start(Duration::from_millis(8));
+        let deadline = pacer.next_tick;
+        let mut stats = InputTimingStats::default();
+        let mut polls = 0;
+
+        pacer.wait_polling(
+            &mut stats,
+            Duration::from_millis(1),
+            |poll_deadline, timer| {
+                timer.sleep_until(poll_deadline);
+                assert!(Instant::now() >= poll_deadline);
+                polls += 1;
+            },
+        );
+
+        assert!(Instant::now() >= deadline);
+        assert!(polls >= 1);
+        assert_eq!(stats.ticks, 1);
+    }
+
+    #[test]
+    fn stats_average_the_deviation() {
+        let mut stats = InputTimingStats::default();
+        stats.record(Duration::from_micros(100), 0);
+        stats.record(Duration::from_micros(300), 0);
+        stats.record(Duration::from_micros(35_000), 2);
+
+        assert_eq!(stats.ticks, 3);
+        assert_eq!(stats.missed_frames, 2);
+        assert_eq!(stats.max_deviation_us, 35_000);
+        assert_eq!(stats.average_deviation_us, 11_800.0);
+    }
+}
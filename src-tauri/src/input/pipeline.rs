@@ -0,0 +1,246 @@
+use tauri::{AppHandle, Emitter};
+
+use super::{
+    attempt::AttemptEvent,
+    charge::ChargeTracker,
+    combo::ComboEvent,
+    emitter::{ButtonHoldTracker, EmitSettings, FrameEmitter, PolledFrame},
+    grouping::{GroupedFrame, GroupingSettings, PressGrouper},
+    motion::{InputMotionPayload, MotionDetector, MotionSettings},
+    notation::NotationStream,
+    reaction::ReactionEvent,
+    segments::{SegmentSettings, SegmentTracker},
+    stream::publish,
+    system::SystemChordRecognizer,
+    InputSettings, Player, SharedInputState,
+};
+
+/// Everything that consumes a mapped frame after it has been polled, shared
+/// by the live worker and playback so both feed the same events and buffers.
+/// P2 frames only reach the frame streams and history; the trainers, stats
+/// and outputs follow P1 alone.
+pub(crate) struct FramePipeline {
+    app: AppHandle,
+    shared: SharedInputState,
+    holds: ButtonHoldTracker,
+    system: SystemChordRecognizer,
+    charge: ChargeTracker,
+    emitter: FrameEmitter,
+    motion: MotionDetector,
+    grouper: PressGrouper,
+    segments: SegmentTracker,
+    notation: NotationStream,
+    /// Settings of the last frame, for the frames still grouped at the end.
+    emit: EmitSettings,
+    grouping: GroupingSettings,
+    segment_settings: SegmentSettings,
+    motion_settings: MotionSettings,
+    notation_events: bool,
+}
+
+impl FramePipeline {
+    pub(crate) fn new(app: AppHandle, shared: SharedInputState) -> Self {
+        Self {
+            holds: ButtonHoldTracker::default(),
+            system: SystemChordRecognizer::default(),
+            charge: ChargeTracker::default(),
+            emitter: FrameEmitter::new(app.clone(), shared.stream.clone()),
+            app,
+            shared,
+            motion: MotionDetector::default(),
+            grouper: PressGrouper::default(),
+            segments: SegmentTracker::default(),
+            notation: NotationStream::default(),
+            emit: EmitSettings::default(),
+            grouping: GroupingSettings::default(),
+            segment_settings: SegmentSettings::default(),
+            motion_settings: MotionSettings::default(),
+            notation_events: false,
+        }
+    }
+
+    pub(crate) fn push(&mut self, mut frame: PolledFrame, settings: &InputSettings) {
+        self.holds.track(&mut frame);
+        self.process(frame, settings);
+    }
+
+    /// Feeds the first frame after `input_resume`. Buttons held through the
+    /// pause are not reported as pressed, and the frame also goes out on
+    /// `input/sync` so edge-tracking consumers restart from its full state.
+    pub(crate) fn resync(&mut self, mut frame: PolledFrame, settings: &InputSettings) {
+        self.holds.track(&mut frame);
+        frame.previous_sf6_mask = frame.sf6_mask;
+        let _ = self.app.emit("input/sync", frame.payload());
+        self.process(frame, settings);
+    }
+
+    fn process(&mut self, mut frame: PolledFrame, settings: &InputSettings) {
+        // Both still fill their frame fields for P2, only without events.
+        let primary = frame.player == Player::P1;
+        let system = self
+            .system
+            .push(&mut frame, settings.grouping.window_frames);
+        let releases = self.charge.push(&mut frame, settings.charge);
+        if primary {
+            for event in system {
+                let _ = self.app.emit("input/system", event);
+            }
+            for release in releases {
+                let _ = self.app.emit("input/charge_release", release);
+            }
+        }
+        self.emitter.push(frame, settings.emit);
+        self.emit = settings.emit;
+        self.grouping = settings.grouping;
+        self.segment_settings = settings.segments;
+        self.motion_settings = settings.motion;
+        self.notation_events = settings.notation_events;
+        for grouped in self.grouper.push(frame, settings.grouping) {
+            self.push_grouped(grouped);
+        }
+        if let Ok(mut history) = self.shared.history.lock() {
+            history.push(frame);
+        }
+        if !primary {
+            return;
+        }
+
+        let motions = self.motion.push(
+            frame.frame,
+            frame.sample.direction,
+            frame.sf6_mask,
+            settings.motion,
+        );
+        // Combo trials only take motions finished by a press.
+        let motion_names: Vec<_> = motions
+            .iter()
+            .filter(|motion| !motion.negative_edge())
+            .map(InputMotionPayload::motion)
+            .collect();
+        for motion in motions {
+            publish(&self.app, &self.shared.stream, "input/motion", motion);
+        }
+
+        let mut combo_result = None;
+        if let Ok(mut combo) = self.shared.combo.lock() {
+            for event in combo.push(
+                frame.frame,
+                frame.sample.direction,
+                frame.sf6_mask,
+                &motion_names,
+            ) {
+                let _ = match event {
+                    ComboEvent::Step(step) => self.app.emit("input/combo_step", step),
+                    ComboEvent::Result(result) => {
+                        combo_result = Some(result.clone());
+                        self.app.emit("input/combo_result", result)
+                    }
+                };
+            }
+        }
+
+        let attempts = self.shared.attempts.lock().map(|mut attempts| {
+            attempts.push(
+                &frame,
+                settings.attempts,
+                settings.motion,
+                combo_result.as_ref(),
+            )
+        });
+        self.emit_attempts(attempts.unwrap_or_default());
+
+        if let Ok(mut reaction) = self.shared.reaction.lock() {
+            for event in reaction.push(&frame) {
+                let _ = match event {
+                    ReactionEvent::Cue(cue) => self.app.emit("input/cue", cue),
+                    ReactionEvent::Result(result) => self.app.emit("input/reaction_result", result),
+                };
+            }
+        }
+
+        if let Ok(mut stats) = self.shared.stats.lock() {
+            stats.push(&frame);
+        }
+        if let Ok(mut anomalies) = self.shared.anomalies.lock() {
+            for anomaly in anomalies.push(&frame, settings.anomaly) {
+                let _ = self.app.emit("input/anomaly", anomaly);
+            }
+        }
+        if let Ok(mut recorder) = self.shared.recorder.lock() {
+            if let Some(recorder) = recorder.as_mut() {
+                recorder.record(&frame);
+            }
+        }
+        // The game mirrors inputs for the side itself, so the virtual pad
+        // gets the physical directions; facing is for display and trainers.
+        if let Ok(mut passthrough) = self.shared.passthrough.lock() {
+            if let Some(pad) = passthrough.as_mut() {
+                let updated = pad.update(
+                    frame.sample.direction_raw,
+                    frame.sf6_mask,
+                    frame.sample.down_mask_raw,
+                );
+                if let Err(message) = updated {
+                    passthrough.take();
+                    let _ = self.app.emit("input/error", message);
+                }
+            }
+        }
+    }
+
+    fn emit_attempts(&self, events: Vec<AttemptEvent>) {
+        for event in events {
+            let _ = match event {
+                AttemptEvent::Start(start) => self.app.emit("input/attempt_start", start),
+                AttemptEvent::End(end) => {
+                    if let Ok(mut stats) = self.shared.stats.lock() {
+                        stats.record_attempt(end.combo_success());
+                    }
+                    self.app.emit("input/attempt_end", end)
+                }
+            };
+        }
+    }
+
+    /// Feeds the streams that report presses rather than raw frames.
+    fn push_grouped(&mut self, grouped: GroupedFrame) {
+        self.emitter
+            .push_edge(grouped, self.emit, self.grouping.window_frames);
+        if grouped.frame.player != Player::P1 {
+            return;
+        }
+        if let Some(segment) = self.segments.push(&grouped, self.segment_settings) {
+            publish(&self.app, &self.shared.stream, "input/segments", segment);
+        }
+        // Fed even while off so motions and jumps are known when turned on.
+        let press = self.notation.push(&grouped, self.motion_settings);
+        if let Some(press) = press.filter(|_| self.notation_events) {
+            publish(&self.app, &self.shared.stream, "input/notation", press);
+        }
+    }
+
+    /// Ends the run. The virtual pad is unplugged here so the game is not left
+    /// with a phantom controller once the worker is gone.
+    pub(crate) fn finish(&mut self) {
+        for grouped in self.grouper.finish() {
+            self.push_grouped(grouped);
+        }
+        self.emitter.finish(self.grouping.window_frames);
+        if let Some(segment) = self.segments.finish() {
+            publish(&self.app, &self.shared.stream, "input/segments", segment);
+        }
+        let mut attempts = Vec::new();
+        if let Ok(mut tracker) = self.shared.attempts.lock() {
+            tracker.finish(self.motion_settings, &mut attempts);
+        }
+        self.emit_attempts(attempts);
+        if let Ok(mut anomalies) = self.shared.anomalies.lock() {
+            for anomaly in anomalies.finish() {
+                let _ = self.app.emit("input/anomaly", anomaly);
+            }
+        }
+        if let Ok(mut passthrough) = self.shared.passthrough.lock() {
+            passthrough.take();
+        }
+    }
+}
@@ -0,0 +1,501 @@
+//! Legacy arcade sticks and other game controllers through DirectInput 8,
+//! for devices that are neither XInput pads nor send DS4-shaped HID reports.
+//! windows-sys has the DirectInput types but no COM wrappers, so the two
+//! interfaces used here are called through hand-written vtables.
+
+use std::ffi::c_void;
+use std::mem::{offset_of, size_of};
+use std::ptr;
+
+use windows_sys::core::{BOOL, GUID, HRESULT};
+use windows_sys::Win32::Devices::HumanInterfaceDevice::{
+    DirectInput8Create, GUID_RxAxis, GUID_RyAxis, GUID_RzAxis, GUID_Slider, GUID_XAxis, GUID_YAxis,
+    GUID_ZAxis, DI8DEVCLASS_GAMECTRL, DIDATAFORMAT, DIDEVICEINSTANCEW, DIDEVTYPE_HID,
+    DIDFT_ANYINSTANCE, DIDFT_AXIS, DIDFT_BUTTON, DIDFT_POV, DIDF_ABSAXIS, DIDOI_ASPECTPOSITION,
+    DIEDFL_ATTACHEDONLY, DIENUM_CONTINUE, DIERR_INPUTLOST, DIERR_NOTACQUIRED, DIJOYSTATE2,
+    DIOBJECTDATAFORMAT, DIPH_DEVICE, DIPROPHEADER, DIPROPRANGE, DIRECTINPUT_VERSION, GUID_POV,
+    LPDIENUMDEVICESCALLBACKW,
+};
+use windows_sys::Win32::System::LibraryLoader::GetModuleHandleW;
+
+use super::super::{
+    analog::AnalogSettings,
+    decoder::{
+        direction_from_analog_stick, direction_from_ds4_hat, dpad_mask_from_hat, select_direction,
+        DirectionArbiter, RightStick,
+    },
+    FrameTimestamp, InputSample, NativeInputDevice, NativeInputMode, BUTTON_13_MASK,
+};
+
+const IID_IDIRECTINPUT8W: GUID = GUID::from_u128(0xbf798031_483a_4da2_aa99_5d64ed369700);
+/// Lets a stick that lacks an object still accept the data format.
+const DIDFT_OPTIONAL: u32 = 0x8000_0000;
+/// `MAKEDIPROP(4)`. Predefined properties are small integers passed where a
+/// GUID pointer is expected; windows-sys spells them as GUID values instead.
+const DIPROP_RANGE: *const GUID = ptr::without_provenance(4);
+/// Axes are centered on zero, so one the stick lacks reads as centered, and
+/// shifted into report bytes so the `hid_*` analog settings apply as they
+/// are.
+const AXIS_MIN: i32 = -128;
+const AXIS_MAX: i32 = 127;
+/// Buttons 1-12 keep their place in `BUTTON_ORDER`; 13-16 would land on the
+/// dpad bits, so they move to `Button13`-`Button16`.
+const NAMED_BUTTON_COUNT: usize = 12;
+const BUTTON_COUNT: usize = 16;
+const POV_COUNT: usize = 4;
+const POV_CENTERED: u32 = 0xFFFF;
+
+#[repr(C)]
+struct IUnknownVtbl {
+    _query_interface_add_ref: [usize; 2],
+    release: unsafe extern "system" fn(*mut c_void) -> u32,
+}
+
+/// Leading entries of `IDirectInput8W`'s vtable; later ones are never called.
+#[repr(C)]
+struct IDirectInput8WVtbl {
+    _base: IUnknownVtbl,
+    create_device: unsafe extern "system" fn(
+        *mut c_void,
+        *const GUID,
+        *mut *mut c_void,
+        *mut c_void,
+    ) -> HRESULT,
+    enum_devices: unsafe extern "system" fn(
+        *mut c_void,
+        u32,
+        LPDIENUMDEVICESCALLBACKW,
+        *mut c_void,
+        u32,
+    ) -> HRESULT,
+}
+
+/// Leading entries of `IDirectInputDevice8W`'s vtable, up to `Poll`.
+#[repr(C)]
+struct IDirectInputDevice8WVtbl {
+    _base: IUnknownVtbl,
+    _get_capabilities_to_get_property: [usize; 3],
+    set_property: unsafe extern "system" fn(*mut c_void, *const GUID, *mut DIPROPHEADER) -> HRESULT,
+    acquire: unsafe extern "system" fn(*mut c_void) -> HRESULT,
+    _unacquire: usize,
+    get_device_state: unsafe extern "system" fn(*mut c_void, u32, *mut c_void) -> HRESULT,
+    _get_device_data: usize,
+    set_data_format: unsafe extern "system" fn(*mut c_void, *mut DIDATAFORMAT) -> HRESULT,
+    _set_event_notification_to_escape: [usize; 13],
+    poll: unsafe extern "system" fn(*mut c_void) -> HRESULT,
+}
+
+/// An owned COM interface pointer, released on drop.
+struct ComPtr<V>(*mut *const V);
+
+impl<V> ComPtr<V> {
+    fn vtable(&self) -> &V {
+        unsafe { &**self.0 }
+    }
+
+    fn raw(&self) -> *mut c_void {
+        self.0.cast()
+    }
+}
+
+impl<V> Drop for ComPtr<V> {
+    fn drop(&mut self) {
+        // Every COM vtable starts with IUnknown's.
+        unsafe { ((**self.0.cast::<*const IUnknownVtbl>()).release)(self.raw()) };
+    }
+}
+
+type DirectInput = ComPtr<IDirectInput8WVtbl>;
+type DirectInputDevice = ComPtr<IDirectInputDevice8WVtbl>;
+
+pub(super) struct DirectInputSource {
+    // Declared before `_direct_input`, so it is released first.
+    device: DirectInputDevice,
+    _direct_input: DirectInput,
+    info: NativeInputDevice,
+    arbiter: DirectionArbiter,
+}
+
+impl DirectInputSource {
+    /// Opens the first attached game controller, or only `requested` when a
+    /// specific one was asked for.
+    pub(super) fn new(requested: Option<&str>) -> Result<Self, String> {
+        let direct_input = direct_input()?;
+        let mut instances = game_controllers(&direct_input)?.into_iter();
+        let instance = match requested {
+            Some(key) => {
+                let guid = u128::from_str_radix(key, 16)
+                    .map_err(|_| format!("Invalid DirectInput device id '{key}'."))?;
+                instances
+                    .find(|instance| guid_value(&instance.guidInstance) == guid)
+                    .ok_or_else(|| format!("DirectInput device {key} is not connected."))?
+            }
+            None => instances.next().ok_or_else(|| {
+                "Native input mode 'directinput' could not find a connected game controller."
+                    .to_string()
+            })?,
+        };
+
+        Ok(Self {
+            device: open_device(&direct_input, &instance)?,
+            _direct_input: direct_input,
+            info: directinput_device(&instance),
+            arbiter: DirectionArbiter::default(),
+        })
+    }
+
+    pub(super) fn device(&self) -> NativeInputDevice {
+        self.info.clone()
+    }
+
+    pub(super) fn poll(
+        &mut self,
+        now: FrameTimestamp,
+        analog: AnalogSettings,
+    ) -> Result<InputSample, String> {
+        let mut state = DIJOYSTATE2::default();
+        let mut hr = read_state(&self.device, &mut state);
+        // Focus changes and other applications can drop the acquisition;
+        // an unplugged stick fails again and is left to the worker.
+        if matches!(hr, DIERR_INPUTLOST | DIERR_NOTACQUIRED) {
+            unsafe { (self.device.vtable().acquire)(self.device.raw()) };
+            hr = read_state(&self.device, &mut state);
+        }
+        if hr < 0 {
+            return Err(format!(
+                "Reading DirectInput device {} failed hr=0x{:08X}",
+                self.info.id, hr as u32
+            ));
+        }
+
+        Ok(sample_from_joy_state(
+            &state,
+            now,
+            analog,
+            &mut self.arbiter,
+        ))
+    }
+}
+
+/// Attached game controllers. XInput pads are listed too, which is why
+/// `xinput` and `directinput` cannot be merged.
+pub(super) fn directinput_devices() -> Vec<NativeInputDevice> {
+    let Ok(direct_input) = direct_input() else {
+        return Vec::new();
+    };
+    game_controllers(&direct_input)
+        .unwrap_or_default()
+        .iter()
+        .map(directinput_device)
+        .collect()
+}
+
+fn directinput_device(instance: &DIDEVICEINSTANCEW) -> NativeInputDevice {
+    let key = format!("{:032x}", guid_value(&instance.guidInstance));
+    let mut device = NativeInputDevice::new(NativeInputMode::DirectInput, &key);
+    let name = &instance.tszProductName;
+    let len = name
+        .iter()
+        .position(|unit| *unit == 0)
+        .unwrap_or(name.len());
+    device.product = Some(if len > 0 {
+        String::from_utf16_lossy(&name[..len])
+    } else {
+        "DirectInput controller".to_string()
+    });
+    // For HID devices, which covers USB arcade sticks, the product GUID
+    // starts with the USB PID and VID.
+    if instance.dwDevType & DIDEVTYPE_HID != 0 {
+        let ids = instance.guidProduct.data1;
+        device.vendor_id = Some(ids as u16);
+        device.product_id = Some((ids >> 16) as u16);
+    }
+    device.openable = Some(true);
+    device
+}
+
+fn direct_input() -> Result<DirectInput, String> {
+    let mut raw = ptr::null_mut();
+    let hr = unsafe {
+        DirectInput8Create(
+            GetModuleHandleW(ptr::null()),
+            DIRECTINPUT_VERSION,
+            &IID_IDIRECTINPUT8W,
+            &mut raw,
+            ptr::null_mut(),
+        )
+    };
+    if hr < 0 || raw.is_null() {
+        return Err(format!("DirectInput8Create failed hr=0x{:08X}", hr as u32));
+    }
+    Ok(ComPtr(raw.cast()))
+}
+
+fn game_controllers(direct_input: &DirectInput) -> Result<Vec<DIDEVICEINSTANCEW>, String> {
+    unsafe extern "system" fn collect(
+        instance: *mut DIDEVICEINSTANCEW,
+        instances: *mut c_void,
+    ) -> BOOL {
+        (*instances.cast::<Vec<DIDEVICEINSTANCEW>>()).push(*instance);
+        DIENUM_CONTINUE as BOOL
+    }
+
+    let mut instances = Vec::<DIDEVICEINSTANCEW>::new();
+    let hr = unsafe {
+        (direct_input.vtable().enum_devices)(
+            direct_input.raw(),
+            DI8DEVCLASS_GAMECTRL,
+            Some(collect),
+            ptr::from_mut(&mut instances).cast(),
+            DIEDFL_ATTACHEDONLY,
+        )
+    };
+    if hr < 0 {
+        return Err(format!(
+            "Enumerating DirectInput devices failed hr=0x{:08X}",
+            hr as u32
+        ));
+    }
+    Ok(instances)
+}
+
+/// Creates the device with the `DIJOYSTATE2` data format and byte-sized
+/// axes, then acquires it. The cooperative level stays at its default,
+/// non-exclusive and background, so the stick keeps reading while the game
+/// has focus.
+fn open_device(
+    direct_input: &DirectInput,
+    instance: &DIDEVICEINSTANCEW,
+) -> Result<DirectInputDevice, String> {
+    let mut raw = ptr::null_mut();
+    let hr = unsafe {
+        (direct_input.vtable().create_device)(
+            direct_input.raw(),
+            &instance.guidInstance,
+            &mut raw,
+            ptr::null_mut(),
+        )
+    };
+    if hr < 0 || raw.is_null() {
+        return Err(format!(
+            "Opening the DirectInput device failed hr=0x{:08X}",
+            hr as u32
+        ));
+    }
+    let device: DirectInputDevice = ComPtr(raw.cast());
+    let vtable = device.vtable();
+
+    let mut objects = joystick_objects();
+    let mut format = DIDATAFORMAT {
+        dwSize: size_of::<DIDATAFORMAT>() as u32,
+        dwObjSize: size_of::<DIOBJECTDATAFORMAT>() as u32,
+        dwFlags: DIDF_ABSAXIS,
+        dwDataSize: size_of::<DIJOYSTATE2>() as u32,
+        dwNumObjs: objects.len() as u32,
+        rgodf: objects.as_mut_ptr(),
+    };
+    let hr = unsafe { (vtable.set_data_format)(device.raw(), &mut format) };
+    if hr < 0 {
+        return Err(format!(
+            "Setting the DirectInput data format failed hr=0x{:08X}",
+            hr as u32
+        ));
+    }
+
+    let mut range = DIPROPRANGE {
+        diph: DIPROPHEADER {
+            dwSize: size_of::<DIPROPRANGE>() as u32,
+            dwHeaderSize: size_of::<DIPROPHEADER>() as u32,
+            dwObj: 0,
+            dwHow: DIPH_DEVICE,
+        },
+        lMin: AXIS_MIN,
+        lMax: AXIS_MAX,
+    };
+    // Fails on sticks without axes, which have nothing to scale.
+    unsafe { (vtable.set_property)(device.raw(), DIPROP_RANGE, &mut range.diph) };
+
+    // A failed acquire is retried on the first poll.
+    unsafe { (vtable.acquire)(device.raw()) };
+    Ok(device)
+}
+
+/// The position objects of `c_dfDIJoystick2`, which windows-sys does not
+/// export, up to the buttons read here: six axes, two sliders, four POV
+/// hats and buttons 1-16, at their `DIJOYSTATE2` offsets.
+fn joystick_objects() -> Vec<DIOBJECTDATAFORMAT> {
+    let object = |guid: *const GUID, offset: usize, kind: u32, flags: u32| DIOBJECTDATAFORMAT {
+        pguid: guid,
+        dwOfs: offset as u32,
+        dwType: kind | DIDFT_ANYINSTANCE | DIDFT_OPTIONAL,
+        dwFlags: flags,
+    };
+    let axes = [
+        (&GUID_XAxis, offset_of!(DIJOYSTATE2, lX)),
+        (&GUID_YAxis, offset_of!(DIJOYSTATE2, lY)),
+        (&GUID_ZAxis, offset_of!(DIJOYSTATE2, lZ)),
+        (&GUID_RxAxis, offset_of!(DIJOYSTATE2, lRx)),
+        (&GUID_RyAxis, offset_of!(DIJOYSTATE2, lRy)),
+        (&GUID_RzAxis, offset_of!(DIJOYSTATE2, lRz)),
+        (&GUID_Slider, offset_of!(DIJOYSTATE2, rglSlider)),
+        (&GUID_Slider, offset_of!(DIJOYSTATE2, rglSlider) + 4),
+    ];
+
+    let mut objects: Vec<_> = axes
+        .into_iter()
+        .map(|(guid, offset)| object(guid, offset, DIDFT_AXIS, DIDOI_ASPECTPOSITION))
+        .collect();
+    objects.extend((0..POV_COUNT).map(|index| {
+        let offset = offset_of!(DIJOYSTATE2, rgdwPOV) + 4 * index;
+        object(&GUID_POV, offset, DIDFT_POV, 0)
+    }));
+    objects.extend((0..BUTTON_COUNT).map(|index| {
+        let offset = offset_of!(DIJOYSTATE2, rgbButtons) + index;
+        object(ptr::null(), offset, DIDFT_BUTTON, 0)
+    }));
+    objects
+}
+
+/// `Poll` only matters for devices that are not interrupt driven; the
+/// others answer `DI_NOEFFECT`.
+fn read_state(device: &DirectInputDevice, state: &mut DIJOYSTATE2) -> HRESULT {
+    let vtable = device.vtable();
+    unsafe {
+        (vtable.poll)(device.raw());
+        (vtable.get_device_state)(
+            device.raw(),
+            size_of::<DIJOYSTATE2>() as u32,
+            ptr::from_mut(state).cast(),
+        )
+    }
+}
+
+fn guid_value(guid: &GUID) -> u128 {
+    (u128::from(guid.data1) << 96)
+        | (u128::from(guid.data2) << 80)
+        | (u128::from(guid.data3) << 64)
+        | u128::from(u64::from_be_bytes(guid.data4))
+}
+
+/// The dpad bits come from the first POV hat instead of button numbers.
+fn sample_from_joy_state(
+    state: &DIJOYSTATE2,
+    now: FrameTimestamp,
+    analog: AnalogSettings,
+    arbiter: &mut DirectionArbiter,
+) -> InputSample {
+    let mut down_mask = state.rgbButtons[..BUTTON_COUNT]
+        .iter()
+        .enumerate()
+        .filter(|(_, button)| **button & 0x80 != 0)
+        .fold(0u32, |mask, (index, _)| {
+            mask | if index < NAMED_BUTTON_COUNT {
+                1 << index
+            } else {
+                BUTTON_13_MASK << (index - NAMED_BUTTON_COUNT)
+            }
+        });
+
+    // Hundredths of a degree clockwise from up. Some drivers report center
+    // as 0xFFFF rather than 0xFFFFFFFF, so only the low word is tested.
+    let pov = state.rgdwPOV[0];
+    let hat = if pov & 0xFFFF == POV_CENTERED {
+        0x0F
+    } else {
+        (((pov + 2_250) / 4_500) % 8) as u8
+    };
+    down_mask |= dpad_mask_from_hat(hat);
+
+    // Gamepads conventionally report the right stick on the Z and Rz axes.
+    let axis = |value: i32| (value.clamp(AXIS_MIN, AXIS_MAX) - AXIS_MIN) as u8;
+    let left_stick = (axis(state.lX), axis(state.lY));
+    let right_stick = (axis(state.lZ), axis(state.lRz));
+    let direction = select_direction(
+        direction_from_ds4_hat(hat),
+        left_stick,
+        right_stick,
+        analog,
+        arbiter,
+    );
+
+    let mut sample = InputSample::new(now, direction, down_mask);
+    sample.left_stick = Some(left_stick);
+    sample.stick_direction = direction_from_analog_stick(left_stick, analog);
+    sample.right_stick = Some(RightStick::from_bytes(right_stick, analog));
+    sample
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::input::{BUTTON_DPAD_MASK, BUTTON_DPAD_RIGHT_MASK, BUTTON_SOUTH_MASK};
+
+    fn state(pov: u32, buttons: &[usize]) -> DIJOYSTATE2 {
+        let mut state = DIJOYSTATE2::default();
+        state.rgdwPOV[0] = pov;
+        for &button in buttons {
+            state.rgbButtons[button] = 0x80;
+        }
+        state
+    }
+
+    fn sample(state: &DIJOYSTATE2) -> InputSample {
+        sample_from_joy_state(
+            state,
+            FrameTimestamp::default(),
+            AnalogSettings::default(),
+            &mut DirectionArbiter::default(),
+        )
+    }
+
+    #[test]
+    fn pov_hat_maps_to_numpad_directions() {
+        let cases = [
+            (0xFFFF_FFFF, 5),
+            (0x0000_FFFF, 5),
+            (0, 8),
+            (4_500, 9),
+            (9_000, 6),
+            (13_500, 3),
+            (18_000, 2),
+            (22_500, 1),
+            (27_000, 4),
+            (31_500, 7),
+            // Just short of a full turn still reads as up.
+            (35_900, 8),
+        ];
+        for (pov, direction) in cases {
+            assert_eq!(sample(&state(pov, &[])).direction, direction, "pov {pov}");
+        }
+        let right = sample(&state(9_000, &[]));
+        assert_eq!(right.down_mask & BUTTON_DPAD_MASK, BUTTON_DPAD_RIGHT_MASK);
+    }
+
+    #[test]
+    fn centered_hat_falls_back_to_the_stick() {
+        let mut down_left = state(POV_CENTERED, &[]);
+        down_left.lX = AXIS_MIN;
+        down_left.lY = AXIS_MAX;
+        assert_eq!(sample(&down_left).direction, 1);
+        assert_eq!(sample(&down_left).down_mask, 0);
+    }
+
+    #[test]
+    fn buttons_13_to_16_skip_the_dpad_bits() {
+        let sample = sample(&state(POV_CENTERED, &[0, 11, 12, 15]));
+        assert_eq!(
+            sample.down_mask,
+            BUTTON_SOUTH_MASK | 1 << 11 | BUTTON_13_MASK | BUTTON_13_MASK << 3
+        );
+        assert_eq!(sample.down_mask & BUTTON_DPAD_MASK, 0);
+    }
+
+    #[test]
+    fn objects_fit_inside_the_state() {
+        let objects = joystick_objects();
+        assert_eq!(objects.len(), 8 + POV_COUNT + BUTTON_COUNT);
+        assert!(objects
+            .iter()
+            .all(|object| (object.dwOfs as usize) < size_of::<DIJOYSTATE2>()));
+    }
+}
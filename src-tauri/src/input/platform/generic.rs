@@ -0,0 +1,185 @@
+//! gilrs-backed fallback for controllers no bespoke backend decodes. gilrs
+//! lays buttons out through the SDL controller database and reads them
+//! through the OS gamepad API, which adds that API's latency, so the XInput
+//! and HID paths stay preferred wherever they apply.
+//!
+//! On Windows gilrs is built on XInput rather than Windows.Gaming.Input,
+//! which only delivers input while this app has focus and would leave the
+//! pad silent with SF6 in front; the opt-in `wgi` mode reads that API.
+
+use std::time::Duration;
+
+use gilrs::{Axis, Button, Gamepad, GamepadId, Gilrs};
+
+use super::super::{
+    analog::AnalogSettings,
+    decoder::{
+        direction_from_analog_stick, select_direction, AnalogTriggers, DirectionArbiter, RightStick,
+    },
+    to_direction, FrameTimestamp, InputSample, NativeInputDevice, NativeInputMode,
+    BUTTON_DPAD_DOWN_MASK, BUTTON_DPAD_LEFT_MASK, BUTTON_DPAD_RIGHT_MASK, BUTTON_DPAD_UP_MASK,
+    BUTTON_EAST_MASK, BUTTON_L1_MASK, BUTTON_L2_MASK, BUTTON_L3_MASK, BUTTON_NORTH_MASK,
+    BUTTON_R1_MASK, BUTTON_R2_MASK, BUTTON_R3_MASK, BUTTON_SELECT_MASK, BUTTON_SOUTH_MASK,
+    BUTTON_START_MASK, BUTTON_WEST_MASK,
+};
+
+/// Some OS APIs announce already attached pads as events shortly after
+/// start-up rather than at once; enumeration waits this long for them.
+const ENUMERATION_SETTLE: Duration = Duration::from_millis(50);
+
+const GAMEPAD_BUTTONS: [(Button, u32); 16] = [
+    (Button::South, BUTTON_SOUTH_MASK),
+    (Button::East, BUTTON_EAST_MASK),
+    (Button::North, BUTTON_NORTH_MASK),
+    (Button::West, BUTTON_WEST_MASK),
+    (Button::LeftTrigger, BUTTON_L1_MASK),
+    (Button::RightTrigger, BUTTON_R1_MASK),
+    (Button::LeftTrigger2, BUTTON_L2_MASK),
+    (Button::RightTrigger2, BUTTON_R2_MASK),
+    (Button::Select, BUTTON_SELECT_MASK),
+    (Button::Start, BUTTON_START_MASK),
+    (Button::LeftThumb, BUTTON_L3_MASK),
+    (Button::RightThumb, BUTTON_R3_MASK),
+    (Button::DPadUp, BUTTON_DPAD_UP_MASK),
+    (Button::DPadDown, BUTTON_DPAD_DOWN_MASK),
+    (Button::DPadLeft, BUTTON_DPAD_LEFT_MASK),
+    (Button::DPadRight, BUTTON_DPAD_RIGHT_MASK),
+];
+
+pub(super) struct GenericGamepadSource {
+    gilrs: Gilrs,
+    id: GamepadId,
+    arbiter: DirectionArbiter,
+}
+
+impl GenericGamepadSource {
+    /// Opens the first connected gamepad, or only `requested` when a
+    /// specific one was asked for.
+    pub(super) fn new(requested: Option<&str>) -> Result<Self, String> {
+        let gilrs = open_gilrs()?;
+        let id = match requested {
+            Some(key) => {
+                let index = key
+                    .parse::<usize>()
+                    .map_err(|_| format!("Invalid generic controller id '{key}'."))?;
+                gilrs
+                    .gamepads()
+                    .map(|(id, _)| id)
+                    .find(|id| usize::from(*id) == index)
+                    .ok_or_else(|| format!("Generic controller {index} is not connected."))?
+            }
+            None => gilrs.gamepads().map(|(id, _)| id).next().ok_or_else(|| {
+                "Native input mode 'generic' could not find a connected controller.".to_string()
+            })?,
+        };
+        Ok(Self {
+            gilrs,
+            id,
+            arbiter: DirectionArbiter::default(),
+        })
+    }
+
+    /// `None` once the gamepad has disconnected.
+    pub(super) fn device(&self) -> Option<NativeInputDevice> {
+        let gamepad = self.gilrs.connected_gamepad(self.id)?;
+        Some(generic_device(self.id, &gamepad))
+    }
+
+    /// Applies every event queued since the last poll, then reads the
+    /// gamepad's resulting state.
+    pub(super) fn poll(
+        &mut self,
+        now: FrameTimestamp,
+        analog: AnalogSettings,
+    ) -> Result<InputSample, String> {
+        while self.gilrs.next_event().is_some() {}
+        let gamepad = self
+            .gilrs
+            .connected_gamepad(self.id)
+            .ok_or_else(|| format!("Generic controller {} is disconnected.", self.id))?;
+        Ok(sample_from_gamepad(
+            &gamepad,
+            now,
+            analog,
+            &mut self.arbiter,
+        ))
+    }
+}
+
+/// Gamepads gilrs sees, leaving out those `known` already lists by VID/PID
+/// so a pad with a bespoke backend is not offered twice.
+pub(super) fn generic_devices(known: &[NativeInputDevice]) -> Vec<NativeInputDevice> {
+    let Ok(gilrs) = open_gilrs() else {
+        return Vec::new();
+    };
+    gilrs
+        .gamepads()
+        .filter(|(_, gamepad)| {
+            let ids = (gamepad.vendor_id(), gamepad.product_id());
+            !known
+                .iter()
+                .any(|device| ids.0.is_some() && (device.vendor_id, device.product_id) == ids)
+        })
+        .map(|(id, gamepad)| generic_device(id, &gamepad))
+        .collect()
+}
+
+fn generic_device(id: GamepadId, gamepad: &Gamepad) -> NativeInputDevice {
+    let mut device = NativeInputDevice::new(NativeInputMode::Generic, &usize::from(id).to_string());
+    device.product = Some(gamepad.name().to_string());
+    device.vendor_id = gamepad.vendor_id();
+    device.product_id = gamepad.product_id();
+    device.openable = Some(true);
+    device
+}
+
+fn open_gilrs() -> Result<Gilrs, String> {
+    let mut gilrs = Gilrs::new()
+        .map_err(|error| format!("Failed to start the generic gamepad backend: {error}"))?;
+    while gilrs
+        .next_event_blocking(Some(ENUMERATION_SETTLE))
+        .is_some()
+    {}
+    Ok(gilrs)
+}
+
+fn sample_from_gamepad(
+    gamepad: &Gamepad,
+    now: FrameTimestamp,
+    analog: AnalogSettings,
+    arbiter: &mut DirectionArbiter,
+) -> InputSample {
+    let down_mask = GAMEPAD_BUTTONS
+        .iter()
+        .filter(|(button, _)| gamepad.is_pressed(*button))
+        .fold(0u32, |mask, (_, bit)| mask | bit);
+
+    let pressed = |button| i32::from(gamepad.is_pressed(button));
+    let dpad_direction = to_direction(
+        pressed(Button::DPadRight) - pressed(Button::DPadLeft),
+        pressed(Button::DPadUp) - pressed(Button::DPadDown),
+    );
+    // gilrs axes run -1.0 to 1.0 with Y up; the shared stick helpers take
+    // report bytes with Y down.
+    let byte = |value: f32| ((value.clamp(-1.0, 1.0) + 1.0) * 127.5).round() as u8;
+    let stick = |x, y| (byte(gamepad.value(x)), byte(-gamepad.value(y)));
+    let left_stick = stick(Axis::LeftStickX, Axis::LeftStickY);
+    let right_stick = stick(Axis::RightStickX, Axis::RightStickY);
+    let direction = select_direction(dpad_direction, left_stick, right_stick, analog, arbiter);
+
+    let mut sample = InputSample::new(now, direction, down_mask);
+    sample.left_stick = Some(left_stick);
+    sample.stick_direction = direction_from_analog_stick(left_stick, analog);
+    sample.right_stick = Some(RightStick::from_bytes(right_stick, analog));
+    // Pads with digital triggers report only 0 or 1 here.
+    let trigger = |button| {
+        gamepad.button_data(button).map_or(0, |data| {
+            (data.value().clamp(0.0, 1.0) * 255.0).round() as u8
+        })
+    };
+    sample.triggers = Some(AnalogTriggers {
+        left: trigger(Button::LeftTrigger2),
+        right: trigger(Button::RightTrigger2),
+    });
+    sample
+}
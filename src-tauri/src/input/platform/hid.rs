@@ -0,0 +1,853 @@
+//! hidapi-backed controller source (PS4-style, DualSense, Switch Pro and
+//! HORIPAD-style Switch pads),
+//! shared by the Windows and macOS backends since hidapi hands both the same
+//! raw input reports.
+
+use std::{
+    ffi::CStr,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        mpsc::{self, Receiver, RecvTimeoutError, Sender},
+        Arc,
+    },
+    thread::{self, JoinHandle},
+    time::{Duration, Instant},
+};
+
+use hidapi::{BusType, DeviceInfo, HidApi, HidDevice};
+
+use super::super::{
+    controller_preset::{self, ControllerPreset, PresetDecoder},
+    decoder::{
+        AnalogTriggers, DecodedInput, DirectionArbiter, Ds4Decoder, DualSenseDecoder,
+        HoriSwitchDecoder, InputDecoder, ProfileDecoder, RightStick, SwitchProDecoder,
+    },
+    device_profile::HidDeviceId,
+    feedback::{FeedbackEffect, FeedbackOutcome},
+    watchdog::{HidDiagnostics, ReportWatchdog},
+    DeviceConnection, FrameClock, FrameTimestamp, HidRejection, InputSample, InputSettings,
+    NativeInputDevice, NativeInputMode, NativeInputRejectedDevice, BUTTON_DPAD_MASK,
+    SERIAL_KEY_PREFIX,
+};
+
+/// `kIOReturnNotPermitted`, which IOKit reports when macOS has not granted the
+/// app Input Monitoring access.
+const IOKIT_NOT_PERMITTED: &str = "0xe00002e2";
+/// Large enough for the 78-byte DS4 Bluetooth report; USB reports are 64.
+pub(super) const HID_REPORT_BUFFER_SIZE: usize = 78;
+/// How long the reader thread blocks per hidapi read, which bounds how late
+/// it notices a stop or a pending output report. Windows only reads this way
+/// when Raw Input could not be set up.
+const READ_TIMEOUT_MS: i32 = 4;
+const GP2040_PS4_PRODUCT_ID: u16 = 0x0401;
+/// Zeroplus, whose VID Brook boards such as the Universal Fighting Board
+/// enumerate under.
+const ZEROPLUS_VENDOR_ID: u16 = 0x0C12;
+
+/// USB output report 0x05: flags, two reserved bytes, weak and strong motor,
+/// then lightbar RGB. Fields whose flag is clear are left unchanged.
+const DS4_OUTPUT_REPORT_LEN: usize = 32;
+const DS4_OUTPUT_RUMBLE: u8 = 0x01;
+const DS4_OUTPUT_LIGHTBAR: u8 = 0x02;
+/// DualSense USB output report 0x02. Rumble goes through the motors'
+/// DS4-compatible mode, flagged in byte 1 with the motors in 3-4; the
+/// lightbar is flagged in byte 2 and its RGB sits at 45-47.
+const DUALSENSE_OUTPUT_REPORT_LEN: usize = 48;
+const DUALSENSE_OUTPUT_RUMBLE: u8 = 0x03;
+const DUALSENSE_OUTPUT_LIGHTBAR: u8 = 0x04;
+const DUALSENSE_CALIBRATION_REPORT_LEN: usize = 41;
+
+/// USB-only commands (report 0x80) that take the Pro Controller out of its
+/// Bluetooth-emulation state: handshake, 3 Mbit baud, handshake again, then
+/// force USB HID so it stops timing out.
+const SWITCH_PRO_USB_HANDSHAKE: [[u8; 2]; 4] =
+    [[0x80, 0x02], [0x80, 0x03], [0x80, 0x02], [0x80, 0x04]];
+/// Output report 0x01 carrying a neutral rumble frame and subcommand 0x03
+/// (set input report mode) with argument 0x30 (standard full report).
+const SWITCH_PRO_FULL_REPORT_MODE: [u8; 12] = [
+    0x01, 0x00, 0x00, 0x01, 0x40, 0x40, 0x00, 0x01, 0x40, 0x40, 0x03, 0x30,
+];
+
+/// Byte layout of the controller's input report, chosen once from VID/PID
+/// when the device is opened.
+#[derive(Clone, Copy)]
+enum HidReportLayout {
+    Ds4,
+    DualSense,
+    SwitchPro,
+    HoriSwitch,
+    /// A gamepad opened only because the loaded profile matches it, so only
+    /// the profile decodes its reports.
+    Profile,
+}
+
+impl HidReportLayout {
+    /// Whether the device sends reports continuously rather than on change,
+    /// so silence means it went quiet. Profile devices may do either, and
+    /// GP2040-CE only reports Switch-mode changes.
+    fn streams(self) -> bool {
+        !matches!(self, Self::Profile | Self::HoriSwitch)
+    }
+}
+
+/// An input report as read, stamped the moment it arrived.
+#[derive(Clone)]
+pub(super) struct TimedReport {
+    pub(super) data: [u8; HID_REPORT_BUFFER_SIZE],
+    pub(super) len: usize,
+    pub(super) at: Instant,
+}
+
+pub(super) enum ReaderMessage {
+    Report(TimedReport),
+    /// The read failed or the device went away; no report follows.
+    Error(String),
+}
+
+/// Output report for the device thread to write, and where to send the
+/// outcome.
+type OutputRequest = (Vec<u8>, Sender<Result<(), String>>);
+
+/// The device is owned by its own thread and every report is timestamped on
+/// arrival: on Windows as Raw Input delivers it, elsewhere by that thread
+/// blocking on hidapi reads. Polls decode whatever was queued since the last
+/// one. Output reports go through the device thread, since hidapi devices
+/// cannot be shared between threads.
+pub(super) struct Ps4HidNativeSource {
+    reports: Receiver<ReaderMessage>,
+    output: Sender<OutputRequest>,
+    stop_flag: Arc<AtomicBool>,
+    reader: Option<JoinHandle<()>>,
+    layout: HidReportLayout,
+    /// As `input_list_devices` would list it.
+    device: NativeInputDevice,
+    bluetooth: bool,
+    vendor_id: u16,
+    product_id: u16,
+    direction: u8,
+    down_mask: u32,
+    left_stick: Option<(u8, u8)>,
+    stick_direction: u8,
+    right_stick: Option<RightStick>,
+    triggers: Option<AnalogTriggers>,
+    arbiter: DirectionArbiter,
+    /// Arrival of the first report since the last poll that changed the
+    /// decoded state.
+    changed_at: Option<Instant>,
+    watchdog: ReportWatchdog,
+    /// Reports decoded since the worker last asked, for diagnostics.
+    reports_read: u32,
+}
+
+impl Ps4HidNativeSource {
+    /// Opens the first supported candidate, or only the one `key` names when
+    /// a specific device was requested: a path, or `serial:<serial number>`.
+    /// Gamepads without a built-in decoder are candidates too while the HID
+    /// profile in `settings` matches them.
+    pub(super) fn new(key: Option<&str>, settings: &InputSettings) -> Result<Self, String> {
+        let api = HidApi::new().map_err(|error| format!("hidapi init error: {error}"))?;
+        let profile = settings.hid_profile.as_deref();
+        let mut permission_denied = false;
+
+        for device_info in api.device_list() {
+            let layout = match is_ps4_hid_candidate(device_info, &settings.hid_allowlist) {
+                Ok(()) => report_layout(device_info),
+                Err(HidRejection::UnsupportedGamepad)
+                    if profile.is_some_and(|profile| {
+                        profile.matches_device(device_info.vendor_id(), device_info.product_id())
+                    }) =>
+                {
+                    HidReportLayout::Profile
+                }
+                Err(_) => continue,
+            };
+            if key.is_some_and(|key| !key_matches(device_info, key)) {
+                continue;
+            }
+
+            match device_info.open_device(&api) {
+                Ok(device) => {
+                    let bluetooth = matches!(device_info.bus_type(), BusType::Bluetooth);
+                    match layout {
+                        HidReportLayout::SwitchPro => enable_switch_pro_full_reports(&device),
+                        HidReportLayout::DualSense if bluetooth => {
+                            enable_dualsense_bluetooth_reports(&device)
+                        }
+                        _ => {}
+                    }
+                    let (report_sender, reports) = mpsc::channel();
+                    let (output, output_requests) = mpsc::channel();
+                    let stop_flag = Arc::new(AtomicBool::new(false));
+                    let reader = spawn_device_thread(
+                        device,
+                        device_info.path(),
+                        &stop_flag,
+                        report_sender,
+                        output_requests,
+                    );
+                    return Ok(Self {
+                        reports,
+                        output,
+                        stop_flag,
+                        reader: Some(reader),
+                        layout,
+                        device: hid_device(device_info),
+                        bluetooth,
+                        vendor_id: device_info.vendor_id(),
+                        product_id: device_info.product_id(),
+                        direction: 5,
+                        down_mask: 0,
+                        left_stick: None,
+                        stick_direction: 5,
+                        right_stick: None,
+                        triggers: None,
+                        arbiter: DirectionArbiter::default(),
+                        changed_at: None,
+                        watchdog: ReportWatchdog::new(),
+                        reports_read: 0,
+                    });
+                }
+                Err(error) => {
+                    permission_denied |= is_permission_error(&error.to_string());
+                }
+            }
+        }
+
+        if permission_denied {
+            return Err("access to the controller was denied. On macOS, allow this app under System Settings > Privacy & Security > Input Monitoring, then restart it.".to_string());
+        }
+
+        match key {
+            Some(key) => Err(format!("HID device '{key}' is not connected.")),
+            None => Err("No supported PS4 HID candidate found.".to_string()),
+        }
+    }
+
+    pub(super) fn poll(
+        &mut self,
+        now: FrameTimestamp,
+        settings: &InputSettings,
+    ) -> Result<InputSample, String> {
+        let down_mask = self.drain_reports(Duration::ZERO, settings)?;
+        Ok(self.sample(now, down_mask))
+    }
+
+    /// Waits up to `deadline` for the next report, so sub-frame polls follow
+    /// the controller's own report rate instead of a timer.
+    pub(super) fn poll_until(
+        &mut self,
+        deadline: Instant,
+        clock: &FrameClock,
+        settings: &InputSettings,
+    ) -> Result<InputSample, String> {
+        let remaining = deadline.saturating_duration_since(Instant::now());
+        let down_mask = self.drain_reports(remaining, settings)?;
+        Ok(self.sample(clock.now(), down_mask))
+    }
+
+    pub(super) fn device(&self) -> NativeInputDevice {
+        self.device.clone()
+    }
+
+    pub(super) fn take_reports_read(&mut self) -> u32 {
+        std::mem::take(&mut self.reports_read)
+    }
+
+    pub(super) fn take_warnings(&mut self) -> Vec<String> {
+        self.watchdog.take_warnings()
+    }
+
+    pub(super) fn diagnostics(&self) -> HidDiagnostics {
+        self.watchdog.diagnostics()
+    }
+
+    fn sample(&mut self, now: FrameTimestamp, down_mask: u32) -> InputSample {
+        let mut sample = InputSample::new(now, self.direction, down_mask);
+        sample.left_stick = self.left_stick;
+        sample.stick_direction = self.stick_direction;
+        sample.right_stick = self.right_stick;
+        sample.triggers = self.triggers;
+        sample.reported_at = self.changed_at.take();
+        sample
+    }
+
+    /// Decodes every queued report, waiting up to `timeout` for the first.
+    /// The controller reports far more often than the worker polls, so
+    /// decoding one per poll would let the queue fall ever further behind.
+    /// Returns the latest state plus any button pressed in an earlier report
+    /// of the batch, so a tap released before the last report still shows.
+    fn drain_reports(
+        &mut self,
+        timeout: Duration,
+        settings: &InputSettings,
+    ) -> Result<u32, String> {
+        let mut pressed = 0u32;
+        let mut timeout = timeout;
+        loop {
+            match self.reports.recv_timeout(timeout) {
+                Ok(ReaderMessage::Report(report)) => {
+                    let data = &report.data[..report.len];
+                    if let Some(decoded) = self.decode(data, settings) {
+                        if decoded.direction != self.direction
+                            || decoded.down_mask != self.down_mask
+                        {
+                            self.changed_at.get_or_insert(report.at);
+                        }
+                        self.direction = decoded.direction;
+                        self.down_mask = decoded.down_mask;
+                        self.left_stick = decoded.left_stick;
+                        self.stick_direction = decoded.stick_direction;
+                        self.right_stick = decoded.right_stick;
+                        self.triggers = decoded.triggers;
+                        self.watchdog.decoded(report.at);
+                    } else {
+                        self.watchdog.undecodable(report.at, data);
+                    }
+                    self.reports_read = self.reports_read.saturating_add(1);
+                    pressed |= self.down_mask;
+                    timeout = Duration::ZERO;
+                }
+                Ok(ReaderMessage::Error(error)) => return Err(error),
+                Err(RecvTimeoutError::Timeout) => break,
+                Err(RecvTimeoutError::Disconnected) => {
+                    return Err("The HID reader thread stopped.".to_string())
+                }
+            }
+        }
+
+        let held = self.direction != 5 || self.down_mask != 0;
+        if self
+            .watchdog
+            .expire(settings.watchdog, self.layout.streams(), held)
+        {
+            self.direction = 5;
+            self.down_mask = 0;
+            self.left_stick = None;
+            self.stick_direction = 5;
+            self.right_stick = None;
+            self.triggers = None;
+            return Ok(0);
+        }
+        Ok(self.down_mask | (pressed & !BUTTON_DPAD_MASK))
+    }
+
+    /// A matching profile takes the reports the built-in decoder rejects,
+    /// such as those of a stick switched out of PS4 mode.
+    fn decode(&mut self, report: &[u8], settings: &InputSettings) -> Option<DecodedInput> {
+        let analog = settings.analog;
+        let arbiter = &mut self.arbiter;
+        match self.layout {
+            HidReportLayout::Ds4 => Ds4Decoder(arbiter).decode(report, analog),
+            HidReportLayout::DualSense => {
+                DualSenseDecoder(arbiter, self.bluetooth).decode(report, analog)
+            }
+            HidReportLayout::SwitchPro => SwitchProDecoder(arbiter).decode(report, analog),
+            HidReportLayout::HoriSwitch => HoriSwitchDecoder(arbiter).decode(report, analog),
+            HidReportLayout::Profile => None,
+        }
+        .or_else(|| {
+            settings
+                .hid_profile
+                .as_deref()
+                .filter(|profile| profile.matches_device(self.vendor_id, self.product_id))
+                .and_then(|profile| ProfileDecoder(profile, arbiter).decode(report, analog))
+        })
+    }
+
+    /// Rumble and lightbar through the DS4 output report, which USB DS4-layout
+    /// devices accept. Bluetooth needs the checksummed report 0x11 instead,
+    /// and DualSense and Switch Pro use their own formats.
+    pub(super) fn feedback(&mut self, effect: FeedbackEffect) -> Result<FeedbackOutcome, String> {
+        let report = match self.layout {
+            HidReportLayout::Ds4 if !self.bluetooth => ds4_output_report(effect),
+            HidReportLayout::DualSense if !self.bluetooth => dualsense_output_report(effect),
+            _ => return Ok(FeedbackOutcome::Unsupported),
+        };
+
+        let (reply, outcome) = mpsc::channel();
+        self.output
+            .send((report, reply))
+            .map_err(|_| "The HID reader thread stopped.".to_string())?;
+        outcome
+            .recv()
+            .map_err(|_| "The HID reader thread stopped.".to_string())?
+            .map(|_| FeedbackOutcome::Applied)
+    }
+}
+
+fn ds4_output_report(effect: FeedbackEffect) -> Vec<u8> {
+    let mut report = vec![0u8; DS4_OUTPUT_REPORT_LEN];
+    report[0] = 0x05;
+    match effect {
+        FeedbackEffect::Rumble { intensity, .. } => {
+            let strength = (intensity * f64::from(u8::MAX)).round() as u8;
+            report[1] = DS4_OUTPUT_RUMBLE;
+            report[4] = strength;
+            report[5] = strength;
+        }
+        FeedbackEffect::Lightbar { red, green, blue } => {
+            report[1] = DS4_OUTPUT_LIGHTBAR;
+            report[6..9].copy_from_slice(&[red, green, blue]);
+        }
+    }
+    report
+}
+
+fn dualsense_output_report(effect: FeedbackEffect) -> Vec<u8> {
+    let mut report = vec![0u8; DUALSENSE_OUTPUT_REPORT_LEN];
+    report[0] = 0x02;
+    match effect {
+        FeedbackEffect::Rumble { intensity, .. } => {
+            let strength = (intensity * f64::from(u8::MAX)).round() as u8;
+            report[1] = DUALSENSE_OUTPUT_RUMBLE;
+            report[3] = strength;
+            report[4] = strength;
+        }
+        FeedbackEffect::Lightbar { red, green, blue } => {
+            report[2] = DUALSENSE_OUTPUT_LIGHTBAR;
+            report[45..48].copy_from_slice(&[red, green, blue]);
+        }
+    }
+    report
+}
+
+impl Drop for Ps4HidNativeSource {
+    fn drop(&mut self) {
+        self.stop_flag.store(true, Ordering::Relaxed);
+        // Closes the output channel, which a Raw Input device thread waits on.
+        self.output = mpsc::channel().0;
+        if let Some(reader) = self.reader.take() {
+            let _ = reader.join();
+        }
+    }
+}
+
+/// Starts the thread that owns `device`. On Windows, Raw Input delivers the
+/// reports of the device at `path` and the thread only writes output
+/// reports; elsewhere, or when Raw Input is unavailable, it reads them too.
+fn spawn_device_thread(
+    device: HidDevice,
+    #[cfg_attr(not(windows), allow(unused_variables))] path: &CStr,
+    stop_flag: &Arc<AtomicBool>,
+    reports: Sender<ReaderMessage>,
+    output_requests: Receiver<OutputRequest>,
+) -> JoinHandle<()> {
+    #[cfg(windows)]
+    if let Ok(subscription) = super::raw_input::subscribe(&path.to_string_lossy(), reports.clone())
+    {
+        return thread::spawn(move || {
+            let _subscription = subscription;
+            for (report, reply) in output_requests {
+                let _ = reply.send(write_report(&device, &report));
+            }
+        });
+    }
+
+    let stop_flag = Arc::clone(stop_flag);
+    thread::spawn(move || read_reports(device, stop_flag, reports, output_requests))
+}
+
+fn write_report(device: &HidDevice, report: &[u8]) -> Result<(), String> {
+    device
+        .write(report)
+        .map(|_| ())
+        .map_err(|error| format!("hidapi write error: {error}"))
+}
+
+/// Reader thread body: blocks on the device in short reads until stopped,
+/// queueing each report with its arrival time. A failed read is queued as
+/// the error and ends the thread.
+fn read_reports(
+    device: HidDevice,
+    stop_flag: Arc<AtomicBool>,
+    reports: Sender<ReaderMessage>,
+    output_requests: Receiver<OutputRequest>,
+) {
+    let mut data = [0u8; HID_REPORT_BUFFER_SIZE];
+    while !stop_flag.load(Ordering::Relaxed) {
+        for (report, reply) in output_requests.try_iter() {
+            let _ = reply.send(write_report(&device, &report));
+        }
+
+        let message = match device.read_timeout(&mut data, READ_TIMEOUT_MS) {
+            Ok(0) => continue,
+            Ok(len) => {
+                let at = Instant::now();
+                ReaderMessage::Report(TimedReport { data, len, at })
+            }
+            Err(error) => {
+                let _ = reports.send(ReaderMessage::Error(format!("hidapi read error: {error}")));
+                return;
+            }
+        };
+        if reports.send(message).is_err() {
+            return;
+        }
+    }
+}
+
+/// Supported candidates for `input_detect`, each test-opened and closed
+/// again, plus the gamepads that were skipped.
+pub(super) fn detect_hid_devices(
+    allowlist: &[HidDeviceId],
+) -> (Vec<NativeInputDevice>, Vec<NativeInputRejectedDevice>) {
+    let Ok(api) = HidApi::new() else {
+        return (Vec::new(), Vec::new());
+    };
+
+    let mut devices = Vec::new();
+    let mut rejected = Vec::new();
+    for device_info in api.device_list() {
+        match is_ps4_hid_candidate(device_info, allowlist) {
+            Ok(()) => {
+                let mut device = hid_device(device_info);
+                device.openable = Some(device_info.open_device(&api).is_ok());
+                devices.push(device);
+            }
+            Err(HidRejection::NotGamepad) => {}
+            Err(reason) => rejected.push(NativeInputRejectedDevice {
+                device: hid_device(device_info),
+                reason,
+            }),
+        }
+    }
+    (devices, rejected)
+}
+
+pub(super) fn list_hid_devices(allowlist: &[HidDeviceId]) -> Vec<NativeInputDevice> {
+    let Ok(api) = HidApi::new() else {
+        return Vec::new();
+    };
+
+    api.device_list()
+        .filter(|device_info| is_ps4_hid_candidate(*device_info, allowlist).is_ok())
+        .map(hid_device)
+        .collect()
+}
+
+fn hid_device(device_info: &DeviceInfo) -> NativeInputDevice {
+    let path = device_info.path().to_string_lossy().into_owned();
+    let mut device = NativeInputDevice::new(NativeInputMode::Hid, &path);
+    device.manufacturer = device_info.manufacturer_string().map(str::to_string);
+    device.product = device_info
+        .product_string()
+        .filter(|product| !product.is_empty())
+        .or_else(|| preset(device_info).map(|preset| preset.name))
+        .map(str::to_string);
+    device.vendor_id = Some(device_info.vendor_id());
+    device.product_id = Some(device_info.product_id());
+    device.path = Some(path);
+    device.serial_number = device_info
+        .serial_number()
+        .filter(|serial| !serial.is_empty())
+        .map(str::to_string);
+    device.connection = Some(match device_info.bus_type() {
+        BusType::Usb => DeviceConnection::Usb,
+        BusType::Bluetooth => DeviceConnection::Bluetooth,
+        _ => DeviceConnection::Other,
+    });
+    device
+}
+
+fn key_matches(device_info: &DeviceInfo, key: &str) -> bool {
+    match key.strip_prefix(SERIAL_KEY_PREFIX) {
+        Some(serial) => device_info.serial_number() == Some(serial),
+        None => device_info.path().to_string_lossy() == key,
+    }
+}
+
+/// The parts of a HID device its classification reads, so the rules can be
+/// exercised without hidapi enumerating real hardware.
+trait HidDescriptor {
+    fn vendor_id(&self) -> u16;
+    fn product_id(&self) -> u16;
+    fn usage_page(&self) -> u16;
+    fn usage(&self) -> u16;
+    fn product_string(&self) -> Option<&str>;
+    fn manufacturer_string(&self) -> Option<&str>;
+    fn path(&self) -> &CStr;
+}
+
+impl HidDescriptor for DeviceInfo {
+    fn vendor_id(&self) -> u16 {
+        DeviceInfo::vendor_id(self)
+    }
+
+    fn product_id(&self) -> u16 {
+        DeviceInfo::product_id(self)
+    }
+
+    fn usage_page(&self) -> u16 {
+        DeviceInfo::usage_page(self)
+    }
+
+    fn usage(&self) -> u16 {
+        DeviceInfo::usage(self)
+    }
+
+    fn product_string(&self) -> Option<&str> {
+        DeviceInfo::product_string(self)
+    }
+
+    fn manufacturer_string(&self) -> Option<&str> {
+        DeviceInfo::manufacturer_string(self)
+    }
+
+    fn path(&self) -> &CStr {
+        DeviceInfo::path(self)
+    }
+}
+
+fn is_ps4_hid_candidate(
+    device_info: &impl HidDescriptor,
+    allowlist: &[HidDeviceId],
+) -> Result<(), HidRejection> {
+    // Switch pads are matched on VID/PID alone, since the Pro Controller does
+    // not report the gamepad usage on every platform.
+    let preset = preset(device_info);
+    if preset.is_some_and(|preset| {
+        matches!(
+            preset.decoder,
+            PresetDecoder::SwitchPro | PresetDecoder::HoriSwitch
+        )
+    }) {
+        return Ok(());
+    }
+
+    // Joystick (0x04) collections are only rejected with a reason; many
+    // sticks in PC mode report one instead of the gamepad usage.
+    if device_info.usage_page() != 0x0001 || !matches!(device_info.usage(), 0x0004 | 0x0005) {
+        return Err(HidRejection::NotGamepad);
+    }
+    // The user vouched for allow-listed boards, joystick usage or not. Brook
+    // boards send DS4 reports in PS4 mode too, but may declare a joystick
+    // and name themselves rather than PS4.
+    let allowed = allowlist.iter().any(|id| {
+        (id.vendor_id, id.product_id) == (device_info.vendor_id(), device_info.product_id())
+    });
+    if allowed || is_brook(device_info) {
+        return Ok(());
+    }
+
+    let product_name = device_info.product_string().unwrap_or("");
+    let path = device_info.path().to_string_lossy().to_ascii_lowercase();
+    let supported = device_info.usage() == 0x0005
+        && (product_name.contains("PS4")
+            || device_info.product_id() == GP2040_PS4_PRODUCT_ID
+            || path.contains("pid_0401")
+            || preset.is_some());
+    if supported {
+        Ok(())
+    } else {
+        Err(HidRejection::UnsupportedGamepad)
+    }
+}
+
+fn preset(device_info: &impl HidDescriptor) -> Option<&'static ControllerPreset> {
+    controller_preset::controller_preset(device_info.vendor_id(), device_info.product_id())
+}
+
+fn is_brook(device_info: &impl HidDescriptor) -> bool {
+    let names_brook =
+        |name: Option<&str>| name.is_some_and(|name| name.to_ascii_lowercase().contains("brook"));
+    device_info.vendor_id() == ZEROPLUS_VENDOR_ID
+        && (names_brook(device_info.product_string())
+            || names_brook(device_info.manufacturer_string()))
+}
+
+fn report_layout(device_info: &impl HidDescriptor) -> HidReportLayout {
+    match preset(device_info).map(|preset| preset.decoder) {
+        Some(PresetDecoder::DualSense) => HidReportLayout::DualSense,
+        Some(PresetDecoder::SwitchPro) => HidReportLayout::SwitchPro,
+        Some(PresetDecoder::HoriSwitch) => HidReportLayout::HoriSwitch,
+        Some(PresetDecoder::Ds4) | None => HidReportLayout::Ds4,
+    }
+}
+
+fn is_permission_error(message: &str) -> bool {
+    let message = message.to_ascii_lowercase();
+    message.contains(IOKIT_NOT_PERMITTED) || message.contains("not permitted")
+}
+
+/// A DualSense paired over Bluetooth sends a reduced report 0x01 until its
+/// calibration feature report is read, and full report 0x31 from then on.
+fn enable_dualsense_bluetooth_reports(device: &HidDevice) {
+    let mut calibration = [0u8; DUALSENSE_CALIBRATION_REPORT_LEN];
+    calibration[0] = 0x05;
+    let _ = device.get_feature_report(&mut calibration);
+}
+
+/// Sends the Pro Controller handshake while the device is still blocking so each
+/// command is acknowledged before the next. The USB commands fail harmlessly
+/// over Bluetooth, where only the report mode subcommand is needed.
+fn enable_switch_pro_full_reports(device: &HidDevice) {
+    let mut reply = [0u8; 64];
+    for command in SWITCH_PRO_USB_HANDSHAKE {
+        if device.write(&command).is_ok() {
+            let _ = device.read_timeout(&mut reply, 100);
+        }
+    }
+    if device.write(&SWITCH_PRO_FULL_REPORT_MODE).is_ok() {
+        let _ = device.read_timeout(&mut reply, 100);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::input::controller_preset::CONTROLLER_PRESETS;
+
+    const GAMEPAD: u16 = 0x0005;
+    const JOYSTICK: u16 = 0x0004;
+
+    struct Descriptor {
+        vendor_id: u16,
+        product_id: u16,
+        usage_page: u16,
+        usage: u16,
+        product: Option<&'static str>,
+        manufacturer: Option<&'static str>,
+    }
+
+    impl HidDescriptor for Descriptor {
+        fn vendor_id(&self) -> u16 {
+            self.vendor_id
+        }
+
+        fn product_id(&self) -> u16 {
+            self.product_id
+        }
+
+        fn usage_page(&self) -> u16 {
+            self.usage_page
+        }
+
+        fn usage(&self) -> u16 {
+            self.usage
+        }
+
+        fn product_string(&self) -> Option<&str> {
+            self.product
+        }
+
+        fn manufacturer_string(&self) -> Option<&str> {
+            self.manufacturer
+        }
+
+        fn path(&self) -> &CStr {
+            c""
+        }
+    }
+
+    fn descriptor(vendor_id: u16, product_id: u16, usage: u16) -> Descriptor {
+        Descriptor {
+            vendor_id,
+            product_id,
+            usage_page: 0x0001,
+            usage,
+            product: None,
+            manufacturer: None,
+        }
+    }
+
+    fn classify(device: &Descriptor) -> Result<(), HidRejection> {
+        is_ps4_hid_candidate(device, &[])
+    }
+
+    #[test]
+    fn presets_are_read_with_their_decoder() {
+        for preset in &CONTROLLER_PRESETS {
+            let device = descriptor(preset.vendor_id, preset.product_id, GAMEPAD);
+            assert!(classify(&device).is_ok(), "{} was rejected", preset.name);
+            let layout = report_layout(&device);
+            let expected = match preset.decoder {
+                PresetDecoder::Ds4 => matches!(layout, HidReportLayout::Ds4),
+                PresetDecoder::DualSense => matches!(layout, HidReportLayout::DualSense),
+                PresetDecoder::SwitchPro => matches!(layout, HidReportLayout::SwitchPro),
+                PresetDecoder::HoriSwitch => matches!(layout, HidReportLayout::HoriSwitch),
+            };
+            assert!(expected, "{} got the wrong layout", preset.name);
+        }
+    }
+
+    #[test]
+    fn ds4_presets_need_the_gamepad_usage() {
+        for preset in CONTROLLER_PRESETS
+            .iter()
+            .filter(|preset| preset.decoder == PresetDecoder::Ds4)
+        {
+            let device = descriptor(preset.vendor_id, preset.product_id, JOYSTICK);
+            assert!(
+                classify(&device) == Err(HidRejection::UnsupportedGamepad),
+                "{} was accepted as a joystick",
+                preset.name
+            );
+        }
+    }
+
+    #[test]
+    fn switch_pads_are_matched_on_ids_alone() {
+        for (vendor_id, product_id) in [(0x057E, 0x2009), (0x0F0D, 0x0092)] {
+            let device = Descriptor {
+                usage_page: 0xFF00,
+                ..descriptor(vendor_id, product_id, 0)
+            };
+            assert!(classify(&device).is_ok());
+        }
+        assert!(matches!(
+            report_layout(&descriptor(0x057E, 0x2009, 0)),
+            HidReportLayout::SwitchPro
+        ));
+        assert!(matches!(
+            report_layout(&descriptor(0x0F0D, 0x0092, 0)),
+            HidReportLayout::HoriSwitch
+        ));
+    }
+
+    #[test]
+    fn brook_boards_are_matched_by_name() {
+        let by_product = Descriptor {
+            product: Some("Brook Universal Fighting Board"),
+            ..descriptor(ZEROPLUS_VENDOR_ID, 0x1234, JOYSTICK)
+        };
+        let by_manufacturer = Descriptor {
+            manufacturer: Some("BROOK"),
+            ..descriptor(ZEROPLUS_VENDOR_ID, 0x1234, GAMEPAD)
+        };
+        assert!(classify(&by_product).is_ok());
+        assert!(classify(&by_manufacturer).is_ok());
+        assert!(matches!(report_layout(&by_product), HidReportLayout::Ds4));
+
+        let unnamed = descriptor(ZEROPLUS_VENDOR_ID, 0x1234, JOYSTICK);
+        assert!(classify(&unnamed) == Err(HidRejection::UnsupportedGamepad));
+        let other_vendor = Descriptor {
+            product: Some("Brook Universal Fighting Board"),
+            ..descriptor(0x1234, 0x1234, JOYSTICK)
+        };
+        assert!(classify(&other_vendor) == Err(HidRejection::UnsupportedGamepad));
+    }
+
+    #[test]
+    fn unknown_devices_are_rejected() {
+        let pc_mode_stick = descriptor(0x1234, 0x5678, GAMEPAD);
+        assert!(classify(&pc_mode_stick) == Err(HidRejection::UnsupportedGamepad));
+        let named_ps4 = Descriptor {
+            product: Some("Wireless PS4 Controller"),
+            ..pc_mode_stick
+        };
+        assert!(classify(&named_ps4).is_ok());
+
+        let keyboard = Descriptor {
+            usage: 0x0006,
+            ..descriptor(0x054C, 0x05C4, 0)
+        };
+        assert!(classify(&keyboard) == Err(HidRejection::NotGamepad));
+
+        let allowlist = [HidDeviceId {
+            vendor_id: 0x1234,
+            product_id: 0x5678,
+        }];
+        let joystick = descriptor(0x1234, 0x5678, JOYSTICK);
+        assert!(is_ps4_hid_candidate(&joystick, &allowlist).is_ok());
+    }
+}
@@ -0,0 +1,612 @@
+use std::{
+    fs::{self, File, OpenOptions},
+    io::{ErrorKind, Read},
+    mem,
+    os::unix::{fs::OpenOptionsExt, io::AsRawFd},
+    path::{Path, PathBuf},
+    time::Instant,
+};
+
+use super::super::{
+    analog::{AnalogSettings, DirectionPriority, DirectionSource},
+    decoder::{AnalogTriggers, DirectionArbiter, RightStick},
+    device_profile::HidDeviceId,
+    feedback::{FeedbackEffect, FeedbackOutcome},
+    pacer::SleepTimer,
+    to_direction,
+    watchdog::HidDiagnostics,
+    DeviceConnection, FrameClock, FrameTimestamp, InputSample, InputSettings,
+    NativeInputDetectResult, NativeInputDevice, NativeInputMode, BUTTON_DPAD_DOWN_MASK,
+    BUTTON_DPAD_LEFT_MASK, BUTTON_DPAD_RIGHT_MASK, BUTTON_DPAD_UP_MASK, BUTTON_EAST_MASK,
+    BUTTON_L1_MASK, BUTTON_L2_MASK, BUTTON_L3_MASK, BUTTON_NORTH_MASK, BUTTON_R1_MASK,
+    BUTTON_R2_MASK, BUTTON_R3_MASK, BUTTON_SELECT_MASK, BUTTON_SOUTH_MASK, BUTTON_START_MASK,
+    BUTTON_WEST_MASK,
+};
+
+const SYSFS_INPUT_DIR: &str = "/sys/class/input";
+/// `BUS_USB` and `BUS_BLUETOOTH` from `linux/input.h`, as found in
+/// `id/bustype`.
+const BUS_USB: u16 = 0x03;
+const BUS_BLUETOOTH: u16 = 0x05;
+const DEV_INPUT_DIR: &str = "/dev/input";
+
+const EV_SYN: u16 = 0x00;
+const EV_KEY: u16 = 0x01;
+const EV_ABS: u16 = 0x03;
+const SYN_REPORT: u16 = 0x00;
+const SYN_DROPPED: u16 = 0x03;
+
+/// `KEY_CNT`: one past the highest key code, and the bit length of the
+/// `EVIOCGKEY` bitmap.
+const KEY_CNT: u16 = 0x300;
+const IOC_READ: u64 = 2;
+
+const BTN_JOYSTICK: u16 = 0x120;
+const BTN_GAMEPAD: u16 = 0x130;
+const JOYSTICK_BUTTON_COUNT: u16 = 12;
+
+const ABS_X: u16 = 0x00;
+const ABS_Y: u16 = 0x01;
+const ABS_Z: u16 = 0x02;
+const ABS_RX: u16 = 0x03;
+const ABS_RY: u16 = 0x04;
+const ABS_RZ: u16 = 0x05;
+const ABS_HAT0X: u16 = 0x10;
+const ABS_HAT0Y: u16 = 0x11;
+
+const GAMEPAD_BUTTONS: [(u16, u32); 16] = [
+    (0x130, BUTTON_SOUTH_MASK),
+    (0x131, BUTTON_EAST_MASK),
+    (0x133, BUTTON_NORTH_MASK),
+    (0x134, BUTTON_WEST_MASK),
+    (0x136, BUTTON_L1_MASK),
+    (0x137, BUTTON_R1_MASK),
+    (0x138, BUTTON_L2_MASK),
+    (0x139, BUTTON_R2_MASK),
+    (0x13A, BUTTON_SELECT_MASK),
+    (0x13B, BUTTON_START_MASK),
+    (0x13D, BUTTON_L3_MASK),
+    (0x13E, BUTTON_R3_MASK),
+    (0x220, BUTTON_DPAD_UP_MASK),
+    (0x221, BUTTON_DPAD_DOWN_MASK),
+    (0x222, BUTTON_DPAD_LEFT_MASK),
+    (0x223, BUTTON_DPAD_RIGHT_MASK),
+];
+
+pub struct BackendSource {
+    device: EvdevGamepadSource,
+}
+
+struct EvdevGamepadSource {
+    file: File,
+    path: PathBuf,
+    buffer: Vec<u8>,
+    state: EvdevGamepadState,
+    /// Set by `SYN_DROPPED`: the kernel's queue overflowed, so events up to
+    /// the next `SYN_REPORT` are incomplete and the state is read back from
+    /// the device instead.
+    dropped: bool,
+}
+
+#[derive(Default)]
+struct EvdevGamepadState {
+    key_mask: u32,
+    /// Buttons pressed since the last sample, so a tap released within one
+    /// poll still shows.
+    pressed: u32,
+    left_x: AbsAxis,
+    left_y: AbsAxis,
+    right_x: AbsAxis,
+    right_y: AbsAxis,
+    left_trigger: AbsAxis,
+    right_trigger: AbsAxis,
+    hat_x: i32,
+    hat_y: i32,
+    arbiter: DirectionArbiter,
+}
+
+#[derive(Clone, Copy, Default)]
+struct AbsAxis {
+    value: i32,
+    minimum: i32,
+    maximum: i32,
+}
+
+impl BackendSource {
+    pub fn new(
+        mode: NativeInputMode,
+        device_id: Option<&str>,
+        _settings: &InputSettings,
+    ) -> Result<Self, String> {
+        match mode {
+            NativeInputMode::Evdev => {
+                let path = device_id.map(|id| mode.device_key(id)).transpose()?;
+                Ok(Self {
+                    device: EvdevGamepadSource::new(path)?,
+                })
+            }
+            _ => Err(format!(
+                "Native input mode '{}' is not available on Linux builds.",
+                mode.name()
+            )),
+        }
+    }
+
+    pub fn poll(
+        &mut self,
+        now: FrameTimestamp,
+        settings: &InputSettings,
+    ) -> Result<InputSample, String> {
+        self.device.poll(now, settings.analog)
+    }
+
+    /// A sub-frame poll taken at `deadline`. Reads never block, so every
+    /// event queued since the last poll is applied at once.
+    pub fn poll_until(
+        &mut self,
+        deadline: Instant,
+        timer: &SleepTimer,
+        clock: &FrameClock,
+        settings: &InputSettings,
+    ) -> Result<InputSample, String> {
+        timer.sleep_until(deadline);
+        self.poll(clock.now(), settings)
+    }
+
+    pub fn take_reports_read(&mut self) -> Option<u32> {
+        None
+    }
+
+    pub fn xinput_user_index(&self) -> Option<u32> {
+        None
+    }
+
+    pub fn take_warnings(&mut self) -> Vec<String> {
+        Vec::new()
+    }
+
+    pub fn hid_diagnostics(&self) -> Option<HidDiagnostics> {
+        None
+    }
+
+    pub fn device(&self) -> Option<NativeInputDevice> {
+        Some(evdev_device(&self.device.path))
+    }
+
+    /// Force feedback over evdev is not wired up yet.
+    pub fn feedback(&mut self, _effect: FeedbackEffect) -> Result<FeedbackOutcome, String> {
+        Ok(FeedbackOutcome::Unsupported)
+    }
+}
+
+impl EvdevGamepadSource {
+    /// Opens the first gamepad node, or only `requested` when a specific
+    /// device was asked for.
+    fn new(requested: Option<&str>) -> Result<Self, String> {
+        let candidates: Vec<PathBuf> = gamepad_candidates()
+            .into_iter()
+            .filter(|path| requested.is_none_or(|requested| path == Path::new(requested)))
+            .collect();
+        if let Some(requested) = requested.filter(|_| candidates.is_empty()) {
+            return Err(format!("evdev device '{requested}' is not connected."));
+        }
+        let mut permission_denied = None;
+
+        for path in candidates {
+            match OpenOptions::new()
+                .read(true)
+                .custom_flags(libc::O_NONBLOCK)
+                .open(&path)
+            {
+                Ok(file) => {
+                    let state = EvdevGamepadState::new(&file);
+                    return Ok(Self {
+                        file,
+                        path,
+                        buffer: vec![0; mem::size_of::<libc::input_event>() * 64],
+                        state,
+                        dropped: false,
+                    });
+                }
+                Err(error) if error.kind() == ErrorKind::PermissionDenied => {
+                    permission_denied.get_or_insert(path);
+                }
+                Err(_) => {}
+            }
+        }
+
+        match permission_denied {
+            Some(path) => Err(format!(
+                "Native input mode 'evdev' was denied access to {}. Add your user to the 'input' group or install a udev rule for the controller, then restart the app.",
+                path.display()
+            )),
+            None => Err("Native input mode 'evdev' could not open a gamepad under /dev/input.".to_string()),
+        }
+    }
+
+    fn poll(&mut self, now: FrameTimestamp, analog: AnalogSettings) -> Result<InputSample, String> {
+        let event_size = mem::size_of::<libc::input_event>();
+
+        loop {
+            match self.file.read(&mut self.buffer) {
+                Ok(0) => break,
+                Ok(read_size) => {
+                    for event in self.buffer[..read_size].chunks_exact(event_size) {
+                        let offset = event_size - 8;
+                        let kind = u16::from_ne_bytes([event[offset], event[offset + 1]]);
+                        let code = u16::from_ne_bytes([event[offset + 2], event[offset + 3]]);
+                        let value = i32::from_ne_bytes([
+                            event[offset + 4],
+                            event[offset + 5],
+                            event[offset + 6],
+                            event[offset + 7],
+                        ]);
+                        match (kind, code) {
+                            (EV_SYN, SYN_DROPPED) => self.dropped = true,
+                            (EV_SYN, SYN_REPORT) if self.dropped => {
+                                self.dropped = false;
+                                self.state.sync(&self.file);
+                            }
+                            _ if self.dropped => {}
+                            _ => self.state.apply(kind, code, value),
+                        }
+                    }
+                }
+                Err(error) if error.kind() == ErrorKind::WouldBlock => break,
+                Err(error) if error.kind() == ErrorKind::Interrupted => continue,
+                Err(error) => {
+                    return Err(format!(
+                        "evdev read error on {}: {error}",
+                        self.path.display()
+                    ))
+                }
+            }
+        }
+
+        Ok(self.state.sample(now, analog))
+    }
+}
+
+impl EvdevGamepadState {
+    /// Starts from what the device holds right now, so a button or lever
+    /// already held when the source opens is not missed until it moves.
+    fn new(file: &File) -> Self {
+        let mut state = Self::default();
+        state.sync(file);
+        state
+    }
+
+    /// Reads the held keys and axis values back from the device
+    /// (`EVIOCGKEY`, `EVIOCGABS`).
+    fn sync(&mut self, file: &File) {
+        let keys = key_state(file);
+        self.key_mask = (0..KEY_CNT)
+            .filter(|code| keys[usize::from(code / 8)] & (1 << (code % 8)) != 0)
+            .filter_map(button_mask)
+            .fold(0, |mask, bit| mask | bit);
+        self.left_x = AbsAxis::from_device(file, ABS_X);
+        self.left_y = AbsAxis::from_device(file, ABS_Y);
+        self.right_x = AbsAxis::from_device(file, ABS_RX);
+        self.right_y = AbsAxis::from_device(file, ABS_RY);
+        self.left_trigger = AbsAxis::from_device(file, ABS_Z);
+        self.right_trigger = AbsAxis::from_device(file, ABS_RZ);
+        self.hat_x = AbsAxis::from_device(file, ABS_HAT0X).value.signum();
+        self.hat_y = AbsAxis::from_device(file, ABS_HAT0Y).value.signum();
+    }
+
+    fn apply(&mut self, kind: u16, code: u16, value: i32) {
+        match kind {
+            EV_KEY => {
+                let Some(mask) = button_mask(code) else {
+                    return;
+                };
+                if value != 0 {
+                    self.key_mask |= mask;
+                    self.pressed |= mask;
+                } else {
+                    self.key_mask &= !mask;
+                }
+            }
+            EV_ABS => match code {
+                ABS_X => self.left_x.value = value,
+                ABS_Y => self.left_y.value = value,
+                ABS_RX => self.right_x.value = value,
+                ABS_RY => self.right_y.value = value,
+                ABS_Z => self.left_trigger.value = value,
+                ABS_RZ => self.right_trigger.value = value,
+                ABS_HAT0X => self.hat_x = value.signum(),
+                ABS_HAT0Y => self.hat_y = value.signum(),
+                _ => {}
+            },
+            _ => {}
+        }
+    }
+
+    /// The dpad settles `direction`, so only its latest state counts.
+    fn sample(&mut self, now: FrameTimestamp, analog: AnalogSettings) -> InputSample {
+        let dpad_mask = BUTTON_DPAD_UP_MASK
+            | BUTTON_DPAD_DOWN_MASK
+            | BUTTON_DPAD_LEFT_MASK
+            | BUTTON_DPAD_RIGHT_MASK;
+        let mut down_mask = self.key_mask | (mem::take(&mut self.pressed) & !dpad_mask);
+
+        if self.hat_y < 0 {
+            down_mask |= BUTTON_DPAD_UP_MASK;
+        }
+        if self.hat_y > 0 {
+            down_mask |= BUTTON_DPAD_DOWN_MASK;
+        }
+        if self.hat_x < 0 {
+            down_mask |= BUTTON_DPAD_LEFT_MASK;
+        }
+        if self.hat_x > 0 {
+            down_mask |= BUTTON_DPAD_RIGHT_MASK;
+        }
+        if self.left_trigger.is_pressed(analog) {
+            down_mask |= BUTTON_L2_MASK;
+        }
+        if self.right_trigger.is_pressed(analog) {
+            down_mask |= BUTTON_R2_MASK;
+        }
+
+        let dpad_horizontal = axis_from_buttons(
+            down_mask & BUTTON_DPAD_RIGHT_MASK != 0,
+            down_mask & BUTTON_DPAD_LEFT_MASK != 0,
+        );
+        let dpad_vertical = axis_from_buttons(
+            down_mask & BUTTON_DPAD_UP_MASK != 0,
+            down_mask & BUTTON_DPAD_DOWN_MASK != 0,
+        );
+
+        let dpad_direction = to_direction(dpad_horizontal, dpad_vertical);
+        let stick = |x: AbsAxis, y: AbsAxis| {
+            let (horizontal, vertical) = analog.stick_direction(x.span(), y.span());
+            to_direction(horizontal, -vertical)
+        };
+        let direction = match analog.direction_source() {
+            DirectionSource::Dpad => dpad_direction,
+            DirectionSource::LeftStick => stick(self.left_x, self.left_y),
+            DirectionSource::RightStick => stick(self.right_x, self.right_y),
+            DirectionSource::Merged => self.arbiter.merge(
+                dpad_direction,
+                stick(self.left_x, self.left_y),
+                analog.direction_priority(DirectionPriority::Dpad),
+            ),
+        };
+
+        let mut sample = InputSample::new(now, direction, down_mask);
+        sample.left_stick = self.left_x.byte().zip(self.left_y.byte());
+        sample.stick_direction = stick(self.left_x, self.left_y);
+        sample.right_stick =
+            self.right_x
+                .byte()
+                .zip(self.right_y.byte())
+                .map(|(x, y)| RightStick {
+                    direction: stick(self.right_x, self.right_y),
+                    x,
+                    y,
+                });
+        sample.triggers = self
+            .left_trigger
+            .byte()
+            .zip(self.right_trigger.byte())
+            .map(|(left, right)| AnalogTriggers { left, right });
+        sample
+    }
+}
+
+impl AbsAxis {
+    fn from_device(file: &File, axis: u16) -> Self {
+        let mut info: libc::input_absinfo = unsafe { mem::zeroed() };
+        let request = eviocgabs(axis);
+        let ret = unsafe { libc::ioctl(file.as_raw_fd(), request as _, &mut info) };
+
+        if ret < 0 {
+            return Self {
+                value: 0,
+                minimum: 0,
+                maximum: 0,
+            };
+        }
+
+        Self {
+            value: info.value,
+            minimum: info.minimum,
+            maximum: info.maximum,
+        }
+    }
+
+    fn span(self) -> (i32, i32, i32) {
+        (self.value, self.minimum, self.maximum)
+    }
+
+    /// The value scaled to a byte; `None` when the device lacks the axis.
+    fn byte(self) -> Option<u8> {
+        if self.maximum <= self.minimum {
+            return None;
+        }
+
+        let offset =
+            i64::from(self.value.clamp(self.minimum, self.maximum)) - i64::from(self.minimum);
+        let range = i64::from(self.maximum) - i64::from(self.minimum);
+        Some((offset * 255 / range) as u8)
+    }
+
+    fn is_pressed(self, analog: AnalogSettings) -> bool {
+        if self.maximum <= self.minimum {
+            return false;
+        }
+
+        analog.trigger_pressed(self.value, self.minimum, self.maximum)
+    }
+}
+
+pub fn input_detect(hid_allowlist: &[HidDeviceId]) -> NativeInputDetectResult {
+    let mut devices = input_list_devices(hid_allowlist);
+    for device in &mut devices {
+        // Opening an event node does not grab it, so this cannot disturb a
+        // running worker.
+        device.openable = device.path.as_ref().map(|path| {
+            OpenOptions::new()
+                .read(true)
+                .custom_flags(libc::O_NONBLOCK)
+                .open(path)
+                .is_ok()
+        });
+    }
+    NativeInputDetectResult::from_devices(devices, Vec::new())
+}
+
+/// evdev lists every gamepad the kernel knows, so the HID allow-list has
+/// nothing to add.
+pub fn input_list_devices(_hid_allowlist: &[HidDeviceId]) -> Vec<NativeInputDevice> {
+    gamepad_candidates()
+        .iter()
+        .map(|path| evdev_device(path))
+        .collect()
+}
+
+fn evdev_device(path: &Path) -> NativeInputDevice {
+    let path_string = path.to_string_lossy().into_owned();
+    let mut device = NativeInputDevice::new(NativeInputMode::Evdev, &path_string);
+    if let Some(name) = path.file_name() {
+        let sysfs_device = Path::new(SYSFS_INPUT_DIR).join(name).join("device");
+        device.product = read_sysfs_string(&sysfs_device.join("name"));
+        device.vendor_id = read_sysfs_hex(&sysfs_device.join("id/vendor"));
+        device.product_id = read_sysfs_hex(&sysfs_device.join("id/product"));
+        device.connection = read_sysfs_hex(&sysfs_device.join("id/bustype")).map(|bus| match bus {
+            BUS_USB => DeviceConnection::Usb,
+            BUS_BLUETOOTH => DeviceConnection::Bluetooth,
+            _ => DeviceConnection::Other,
+        });
+    }
+    device.path = Some(path_string);
+    device
+}
+
+fn read_sysfs_string(path: &Path) -> Option<String> {
+    fs::read_to_string(path)
+        .ok()
+        .map(|value| value.trim().to_string())
+}
+
+fn read_sysfs_hex(path: &Path) -> Option<u16> {
+    u16::from_str_radix(read_sysfs_string(path)?.as_str(), 16).ok()
+}
+
+/// Lists `/dev/input/event*` nodes whose capabilities include gamepad or
+/// joystick buttons. Capabilities come from sysfs, which stays readable even
+/// when the event node itself is not, so permission problems surface on open.
+fn gamepad_candidates() -> Vec<PathBuf> {
+    let Ok(entries) = fs::read_dir(SYSFS_INPUT_DIR) else {
+        return Vec::new();
+    };
+
+    let mut candidates: Vec<PathBuf> = entries
+        .filter_map(|entry| entry.ok())
+        .filter_map(|entry| {
+            let name = entry.file_name().to_string_lossy().into_owned();
+            if !name.starts_with("event") {
+                return None;
+            }
+
+            let keys = fs::read_to_string(entry.path().join("device/capabilities/key")).ok()?;
+            let is_gamepad =
+                has_capability(&keys, BTN_GAMEPAD) || has_capability(&keys, BTN_JOYSTICK);
+            is_gamepad.then(|| Path::new(DEV_INPUT_DIR).join(name))
+        })
+        .collect();
+
+    candidates.sort();
+    candidates
+}
+
+/// Tests a bit in a sysfs capability bitmap: space separated hex words of
+/// `BITS_PER_LONG` bits, most significant word first.
+fn has_capability(bitmap: &str, code: u16) -> bool {
+    let word_bits = usize::BITS as usize;
+    let index = usize::from(code);
+
+    bitmap
+        .split_whitespace()
+        .rev()
+        .nth(index / word_bits)
+        .and_then(|word| u64::from_str_radix(word, 16).ok())
+        .is_some_and(|word| (word >> (index % word_bits)) & 1 == 1)
+}
+
+fn button_mask(code: u16) -> Option<u32> {
+    if let Some((_, mask)) = GAMEPAD_BUTTONS
+        .iter()
+        .find(|(candidate, _)| *candidate == code)
+    {
+        return Some(*mask);
+    }
+
+    // Generic HID joysticks (most arcade sticks) report BTN_TRIGGER..BTN_BASE6;
+    // treat them like DirectInput buttons 1-12.
+    (BTN_JOYSTICK..BTN_JOYSTICK + JOYSTICK_BUTTON_COUNT)
+        .contains(&code)
+        .then(|| 1 << (code - BTN_JOYSTICK))
+}
+
+fn axis_from_buttons(positive: bool, negative: bool) -> i32 {
+    match (positive, negative) {
+        (true, false) => 1,
+        (false, true) => -1,
+        _ => 0,
+    }
+}
+
+/// Bitmap of held keys, one bit per code; empty if the device refuses.
+fn key_state(file: &File) -> [u8; KEY_CNT as usize / 8] {
+    let mut keys = [0u8; KEY_CNT as usize / 8];
+    let request = eviocgkey(keys.len());
+    let ret = unsafe { libc::ioctl(file.as_raw_fd(), request as _, keys.as_mut_ptr()) };
+    if ret < 0 {
+        keys.fill(0);
+    }
+    keys
+}
+
+/// `EVIOCGKEY(len)`, i.e. `_IOC(_IOC_READ, 'E', 0x18, len)`.
+fn eviocgkey(len: usize) -> u64 {
+    (IOC_READ << 30) | ((len as u64) << 16) | (u64::from(b'E') << 8) | 0x18
+}
+
+/// `EVIOCGABS(axis)`, i.e. `_IOR('E', 0x40 + axis, struct input_absinfo)`.
+fn eviocgabs(axis: u16) -> u64 {
+    let size = mem::size_of::<libc::input_absinfo>() as u64;
+    (IOC_READ << 30) | (size << 16) | (u64::from(b'E') << 8) | (0x40 + u64::from(axis))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BTN_SOUTH: u16 = 0x130;
+    const BTN_DPAD_UP: u16 = 0x220;
+
+    fn sample(state: &mut EvdevGamepadState) -> InputSample {
+        state.sample(FrameTimestamp::default(), AnalogSettings::default())
+    }
+
+    #[test]
+    fn a_tap_within_one_poll_still_shows() {
+        let mut state = EvdevGamepadState::default();
+        state.apply(EV_KEY, BTN_SOUTH, 1);
+        state.apply(EV_KEY, BTN_SOUTH, 0);
+        assert_eq!(sample(&mut state).down_mask, BUTTON_SOUTH_MASK);
+        assert_eq!(sample(&mut state).down_mask, 0);
+    }
+
+    #[test]
+    fn a_dpad_tap_within_one_poll_is_dropped() {
+        let mut state = EvdevGamepadState::default();
+        state.apply(EV_KEY, BTN_DPAD_UP, 1);
+        state.apply(EV_KEY, BTN_DPAD_UP, 0);
+        let sample = sample(&mut state);
+        assert_eq!(sample.down_mask, 0);
+        assert_eq!(sample.direction, 5);
+    }
+}
@@ -0,0 +1,127 @@
+use std::time::Instant;
+
+use super::super::{
+    device_profile::HidDeviceId,
+    feedback::{FeedbackEffect, FeedbackOutcome},
+    pacer::SleepTimer,
+    watchdog::HidDiagnostics,
+    FrameClock, FrameTimestamp, InputSample, InputSettings, NativeInputDetectResult,
+    NativeInputDevice, NativeInputMode,
+};
+use super::generic::{generic_devices, GenericGamepadSource};
+use super::hid::{detect_hid_devices, list_hid_devices, Ps4HidNativeSource};
+
+pub struct BackendSource {
+    backend: NativeBackend,
+}
+
+enum NativeBackend {
+    Hid(Box<Ps4HidNativeSource>),
+    Generic(GenericGamepadSource),
+}
+
+impl BackendSource {
+    pub fn new(
+        mode: NativeInputMode,
+        device_id: Option<&str>,
+        settings: &InputSettings,
+    ) -> Result<Self, String> {
+        let device_key = device_id.map(|id| mode.device_key(id)).transpose()?;
+        let backend = match mode {
+            NativeInputMode::Hid => {
+                let device = Ps4HidNativeSource::new(device_key, settings).map_err(|error| {
+                    format!(
+                        "Native input mode 'hid' could not open a supported PS4 HID device: {error}"
+                    )
+                })?;
+                NativeBackend::Hid(Box::new(device))
+            }
+            NativeInputMode::Generic => {
+                NativeBackend::Generic(GenericGamepadSource::new(device_key)?)
+            }
+            _ => {
+                return Err(format!(
+                    "Native input mode '{}' is not available on macOS builds.",
+                    mode.name()
+                ))
+            }
+        };
+        Ok(Self { backend })
+    }
+
+    pub fn poll(
+        &mut self,
+        now: FrameTimestamp,
+        settings: &InputSettings,
+    ) -> Result<InputSample, String> {
+        match &mut self.backend {
+            NativeBackend::Hid(device) => device.poll(now, settings),
+            NativeBackend::Generic(source) => source.poll(now, settings.analog),
+        }
+    }
+
+    pub fn poll_until(
+        &mut self,
+        deadline: Instant,
+        timer: &SleepTimer,
+        clock: &FrameClock,
+        settings: &InputSettings,
+    ) -> Result<InputSample, String> {
+        if let NativeBackend::Hid(device) = &mut self.backend {
+            return device.poll_until(deadline, clock, settings);
+        }
+        timer.sleep_until(deadline);
+        self.poll(clock.now(), settings)
+    }
+
+    pub fn take_reports_read(&mut self) -> Option<u32> {
+        match &mut self.backend {
+            NativeBackend::Hid(device) => Some(device.take_reports_read()),
+            NativeBackend::Generic(_) => None,
+        }
+    }
+
+    pub fn xinput_user_index(&self) -> Option<u32> {
+        None
+    }
+
+    pub fn take_warnings(&mut self) -> Vec<String> {
+        match &mut self.backend {
+            NativeBackend::Hid(device) => device.take_warnings(),
+            NativeBackend::Generic(_) => Vec::new(),
+        }
+    }
+
+    pub fn device(&self) -> Option<NativeInputDevice> {
+        match &self.backend {
+            NativeBackend::Hid(device) => Some(device.device()),
+            NativeBackend::Generic(source) => source.device(),
+        }
+    }
+
+    pub fn hid_diagnostics(&self) -> Option<HidDiagnostics> {
+        match &self.backend {
+            NativeBackend::Hid(device) => Some(device.diagnostics()),
+            NativeBackend::Generic(_) => None,
+        }
+    }
+
+    pub fn feedback(&mut self, effect: FeedbackEffect) -> Result<FeedbackOutcome, String> {
+        match &mut self.backend {
+            NativeBackend::Hid(device) => device.feedback(effect),
+            NativeBackend::Generic(_) => Ok(FeedbackOutcome::Unsupported),
+        }
+    }
+}
+
+pub fn input_detect(hid_allowlist: &[HidDeviceId]) -> NativeInputDetectResult {
+    let (mut devices, rejected) = detect_hid_devices(hid_allowlist);
+    devices.extend(generic_devices(&devices));
+    NativeInputDetectResult::from_devices(devices, rejected)
+}
+
+pub fn input_list_devices(hid_allowlist: &[HidDeviceId]) -> Vec<NativeInputDevice> {
+    let mut devices = list_hid_devices(hid_allowlist);
+    devices.extend(generic_devices(&devices));
+    devices
+}
@@ -0,0 +1,221 @@
+use std::time::Instant;
+
+use serde::{Deserialize, Serialize};
+
+use super::super::{
+    analog::DirectionPriority,
+    feedback::{FeedbackEffect, FeedbackOutcome},
+    pacer::SleepTimer,
+    watchdog::HidDiagnostics,
+    FrameClock, FrameTimestamp, InputSample, InputSettings, NativeInputDevice, NativeInputMode,
+};
+use super::BackendSource;
+
+/// How the sources of a multi-mode `input_start` are combined. `Combine`
+/// ORs their buttons and takes `direction` from the first one, in the order
+/// they were requested, that is not neutral. `LastActive` reads only the
+/// source that most recently left neutral, so a player can put the pad down
+/// and pick up the stick without restarting input.
+#[derive(Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MergePolicy {
+    #[default]
+    Combine,
+    LastActive,
+}
+
+/// The backends of one `input_start`, polled together every tick and merged
+/// by the configured [`MergePolicy`].
+pub struct InputSource {
+    sources: Vec<(NativeInputMode, BackendSource)>,
+    /// Source `LastActive` reads.
+    active: usize,
+    /// Which sources were off neutral on the previous poll.
+    was_active: Vec<bool>,
+    /// `active` changed since the worker last asked.
+    active_changed: bool,
+}
+
+impl InputSource {
+    /// Opens every mode in `modes`. `device_id` pins the source whose mode it
+    /// belongs to. One source failing to open fails the whole start.
+    pub fn new(
+        modes: &[NativeInputMode],
+        device_id: Option<&str>,
+        settings: &InputSettings,
+    ) -> Result<Self, String> {
+        let mut sources = Vec::with_capacity(modes.len());
+        for &mode in modes {
+            let pinned = device_id.filter(|device_id| mode.device_key(device_id).is_ok());
+            let source = BackendSource::new(mode, pinned, settings)
+                .map_err(|error| Self::source_error(modes, mode, error))?;
+            sources.push((mode, source));
+        }
+        Ok(Self {
+            was_active: vec![false; sources.len()],
+            sources,
+            active: 0,
+            active_changed: false,
+        })
+    }
+
+    /// An `Err` from any source fails the tick, so the worker reopens them
+    /// all once it decides the device is gone. `reported_at` is the earliest
+    /// of the sources'.
+    pub fn poll(
+        &mut self,
+        now: FrameTimestamp,
+        settings: &InputSettings,
+    ) -> Result<InputSample, String> {
+        let merged = self.sources.len() > 1;
+        let mut samples = Vec::with_capacity(self.sources.len());
+        for (mode, source) in &mut self.sources {
+            let sample = source.poll(now, settings).map_err(|error| {
+                if merged {
+                    format!("Input source '{}': {error}", mode.name())
+                } else {
+                    error
+                }
+            })?;
+            samples.push(sample);
+        }
+
+        if settings.merge == MergePolicy::LastActive {
+            return Ok(self.last_active(samples));
+        }
+        let mut direction = 5;
+        let mut down_mask = 0;
+        let mut left_stick = None;
+        let mut stick_direction = 5;
+        let mut auto_priority = DirectionPriority::Dpad;
+        let mut right_stick = None;
+        let mut triggers = None;
+        let mut reported_at: Option<Instant> = None;
+        for sample in samples {
+            if direction == 5 {
+                direction = sample.direction;
+            }
+            if stick_direction == 5 {
+                stick_direction = sample.stick_direction;
+                auto_priority = sample.auto_priority;
+            }
+            down_mask |= sample.down_mask;
+            left_stick = left_stick.or(sample.left_stick);
+            right_stick = right_stick.or(sample.right_stick);
+            triggers = triggers.or(sample.triggers);
+            reported_at = reported_at.into_iter().chain(sample.reported_at).min();
+        }
+        let mut sample = InputSample::new(now, direction, down_mask);
+        sample.left_stick = left_stick;
+        sample.stick_direction = stick_direction;
+        sample.auto_priority = auto_priority;
+        sample.right_stick = right_stick;
+        sample.triggers = triggers;
+        sample.reported_at = reported_at;
+        Ok(sample)
+    }
+
+    /// Hands over to the first source that left neutral this poll, unless
+    /// the active one is still held.
+    fn last_active(&mut self, mut samples: Vec<InputSample>) -> InputSample {
+        let active: Vec<bool> = samples
+            .iter()
+            .map(|sample| sample.direction != 5 || sample.down_mask != 0)
+            .collect();
+        if !active[self.active] {
+            let woke = (0..active.len()).find(|&index| active[index] && !self.was_active[index]);
+            if let Some(index) = woke {
+                self.active = index;
+                self.active_changed = true;
+            }
+        }
+        self.was_active = active;
+        samples.swap_remove(self.active)
+    }
+
+    /// Mode of the source `LastActive` switched to since the last call.
+    pub fn take_active_change(&mut self) -> Option<NativeInputMode> {
+        std::mem::take(&mut self.active_changed).then(|| self.sources[self.active].0)
+    }
+
+    /// A lone source blocks on its own report rate; merged sources are read
+    /// together once the deadline is reached.
+    pub fn poll_until(
+        &mut self,
+        deadline: Instant,
+        timer: &SleepTimer,
+        clock: &FrameClock,
+        settings: &InputSettings,
+    ) -> Result<InputSample, String> {
+        if let [(_, source)] = self.sources.as_mut_slice() {
+            return source.poll_until(deadline, timer, clock, settings);
+        }
+        timer.sleep_until(deadline);
+        self.poll(clock.now(), settings)
+    }
+
+    /// Reports drained by every queueing source since the last call.
+    pub fn take_reports_read(&mut self) -> Option<u32> {
+        self.sources
+            .iter_mut()
+            .filter_map(|(_, source)| source.take_reports_read())
+            .reduce(u32::saturating_add)
+    }
+
+    /// Warnings every source raised since the last call.
+    pub fn take_warnings(&mut self) -> Vec<String> {
+        self.sources
+            .iter_mut()
+            .flat_map(|(_, source)| source.take_warnings())
+            .collect()
+    }
+
+    pub fn hid_diagnostics(&self) -> Option<HidDiagnostics> {
+        self.sources
+            .iter()
+            .find_map(|(_, source)| source.hid_diagnostics())
+    }
+
+    /// What each source has open, in the order the modes were requested.
+    pub fn devices(&self) -> Vec<NativeInputDevice> {
+        self.sources
+            .iter()
+            .filter_map(|(_, source)| source.device())
+            .collect()
+    }
+
+    /// Slot of the XInput source, if one of the modes is XInput.
+    pub fn xinput_user_index(&self) -> Option<u32> {
+        self.sources
+            .iter()
+            .find_map(|(_, source)| source.xinput_user_index())
+    }
+
+    /// Sends `effect` to every source that supports it.
+    pub fn feedback(&mut self, effect: FeedbackEffect) -> Result<FeedbackOutcome, String> {
+        let mut outcome = Ok(FeedbackOutcome::Unsupported);
+        for (_, source) in &mut self.sources {
+            match source.feedback(effect) {
+                Ok(FeedbackOutcome::Applied) => outcome = Ok(FeedbackOutcome::Applied),
+                Ok(FeedbackOutcome::Unsupported) => {}
+                Err(error) => {
+                    if !matches!(outcome, Ok(FeedbackOutcome::Applied)) {
+                        outcome = Err(error);
+                    }
+                }
+            }
+        }
+        outcome
+    }
+
+    fn source_error(modes: &[NativeInputMode], mode: NativeInputMode, error: String) -> String {
+        if modes.len() > 1 {
+            format!(
+                "Merged input could not open source '{}': {error}",
+                mode.name()
+            )
+        } else {
+            error
+        }
+    }
+}
@@ -0,0 +1,556 @@
+#[cfg(windows)]
+mod directinput;
+#[cfg(any(windows, target_os = "macos"))]
+mod generic;
+#[cfg(any(windows, target_os = "macos"))]
+mod hid;
+mod merged;
+mod passthrough;
+mod raw;
+#[cfg(windows)]
+mod raw_input;
+#[cfg(windows)]
+mod wgi;
+
+#[cfg(not(any(windows, target_os = "linux", target_os = "macos")))]
+mod imp {
+    use std::time::Instant;
+
+    use super::super::{
+        device_profile::HidDeviceId,
+        feedback::{FeedbackEffect, FeedbackOutcome},
+        pacer::SleepTimer,
+        watchdog::HidDiagnostics,
+        FrameClock, FrameTimestamp, InputSample, InputSettings, NativeInputDetectResult,
+        NativeInputDevice, NativeInputMode,
+    };
+
+    pub struct BackendSource;
+
+    impl BackendSource {
+        pub fn new(
+            _mode: NativeInputMode,
+            _device_id: Option<&str>,
+            _settings: &InputSettings,
+        ) -> Result<Self, String> {
+            Err("Native input is not available on this platform.".to_string())
+        }
+
+        pub fn poll(
+            &mut self,
+            now: FrameTimestamp,
+            _settings: &InputSettings,
+        ) -> Result<InputSample, String> {
+            Ok(InputSample::neutral(now))
+        }
+
+        pub fn poll_until(
+            &mut self,
+            deadline: Instant,
+            timer: &SleepTimer,
+            clock: &FrameClock,
+            settings: &InputSettings,
+        ) -> Result<InputSample, String> {
+            timer.sleep_until(deadline);
+            self.poll(clock.now(), settings)
+        }
+
+        pub fn take_reports_read(&mut self) -> Option<u32> {
+            None
+        }
+
+        pub fn xinput_user_index(&self) -> Option<u32> {
+            None
+        }
+
+        pub fn take_warnings(&mut self) -> Vec<String> {
+            Vec::new()
+        }
+
+        pub fn hid_diagnostics(&self) -> Option<HidDiagnostics> {
+            None
+        }
+
+        pub fn feedback(&mut self, _effect: FeedbackEffect) -> Result<FeedbackOutcome, String> {
+            Ok(FeedbackOutcome::Unsupported)
+        }
+    }
+
+    pub fn input_detect(_hid_allowlist: &[HidDeviceId]) -> NativeInputDetectResult {
+        NativeInputDetectResult::default()
+    }
+
+    pub fn input_list_devices(_hid_allowlist: &[HidDeviceId]) -> Vec<NativeInputDevice> {
+        Vec::new()
+    }
+}
+
+#[cfg(windows)]
+mod imp {
+    use std::time::Instant;
+
+    use windows_sys::Win32::UI::Input::KeyboardAndMouse::GetAsyncKeyState;
+    use windows_sys::Win32::UI::Input::XboxController::{
+        XInputGetBatteryInformation, XInputGetCapabilities, XInputGetState, XInputSetState,
+        BATTERY_DEVTYPE_GAMEPAD, BATTERY_LEVEL_EMPTY, BATTERY_LEVEL_LOW, BATTERY_LEVEL_MEDIUM,
+        BATTERY_TYPE_ALKALINE, BATTERY_TYPE_DISCONNECTED, BATTERY_TYPE_NIMH, BATTERY_TYPE_WIRED,
+        XINPUT_BATTERY_INFORMATION, XINPUT_CAPABILITIES, XINPUT_CAPS_FFB_SUPPORTED,
+        XINPUT_CAPS_NO_NAVIGATION, XINPUT_CAPS_PMD_SUPPORTED, XINPUT_CAPS_VOICE_SUPPORTED,
+        XINPUT_CAPS_WIRELESS, XINPUT_DEVSUBTYPE_ARCADE_PAD, XINPUT_DEVSUBTYPE_ARCADE_STICK,
+        XINPUT_DEVSUBTYPE_DANCE_PAD, XINPUT_DEVSUBTYPE_DRUM_KIT, XINPUT_DEVSUBTYPE_FLIGHT_STICK,
+        XINPUT_DEVSUBTYPE_GAMEPAD, XINPUT_DEVSUBTYPE_GUITAR, XINPUT_DEVSUBTYPE_WHEEL,
+        XINPUT_FLAG_GAMEPAD, XINPUT_STATE, XINPUT_VIBRATION, XUSER_MAX_COUNT,
+    };
+
+    use super::super::{
+        analog::{AnalogSettings, DirectionPriority},
+        decoder::{DirectionArbiter, InputDecoder, XInputDecoder, XInputGamepad},
+        device_profile::HidDeviceId,
+        feedback::{FeedbackEffect, FeedbackOutcome},
+        pacer::SleepTimer,
+        watchdog::HidDiagnostics,
+        FrameClock, FrameTimestamp, InputSample, InputSettings, NativeInputDetectResult,
+        NativeInputDevice, NativeInputMode, XInputBattery, XInputBatteryLevel, XInputBatteryType,
+        XInputControllerInfo,
+    };
+    use super::directinput::{directinput_devices, DirectInputSource};
+    use super::generic::{generic_devices, GenericGamepadSource};
+    use super::hid::{detect_hid_devices, list_hid_devices, Ps4HidNativeSource};
+    use super::wgi::{wgi_devices, WgiGamepadSource};
+
+    const ERROR_DEVICE_NOT_CONNECTED: u32 = 1167;
+
+    pub struct BackendSource {
+        backend: NativeBackend,
+    }
+
+    enum NativeBackend {
+        XInput(XInputPrimarySource),
+        Hid(Box<Ps4HidNativeSource>),
+        Keyboard(KeyboardSource),
+        DirectInput(DirectInputSource),
+        Generic(Box<GenericGamepadSource>),
+        Wgi(WgiGamepadSource),
+    }
+
+    struct XInputPrimarySource {
+        preferred_user_index: u32,
+        pinned: bool,
+        arbiter: DirectionArbiter,
+    }
+
+    struct KeyboardSource;
+
+    impl BackendSource {
+        pub fn new(
+            mode: NativeInputMode,
+            device_id: Option<&str>,
+            settings: &InputSettings,
+        ) -> Result<Self, String> {
+            let device_key = device_id.map(|id| mode.device_key(id)).transpose()?;
+            let backend = match mode {
+                NativeInputMode::XInput => {
+                    let user_index = device_key.map(parse_xinput_user_index).transpose()?;
+                    NativeBackend::XInput(XInputPrimarySource::new(user_index)?)
+                }
+                NativeInputMode::Hid => {
+                    let source = Ps4HidNativeSource::new(device_key, settings)
+                        .map_err(|error| {
+                        format!(
+                            "Native input mode 'hid' could not open a supported PS4 HID device: {error}"
+                        )
+                    })?;
+                    NativeBackend::Hid(Box::new(source))
+                }
+                NativeInputMode::Keyboard => NativeBackend::Keyboard(KeyboardSource),
+                NativeInputMode::DirectInput => {
+                    NativeBackend::DirectInput(DirectInputSource::new(device_key)?)
+                }
+                NativeInputMode::Generic => {
+                    NativeBackend::Generic(Box::new(GenericGamepadSource::new(device_key)?))
+                }
+                NativeInputMode::Wgi => NativeBackend::Wgi(WgiGamepadSource::new(device_key)?),
+                NativeInputMode::Evdev => {
+                    return Err(format!(
+                        "Native input mode '{}' is available only on Linux builds.",
+                        mode.name()
+                    ))
+                }
+                NativeInputMode::Auto => {
+                    return Err("Native input mode 'auto' opens no source itself.".to_string())
+                }
+            };
+
+            Ok(Self { backend })
+        }
+
+        /// An `Err` means the device could not be read this tick; the worker
+        /// decides when repeated failures amount to a disconnect.
+        pub fn poll(
+            &mut self,
+            now: FrameTimestamp,
+            settings: &InputSettings,
+        ) -> Result<InputSample, String> {
+            match &mut self.backend {
+                NativeBackend::XInput(source) => source.poll(now, settings.analog),
+                NativeBackend::Hid(source) => source.poll(now, settings),
+                NativeBackend::Keyboard(source) => Ok(source.poll(now, settings)),
+                NativeBackend::DirectInput(source) => source.poll(now, settings.analog),
+                NativeBackend::Generic(source) => source.poll(now, settings.analog),
+                NativeBackend::Wgi(source) => source.poll(now, settings.analog),
+            }
+        }
+
+        /// A sub-frame poll taken by `deadline`. HID blocks on the device's
+        /// own report rate; the other APIs only return the current state, so
+        /// they are slept up to the deadline and read once.
+        pub fn poll_until(
+            &mut self,
+            deadline: Instant,
+            timer: &SleepTimer,
+            clock: &FrameClock,
+            settings: &InputSettings,
+        ) -> Result<InputSample, String> {
+            if let NativeBackend::Hid(source) = &mut self.backend {
+                return source.poll_until(deadline, clock, settings);
+            }
+            timer.sleep_until(deadline);
+            self.poll(clock.now(), settings)
+        }
+
+        /// Reports drained since the last call; `None` for the APIs that only
+        /// return the current state.
+        pub fn take_reports_read(&mut self) -> Option<u32> {
+            match &mut self.backend {
+                NativeBackend::Hid(source) => Some(source.take_reports_read()),
+                _ => None,
+            }
+        }
+
+        /// Slot an XInput source read last; `None` for the other backends.
+        pub fn xinput_user_index(&self) -> Option<u32> {
+            match &self.backend {
+                NativeBackend::XInput(source) => Some(source.preferred_user_index),
+                _ => None,
+            }
+        }
+
+        /// What the source has open; `None` for the keyboard.
+        pub fn device(&self) -> Option<NativeInputDevice> {
+            match &self.backend {
+                NativeBackend::XInput(source) => Some(xinput_device(source.preferred_user_index)),
+                NativeBackend::Hid(source) => Some(source.device()),
+                NativeBackend::Keyboard(_) => None,
+                NativeBackend::DirectInput(source) => Some(source.device()),
+                NativeBackend::Generic(source) => source.device(),
+                NativeBackend::Wgi(source) => Some(source.device()),
+            }
+        }
+
+        /// Stale-state warnings raised since the last call.
+        pub fn take_warnings(&mut self) -> Vec<String> {
+            match &mut self.backend {
+                NativeBackend::Hid(source) => source.take_warnings(),
+                _ => Vec::new(),
+            }
+        }
+
+        pub fn hid_diagnostics(&self) -> Option<HidDiagnostics> {
+            match &self.backend {
+                NativeBackend::Hid(source) => Some(source.diagnostics()),
+                _ => None,
+            }
+        }
+
+        pub fn feedback(&mut self, effect: FeedbackEffect) -> Result<FeedbackOutcome, String> {
+            match &mut self.backend {
+                NativeBackend::XInput(source) => source.feedback(effect),
+                NativeBackend::Hid(source) => source.feedback(effect),
+                NativeBackend::Keyboard(_)
+                | NativeBackend::DirectInput(_)
+                | NativeBackend::Generic(_)
+                | NativeBackend::Wgi(_) => Ok(FeedbackOutcome::Unsupported),
+            }
+        }
+    }
+
+    impl XInputPrimarySource {
+        /// A pinned source only ever reads `user_index`; otherwise it prefers
+        /// the last slot that answered and falls back to scanning 0-3.
+        fn new(user_index: Option<u32>) -> Result<Self, String> {
+            match user_index {
+                Some(user_index) if is_xinput_connected(user_index) => Ok(Self {
+                    preferred_user_index: user_index,
+                    pinned: true,
+                    arbiter: DirectionArbiter::default(),
+                }),
+                Some(user_index) => Err(format!(
+                    "XInput controller in slot {user_index} is not connected."
+                )),
+                None => (0..XUSER_MAX_COUNT)
+                    .find(|user_index| is_xinput_connected(*user_index))
+                    .map(|preferred_user_index| Self {
+                        preferred_user_index,
+                        pinned: false,
+                        arbiter: DirectionArbiter::default(),
+                    })
+                    .ok_or_else(|| {
+                        "Native input mode 'xinput' did not detect a connected controller."
+                            .to_string()
+                    }),
+            }
+        }
+
+        fn poll(
+            &mut self,
+            now: FrameTimestamp,
+            analog: AnalogSettings,
+        ) -> Result<InputSample, String> {
+            let mut visited = [false; XUSER_MAX_COUNT as usize];
+            let order = [self.preferred_user_index, 0, 1, 2, 3];
+            let order = if self.pinned { &order[..1] } else { &order[..] };
+
+            for &user_index in order {
+                if user_index >= XUSER_MAX_COUNT {
+                    continue;
+                }
+
+                let slot = user_index as usize;
+                if visited[slot] {
+                    continue;
+                }
+                visited[slot] = true;
+
+                let mut state = XINPUT_STATE::default();
+                let ret = unsafe { XInputGetState(user_index, &mut state) };
+
+                if ret == 0 {
+                    self.preferred_user_index = user_index;
+                    return Ok(sample_from_xinput_state(
+                        &state,
+                        now,
+                        analog,
+                        &mut self.arbiter,
+                    ));
+                }
+
+                if ret == ERROR_DEVICE_NOT_CONNECTED {
+                    continue;
+                }
+
+                return Err(format!(
+                    "XInputGetState failed user={} ret={} (0x{:08X})",
+                    user_index, ret, ret
+                ));
+            }
+
+            Err("No XInput controller is connected.".to_string())
+        }
+
+        /// Rumbles the slot that last answered. XInput has no lightbar.
+        fn feedback(&mut self, effect: FeedbackEffect) -> Result<FeedbackOutcome, String> {
+            let FeedbackEffect::Rumble { intensity, .. } = effect else {
+                return Ok(FeedbackOutcome::Unsupported);
+            };
+
+            let speed = (intensity * f64::from(u16::MAX)).round() as u16;
+            let vibration = XINPUT_VIBRATION {
+                wLeftMotorSpeed: speed,
+                wRightMotorSpeed: speed,
+            };
+            let ret = unsafe { XInputSetState(self.preferred_user_index, &vibration) };
+            if ret != 0 {
+                return Err(format!(
+                    "XInputSetState failed user={} ret={}",
+                    self.preferred_user_index, ret
+                ));
+            }
+            Ok(FeedbackOutcome::Applied)
+        }
+    }
+
+    impl KeyboardSource {
+        fn poll(&mut self, now: FrameTimestamp, settings: &InputSettings) -> InputSample {
+            let (direction, down_mask) = settings
+                .keyboard
+                .resolve(|key| unsafe { GetAsyncKeyState(i32::from(key)) } as u16 & 0x8000 != 0);
+            InputSample::new(now, direction, down_mask)
+        }
+    }
+
+    pub fn input_detect(hid_allowlist: &[HidDeviceId]) -> NativeInputDetectResult {
+        let (hid_devices, rejected) = detect_hid_devices(hid_allowlist);
+        let mut devices = xinput_devices();
+        devices.extend(hid_devices);
+        devices.extend(directinput_devices());
+        devices.extend(generic_devices(&devices));
+        devices.extend(wgi_devices());
+
+        let mut result = NativeInputDetectResult::from_devices(devices, rejected);
+        result.keyboard = true;
+        result
+    }
+
+    /// Battery and capabilities of XInput slot `user_index`; an empty slot is
+    /// `connected: false` rather than an error.
+    pub fn xinput_controller_info(user_index: u32) -> Result<XInputControllerInfo, String> {
+        if user_index >= XUSER_MAX_COUNT {
+            return Err(format!("Invalid XInput user index '{user_index}'."));
+        }
+        let mut capabilities = XINPUT_CAPABILITIES::default();
+        let ret =
+            unsafe { XInputGetCapabilities(user_index, XINPUT_FLAG_GAMEPAD, &mut capabilities) };
+        if ret == ERROR_DEVICE_NOT_CONNECTED {
+            return Ok(XInputControllerInfo {
+                user_index,
+                ..XInputControllerInfo::default()
+            });
+        }
+        if ret != 0 {
+            return Err(format!(
+                "XInputGetCapabilities failed user={user_index} ret={ret}"
+            ));
+        }
+
+        // Battery reporting needs XInput 1.3 or later; without it the rest
+        // of the answer still stands.
+        let mut battery = XINPUT_BATTERY_INFORMATION::default();
+        let ret = unsafe {
+            XInputGetBatteryInformation(user_index, BATTERY_DEVTYPE_GAMEPAD, &mut battery)
+        };
+        let battery = (ret == 0).then_some(XInputBattery {
+            battery_type: match battery.BatteryType {
+                BATTERY_TYPE_DISCONNECTED => XInputBatteryType::Disconnected,
+                BATTERY_TYPE_WIRED => XInputBatteryType::Wired,
+                BATTERY_TYPE_ALKALINE => XInputBatteryType::Alkaline,
+                BATTERY_TYPE_NIMH => XInputBatteryType::Nimh,
+                _ => XInputBatteryType::Unknown,
+            },
+            level: match battery.BatteryLevel {
+                BATTERY_LEVEL_EMPTY => XInputBatteryLevel::Empty,
+                BATTERY_LEVEL_LOW => XInputBatteryLevel::Low,
+                BATTERY_LEVEL_MEDIUM => XInputBatteryLevel::Medium,
+                _ => XInputBatteryLevel::Full,
+            },
+        });
+
+        let flags = capabilities.Flags;
+        Ok(XInputControllerInfo {
+            user_index,
+            connected: true,
+            battery,
+            subtype: match capabilities.SubType {
+                XINPUT_DEVSUBTYPE_GAMEPAD => Some("gamepad"),
+                XINPUT_DEVSUBTYPE_WHEEL => Some("wheel"),
+                XINPUT_DEVSUBTYPE_ARCADE_STICK => Some("arcade_stick"),
+                XINPUT_DEVSUBTYPE_FLIGHT_STICK => Some("flight_stick"),
+                XINPUT_DEVSUBTYPE_DANCE_PAD => Some("dance_pad"),
+                XINPUT_DEVSUBTYPE_GUITAR => Some("guitar"),
+                XINPUT_DEVSUBTYPE_DRUM_KIT => Some("drum_kit"),
+                XINPUT_DEVSUBTYPE_ARCADE_PAD => Some("arcade_pad"),
+                _ => None,
+            },
+            wireless: flags & XINPUT_CAPS_WIRELESS != 0,
+            force_feedback: flags & XINPUT_CAPS_FFB_SUPPORTED != 0,
+            voice: flags & XINPUT_CAPS_VOICE_SUPPORTED != 0,
+            plugin_modules: flags & XINPUT_CAPS_PMD_SUPPORTED != 0,
+            navigation: flags & XINPUT_CAPS_NO_NAVIGATION == 0,
+        })
+    }
+
+    pub fn input_list_devices(hid_allowlist: &[HidDeviceId]) -> Vec<NativeInputDevice> {
+        let mut devices = xinput_devices();
+        devices.extend(list_hid_devices(hid_allowlist));
+        devices.extend(directinput_devices());
+        devices.extend(generic_devices(&devices));
+        devices.extend(wgi_devices());
+        devices
+    }
+
+    fn xinput_devices() -> Vec<NativeInputDevice> {
+        (0..XUSER_MAX_COUNT)
+            .filter(|index| is_xinput_connected(*index))
+            .map(xinput_device)
+            .collect()
+    }
+
+    fn xinput_device(user_index: u32) -> NativeInputDevice {
+        let mut device = NativeInputDevice::new(NativeInputMode::XInput, &user_index.to_string());
+        device.product = Some(format!("XInput controller {}", user_index + 1));
+        device.xinput_user_index = Some(user_index);
+        device.openable = Some(true);
+        device
+    }
+
+    fn parse_xinput_user_index(key: &str) -> Result<u32, String> {
+        key.parse::<u32>()
+            .ok()
+            .filter(|user_index| *user_index < XUSER_MAX_COUNT)
+            .ok_or_else(|| format!("Invalid XInput user index '{key}'."))
+    }
+
+    fn is_xinput_connected(user_index: u32) -> bool {
+        let mut state = XINPUT_STATE::default();
+        unsafe { XInputGetState(user_index, &mut state) == 0 }
+    }
+
+    fn sample_from_xinput_state(
+        state: &XINPUT_STATE,
+        now: FrameTimestamp,
+        analog: AnalogSettings,
+        arbiter: &mut DirectionArbiter,
+    ) -> InputSample {
+        let gamepad = state.Gamepad;
+        let gamepad = XInputGamepad {
+            buttons: gamepad.wButtons,
+            left_trigger: gamepad.bLeftTrigger,
+            right_trigger: gamepad.bRightTrigger,
+            thumb_lx: gamepad.sThumbLX,
+            thumb_ly: gamepad.sThumbLY,
+            thumb_rx: gamepad.sThumbRX,
+            thumb_ry: gamepad.sThumbRY,
+        };
+        sample_from_xinput_gamepad(&gamepad, now, analog, arbiter)
+    }
+
+    /// Also decodes Windows.Gaming.Input readings, which arrive converted to
+    /// the XInput layout.
+    pub(super) fn sample_from_xinput_gamepad(
+        gamepad: &XInputGamepad,
+        now: FrameTimestamp,
+        analog: AnalogSettings,
+        arbiter: &mut DirectionArbiter,
+    ) -> InputSample {
+        let decoded = XInputDecoder(arbiter)
+            .decode(gamepad, analog)
+            .unwrap_or_default();
+        let mut sample = InputSample::new(now, decoded.direction, decoded.down_mask);
+        sample.left_stick = decoded.left_stick;
+        sample.stick_direction = decoded.stick_direction;
+        sample.auto_priority = DirectionPriority::Combined;
+        sample.right_stick = decoded.right_stick;
+        sample.triggers = decoded.triggers;
+        sample
+    }
+}
+
+#[cfg(target_os = "linux")]
+#[path = "linux.rs"]
+mod imp;
+
+#[cfg(target_os = "macos")]
+#[path = "macos.rs"]
+mod imp;
+
+#[cfg(windows)]
+pub use imp::xinput_controller_info;
+use imp::BackendSource;
+pub use imp::{input_detect, input_list_devices};
+pub use merged::{InputSource, MergePolicy};
+pub use passthrough::VirtualPad;
+pub use raw::RawHidReader;
+
+#[cfg(not(windows))]
+pub fn xinput_controller_info(_user_index: u32) -> Result<super::XInputControllerInfo, String> {
+    Err("XInput is available only on Windows builds.".to_string())
+}
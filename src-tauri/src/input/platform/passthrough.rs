@@ -0,0 +1,139 @@
+//! Virtual Xbox 360 pad fed with the worker's cleaned, remapped state, so the
+//! game can read this app instead of the physical controller.
+
+#[cfg(not(windows))]
+mod imp {
+    pub struct VirtualPad;
+
+    impl VirtualPad {
+        pub fn new() -> Result<Self, String> {
+            Err("Controller passthrough is available only on Windows builds.".to_string())
+        }
+
+        pub fn update(
+            &mut self,
+            _direction: u8,
+            _sf6_mask: u16,
+            _physical_mask: u32,
+        ) -> Result<(), String> {
+            Ok(())
+        }
+    }
+}
+
+#[cfg(windows)]
+mod imp {
+    use vigem_client::{Client, TargetId, XButtons, XGamepad, Xbox360Wired};
+    use windows_sys::Win32::UI::Input::XboxController::{
+        XINPUT_GAMEPAD_A, XINPUT_GAMEPAD_B, XINPUT_GAMEPAD_BACK, XINPUT_GAMEPAD_DPAD_DOWN,
+        XINPUT_GAMEPAD_DPAD_LEFT, XINPUT_GAMEPAD_DPAD_RIGHT, XINPUT_GAMEPAD_DPAD_UP,
+        XINPUT_GAMEPAD_LEFT_SHOULDER, XINPUT_GAMEPAD_RIGHT_SHOULDER, XINPUT_GAMEPAD_START,
+        XINPUT_GAMEPAD_X, XINPUT_GAMEPAD_Y,
+    };
+
+    use super::super::super::{
+        direction_axes, BUTTON_DI_MASK, BUTTON_HK_MASK, BUTTON_HP_MASK, BUTTON_LK_MASK,
+        BUTTON_LP_MASK, BUTTON_MK_MASK, BUTTON_MP_MASK, BUTTON_PARRY_MASK, BUTTON_SELECT_MASK,
+        BUTTON_START_MASK,
+    };
+
+    /// SF6 buttons onto the game's default pad layout, so the virtual pad
+    /// needs no rebinding in-game. HK and Parry sit on the triggers.
+    const SF6_TO_XINPUT_BUTTONS: [(u16, u16); 6] = [
+        (BUTTON_LP_MASK, XINPUT_GAMEPAD_X),
+        (BUTTON_MP_MASK, XINPUT_GAMEPAD_Y),
+        (BUTTON_HP_MASK, XINPUT_GAMEPAD_RIGHT_SHOULDER),
+        (BUTTON_LK_MASK, XINPUT_GAMEPAD_A),
+        (BUTTON_MK_MASK, XINPUT_GAMEPAD_B),
+        (BUTTON_DI_MASK, XINPUT_GAMEPAD_LEFT_SHOULDER),
+    ];
+    /// Menu buttons have no SF6 binding and are forwarded as pressed.
+    const PHYSICAL_TO_XINPUT_BUTTONS: [(u32, u16); 2] = [
+        (BUTTON_START_MASK, XINPUT_GAMEPAD_START),
+        (BUTTON_SELECT_MASK, XINPUT_GAMEPAD_BACK),
+    ];
+
+    pub struct VirtualPad {
+        target: Xbox360Wired<Client>,
+    }
+
+    impl VirtualPad {
+        /// Plugs in a virtual Xbox 360 pad. It stays attached until dropped.
+        pub fn new() -> Result<Self, String> {
+            let client = Client::connect().map_err(|error| match error {
+                vigem_client::Error::BusNotFound => {
+                    "Controller passthrough needs the ViGEmBus driver, which is not installed."
+                        .to_string()
+                }
+                error => format!("Failed to connect to ViGEmBus: {error}"),
+            })?;
+
+            let mut target = Xbox360Wired::new(client, TargetId::XBOX360_WIRED);
+            target
+                .plugin()
+                .map_err(|error| format!("Failed to plug in the virtual controller: {error}"))?;
+            target
+                .wait_ready()
+                .map_err(|error| format!("Virtual controller did not become ready: {error}"))?;
+
+            Ok(Self { target })
+        }
+
+        /// Submits one frame: numpad `direction` as both dpad bits and a fully
+        /// deflected left stick, SF6 buttons per the default layout.
+        pub fn update(
+            &mut self,
+            direction: u8,
+            sf6_mask: u16,
+            physical_mask: u32,
+        ) -> Result<(), String> {
+            let mut buttons = 0u16;
+            for (sf6, xinput) in SF6_TO_XINPUT_BUTTONS {
+                if sf6_mask & sf6 != 0 {
+                    buttons |= xinput;
+                }
+            }
+            for (physical, xinput) in PHYSICAL_TO_XINPUT_BUTTONS {
+                if physical_mask & physical != 0 {
+                    buttons |= xinput;
+                }
+            }
+
+            let (horizontal, vertical) = direction_axes(direction);
+            if horizontal > 0 {
+                buttons |= XINPUT_GAMEPAD_DPAD_RIGHT;
+            }
+            if horizontal < 0 {
+                buttons |= XINPUT_GAMEPAD_DPAD_LEFT;
+            }
+            if vertical > 0 {
+                buttons |= XINPUT_GAMEPAD_DPAD_UP;
+            }
+            if vertical < 0 {
+                buttons |= XINPUT_GAMEPAD_DPAD_DOWN;
+            }
+
+            let trigger = |mask: u16| if sf6_mask & mask != 0 { u8::MAX } else { 0 };
+            let gamepad = XGamepad {
+                buttons: XButtons { raw: buttons },
+                left_trigger: trigger(BUTTON_PARRY_MASK),
+                right_trigger: trigger(BUTTON_HK_MASK),
+                thumb_lx: (horizontal * i32::from(i16::MAX)) as i16,
+                thumb_ly: (vertical * i32::from(i16::MAX)) as i16,
+                ..XGamepad::default()
+            };
+
+            self.target
+                .update(&gamepad)
+                .map_err(|error| format!("Failed to update the virtual controller: {error}"))
+        }
+    }
+
+    impl Drop for VirtualPad {
+        fn drop(&mut self) {
+            let _ = self.target.unplug();
+        }
+    }
+}
+
+pub use imp::VirtualPad;
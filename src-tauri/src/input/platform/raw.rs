@@ -0,0 +1,93 @@
+//! Unfiltered HID report reader behind `input_start_raw`, for capturing what
+//! an unsupported controller actually sends so a decoder can be written.
+
+use serde::Serialize;
+
+/// Identity of the device being captured, sent once on `input/raw_info`.
+#[derive(Clone, Default, Serialize)]
+pub struct RawHidInfo {
+    path: String,
+    vendor_id: Option<u16>,
+    product_id: Option<u16>,
+    manufacturer: Option<String>,
+    product: Option<String>,
+}
+
+#[cfg(not(any(windows, target_os = "macos")))]
+mod imp {
+    use super::RawHidInfo;
+
+    pub struct RawHidReader;
+
+    impl RawHidReader {
+        pub fn open(_path: &str) -> Result<(Self, RawHidInfo), String> {
+            Err("Raw HID capture is available only on Windows and macOS builds.".to_string())
+        }
+
+        pub fn read(&mut self, _timeout_ms: i32) -> Result<Option<&[u8]>, String> {
+            Ok(None)
+        }
+    }
+}
+
+#[cfg(any(windows, target_os = "macos"))]
+mod imp {
+    use std::ffi::CString;
+
+    use hidapi::{HidApi, HidDevice};
+
+    use super::RawHidInfo;
+
+    /// Larger than any report a gamepad is expected to send.
+    const RAW_REPORT_BUFFER_SIZE: usize = 512;
+
+    pub struct RawHidReader {
+        device: HidDevice,
+        buffer: [u8; RAW_REPORT_BUFFER_SIZE],
+    }
+
+    impl RawHidReader {
+        /// Opens the device at `path` whatever it claims to be.
+        pub fn open(path: &str) -> Result<(Self, RawHidInfo), String> {
+            let api = HidApi::new().map_err(|error| format!("hidapi init error: {error}"))?;
+            let c_path =
+                CString::new(path).map_err(|_| format!("Invalid HID device path '{path}'."))?;
+            let device = api
+                .open_path(&c_path)
+                .map_err(|error| format!("Failed to open HID device '{path}': {error}"))?;
+
+            let mut info = RawHidInfo {
+                path: path.to_string(),
+                ..RawHidInfo::default()
+            };
+            if let Some(device_info) = api
+                .device_list()
+                .find(|device_info| device_info.path() == c_path.as_c_str())
+            {
+                info.vendor_id = Some(device_info.vendor_id());
+                info.product_id = Some(device_info.product_id());
+                info.manufacturer = device_info.manufacturer_string().map(str::to_string);
+                info.product = device_info.product_string().map(str::to_string);
+            }
+
+            Ok((
+                Self {
+                    device,
+                    buffer: [0; RAW_REPORT_BUFFER_SIZE],
+                },
+                info,
+            ))
+        }
+
+        /// The next report, or `None` if none arrived within `timeout_ms`.
+        pub fn read(&mut self, timeout_ms: i32) -> Result<Option<&[u8]>, String> {
+            let read_size = self
+                .device
+                .read_timeout(&mut self.buffer, timeout_ms)
+                .map_err(|error| format!("hidapi read error: {error}"))?;
+            Ok((read_size > 0).then(|| &self.buffer[..read_size]))
+        }
+    }
+}
+
+pub use imp::RawHidReader;
@@ -0,0 +1,323 @@
+//! HID input reports through Raw Input. One hidden message-only window is
+//! registered for every joystick and gamepad, and its thread stamps each
+//! `WM_INPUT` report the moment the message arrives and hands it to the HID
+//! source reading that device, so no read sits in a timeout in between.
+//! `RIDEV_INPUTSINK` keeps the reports coming while SF6 has focus.
+
+use std::collections::{hash_map::Entry, HashMap};
+use std::mem::{offset_of, size_of};
+use std::sync::{
+    atomic::{AtomicU64, Ordering},
+    mpsc::{self, Sender},
+    Mutex, OnceLock,
+};
+use std::thread;
+use std::time::Instant;
+use std::{ptr, slice};
+
+use windows_sys::Win32::Foundation::{HANDLE, HWND};
+use windows_sys::Win32::System::LibraryLoader::GetModuleHandleW;
+use windows_sys::Win32::UI::Input::{
+    GetRawInputData, GetRawInputDeviceInfoW, RegisterRawInputDevices, HRAWINPUT, RAWHID, RAWINPUT,
+    RAWINPUTDEVICE, RAWINPUTHEADER, RIDEV_DEVNOTIFY, RIDEV_INPUTSINK, RIDI_DEVICENAME, RID_INPUT,
+    RIM_TYPEHID,
+};
+use windows_sys::Win32::UI::WindowsAndMessaging::{
+    CreateWindowExW, DispatchMessageW, GetMessageW, GIDC_ARRIVAL, GIDC_REMOVAL, HWND_MESSAGE, MSG,
+    WM_INPUT, WM_INPUT_DEVICE_CHANGE,
+};
+
+use super::hid::{ReaderMessage, TimedReport, HID_REPORT_BUFFER_SIZE};
+
+const USAGE_PAGE_GENERIC_DESKTOP: u16 = 0x01;
+const USAGE_JOYSTICK: u16 = 0x04;
+const USAGE_GAMEPAD: u16 = 0x05;
+
+/// Sources waiting on reports, each with the device path it reads.
+static SUBSCRIBERS: Mutex<Vec<Subscriber>> = Mutex::new(Vec::new());
+static NEXT_SUBSCRIBER: AtomicU64 = AtomicU64::new(0);
+/// Whether the window thread came up; it is started once per process and
+/// runs until exit.
+static WINDOW: OnceLock<Result<(), String>> = OnceLock::new();
+
+struct Subscriber {
+    id: u64,
+    path: String,
+    reports: Sender<ReaderMessage>,
+}
+
+/// Stops the delivery of a device's reports when dropped.
+pub(super) struct Subscription {
+    id: u64,
+}
+
+impl Drop for Subscription {
+    fn drop(&mut self) {
+        if let Ok(mut subscribers) = SUBSCRIBERS.lock() {
+            subscribers.retain(|subscriber| subscriber.id != self.id);
+        }
+    }
+}
+
+/// Sends every input report of the HID device at `path`, as hidapi lists
+/// it, to `reports`, and its removal as an error. Fails when the window
+/// thread could not be set up, in which case reading falls back to hidapi.
+pub(super) fn subscribe(
+    path: &str,
+    reports: Sender<ReaderMessage>,
+) -> Result<Subscription, String> {
+    WINDOW
+        .get_or_init(|| {
+            let (ready, started) = mpsc::channel();
+            thread::spawn(move || run_window(ready));
+            started
+                .recv()
+                .unwrap_or_else(|_| Err("The Raw Input thread stopped.".to_string()))
+        })
+        .clone()?;
+
+    let id = NEXT_SUBSCRIBER.fetch_add(1, Ordering::Relaxed);
+    let mut subscribers = SUBSCRIBERS
+        .lock()
+        .map_err(|_| "The Raw Input subscriber list is poisoned.".to_string())?;
+    subscribers.push(Subscriber {
+        id,
+        path: path_key(path),
+        reports,
+    });
+    Ok(Subscription { id })
+}
+
+/// Window thread body: creates the window, reports whether that worked,
+/// then pumps its messages for the rest of the process.
+fn run_window(ready: Sender<Result<(), String>>) {
+    if let Err(error) = create_window() {
+        let _ = ready.send(Err(error));
+        return;
+    }
+    let _ = ready.send(Ok(()));
+
+    // Raw Input names each device by a handle that stays the same while it
+    // is plugged in.
+    let mut paths: HashMap<HANDLE, String> = HashMap::new();
+    let mut buffer = Vec::new();
+    let mut message = MSG::default();
+    while unsafe { GetMessageW(&mut message, ptr::null_mut(), 0, 0) } > 0 {
+        let at = Instant::now();
+        match message.message {
+            WM_INPUT => deliver(message.lParam as HRAWINPUT, at, &mut buffer, &mut paths),
+            WM_INPUT_DEVICE_CHANGE => {
+                let device = message.lParam as HANDLE;
+                match message.wParam as u32 {
+                    GIDC_ARRIVAL => {
+                        if let Some(path) = device_path(device) {
+                            paths.insert(device, path);
+                        }
+                    }
+                    GIDC_REMOVAL => {
+                        if let Some(path) = paths.remove(&device) {
+                            send(&path, || {
+                                ReaderMessage::Error(format!(
+                                    "HID device '{path}' was disconnected."
+                                ))
+                            });
+                        }
+                    }
+                    _ => {}
+                }
+            }
+            _ => {}
+        }
+        // Lets `DefWindowProcW` release the input once it is read.
+        unsafe { DispatchMessageW(&message) };
+    }
+}
+
+/// A message-only window of the predefined `STATIC` class, which needs no
+/// window procedure of its own, registered for joystick and gamepad input.
+fn create_window() -> Result<HWND, String> {
+    let class: Vec<u16> = "STATIC\0".encode_utf16().collect();
+    let window = unsafe {
+        CreateWindowExW(
+            0,
+            class.as_ptr(),
+            ptr::null(),
+            0,
+            0,
+            0,
+            0,
+            0,
+            HWND_MESSAGE,
+            ptr::null_mut(),
+            GetModuleHandleW(ptr::null()),
+            ptr::null(),
+        )
+    };
+    if window.is_null() {
+        return Err(format!(
+            "Failed to create the Raw Input window: {}",
+            std::io::Error::last_os_error()
+        ));
+    }
+
+    let devices = [USAGE_JOYSTICK, USAGE_GAMEPAD].map(|usage| RAWINPUTDEVICE {
+        usUsagePage: USAGE_PAGE_GENERIC_DESKTOP,
+        usUsage: usage,
+        dwFlags: RIDEV_INPUTSINK | RIDEV_DEVNOTIFY,
+        hwndTarget: window,
+    });
+    let registered = unsafe {
+        RegisterRawInputDevices(
+            devices.as_ptr(),
+            devices.len() as u32,
+            size_of::<RAWINPUTDEVICE>() as u32,
+        )
+    };
+    if registered == 0 {
+        return Err(format!(
+            "Failed to register for Raw Input: {}",
+            std::io::Error::last_os_error()
+        ));
+    }
+    Ok(window)
+}
+
+/// Reads the `WM_INPUT` behind `input` and sends each HID report in it to
+/// the sources reading that device, stamped `at`. `buffer` is kept between
+/// messages; it holds `u64`s so the `RAWINPUT` in it is aligned.
+fn deliver(
+    input: HRAWINPUT,
+    at: Instant,
+    buffer: &mut Vec<u64>,
+    paths: &mut HashMap<HANDLE, String>,
+) {
+    let header_size = size_of::<RAWINPUTHEADER>() as u32;
+    let mut size = 0u32;
+    unsafe { GetRawInputData(input, RID_INPUT, ptr::null_mut(), &mut size, header_size) };
+    let capacity = (size as usize).max(size_of::<RAWINPUT>());
+    buffer.resize(capacity.div_ceil(size_of::<u64>()), 0);
+    let read = unsafe {
+        GetRawInputData(
+            input,
+            RID_INPUT,
+            buffer.as_mut_ptr().cast(),
+            &mut size,
+            header_size,
+        )
+    };
+    let data_offset = offset_of!(RAWINPUT, data) + offset_of!(RAWHID, bRawData);
+    if read == u32::MAX || (read as usize) < data_offset {
+        return;
+    }
+
+    let input = unsafe { &*buffer.as_ptr().cast::<RAWINPUT>() };
+    if input.header.dwType != RIM_TYPEHID {
+        return;
+    }
+    let hid = unsafe { input.data.hid };
+    let bytes = unsafe { slice::from_raw_parts(buffer.as_ptr().cast::<u8>(), read as usize) };
+    let len = hid.dwSizeHid as usize * hid.dwCount as usize;
+    let Some(data) = bytes.get(data_offset..data_offset + len) else {
+        return;
+    };
+
+    let device = input.header.hDevice;
+    let path = match paths.entry(device) {
+        Entry::Occupied(entry) => entry.into_mut(),
+        Entry::Vacant(entry) => match device_path(device) {
+            Some(path) => entry.insert(path),
+            None => return,
+        },
+    };
+    for report in reports(data, hid.dwSizeHid as usize, at) {
+        send(path, || ReaderMessage::Report(report.clone()));
+    }
+}
+
+/// Sends a message made by `message` to every source reading `path`.
+fn send(path: &str, message: impl Fn() -> ReaderMessage) {
+    if let Ok(subscribers) = SUBSCRIBERS.lock() {
+        for subscriber in subscribers
+            .iter()
+            .filter(|subscriber| subscriber.path == path)
+        {
+            let _ = subscriber.reports.send(message());
+        }
+    }
+}
+
+/// The reports packed into one `WM_INPUT`, `report_size` bytes each. A
+/// leading report ID of 0 means the device uses none and is dropped, as
+/// hidapi reads drop it, so the decoders see the same bytes either way.
+fn reports(data: &[u8], report_size: usize, at: Instant) -> impl Iterator<Item = TimedReport> + '_ {
+    data.chunks_exact(report_size.max(1)).map(move |report| {
+        let report = report.strip_prefix(&[0]).unwrap_or(report);
+        let len = report.len().min(HID_REPORT_BUFFER_SIZE);
+        let mut data = [0u8; HID_REPORT_BUFFER_SIZE];
+        data[..len].copy_from_slice(&report[..len]);
+        TimedReport { data, len, at }
+    })
+}
+
+/// The device interface path of `device`, in the form [`path_key`] compares.
+fn device_path(device: HANDLE) -> Option<String> {
+    let mut len = 0u32;
+    unsafe { GetRawInputDeviceInfoW(device, RIDI_DEVICENAME, ptr::null_mut(), &mut len) };
+    let mut name = vec![0u16; len as usize];
+    let read = unsafe {
+        GetRawInputDeviceInfoW(device, RIDI_DEVICENAME, name.as_mut_ptr().cast(), &mut len)
+    };
+    if read == u32::MAX || read == 0 {
+        return None;
+    }
+    name.truncate(read as usize);
+    let name = String::from_utf16_lossy(&name);
+    Some(path_key(name.trim_end_matches('\0')))
+}
+
+/// Raw Input and hidapi name a device by the same interface path, but not
+/// always in the same case, and older Windows versions start Raw Input's
+/// with `\??\` rather than `\\?\`.
+fn path_key(path: &str) -> String {
+    let path = path.to_lowercase();
+    match path.strip_prefix(r"\??\") {
+        Some(rest) => format!(r"\\?\{rest}"),
+        None => path,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn packed_reports_split_and_drop_a_zero_report_id() {
+        let at = Instant::now();
+        let data = [0x00, 1, 2, 3, 0x00, 4, 5, 6];
+        let split: Vec<_> = reports(&data, 4, at)
+            .map(|report| report.data[..report.len].to_vec())
+            .collect();
+        assert_eq!(split, [vec![1, 2, 3], vec![4, 5, 6]]);
+
+        // A DS4 USB report keeps its 0x01 ID, as hidapi hands it over.
+        let ds4 = [0x01, 0x80, 0x80];
+        let report = reports(&ds4, 3, at).next().unwrap();
+        assert_eq!(&report.data[..report.len], &ds4);
+    }
+
+    #[test]
+    fn reports_longer_than_the_buffer_are_cut_to_it() {
+        let data = [0x11; HID_REPORT_BUFFER_SIZE + 10];
+        let report = reports(&data, data.len(), Instant::now()).next().unwrap();
+        assert_eq!(report.len, HID_REPORT_BUFFER_SIZE);
+    }
+
+    #[test]
+    fn raw_input_and_hidapi_paths_compare_equal() {
+        let hidapi =
+            r"\\?\HID#VID_054C&PID_05C4#7&1a2b3c4d&0&0000#{4d1e55b2-f16f-11cf-88cb-001111000030}";
+        let raw_input =
+            r"\??\hid#vid_054c&pid_05c4#7&1a2b3c4d&0&0000#{4d1e55b2-f16f-11cf-88cb-001111000030}";
+        assert_eq!(path_key(hidapi), path_key(raw_input));
+    }
+}
@@ -0,0 +1,225 @@
+//! Xbox-layout gamepads through Windows.Gaming.Input, which has no four-slot
+//! limit and reads the triggers as analogs of their own.
+//!
+//! Windows only hands a desktop app its readings while one of that app's
+//! windows has focus; the rest of the time every pad reads neutral. Once SF6
+//! is in the foreground this mode therefore goes quiet, which is why the
+//! default `auto` order leaves it out.
+
+use std::sync::{
+    atomic::{AtomicBool, Ordering},
+    Arc, Once,
+};
+use std::thread;
+use std::time::Duration;
+
+use windows::Foundation::EventHandler;
+use windows::Gaming::Input::{Gamepad, GamepadButtons, GamepadReading, RawGameController};
+
+use super::super::{
+    analog::AnalogSettings,
+    decoder::{DirectionArbiter, XInputGamepad},
+    FrameTimestamp, InputSample, NativeInputDevice, NativeInputMode,
+};
+use super::imp::sample_from_xinput_gamepad;
+
+/// Windows fills `Gamepad::Gamepads` a moment after the first call rather
+/// than at once; the first enumeration of a run waits this long for it.
+const ENUMERATION_SETTLE: Duration = Duration::from_millis(50);
+
+/// Each Windows.Gaming.Input button and the XInput bit it decodes as.
+const XINPUT_BUTTONS: [(GamepadButtons, u16); 14] = [
+    (GamepadButtons::DPadUp, 0x0001),
+    (GamepadButtons::DPadDown, 0x0002),
+    (GamepadButtons::DPadLeft, 0x0004),
+    (GamepadButtons::DPadRight, 0x0008),
+    (GamepadButtons::Menu, 0x0010),
+    (GamepadButtons::View, 0x0020),
+    (GamepadButtons::LeftThumbstick, 0x0040),
+    (GamepadButtons::RightThumbstick, 0x0080),
+    (GamepadButtons::LeftShoulder, 0x0100),
+    (GamepadButtons::RightShoulder, 0x0200),
+    (GamepadButtons::A, 0x1000),
+    (GamepadButtons::B, 0x2000),
+    (GamepadButtons::X, 0x4000),
+    (GamepadButtons::Y, 0x8000),
+];
+
+pub(super) struct WgiGamepadSource {
+    gamepad: Gamepad,
+    info: NativeInputDevice,
+    /// Set by the `GamepadRemoved` handler once this pad is gone.
+    removed: Arc<AtomicBool>,
+    removed_token: i64,
+    arbiter: DirectionArbiter,
+}
+
+impl WgiGamepadSource {
+    /// Opens the first connected gamepad, or only `requested` when a
+    /// specific one was asked for.
+    pub(super) fn new(requested: Option<&str>) -> Result<Self, String> {
+        let mut gamepads = gamepads()?.into_iter();
+        let (gamepad, info) = match requested {
+            Some(key) => gamepads
+                .find(|(_, device)| NativeInputMode::Wgi.device_key(&device.id) == Ok(key))
+                .ok_or_else(|| format!("Windows.Gaming.Input gamepad '{key}' is not connected."))?,
+            None => gamepads.next().ok_or_else(|| {
+                "Native input mode 'wgi' could not find a connected gamepad.".to_string()
+            })?,
+        };
+
+        let removed = Arc::new(AtomicBool::new(false));
+        let handler = {
+            let gamepad = gamepad.clone();
+            let removed = Arc::clone(&removed);
+            EventHandler::new(move |_, gone: windows::core::Ref<Gamepad>| {
+                if gone.as_ref() == Some(&gamepad) {
+                    removed.store(true, Ordering::Relaxed);
+                }
+                Ok(())
+            })
+        };
+        let removed_token = Gamepad::GamepadRemoved(&handler).map_err(|error| {
+            format!("Failed to watch Windows.Gaming.Input gamepad removal: {error}")
+        })?;
+
+        Ok(Self {
+            gamepad,
+            info,
+            removed,
+            removed_token,
+            arbiter: DirectionArbiter::default(),
+        })
+    }
+
+    pub(super) fn device(&self) -> NativeInputDevice {
+        self.info.clone()
+    }
+
+    pub(super) fn poll(
+        &mut self,
+        now: FrameTimestamp,
+        analog: AnalogSettings,
+    ) -> Result<InputSample, String> {
+        if self.removed.load(Ordering::Relaxed) {
+            return Err(format!(
+                "Windows.Gaming.Input gamepad '{}' is disconnected.",
+                self.info.id
+            ));
+        }
+        let reading = self
+            .gamepad
+            .GetCurrentReading()
+            .map_err(|error| format!("Failed to read a Windows.Gaming.Input gamepad: {error}"))?;
+        Ok(sample_from_xinput_gamepad(
+            &xinput_gamepad(&reading),
+            now,
+            analog,
+            &mut self.arbiter,
+        ))
+    }
+}
+
+impl Drop for WgiGamepadSource {
+    fn drop(&mut self) {
+        let _ = Gamepad::RemoveGamepadRemoved(self.removed_token);
+    }
+}
+
+pub(super) fn wgi_devices() -> Vec<NativeInputDevice> {
+    gamepads()
+        .unwrap_or_default()
+        .into_iter()
+        .map(|(_, device)| device)
+        .collect()
+}
+
+/// Every connected gamepad with its device entry, keyed by the controller's
+/// `NonRoamableId`, which stays the same while it is plugged in.
+fn gamepads() -> Result<Vec<(Gamepad, NativeInputDevice)>, String> {
+    static SETTLE: Once = Once::new();
+    let list = || {
+        Gamepad::Gamepads()
+            .map_err(|error| format!("Failed to list Windows.Gaming.Input gamepads: {error}"))
+    };
+    list()?;
+    SETTLE.call_once(|| thread::sleep(ENUMERATION_SETTLE));
+
+    Ok(list()?
+        .into_iter()
+        .filter_map(|gamepad| {
+            let raw = RawGameController::FromGameController(&gamepad).ok()?;
+            let key = raw.NonRoamableId().ok()?.to_string();
+            let mut device = NativeInputDevice::new(NativeInputMode::Wgi, &key);
+            device.product = Some(
+                raw.DisplayName()
+                    .map(|name| name.to_string())
+                    .ok()
+                    .filter(|name| !name.is_empty())
+                    .unwrap_or_else(|| "Windows.Gaming.Input gamepad".to_string()),
+            );
+            device.vendor_id = raw.HardwareVendorId().ok();
+            device.product_id = raw.HardwareProductId().ok();
+            device.openable = Some(true);
+            Some((gamepad, device))
+        })
+        .collect())
+}
+
+/// `reading` in the XInput layout: sticks scaled to `i16` with Y up, as both
+/// APIs have it, and triggers to `u8`.
+fn xinput_gamepad(reading: &GamepadReading) -> XInputGamepad {
+    let axis = |value: f64| (value.clamp(-1.0, 1.0) * f64::from(i16::MAX)).round() as i16;
+    let trigger = |value: f64| (value.clamp(0.0, 1.0) * f64::from(u8::MAX)).round() as u8;
+    XInputGamepad {
+        buttons: XINPUT_BUTTONS
+            .iter()
+            .filter(|(button, _)| reading.Buttons.contains(*button))
+            .fold(0, |buttons, (_, bit)| buttons | bit),
+        left_trigger: trigger(reading.LeftTrigger),
+        right_trigger: trigger(reading.RightTrigger),
+        thumb_lx: axis(reading.LeftThumbstickX),
+        thumb_ly: axis(reading.LeftThumbstickY),
+        thumb_rx: axis(reading.RightThumbstickX),
+        thumb_ry: axis(reading.RightThumbstickY),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn reading(buttons: GamepadButtons) -> GamepadReading {
+        GamepadReading {
+            Buttons: buttons,
+            ..GamepadReading::default()
+        }
+    }
+
+    #[test]
+    fn buttons_map_to_their_xinput_bits() {
+        let gamepad = xinput_gamepad(&reading(GamepadButtons(
+            GamepadButtons::A.0 | GamepadButtons::DPadLeft.0 | GamepadButtons::Menu.0,
+        )));
+        assert_eq!(gamepad.buttons, 0x1000 | 0x0004 | 0x0010);
+
+        // Paddles have no XInput counterpart.
+        assert_eq!(xinput_gamepad(&reading(GamepadButtons::Paddle1)).buttons, 0);
+    }
+
+    #[test]
+    fn analogs_scale_to_the_xinput_ranges() {
+        let gamepad = xinput_gamepad(&GamepadReading {
+            LeftTrigger: 1.0,
+            RightTrigger: 0.5,
+            LeftThumbstickX: -1.0,
+            LeftThumbstickY: 1.0,
+            RightThumbstickX: 2.0,
+            ..GamepadReading::default()
+        });
+
+        assert_eq!((gamepad.left_trigger, gamepad.right_trigger), (255, 128));
+        assert_eq!((gamepad.thumb_lx, gamepad.thumb_ly), (-32767, 32767));
+        assert_eq!((gamepad.thumb_rx, gamepad.thumb_ry), (32767, 0));
+    }
+}
@@ -0,0 +1,80 @@
+use std::{path::Path, time::Duration};
+
+use super::{
+    analog::DirectionPriority,
+    recording::{RecordedFrame, RecordingReader},
+    InputSample, FRAME_DURATION,
+};
+
+pub(crate) const MIN_PLAYBACK_SPEED: f64 = 0.25;
+pub(crate) const MAX_PLAYBACK_SPEED: f64 = 2.0;
+
+/// File-backed stand-in for a controller: replays a recording frame by frame
+/// with frame numbers re-based to start at 0.
+pub(crate) struct PlaybackSource {
+    frames: Vec<RecordedFrame>,
+    base_frame: u64,
+    next: usize,
+}
+
+impl PlaybackSource {
+    pub(crate) fn open(path: &Path) -> Result<Self, String> {
+        let mut reader = RecordingReader::open(path)?;
+        let mut frames = Vec::new();
+        while let Some(frame) = reader.next_frame()? {
+            frames.push(frame);
+        }
+
+        let base_frame = frames
+            .first()
+            .map(|frame| frame.frame)
+            .ok_or_else(|| format!("Recording '{}' contains no frames.", path.display()))?;
+
+        Ok(Self {
+            frames,
+            base_frame,
+            next: 0,
+        })
+    }
+
+    /// Returns the recorded frames due at or before playback frame `frame`,
+    /// as `(rebased_frame, sample, connected)`.
+    pub(crate) fn take_due(&mut self, frame: u64) -> Vec<(u64, InputSample, bool)> {
+        let mut due = Vec::new();
+        while let Some(recorded) = self.frames.get(self.next) {
+            let rebased = recorded.frame.saturating_sub(self.base_frame);
+            if rebased > frame {
+                break;
+            }
+
+            let sample = InputSample {
+                timestamp_ms: recorded.timestamp_us / 1_000,
+                timestamp_us: recorded.timestamp_us,
+                direction: recorded.direction,
+                direction_raw: recorded.direction,
+                down_mask: recorded.down_mask,
+                down_mask_raw: recorded.down_mask,
+                left_stick: None,
+                stick_direction: 5,
+                auto_priority: DirectionPriority::Dpad,
+                right_stick: None,
+                triggers: None,
+                first_press_us: None,
+                reported_at: None,
+                device_timestamp_us: None,
+            };
+            due.push((rebased, sample, recorded.connected));
+            self.next += 1;
+        }
+        due
+    }
+
+    pub(crate) fn is_finished(&self) -> bool {
+        self.next >= self.frames.len()
+    }
+}
+
+/// Tick length for replaying at `speed` times the original 60 fps.
+pub(crate) fn playback_frame_duration(speed: f64) -> Duration {
+    FRAME_DURATION.div_f64(speed)
+}
@@ -0,0 +1,275 @@
+use std::{
+    collections::hash_map::RandomState,
+    hash::{BuildHasher, Hasher},
+};
+
+use serde::{Deserialize, Serialize};
+
+use super::{emitter::PolledFrame, mapping::name_to_mask, FRAME_DURATION, SF6_BUTTON_ORDER};
+
+const DEFAULT_TIMEOUT_FRAMES: u64 = 60;
+/// Results kept for `reaction_get_summary`; the oldest are dropped beyond it.
+const MAX_RESULTS: usize = 1_000;
+
+/// Serialized drill, e.g. `{"buttons": ["HP", "HK"], "min_delay_frames": 60,
+/// "max_delay_frames": 240}`.
+#[derive(Clone, Deserialize)]
+pub struct ReactionConfig {
+    /// SF6 buttons that answer the cue, all down with at least one newly
+    /// pressed, like a combo step.
+    buttons: Vec<String>,
+    /// Range after the previous result from which the next cue's delay is
+    /// drawn.
+    min_delay_frames: u64,
+    max_delay_frames: u64,
+    /// Frames after the cue after which it counts as missed; 60 by default.
+    #[serde(default)]
+    timeout_frames: Option<u64>,
+}
+
+/// Payload of `input/cue`.
+#[derive(Clone, Serialize)]
+pub struct InputCuePayload {
+    frame: u64,
+    timestamp_us: u64,
+}
+
+/// Payload of `input/reaction_result`.
+#[derive(Clone, Copy, Serialize)]
+pub struct ReactionResult {
+    /// Frames from the cue to the answer. For an early press, frames before
+    /// the cue was due.
+    frames: u64,
+    ms: f64,
+    early: bool,
+    /// No answer within the timeout; `frames` is then the timeout.
+    missed: bool,
+}
+
+/// Answer to `reaction_get_summary` and `reaction_stop`. Early and missed
+/// attempts are counted but left out of the timing figures.
+#[derive(Clone, Serialize)]
+pub struct ReactionSummary {
+    active: bool,
+    attempts: usize,
+    hits: usize,
+    early: usize,
+    missed: usize,
+    average_frames: Option<f64>,
+    best_frames: Option<u64>,
+    worst_frames: Option<u64>,
+    average_ms: Option<f64>,
+    results: Vec<ReactionResult>,
+}
+
+pub(crate) enum ReactionEvent {
+    Cue(InputCuePayload),
+    Result(ReactionResult),
+}
+
+#[derive(Clone, Copy)]
+enum ReactionPhase {
+    /// Waiting for the next frame to draw a delay from.
+    Unscheduled,
+    Waiting {
+        cue_frame: u64,
+    },
+    Cued {
+        frame: u64,
+        timestamp_us: u64,
+    },
+}
+
+struct ReactionDrill {
+    buttons: u16,
+    min_delay_frames: u64,
+    max_delay_frames: u64,
+    timeout_frames: u64,
+    phase: ReactionPhase,
+    last_frame: Option<u64>,
+    /// xorshift64 state; cue timing only has to be unpredictable to a human.
+    rng: u64,
+}
+
+impl ReactionDrill {
+    fn from_config(config: &ReactionConfig) -> Result<Self, String> {
+        let mut buttons = 0u16;
+        for button in &config.buttons {
+            buttons |= name_to_mask(&SF6_BUTTON_ORDER, button)
+                .ok_or_else(|| format!("Unknown SF6 button '{button}' in reaction drill."))?;
+        }
+        if buttons == 0 {
+            return Err("Reaction drill needs at least one target button.".to_string());
+        }
+        if config.min_delay_frames == 0 || config.min_delay_frames > config.max_delay_frames {
+            return Err(
+                "Reaction cue delay must be at least 1 frame, with min_delay_frames not above max_delay_frames."
+                    .to_string(),
+            );
+        }
+        let timeout_frames = config.timeout_frames.unwrap_or(DEFAULT_TIMEOUT_FRAMES);
+        if timeout_frames == 0 {
+            return Err("Reaction timeout must be at least 1 frame.".to_string());
+        }
+
+        Ok(Self {
+            buttons,
+            min_delay_frames: config.min_delay_frames,
+            max_delay_frames: config.max_delay_frames,
+            timeout_frames,
+            phase: ReactionPhase::Unscheduled,
+            last_frame: None,
+            rng: RandomState::new().build_hasher().finish() | 1,
+        })
+    }
+
+    fn next_delay(&mut self) -> u64 {
+        self.rng ^= self.rng << 13;
+        self.rng ^= self.rng >> 7;
+        self.rng ^= self.rng << 17;
+        let span = self.max_delay_frames - self.min_delay_frames + 1;
+        self.min_delay_frames + self.rng % span
+    }
+
+    fn push(&mut self, frame: &PolledFrame) -> Vec<ReactionEvent> {
+        // A new run restarts frame numbers, so a pending cue would never come.
+        if self.last_frame.is_some_and(|last| frame.frame < last) {
+            self.phase = ReactionPhase::Unscheduled;
+        }
+        self.last_frame = Some(frame.frame);
+
+        let answered = frame.sf6_mask & self.buttons == self.buttons
+            && frame.sf6_mask & !frame.previous_sf6_mask & self.buttons != 0;
+        let pressed_us = frame
+            .sample
+            .first_press_us
+            .unwrap_or(frame.sample.timestamp_us);
+        let mut events = Vec::new();
+
+        match self.phase {
+            ReactionPhase::Unscheduled => {
+                self.phase = ReactionPhase::Waiting {
+                    cue_frame: frame.frame + self.next_delay(),
+                };
+            }
+            ReactionPhase::Waiting { cue_frame } if answered => {
+                let frames = cue_frame.saturating_sub(frame.frame);
+                events.push(ReactionEvent::Result(ReactionResult {
+                    frames,
+                    ms: frames_to_ms(frames),
+                    early: true,
+                    missed: false,
+                }));
+                self.phase = ReactionPhase::Waiting {
+                    cue_frame: frame.frame + self.next_delay(),
+                };
+            }
+            ReactionPhase::Waiting { cue_frame } if frame.frame >= cue_frame => {
+                let timestamp_us = frame.sample.timestamp_us;
+                events.push(ReactionEvent::Cue(InputCuePayload {
+                    frame: frame.frame,
+                    timestamp_us,
+                }));
+                self.phase = ReactionPhase::Cued {
+                    frame: frame.frame,
+                    timestamp_us,
+                };
+            }
+            ReactionPhase::Cued {
+                frame: cue_frame,
+                timestamp_us,
+            } => {
+                let frames = frame.frame - cue_frame;
+                let missed = frames > self.timeout_frames;
+                if answered || missed {
+                    let frames = frames.min(self.timeout_frames);
+                    let ms = if missed {
+                        frames_to_ms(frames)
+                    } else {
+                        pressed_us.saturating_sub(timestamp_us) as f64 / 1_000.0
+                    };
+                    events.push(ReactionEvent::Result(ReactionResult {
+                        frames,
+                        ms,
+                        early: false,
+                        missed,
+                    }));
+                    self.phase = ReactionPhase::Waiting {
+                        cue_frame: frame.frame + self.next_delay(),
+                    };
+                }
+            }
+            ReactionPhase::Waiting { .. } => {}
+        }
+        events
+    }
+}
+
+/// The running drill, if any, and the results of the current session. Cues
+/// and answers are both judged on worker frames, so IPC delay to the frontend
+/// does not enter the measurement.
+#[derive(Default)]
+pub(crate) struct ReactionTrainer {
+    drill: Option<ReactionDrill>,
+    results: Vec<ReactionResult>,
+}
+
+impl ReactionTrainer {
+    /// Starts a new session, dropping the results of the previous one.
+    pub(crate) fn start(&mut self, config: &ReactionConfig) -> Result<(), String> {
+        self.drill = Some(ReactionDrill::from_config(config)?);
+        self.results.clear();
+        Ok(())
+    }
+
+    /// Stops cueing; the results stay available until the next start.
+    pub(crate) fn stop(&mut self) -> ReactionSummary {
+        self.drill = None;
+        self.summary()
+    }
+
+    pub(crate) fn push(&mut self, frame: &PolledFrame) -> Vec<ReactionEvent> {
+        let Some(drill) = self.drill.as_mut() else {
+            return Vec::new();
+        };
+        let events = drill.push(frame);
+        for event in &events {
+            if let ReactionEvent::Result(result) = event {
+                if self.results.len() == MAX_RESULTS {
+                    self.results.remove(0);
+                }
+                self.results.push(*result);
+            }
+        }
+        events
+    }
+
+    pub(crate) fn summary(&self) -> ReactionSummary {
+        let hits: Vec<&ReactionResult> = self
+            .results
+            .iter()
+            .filter(|result| !result.early && !result.missed)
+            .collect();
+        let average = |value: fn(&ReactionResult) -> f64| {
+            (!hits.is_empty())
+                .then(|| hits.iter().map(|result| value(result)).sum::<f64>() / hits.len() as f64)
+        };
+
+        ReactionSummary {
+            active: self.drill.is_some(),
+            attempts: self.results.len(),
+            hits: hits.len(),
+            early: self.results.iter().filter(|result| result.early).count(),
+            missed: self.results.iter().filter(|result| result.missed).count(),
+            average_frames: average(|result| result.frames as f64),
+            best_frames: hits.iter().map(|result| result.frames).min(),
+            worst_frames: hits.iter().map(|result| result.frames).max(),
+            average_ms: average(|result| result.ms),
+            results: self.results.clone(),
+        }
+    }
+}
+
+fn frames_to_ms(frames: u64) -> f64 {
+    frames as f64 * FRAME_DURATION.as_secs_f64() * 1_000.0
+}
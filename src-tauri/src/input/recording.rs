@@ -0,0 +1,217 @@
+use std::{
+    fs::{self, File},
+    io::{BufRead, BufReader, BufWriter, Write},
+    path::{Path, PathBuf},
+};
+
+use serde::{Deserialize, Serialize};
+
+use super::emitter::PolledFrame;
+
+/// Frames written between explicit flushes, so a crash loses at most about a
+/// second of a session.
+const FLUSH_INTERVAL_FRAMES: u64 = 60;
+/// Version 2 files start with a [`RecordingHeader`] line; version 1 files
+/// start straight with a frame.
+const RECORDING_VERSION: u32 = 2;
+
+#[derive(Serialize, Deserialize)]
+struct RecordingHeader {
+    version: u32,
+}
+
+/// One line of a recording file (newline-delimited JSON). Masks are stored
+/// raw so a recording can be replayed under a different button mapping.
+#[derive(Clone, Copy, Serialize, Deserialize)]
+pub(crate) struct RecordedFrame {
+    pub frame: u64,
+    pub timestamp_us: u64,
+    pub direction: u8,
+    pub down_mask: u32,
+    pub connected: bool,
+}
+
+#[derive(Clone, Serialize)]
+pub struct InputRecordingSummary {
+    path: String,
+    frames: u64,
+    duration_ms: u64,
+    file_size: u64,
+}
+
+pub(crate) struct InputRecorder {
+    path: PathBuf,
+    writer: BufWriter<File>,
+    frames: u64,
+    first_timestamp_us: Option<u64>,
+    last_timestamp_us: u64,
+    error: Option<String>,
+}
+
+impl InputRecorder {
+    pub(crate) fn create(path: PathBuf) -> Result<Self, String> {
+        let file = File::create(&path)
+            .map_err(|error| format!("Failed to create recording '{}': {error}", path.display()))?;
+        let mut writer = BufWriter::new(file);
+        let header = RecordingHeader {
+            version: RECORDING_VERSION,
+        };
+        serde_json::to_writer(&mut writer, &header)
+            .map_err(|error| error.to_string())
+            .and_then(|_| writer.write_all(b"\n").map_err(|error| error.to_string()))
+            .map_err(|error| format!("Failed to write recording '{}': {error}", path.display()))?;
+
+        Ok(Self {
+            path,
+            writer,
+            frames: 0,
+            first_timestamp_us: None,
+            last_timestamp_us: 0,
+            error: None,
+        })
+    }
+
+    pub(crate) fn path(&self) -> String {
+        self.path.display().to_string()
+    }
+
+    /// Appends a frame. The first write error is kept and reported by
+    /// [`InputRecorder::finish`] rather than interrupting the worker.
+    pub(crate) fn record(&mut self, frame: &PolledFrame) {
+        if self.error.is_some() {
+            return;
+        }
+
+        let line = RecordedFrame {
+            frame: frame.frame,
+            timestamp_us: frame.sample.timestamp_us,
+            direction: frame.sample.direction,
+            down_mask: frame.sample.down_mask,
+            connected: frame.connected,
+        };
+        let written = serde_json::to_writer(&mut self.writer, &line)
+            .map_err(|error| error.to_string())
+            .and_then(|_| {
+                self.writer
+                    .write_all(b"\n")
+                    .map_err(|error| error.to_string())
+            });
+        if let Err(error) = written {
+            self.error = Some(error);
+            return;
+        }
+
+        self.frames += 1;
+        self.first_timestamp_us.get_or_insert(line.timestamp_us);
+        self.last_timestamp_us = line.timestamp_us;
+        if self.frames.is_multiple_of(FLUSH_INTERVAL_FRAMES) {
+            let _ = self.writer.flush();
+        }
+    }
+
+    pub(crate) fn finish(mut self) -> Result<InputRecordingSummary, String> {
+        let path = self.path();
+        if let Some(error) = self.error.take() {
+            return Err(format!("Failed to write recording '{path}': {error}"));
+        }
+        self.writer
+            .flush()
+            .map_err(|error| format!("Failed to write recording '{path}': {error}"))?;
+
+        let duration_us = self
+            .first_timestamp_us
+            .map_or(0, |first| self.last_timestamp_us.saturating_sub(first));
+        let file_size = fs::metadata(&self.path)
+            .map(|metadata| metadata.len())
+            .unwrap_or(0);
+
+        Ok(InputRecordingSummary {
+            path,
+            frames: self.frames,
+            duration_ms: duration_us / 1_000,
+            file_size,
+        })
+    }
+}
+
+/// Reads the frames of a recording of any version, one line at a time.
+pub(crate) struct RecordingReader {
+    path: PathBuf,
+    reader: BufReader<File>,
+    line: String,
+    line_number: usize,
+    header_checked: bool,
+    bytes_read: u64,
+    total_bytes: u64,
+}
+
+impl RecordingReader {
+    pub(crate) fn open(path: &Path) -> Result<Self, String> {
+        let file = File::open(path)
+            .map_err(|error| format!("Failed to open recording '{}': {error}", path.display()))?;
+        let total_bytes = file.metadata().map(|metadata| metadata.len()).unwrap_or(0);
+
+        Ok(Self {
+            path: path.to_path_buf(),
+            reader: BufReader::new(file),
+            line: String::new(),
+            line_number: 0,
+            header_checked: false,
+            bytes_read: 0,
+            total_bytes,
+        })
+    }
+
+    /// Share of the file read so far, from 0 to 1.
+    pub(crate) fn progress(&self) -> f64 {
+        if self.total_bytes == 0 {
+            return 1.0;
+        }
+        (self.bytes_read as f64 / self.total_bytes as f64).min(1.0)
+    }
+
+    /// The next frame, or `None` at the end of the file. Fields added by later
+    /// versions are ignored; a file from a newer major version is refused.
+    pub(crate) fn next_frame(&mut self) -> Result<Option<RecordedFrame>, String> {
+        loop {
+            self.line.clear();
+            let read = self.reader.read_line(&mut self.line).map_err(|error| {
+                format!(
+                    "Failed to read recording '{}': {error}",
+                    self.path.display()
+                )
+            })?;
+            if read == 0 {
+                return Ok(None);
+            }
+            self.bytes_read += read as u64;
+            self.line_number += 1;
+
+            let line = self.line.trim();
+            if line.is_empty() {
+                continue;
+            }
+            if !self.header_checked {
+                self.header_checked = true;
+                if let Ok(header) = serde_json::from_str::<RecordingHeader>(line) {
+                    if header.version > RECORDING_VERSION {
+                        return Err(format!(
+                            "Recording '{}' uses format version {}; this build reads up to version {RECORDING_VERSION}.",
+                            self.path.display(),
+                            header.version
+                        ));
+                    }
+                    continue;
+                }
+            }
+
+            return serde_json::from_str(line).map(Some).map_err(|error| {
+                format!(
+                    "Recording '{}' line {} is not a recorded frame: {error}",
+                    self.path.display(),
+                    self.line_number
+                )
+            });
+        }
+    }
+}
@@ -0,0 +1,108 @@
+use serde::Serialize;
+
+use super::{grouping::GroupedFrame, mask_to_sf6_buttons};
+
+/// SF6's training-mode input display stops counting at 99.
+const DEFAULT_MAX_HOLD_FRAMES: u64 = 99;
+
+#[derive(Clone, Copy)]
+pub(crate) struct SegmentSettings {
+    pub enabled: bool,
+    /// Cap on the reported `frames`; longer holds still form one segment.
+    pub max_hold_frames: u64,
+}
+
+impl Default for SegmentSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            max_hold_frames: DEFAULT_MAX_HOLD_FRAMES,
+        }
+    }
+}
+
+/// One input-display row: a direction and button set held unchanged from
+/// `start_frame` for `frames` frames.
+#[derive(Clone, Serialize)]
+pub struct InputSegmentPayload {
+    direction: u8,
+    buttons: Vec<String>,
+    start_frame: u64,
+    frames: u64,
+    /// The segment opened on a press grouped from several frames.
+    simultaneous: bool,
+}
+
+struct OpenSegment {
+    direction: u8,
+    sf6_mask: u16,
+    start_frame: u64,
+    last_frame: u64,
+    simultaneous: bool,
+}
+
+impl OpenSegment {
+    fn close(&self, end_frame: u64, max_hold_frames: u64) -> InputSegmentPayload {
+        InputSegmentPayload {
+            direction: self.direction,
+            buttons: mask_to_sf6_buttons(self.sf6_mask),
+            start_frame: self.start_frame,
+            frames: end_frame
+                .saturating_sub(self.start_frame)
+                .min(max_hold_frames),
+            simultaneous: self.simultaneous,
+        }
+    }
+}
+
+/// Coalesces identical consecutive frames, yielding each segment once the
+/// state changes and the final one when the run ends. Fed after press
+/// grouping, so a plink split across two frames opens a single segment.
+#[derive(Default)]
+pub(crate) struct SegmentTracker {
+    open: Option<OpenSegment>,
+    max_hold_frames: u64,
+}
+
+impl SegmentTracker {
+    pub(crate) fn push(
+        &mut self,
+        grouped: &GroupedFrame,
+        settings: SegmentSettings,
+    ) -> Option<InputSegmentPayload> {
+        let frame = &grouped.frame;
+        self.max_hold_frames = settings.max_hold_frames;
+        if !settings.enabled {
+            self.open = None;
+            return None;
+        }
+
+        let direction = frame.sample.direction;
+        if let Some(open) = self.open.as_mut() {
+            if open.direction == direction && open.sf6_mask == frame.sf6_mask {
+                open.last_frame = frame.frame;
+                return None;
+            }
+        }
+
+        let closed = self
+            .open
+            .take()
+            .map(|open| open.close(frame.frame, self.max_hold_frames));
+        self.open = Some(OpenSegment {
+            direction,
+            sf6_mask: frame.sf6_mask,
+            start_frame: frame.frame,
+            last_frame: frame.frame,
+            simultaneous: grouped.simultaneous,
+        });
+        closed
+    }
+
+    /// Closes the open segment, which lasts through the last frame seen.
+    pub(crate) fn finish(&mut self) -> Option<InputSegmentPayload> {
+        self.open
+            .take()
+            .map(|open| open.close(open.last_frame + 1, self.max_hold_frames))
+    }
+}
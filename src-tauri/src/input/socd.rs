@@ -0,0 +1,293 @@
+use serde::{Deserialize, Serialize};
+
+use super::{
+    analog::{AnalogSettings, DirectionSource},
+    decoder::DirectionArbiter,
+    to_direction, InputSample, BUTTON_DPAD_DOWN_MASK, BUTTON_DPAD_LEFT_MASK,
+    BUTTON_DPAD_RIGHT_MASK, BUTTON_DPAD_UP_MASK,
+};
+
+const DPAD_MASK: u32 =
+    BUTTON_DPAD_UP_MASK | BUTTON_DPAD_DOWN_MASK | BUTTON_DPAD_LEFT_MASK | BUTTON_DPAD_RIGHT_MASK;
+
+/// How simultaneous opposite directions (SOCD) on the dpad are resolved.
+///
+/// `Neutral` cancels both axes, `LastInput` keeps whichever side was pressed
+/// most recently, `FirstInput` the one held longer, and `UpPriority` cancels
+/// left+right but resolves up+down to up.
+#[derive(Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SocdPolicy {
+    #[default]
+    Neutral,
+    LastInput,
+    FirstInput,
+    UpPriority,
+}
+
+/// Worker-owned SOCD stage that runs between the decoder and the emitter.
+/// It only touches samples with dpad bits; stick-derived directions cannot
+/// hold opposite sides and pass through unchanged, as does the direction of
+/// any sample whose direction source is a stick. Under
+/// [`DirectionSource::Merged`] the cleaned dpad is merged with the sample's
+/// stick direction again, so the configured priority still applies.
+#[derive(Default)]
+pub(crate) struct SocdCleaner {
+    horizontal: AxisMemory,
+    vertical: AxisMemory,
+    arbiter: DirectionArbiter,
+}
+
+/// Last-input bookkeeping for one axis. `last` is the side pressed most
+/// recently: 1 positive, -1 negative, 0 when both went down on the same frame.
+#[derive(Default)]
+struct AxisMemory {
+    positive: bool,
+    negative: bool,
+    last: i32,
+}
+
+impl SocdCleaner {
+    pub(crate) fn clean(
+        &mut self,
+        sample: &mut InputSample,
+        policy: SocdPolicy,
+        analog: AnalogSettings,
+    ) {
+        let mask = sample.down_mask;
+        let horizontal = self.horizontal.update(
+            mask & BUTTON_DPAD_RIGHT_MASK != 0,
+            mask & BUTTON_DPAD_LEFT_MASK != 0,
+        );
+        let vertical = self.vertical.update(
+            mask & BUTTON_DPAD_UP_MASK != 0,
+            mask & BUTTON_DPAD_DOWN_MASK != 0,
+        );
+
+        if mask & DPAD_MASK == 0 {
+            // Keeps the arbiter's last-moved record current while only the
+            // stick is held.
+            if analog.direction_source() == DirectionSource::Merged {
+                self.arbiter.merge(
+                    5,
+                    sample.stick_direction,
+                    analog.direction_priority(sample.auto_priority),
+                );
+            }
+            return;
+        }
+
+        let horizontal = horizontal.unwrap_or(match policy {
+            SocdPolicy::LastInput => self.horizontal.last,
+            SocdPolicy::FirstInput => -self.horizontal.last,
+            SocdPolicy::Neutral | SocdPolicy::UpPriority => 0,
+        });
+        let vertical = vertical.unwrap_or(match policy {
+            SocdPolicy::LastInput => self.vertical.last,
+            SocdPolicy::FirstInput => -self.vertical.last,
+            SocdPolicy::UpPriority => 1,
+            SocdPolicy::Neutral => 0,
+        });
+
+        let mut down_mask = mask & !DPAD_MASK;
+        if horizontal > 0 {
+            down_mask |= BUTTON_DPAD_RIGHT_MASK;
+        }
+        if horizontal < 0 {
+            down_mask |= BUTTON_DPAD_LEFT_MASK;
+        }
+        if vertical > 0 {
+            down_mask |= BUTTON_DPAD_UP_MASK;
+        }
+        if vertical < 0 {
+            down_mask |= BUTTON_DPAD_DOWN_MASK;
+        }
+
+        sample.down_mask = down_mask;
+        let dpad = to_direction(horizontal, vertical);
+        match analog.direction_source() {
+            DirectionSource::Dpad => sample.direction = dpad,
+            DirectionSource::Merged => {
+                sample.direction = self.arbiter.merge(
+                    dpad,
+                    sample.stick_direction,
+                    analog.direction_priority(sample.auto_priority),
+                );
+            }
+            DirectionSource::LeftStick | DirectionSource::RightStick => {}
+        }
+    }
+}
+
+impl AxisMemory {
+    /// Records this frame's raw state and returns the axis value, or `None`
+    /// when both sides are held and the policy has to decide.
+    fn update(&mut self, positive: bool, negative: bool) -> Option<i32> {
+        let new_positive = positive && !self.positive;
+        let new_negative = negative && !self.negative;
+        match (new_positive, new_negative) {
+            (true, true) => self.last = 0,
+            (true, false) => self.last = 1,
+            (false, true) => self.last = -1,
+            (false, false) => {}
+        }
+        self.positive = positive;
+        self.negative = negative;
+
+        match (positive, negative) {
+            (true, true) => None,
+            (true, false) => Some(1),
+            (false, true) => Some(-1),
+            (false, false) => Some(0),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::input::{analog::DirectionPriority, clock::FrameTimestamp, BUTTON_SOUTH_MASK};
+
+    const UP: u32 = BUTTON_DPAD_UP_MASK;
+    const DOWN: u32 = BUTTON_DPAD_DOWN_MASK;
+    const LEFT: u32 = BUTTON_DPAD_LEFT_MASK;
+    const RIGHT: u32 = BUTTON_DPAD_RIGHT_MASK;
+
+    /// Feeds one dpad mask per frame and returns the cleaned directions.
+    fn directions(policy: SocdPolicy, masks: &[u32]) -> Vec<u8> {
+        let mut cleaner = SocdCleaner::default();
+        masks
+            .iter()
+            .map(|&mask| {
+                let mut sample = InputSample::new(FrameTimestamp::default(), 5, mask);
+                cleaner.clean(&mut sample, policy, AnalogSettings::default());
+                sample.direction
+            })
+            .collect()
+    }
+
+    // Hold one side, press the opposite, release the first, release both.
+    const HORIZONTAL: &[u32] = &[RIGHT, RIGHT | LEFT, LEFT, 0];
+    const VERTICAL: &[u32] = &[DOWN, DOWN | UP, UP, 0];
+    // Hold one side, press the opposite, release the opposite.
+    const HORIZONTAL_TAP: &[u32] = &[RIGHT, RIGHT | LEFT, RIGHT];
+    const VERTICAL_TAP: &[u32] = &[UP, UP | DOWN, UP];
+
+    #[test]
+    fn neutral_cancels_both_axes() {
+        let policy = SocdPolicy::Neutral;
+
+        assert_eq!(directions(policy, HORIZONTAL), [6, 5, 4, 5]);
+        assert_eq!(directions(policy, VERTICAL), [2, 5, 8, 5]);
+        assert_eq!(directions(policy, HORIZONTAL_TAP), [6, 5, 6]);
+        assert_eq!(directions(policy, VERTICAL_TAP), [8, 5, 8]);
+    }
+
+    #[test]
+    fn last_input_follows_the_latest_press() {
+        let policy = SocdPolicy::LastInput;
+
+        assert_eq!(directions(policy, HORIZONTAL), [6, 4, 4, 5]);
+        assert_eq!(directions(policy, VERTICAL), [2, 8, 8, 5]);
+        assert_eq!(directions(policy, HORIZONTAL_TAP), [6, 4, 6]);
+        assert_eq!(directions(policy, VERTICAL_TAP), [8, 2, 8]);
+    }
+
+    #[test]
+    fn first_input_keeps_the_earlier_press() {
+        let policy = SocdPolicy::FirstInput;
+
+        assert_eq!(directions(policy, HORIZONTAL), [6, 6, 4, 5]);
+        assert_eq!(directions(policy, VERTICAL), [2, 2, 8, 5]);
+        assert_eq!(directions(policy, HORIZONTAL_TAP), [6, 6, 6]);
+        assert_eq!(directions(policy, VERTICAL_TAP), [8, 8, 8]);
+    }
+
+    #[test]
+    fn up_priority_resolves_up_and_cancels_left_right() {
+        let policy = SocdPolicy::UpPriority;
+
+        assert_eq!(directions(policy, HORIZONTAL), [6, 5, 4, 5]);
+        assert_eq!(directions(policy, VERTICAL), [2, 8, 8, 5]);
+        assert_eq!(directions(policy, HORIZONTAL_TAP), [6, 5, 6]);
+        assert_eq!(directions(policy, VERTICAL_TAP), [8, 8, 8]);
+    }
+
+    #[test]
+    fn opposites_pressed_together_resolve_to_neutral() {
+        for policy in [SocdPolicy::LastInput, SocdPolicy::FirstInput] {
+            assert_eq!(directions(policy, &[RIGHT | LEFT, RIGHT]), [5, 6]);
+        }
+    }
+
+    #[test]
+    fn axes_resolve_independently() {
+        let masks = [RIGHT | DOWN, RIGHT | DOWN | LEFT, DOWN | LEFT];
+
+        assert_eq!(directions(SocdPolicy::Neutral, &masks), [3, 2, 1]);
+        assert_eq!(directions(SocdPolicy::LastInput, &masks), [3, 1, 1]);
+    }
+
+    #[test]
+    fn cleaned_dpad_bits_match_the_direction() {
+        let mut cleaner = SocdCleaner::default();
+        let other = BUTTON_SOUTH_MASK;
+        let mut sample = InputSample::new(FrameTimestamp::default(), 5, RIGHT | LEFT | UP | other);
+
+        cleaner.clean(&mut sample, SocdPolicy::Neutral, AnalogSettings::default());
+
+        assert_eq!(sample.direction, 8);
+        assert_eq!(sample.down_mask, UP | other);
+    }
+
+    #[test]
+    fn merged_source_settles_the_cleaned_dpad_against_the_stick() {
+        let clean = |priority, stick_direction| {
+            let analog =
+                AnalogSettings::default().with_direction(DirectionSource::Merged, priority);
+            let mut cleaner = SocdCleaner::default();
+            let mut sample = InputSample::new(FrameTimestamp::default(), 5, RIGHT | LEFT | UP);
+            sample.stick_direction = stick_direction;
+            cleaner.clean(&mut sample, SocdPolicy::Neutral, analog);
+            sample.direction
+        };
+
+        assert_eq!(clean(DirectionPriority::Combined, 6), 9);
+        assert_eq!(clean(DirectionPriority::Dpad, 6), 8);
+        assert_eq!(clean(DirectionPriority::Stick, 6), 6);
+        assert_eq!(clean(DirectionPriority::Stick, 5), 8);
+    }
+
+    #[test]
+    fn merged_source_remembers_stick_moves_between_dpad_presses() {
+        let analog = AnalogSettings::default()
+            .with_direction(DirectionSource::Merged, DirectionPriority::LastMoved);
+        let mut cleaner = SocdCleaner::default();
+        // Without dpad bits the source has already merged to the stick.
+        let mut clean = |mask, stick_direction| {
+            let mut sample = InputSample::new(FrameTimestamp::default(), stick_direction, mask);
+            sample.stick_direction = stick_direction;
+            cleaner.clean(&mut sample, SocdPolicy::Neutral, analog);
+            sample.direction
+        };
+
+        assert_eq!(clean(UP, 5), 8);
+        assert_eq!(clean(UP, 2), 2);
+        assert_eq!(clean(0, 3), 3);
+        assert_eq!(clean(UP, 3), 8);
+        assert_eq!(clean(UP, 6), 6);
+    }
+
+    #[test]
+    fn stick_sources_keep_their_direction() {
+        let analog = AnalogSettings::default()
+            .with_direction(DirectionSource::LeftStick, DirectionPriority::Combined);
+        let mut cleaner = SocdCleaner::default();
+        let mut sample = InputSample::new(FrameTimestamp::default(), 3, RIGHT | LEFT);
+
+        cleaner.clean(&mut sample, SocdPolicy::Neutral, analog);
+
+        assert_eq!(sample.direction, 3);
+        assert_eq!(sample.down_mask, 0);
+    }
+}
@@ -0,0 +1,198 @@
+use std::collections::BTreeMap;
+
+use serde::Serialize;
+
+use super::{emitter::PolledFrame, mapping::name_to_mask, SF6_BUTTON_ORDER};
+
+/// Longer holds and gaps land in this bucket so a button left held (or a
+/// pair pressed minutes apart) cannot grow the histograms without bound.
+const MAX_HISTOGRAM_FRAMES: u64 = 120;
+
+/// Frame-count histogram: bucket (in frames) to occurrences.
+type FrameHistogram = BTreeMap<u64, u64>;
+
+fn record(histogram: &mut FrameHistogram, frames: u64) {
+    *histogram
+        .entry(frames.min(MAX_HISTOGRAM_FRAMES))
+        .or_default() += 1;
+}
+
+#[derive(Default)]
+struct ButtonStats {
+    presses: u64,
+    hold_frames: FrameHistogram,
+    pressed_at: Option<u64>,
+}
+
+/// Gaps from a press of `first` to the next press of `second`. With the same
+/// button on both sides this measures the interval between repeated presses.
+struct PairStats {
+    first: usize,
+    second: usize,
+    gap_frames: FrameHistogram,
+    pending: Option<u64>,
+}
+
+#[derive(Clone, Serialize)]
+pub struct InputButtonStats {
+    button: &'static str,
+    presses: u64,
+    hold_frames: FrameHistogram,
+}
+
+#[derive(Clone, Serialize)]
+pub struct InputPairStats {
+    first: &'static str,
+    second: &'static str,
+    gap_frames: FrameHistogram,
+}
+
+#[derive(Clone, Serialize)]
+pub struct InputStatsSummary {
+    /// Worker frames seen since the last reset.
+    frames: u64,
+    first_frame: Option<u64>,
+    last_frame: Option<u64>,
+    buttons: Vec<InputButtonStats>,
+    pairs: Vec<InputPairStats>,
+    /// Attempts segmented since the last reset, and of those the ones the
+    /// armed combo trial passed or failed.
+    attempts: u64,
+    attempts_passed: u64,
+    attempts_failed: u64,
+}
+
+/// Per-session execution statistics over the SF6 buttons, keyed by the same
+/// frame numbers as `InputFramePayload.frame`.
+#[derive(Default)]
+pub(crate) struct InputStats {
+    frames: u64,
+    first_frame: Option<u64>,
+    last_frame: Option<u64>,
+    previous_mask: u16,
+    buttons: [ButtonStats; SF6_BUTTON_ORDER.len()],
+    pairs: Vec<PairStats>,
+    attempts: u64,
+    attempts_passed: u64,
+    attempts_failed: u64,
+}
+
+impl InputStats {
+    pub(crate) fn push(&mut self, frame: &PolledFrame) {
+        self.frames += 1;
+        self.first_frame.get_or_insert(frame.frame);
+        self.last_frame = Some(frame.frame);
+
+        let mask = frame.sf6_mask;
+        let pressed = mask & !self.previous_mask;
+        let released = self.previous_mask & !mask;
+        self.previous_mask = mask;
+
+        for (index, button) in self.buttons.iter_mut().enumerate() {
+            let bit = 1u16 << index;
+            if released & bit != 0 {
+                if let Some(pressed_at) = button.pressed_at.take() {
+                    record(&mut button.hold_frames, frame.frame - pressed_at);
+                }
+            }
+            if pressed & bit != 0 {
+                button.presses += 1;
+                button.pressed_at = Some(frame.frame);
+            }
+        }
+
+        for pair in &mut self.pairs {
+            if pressed & (1 << pair.second) != 0 {
+                if let Some(pending) = pair.pending.take() {
+                    record(&mut pair.gap_frames, frame.frame - pending);
+                }
+            }
+            if pressed & (1 << pair.first) != 0 {
+                pair.pending = Some(frame.frame);
+            }
+        }
+    }
+
+    /// Counts a finished attempt and the combo result judged during it.
+    pub(crate) fn record_attempt(&mut self, combo_success: Option<bool>) {
+        self.attempts += 1;
+        match combo_success {
+            Some(true) => self.attempts_passed += 1,
+            Some(false) => self.attempts_failed += 1,
+            None => {}
+        }
+    }
+
+    /// Clears everything counted so far. Buttons already held are not
+    /// counted when they are released.
+    pub(crate) fn reset(&mut self) {
+        let pairs = self
+            .pairs
+            .iter()
+            .map(|pair| (pair.first, pair.second))
+            .collect::<Vec<_>>();
+        *self = Self {
+            previous_mask: self.previous_mask,
+            ..Self::default()
+        };
+        self.set_pair_indices(pairs);
+    }
+
+    /// Replaces the tracked pairs, given as SF6 button names. Their
+    /// histograms start empty.
+    pub(crate) fn set_pairs(&mut self, pairs: &[(String, String)]) -> Result<(), String> {
+        let mut indices = Vec::with_capacity(pairs.len());
+        for (first, second) in pairs {
+            indices.push((button_index(first)?, button_index(second)?));
+        }
+        self.set_pair_indices(indices);
+        Ok(())
+    }
+
+    fn set_pair_indices(&mut self, pairs: Vec<(usize, usize)>) {
+        self.pairs = pairs
+            .into_iter()
+            .map(|(first, second)| PairStats {
+                first,
+                second,
+                gap_frames: FrameHistogram::new(),
+                pending: None,
+            })
+            .collect();
+    }
+
+    pub(crate) fn summary(&self) -> InputStatsSummary {
+        InputStatsSummary {
+            frames: self.frames,
+            first_frame: self.first_frame,
+            last_frame: self.last_frame,
+            buttons: SF6_BUTTON_ORDER
+                .iter()
+                .zip(&self.buttons)
+                .map(|(name, button)| InputButtonStats {
+                    button: name,
+                    presses: button.presses,
+                    hold_frames: button.hold_frames.clone(),
+                })
+                .collect(),
+            pairs: self
+                .pairs
+                .iter()
+                .map(|pair| InputPairStats {
+                    first: SF6_BUTTON_ORDER[pair.first],
+                    second: SF6_BUTTON_ORDER[pair.second],
+                    gap_frames: pair.gap_frames.clone(),
+                })
+                .collect(),
+            attempts: self.attempts,
+            attempts_passed: self.attempts_passed,
+            attempts_failed: self.attempts_failed,
+        }
+    }
+}
+
+fn button_index(name: &str) -> Result<usize, String> {
+    name_to_mask(&SF6_BUTTON_ORDER, name)
+        .map(|mask| mask.trailing_zeros() as usize)
+        .ok_or_else(|| format!("Unknown SF6 button '{name}' in stats pair."))
+}
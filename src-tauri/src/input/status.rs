@@ -0,0 +1,177 @@
+use std::sync::{Arc, Mutex};
+
+use serde::Serialize;
+use tauri::{AppHandle, Emitter};
+
+use super::{NativeInputMode, Player, WorkerKind};
+
+#[derive(Clone, Copy, Default, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum InputWorkerState {
+    /// `input_start` is replacing a running worker with a different mode or
+    /// device; the old one reports `stopped` next, then the new one starts.
+    Restarting,
+    Starting,
+    Running,
+    #[default]
+    Stopped,
+    /// The worker hit a fatal error and exited.
+    Error,
+}
+
+/// Payload of `input/status`, e.g. `{"state": "error", "message": "..."}`.
+#[derive(Clone, Serialize)]
+struct InputStatusPayload {
+    player: Player,
+    state: InputWorkerState,
+    message: Option<String>,
+    /// Key of the device profile the run applied.
+    profile: Option<String>,
+}
+
+/// Answer to `input_status`.
+#[derive(Clone, Default, Serialize)]
+pub struct InputStatus {
+    player: Player,
+    /// `None` before the first start.
+    kind: Option<WorkerKind>,
+    /// Mode of a live run, the first of `modes` when several are merged.
+    mode: Option<NativeInputMode>,
+    modes: Vec<NativeInputMode>,
+    /// Key of the device profile applied when the run opened its device.
+    profile: Option<String>,
+    state: InputWorkerState,
+    /// Whether a worker thread is alive, which `state` alone cannot tell
+    /// once a worker exits without reporting.
+    running: bool,
+    /// `input_pause` is muting the live worker.
+    paused: bool,
+    /// Last frame the current or most recent run produced.
+    frame: u64,
+    /// Fatal error of the most recent run.
+    last_error: Option<String>,
+    /// Number of the current or most recent run, counting from 1.
+    run: u64,
+    /// XInput slot the live run reads, or read last before a disconnect.
+    xinput_user_index: Option<u32>,
+}
+
+impl InputStatus {
+    pub(crate) fn with_running(mut self, running: bool) -> Self {
+        self.running = running;
+        self
+    }
+
+    pub(crate) fn set_paused(&mut self, paused: bool) {
+        self.paused = paused;
+    }
+
+    pub(crate) fn xinput_user_index(&self) -> Option<u32> {
+        self.xinput_user_index
+    }
+}
+
+/// Worker-side handle that keeps the shared [`InputStatus`] current and
+/// mirrors every state change to `input/status`.
+pub(crate) struct StatusReporter {
+    app: AppHandle,
+    status: Arc<Mutex<InputStatus>>,
+    player: Player,
+}
+
+impl StatusReporter {
+    /// Resets the shared status for a new run and reports `starting`.
+    pub(crate) fn start(
+        app: AppHandle,
+        status: Arc<Mutex<InputStatus>>,
+        kind: WorkerKind,
+        modes: &[NativeInputMode],
+        profile: Option<String>,
+        player: Player,
+    ) -> Self {
+        if let Ok(mut status) = status.lock() {
+            *status = InputStatus {
+                player,
+                kind: Some(kind),
+                mode: modes.first().copied(),
+                modes: modes.to_vec(),
+                profile,
+                run: status.run + 1,
+                ..InputStatus::default()
+            };
+        }
+        let reporter = Self {
+            app,
+            status,
+            player,
+        };
+        reporter.set(InputWorkerState::Starting, None);
+        reporter
+    }
+
+    pub(crate) fn run(&self) -> u64 {
+        self.status
+            .lock()
+            .map(|status| status.run)
+            .unwrap_or_default()
+    }
+
+    pub(crate) fn running(&self) {
+        self.set(InputWorkerState::Running, None);
+    }
+
+    pub(crate) fn stopped(&self) {
+        self.set(InputWorkerState::Stopped, None);
+    }
+
+    pub(crate) fn error(&self, message: String) {
+        self.set(InputWorkerState::Error, Some(message));
+    }
+
+    pub(crate) fn xinput_user_index(&self, user_index: Option<u32>) {
+        if let Ok(mut status) = self.status.lock() {
+            status.xinput_user_index = user_index;
+        }
+    }
+
+    pub(crate) fn frame(&self, frame: u64) {
+        if let Ok(mut status) = self.status.lock() {
+            status.frame = frame;
+        }
+    }
+
+    fn set(&self, state: InputWorkerState, message: Option<String>) {
+        let mut profile = None;
+        if let Ok(mut status) = self.status.lock() {
+            status.state = state;
+            if message.is_some() {
+                status.last_error.clone_from(&message);
+            }
+            profile.clone_from(&status.profile);
+        }
+        emit_status(&self.app, self.player, state, message, profile);
+    }
+}
+
+/// Announces from the command side that the running worker is being replaced.
+pub(crate) fn emit_restarting(app: &AppHandle, player: Player) {
+    emit_status(app, player, InputWorkerState::Restarting, None, None);
+}
+
+fn emit_status(
+    app: &AppHandle,
+    player: Player,
+    state: InputWorkerState,
+    message: Option<String>,
+    profile: Option<String>,
+) {
+    let _ = app.emit(
+        "input/status",
+        InputStatusPayload {
+            player,
+            state,
+            message,
+            profile,
+        },
+    );
+}
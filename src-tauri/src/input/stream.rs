@@ -0,0 +1,223 @@
+//! WebSocket mirror of the input events for overlays outside the app, such
+//! as an OBS browser source.
+
+use std::{
+    io::ErrorKind,
+    net::{Ipv4Addr, SocketAddr, TcpListener, TcpStream},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        mpsc::{sync_channel, Receiver, SyncSender},
+        Arc, Mutex,
+    },
+    thread::{self, JoinHandle},
+    time::Duration,
+};
+
+use serde::Serialize;
+use tauri::{AppHandle, Emitter};
+use tungstenite::Message;
+
+use super::{emitter::InputEmitMode, InputSettings};
+
+/// Bumped whenever a mirrored payload changes incompatibly.
+const STREAM_PROTOCOL_VERSION: u32 = 1;
+/// Events forwarded to clients; everything else stays in the webview.
+const STREAM_EVENTS: [&str; 5] = [
+    "input/frame",
+    "input/frames",
+    "input/edge",
+    "input/segments",
+    "input/motion",
+];
+/// Messages a client may fall behind by, about four seconds of frames,
+/// before it is dropped rather than slowing the worker down.
+const MAX_QUEUED_MESSAGES: usize = 256;
+const ACCEPT_POLL_INTERVAL: Duration = Duration::from_millis(50);
+const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(2);
+const WRITE_TIMEOUT: Duration = Duration::from_secs(1);
+
+pub(crate) type StreamHandle = Arc<Mutex<Option<InputStreamServer>>>;
+
+/// First message on every connection.
+#[derive(Serialize)]
+struct StreamHello {
+    event: &'static str,
+    protocol: u32,
+    emit_mode: InputEmitMode,
+    events: [&'static str; STREAM_EVENTS.len()],
+}
+
+#[derive(Clone, Serialize)]
+pub struct InputStreamInfo {
+    address: String,
+    protocol: u32,
+}
+
+type ClientQueues = Arc<Mutex<Vec<SyncSender<Arc<str>>>>>;
+
+/// Accepts clients on its own thread; each client is written by a thread of
+/// its own, so a stalled socket only ever blocks its writer.
+pub(crate) struct InputStreamServer {
+    address: SocketAddr,
+    stop_flag: Arc<AtomicBool>,
+    clients: ClientQueues,
+    accept_handle: Option<JoinHandle<()>>,
+}
+
+impl InputStreamServer {
+    /// Listens on localhost, or on every interface when `lan` is set.
+    pub(crate) fn start(
+        port: u16,
+        lan: bool,
+        settings: Arc<Mutex<InputSettings>>,
+    ) -> Result<Self, String> {
+        let host = if lan {
+            Ipv4Addr::UNSPECIFIED
+        } else {
+            Ipv4Addr::LOCALHOST
+        };
+        let requested = SocketAddr::from((host, port));
+        let listener = TcpListener::bind(requested)
+            .and_then(|listener| listener.set_nonblocking(true).map(|_| listener))
+            .map_err(|error| format!("Failed to start input streaming on {requested}: {error}"))?;
+        let address = listener.local_addr().unwrap_or(requested);
+
+        let stop_flag = Arc::new(AtomicBool::new(false));
+        let clients = ClientQueues::default();
+        let accept_handle = {
+            let stop_flag = Arc::clone(&stop_flag);
+            let clients = Arc::clone(&clients);
+            thread::spawn(move || accept_clients(listener, stop_flag, clients, settings))
+        };
+
+        Ok(Self {
+            address,
+            stop_flag,
+            clients,
+            accept_handle: Some(accept_handle),
+        })
+    }
+
+    pub(crate) fn address(&self) -> SocketAddr {
+        self.address
+    }
+
+    pub(crate) fn info(&self) -> InputStreamInfo {
+        InputStreamInfo {
+            address: self.address.to_string(),
+            protocol: STREAM_PROTOCOL_VERSION,
+        }
+    }
+
+    /// Stops accepting and closes every client once its queue is written.
+    pub(crate) fn stop(mut self) {
+        self.stop_flag.store(true, Ordering::Relaxed);
+        if let Some(handle) = self.accept_handle.take() {
+            let _ = handle.join();
+        }
+        if let Ok(mut clients) = self.clients.lock() {
+            clients.clear();
+        }
+    }
+
+    /// Queues `message` for every client, dropping those whose queue is full.
+    fn broadcast(&self, message: Arc<str>) {
+        if let Ok(mut clients) = self.clients.lock() {
+            clients.retain(|client| client.try_send(Arc::clone(&message)).is_ok());
+        }
+    }
+
+    fn has_clients(&self) -> bool {
+        self.clients
+            .lock()
+            .map(|clients| !clients.is_empty())
+            .unwrap_or(false)
+    }
+}
+
+/// Emits `payload` to the webview and, for [`STREAM_EVENTS`], to the stream
+/// clients too.
+pub(crate) fn publish<T: Serialize + Clone>(
+    app: &AppHandle,
+    stream: &StreamHandle,
+    event: &str,
+    payload: T,
+) {
+    if STREAM_EVENTS.contains(&event) {
+        if let Ok(server) = stream.lock() {
+            if let Some(server) = server.as_ref().filter(|server| server.has_clients()) {
+                // Every message after the hello is `{"event": ..., "payload":
+                // ...}`, the payload being exactly what the webview receives.
+                if let Ok(payload) = serde_json::to_string(&payload) {
+                    let message = format!(r#"{{"event":"{event}","payload":{payload}}}"#);
+                    server.broadcast(message.into());
+                }
+            }
+        }
+    }
+    let _ = app.emit(event, payload);
+}
+
+fn accept_clients(
+    listener: TcpListener,
+    stop_flag: Arc<AtomicBool>,
+    clients: ClientQueues,
+    settings: Arc<Mutex<InputSettings>>,
+) {
+    while !stop_flag.load(Ordering::Relaxed) {
+        let stream = match listener.accept() {
+            Ok((stream, _)) => stream,
+            Err(error) if error.kind() == ErrorKind::Interrupted => continue,
+            Err(_) => {
+                thread::sleep(ACCEPT_POLL_INTERVAL);
+                continue;
+            }
+        };
+
+        let hello = StreamHello {
+            event: "hello",
+            protocol: STREAM_PROTOCOL_VERSION,
+            emit_mode: settings
+                .lock()
+                .map(|settings| settings.emit.mode)
+                .unwrap_or_default(),
+            events: STREAM_EVENTS,
+        };
+        let Ok(hello) = serde_json::to_string(&hello) else {
+            continue;
+        };
+        let (sender, receiver) = sync_channel(MAX_QUEUED_MESSAGES);
+        if let Ok(mut clients) = clients.lock() {
+            clients.push(sender);
+        }
+        thread::spawn(move || serve_client(stream, hello, receiver));
+    }
+}
+
+/// Writes `hello` and then every queued message until the server drops the
+/// queue or the client goes away. Clients are not read from; nothing they
+/// send is needed.
+fn serve_client(stream: TcpStream, hello: String, messages: Receiver<Arc<str>>) {
+    // Accepted sockets may inherit the listener's non-blocking mode.
+    let configured = stream
+        .set_nonblocking(false)
+        .and_then(|_| stream.set_read_timeout(Some(HANDSHAKE_TIMEOUT)))
+        .and_then(|_| stream.set_write_timeout(Some(WRITE_TIMEOUT)));
+    if configured.is_err() {
+        return;
+    }
+    let Ok(mut socket) = tungstenite::accept(stream) else {
+        return;
+    };
+
+    if socket.send(Message::text(hello)).is_err() {
+        return;
+    }
+    for message in messages {
+        if socket.send(Message::text(message.to_string())).is_err() {
+            return;
+        }
+    }
+    let _ = socket.close(None);
+    let _ = socket.flush();
+}
@@ -0,0 +1,65 @@
+use std::time::{Duration, Instant};
+
+use super::{InputSample, BUTTON_DPAD_MASK};
+
+/// The frame cadence; polling at this rate means one poll per frame.
+pub(crate) const FRAME_RATE_HZ: u32 = 60;
+pub(crate) const MAX_POLLING_HZ: u32 = 1_000;
+
+#[derive(Clone, Copy)]
+pub(crate) struct PollingSettings {
+    pub polling_hz: u32,
+}
+
+impl Default for PollingSettings {
+    fn default() -> Self {
+        Self {
+            polling_hz: FRAME_RATE_HZ,
+        }
+    }
+}
+
+impl PollingSettings {
+    /// Time between sub-frame polls, or `None` at the plain one-poll-per-frame
+    /// rate.
+    pub(crate) fn sub_poll_interval(self) -> Option<Duration> {
+        (self.polling_hz > FRAME_RATE_HZ).then(|| Duration::from_secs(1) / self.polling_hz)
+    }
+}
+
+/// Folds the polls made between two frame ticks into the tick's own sample,
+/// so a button pressed and released in between still reaches the frame.
+#[derive(Default)]
+pub(crate) struct SubFrameAggregator {
+    previous_mask: u32,
+    pressed: u32,
+    first_press_us: Option<u64>,
+    reported_at: Option<Instant>,
+}
+
+impl SubFrameAggregator {
+    /// Dpad bits follow `direction`, which is taken from the last poll only,
+    /// so they are not held over either.
+    pub(crate) fn push(&mut self, sample: &InputSample) {
+        let pressed = sample.down_mask & !self.previous_mask & !BUTTON_DPAD_MASK;
+        if pressed != 0 && self.pressed == 0 {
+            self.first_press_us = Some(sample.timestamp_us);
+        }
+        self.pressed |= pressed;
+        self.previous_mask = sample.down_mask;
+        if self.reported_at.is_none() {
+            self.reported_at = sample.reported_at;
+        }
+    }
+
+    /// Adds the tick's own poll, then ORs every press seen since the previous
+    /// tick into it and stamps when the first of them was polled and when the
+    /// first change since the previous tick was reported.
+    pub(crate) fn finish(&mut self, sample: &mut InputSample) {
+        self.push(sample);
+        sample.down_mask |= self.pressed;
+        sample.first_press_us = self.first_press_us.take();
+        sample.reported_at = self.reported_at.take();
+        self.pressed = 0;
+    }
+}
@@ -0,0 +1,146 @@
+use serde::Serialize;
+
+use super::{
+    emitter::PolledFrame, BUTTON_DI_MASK, BUTTON_HK_MASK, BUTTON_HP_MASK, BUTTON_LK_MASK,
+    BUTTON_LP_MASK, BUTTON_MK_MASK, BUTTON_MP_MASK, BUTTON_PARRY_MASK, SF6_BUTTON_ORDER,
+};
+
+/// SF6 system mechanics, in the bit order of [`PolledFrame::system`].
+#[derive(Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SystemAction {
+    DriveImpact,
+    /// Drive Parry; Drive Rush is a parry with a forward dash.
+    Parry,
+    Throw,
+}
+
+struct SystemChord {
+    action: SystemAction,
+    /// Two buttons pressed together.
+    chord: u16,
+    /// Macro button that performs the action alone; 0 when there is none.
+    dedicated: u16,
+}
+
+const SYSTEM_CHORDS: [SystemChord; 3] = [
+    SystemChord {
+        action: SystemAction::DriveImpact,
+        chord: BUTTON_HP_MASK | BUTTON_HK_MASK,
+        dedicated: BUTTON_DI_MASK,
+    },
+    SystemChord {
+        action: SystemAction::Parry,
+        chord: BUTTON_MP_MASK | BUTTON_MK_MASK,
+        dedicated: BUTTON_PARRY_MASK,
+    },
+    SystemChord {
+        action: SystemAction::Throw,
+        chord: BUTTON_LP_MASK | BUTTON_LK_MASK,
+        dedicated: 0,
+    },
+];
+
+#[derive(Clone, Copy, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SystemPhase {
+    Press,
+    Release,
+}
+
+/// Payload of `input/system`, e.g. `{"action": "parry", "phase": "release",
+/// "frame": 812, "held_frames": 3}`.
+#[derive(Clone, Serialize)]
+pub struct InputSystemPayload {
+    action: SystemAction,
+    phase: SystemPhase,
+    frame: u64,
+    /// Frames the action was held, on release only. A perfect parry attempt
+    /// is a short tap, a held parry a long one.
+    held_frames: Option<u64>,
+}
+
+pub(crate) fn system_actions(mask: u8) -> Vec<SystemAction> {
+    SYSTEM_CHORDS
+        .iter()
+        .enumerate()
+        .filter(|(index, _)| mask & (1 << index) != 0)
+        .map(|(_, chord)| chord.action)
+        .collect()
+}
+
+#[derive(Clone, Copy, Default)]
+struct ChordState {
+    /// Both chord buttons are down and were pressed within the window.
+    chord_held: bool,
+    active_since: Option<u64>,
+}
+
+/// Recognizes the system chords and their macro buttons after mapping, so
+/// both produce the same `input/system` events.
+#[derive(Default)]
+pub(crate) struct SystemChordRecognizer {
+    states: [ChordState; SYSTEM_CHORDS.len()],
+}
+
+impl SystemChordRecognizer {
+    /// Sets `frame.system` and returns the presses and releases it starts.
+    /// Chord buttons count as together when their presses are at most
+    /// `window_frames` apart, the same tolerance as press grouping.
+    pub(crate) fn push(
+        &mut self,
+        frame: &mut PolledFrame,
+        window_frames: u64,
+    ) -> Vec<InputSystemPayload> {
+        let mask = frame.sf6_mask;
+        let pressed = mask & !frame.previous_sf6_mask;
+        let mut events = Vec::new();
+        frame.system = 0;
+
+        for (index, (chord, state)) in SYSTEM_CHORDS.iter().zip(&mut self.states).enumerate() {
+            if mask & chord.chord != chord.chord {
+                state.chord_held = false;
+            } else if !state.chord_held && pressed & chord.chord != 0 {
+                state.chord_held = press_spread(frame, chord.chord) <= window_frames;
+            }
+
+            let active = state.chord_held || mask & chord.dedicated != 0;
+            match (active, state.active_since) {
+                (true, None) => {
+                    state.active_since = Some(frame.frame);
+                    events.push(InputSystemPayload {
+                        action: chord.action,
+                        phase: SystemPhase::Press,
+                        frame: frame.frame,
+                        held_frames: None,
+                    });
+                }
+                (false, Some(since)) => {
+                    state.active_since = None;
+                    events.push(InputSystemPayload {
+                        action: chord.action,
+                        phase: SystemPhase::Release,
+                        frame: frame.frame,
+                        held_frames: Some(frame.frame - since),
+                    });
+                }
+                _ => {}
+            }
+            if active {
+                frame.system |= 1 << index;
+            }
+        }
+
+        events
+    }
+}
+
+/// Frames between the first and last press of the buttons in `chord`.
+fn press_spread(frame: &PolledFrame, chord: u16) -> u64 {
+    let presses = (0..SF6_BUTTON_ORDER.len())
+        .filter(|index| chord & (1 << index) != 0)
+        .map(|index| frame.pressed_at[index]);
+    let first = presses.clone().min().unwrap_or(frame.frame);
+    let last = presses.max().unwrap_or(frame.frame);
+    last - first
+}
@@ -0,0 +1,136 @@
+//! Expiry of HID state the device stopped confirming. Backends that cache
+//! the last decoded report would otherwise show a button held forever once
+//! reports stop arriving or stop decoding.
+
+// Only the hidapi backend, on Windows and macOS, runs a watchdog.
+#![cfg_attr(not(any(windows, target_os = "macos")), allow(dead_code))]
+
+use std::time::{Duration, Instant};
+
+use serde::Serialize;
+
+const DEFAULT_STALE_MS: u64 = 250;
+pub(crate) const MIN_STALE_MS: u64 = 50;
+pub(crate) const MAX_STALE_MS: u64 = 5_000;
+/// Leading bytes of an undecodable report quoted in the warning.
+const SAMPLE_BYTES: usize = 16;
+
+#[derive(Clone, Copy)]
+pub(crate) struct WatchdogSettings {
+    /// How long the held state survives without a decodable report.
+    pub stale_after: Duration,
+}
+
+impl Default for WatchdogSettings {
+    fn default() -> Self {
+        Self {
+            stale_after: Duration::from_millis(DEFAULT_STALE_MS),
+        }
+    }
+}
+
+/// Answer to `input_hid_diagnostics`. A run of undecodable reports with few
+/// decoded ones means the decoder does not understand the device; no reports
+/// at all means the device went quiet.
+#[derive(Clone, Copy, Default, Serialize)]
+pub struct HidDiagnostics {
+    reports: u64,
+    decoded: u64,
+    undecodable: u64,
+    consecutive_undecodable: u64,
+    /// Times the held state was released for going stale.
+    expiries: u64,
+    since_last_decoded_ms: u64,
+}
+
+/// Tracks when the device last sent a report the decoder understood.
+pub(crate) struct ReportWatchdog {
+    last_decoded_at: Instant,
+    last_report_at: Instant,
+    last_undecodable: Vec<u8>,
+    /// The current stale stretch was already reported and expired.
+    expired: bool,
+    diagnostics: HidDiagnostics,
+    warnings: Vec<String>,
+}
+
+impl ReportWatchdog {
+    pub(crate) fn new() -> Self {
+        let now = Instant::now();
+        Self {
+            last_decoded_at: now,
+            last_report_at: now,
+            last_undecodable: Vec::new(),
+            expired: false,
+            diagnostics: HidDiagnostics::default(),
+            warnings: Vec::new(),
+        }
+    }
+
+    pub(crate) fn decoded(&mut self, at: Instant) {
+        self.last_decoded_at = at;
+        self.last_report_at = at;
+        self.expired = false;
+        self.diagnostics.reports += 1;
+        self.diagnostics.decoded += 1;
+        self.diagnostics.consecutive_undecodable = 0;
+    }
+
+    pub(crate) fn undecodable(&mut self, at: Instant, report: &[u8]) {
+        self.last_report_at = at;
+        self.diagnostics.reports += 1;
+        self.diagnostics.undecodable += 1;
+        self.diagnostics.consecutive_undecodable += 1;
+        self.last_undecodable.clear();
+        self.last_undecodable
+            .extend_from_slice(&report[..report.len().min(SAMPLE_BYTES)]);
+    }
+
+    /// Whether the held state should be released now. Silence alone only
+    /// counts while something is `held` and when `streams` is set: devices
+    /// that report on change stay quiet for as long as a button is held,
+    /// which is not a fault.
+    pub(crate) fn expire(&mut self, settings: WatchdogSettings, streams: bool, held: bool) -> bool {
+        let stale_for = self.last_decoded_at.elapsed();
+        if self.expired || stale_for < settings.stale_after {
+            return false;
+        }
+
+        let undecodable = self.last_report_at > self.last_decoded_at;
+        let warning = if undecodable {
+            let sample: Vec<String> = self
+                .last_undecodable
+                .iter()
+                .map(|byte| format!("{byte:02x}"))
+                .collect();
+            format!(
+                "HID reports stopped decoding: {} in a row, the last starting {}. The held input was released.",
+                self.diagnostics.consecutive_undecodable,
+                sample.join(" ")
+            )
+        } else if streams && held {
+            format!(
+                "The HID device sent no report for {} ms. The held input was released.",
+                stale_for.as_millis()
+            )
+        } else {
+            return false;
+        };
+
+        self.expired = true;
+        self.diagnostics.expiries += 1;
+        self.warnings.push(warning);
+        true
+    }
+
+    pub(crate) fn take_warnings(&mut self) -> Vec<String> {
+        std::mem::take(&mut self.warnings)
+    }
+
+    pub(crate) fn diagnostics(&self) -> HidDiagnostics {
+        HidDiagnostics {
+            since_last_decoded_ms: self.last_decoded_at.elapsed().as_millis() as u64,
+            ..self.diagnostics
+        }
+    }
+}
@@ -14,8 +14,69 @@ pub fn run() {
         .invoke_handler(tauri::generate_handler![
             greet,
             input::input_detect,
+            input::input_list_devices,
             input::input_start,
-            input::input_stop
+            input::input_play,
+            input::input_start_raw,
+            input::input_stop,
+            input::input_pause,
+            input::input_resume,
+            input::input_stream_start,
+            input::input_stream_stop,
+            input::input_controller_info,
+            input::input_status,
+            input::input_passthrough_start,
+            input::input_passthrough_stop,
+            input::input_feedback,
+            input::input_timing_stats,
+            input::input_hid_diagnostics,
+            input::input_set_hid_watchdog,
+            input::input_set_latency_tracking,
+            input::input_ack,
+            input::input_latency_report,
+            input::input_get_history,
+            input::input_get_notation,
+            input::input_set_history_seconds,
+            input::input_get_stats,
+            input::input_get_anomalies,
+            input::input_set_anomaly_thresholds,
+            input::input_reset_stats,
+            input::input_set_stats_pairs,
+            input::input_record_start,
+            input::input_record_stop,
+            input::input_export,
+            input::input_set_mapping,
+            input::input_get_mapping,
+            input::input_set_emit_mode,
+            input::input_set_segments,
+            input::input_set_notation_events,
+            input::input_set_press_grouping,
+            input::input_set_socd,
+            input::input_set_merge_policy,
+            input::input_set_auto_order,
+            input::input_set_motion_window,
+            input::input_set_motion_rules,
+            input::input_set_attempt_trigger,
+            input::input_set_charge,
+            input::input_set_polling_rate,
+            input::input_set_facing,
+            input::input_load_hid_profile,
+            input::input_clear_hid_profile,
+            input::input_profile_save,
+            input::input_profile_list,
+            input::input_profile_delete,
+            input::input_set_hid_allowlist,
+            input::input_get_hid_allowlist,
+            input::input_configure,
+            input::input_calibrate_stick,
+            input::input_set_keyboard_bindings,
+            input::input_get_keyboard_bindings,
+            input::combo_load,
+            input::combo_arm,
+            input::combo_disarm,
+            input::reaction_start,
+            input::reaction_stop,
+            input::reaction_get_summary
         ])
         .run(tauri::generate_context!())
         .expect("error while running tauri application");
@@ -31,6 +31,11 @@ export const PHYSICAL_BUTTON_LABELS: Record<PhysicalButton, string> = {
   DPadDown: "D-Pad Down",
   DPadLeft: "D-Pad Left",
   DPadRight: "D-Pad Right",
+  Touchpad: "Touchpad Click",
+  Button13: "Button 13",
+  Button14: "Button 14",
+  Button15: "Button 15",
+  Button16: "Button 16",
 };
 
 export const ATTACK_ACTIONS: ReadonlyArray<AttackActionDefinition> = [
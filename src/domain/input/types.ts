@@ -41,6 +41,11 @@ export const PHYSICAL_BUTTONS = [
   "DPadDown",
   "DPadLeft",
   "DPadRight",
+  "Touchpad",
+  "Button13",
+  "Button14",
+  "Button15",
+  "Button16",
 ] as const;
 
 export type PhysicalButton = (typeof PHYSICAL_BUTTONS)[number];